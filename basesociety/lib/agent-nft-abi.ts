@@ -61,6 +61,59 @@ export const AgentNFTABI = [
       },
     ],
   },
+  {
+    type: "function",
+    name: "getAgentProfile",
+    inputs: [{ name: "tokenId", type: "uint256", internalType: "uint256" }],
+    outputs: [
+      {
+        name: "",
+        type: "tuple",
+        internalType: "struct AgentProfile",
+        components: [
+          { name: "personality", type: "string", internalType: "string" },
+          { name: "desires", type: "string", internalType: "string" },
+          { name: "skills", type: "string[]", internalType: "string[]" },
+          { name: "activityLogHash", type: "bytes32", internalType: "bytes32" },
+          { name: "lastPassionTimestamp", type: "uint256", internalType: "uint256" },
+          { name: "happinessScore", type: "uint8", internalType: "uint8" },
+        ],
+      },
+    ],
+    stateMutability: "view",
+  },
+  {
+    type: "function",
+    name: "updateActivityLogHash",
+    inputs: [
+      { name: "tokenId", type: "uint256", internalType: "uint256" },
+      { name: "activityLogHash", type: "bytes32", internalType: "bytes32" },
+      { name: "logSize", type: "uint256", internalType: "uint256" },
+    ],
+    outputs: [],
+    stateMutability: "nonpayable",
+  },
+  {
+    type: "event",
+    name: "ActivityLogHashUpdated",
+    inputs: [
+      {
+        name: "_tokenId",
+        type: "uint256",
+        indexed: true,
+      },
+      {
+        name: "_activityLogHash",
+        type: "bytes32",
+        indexed: false,
+      },
+      {
+        name: "_logSize",
+        type: "uint256",
+        indexed: false,
+      },
+    ],
+  },
 ] as const
 
 export type AgentProfile = {
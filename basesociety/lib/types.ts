@@ -5,9 +5,18 @@ export interface AgentProfile {
   skills: string[];
 }
 
+export interface AgentDeath {
+  agent_id: string;
+  died_at: number;  // Unix seconds
+  death_tx_hash: string | null;
+  epitaph: string | null;
+}
+
 export interface AgentDetails {
   agent_id: string;  // Snake_case from backend
   profile: AgentProfile;
+  status: 'alive' | 'dead';
+  death: AgentDeath | null;
 }
 
 export interface CustomMessage {
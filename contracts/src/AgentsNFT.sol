@@ -22,6 +22,8 @@ contract AgentNFT is
 
     event Minted(uint256 indexed _tokenId, address indexed _creator, address indexed _owner);
 
+    event ActivityLogHashUpdated(uint256 indexed _tokenId, bytes32 _activityLogHash, uint256 _logSize);
+
     struct TokenData {
         address owner;
         address[] authorizedUsers;
@@ -161,6 +163,23 @@ contract AgentNFT is
         $.agentProfiles[tokenId].lastPassionTimestamp = block.timestamp;
     }
 
+    /// @notice Commits the Merkle root of an agent's off-chain activity log.
+    ///         This function is access-controlled and can only be called by the designated oracle.
+    /// @param tokenId The token ID of the agent.
+    /// @param activityLogHash The root over the first `logSize` messages of the log.
+    /// @param logSize The number of messages the root covers.
+    function updateActivityLogHash(uint256 tokenId, bytes32 activityLogHash, uint256 logSize)
+        public
+        virtual
+        onlyOracle
+    {
+        AgentNFTStorage storage $ = _getAgentStorage();
+        require(_exists(tokenId), "Token does not exist");
+
+        $.agentProfiles[tokenId].activityLogHash = activityLogHash;
+        emit ActivityLogHashUpdated(tokenId, activityLogHash, logSize);
+    }
+
     function mint(
         IntelligentData[] calldata iDatas,
         address to,
@@ -15,6 +15,7 @@ contract DecayOracle is Initializable, OwnableUpgradeable {
     event AgentRegistered(uint256 indexed tokenId, address indexed registeredBy);
     event OracleHappinessUpdateTriggered(uint256 indexed tokenId, uint8 oldHappiness, uint8 newHappiness);
     event AgentNFTAddressUpdated(address indexed oldAddress, address indexed newAddress);
+    event ActivityLogAnchored(uint256 indexed tokenId, bytes32 root, uint256 logSize);
 
     /// @custom:oz-upgrades-unsafe-allow constructor
     constructor() {
@@ -35,13 +36,59 @@ contract DecayOracle is Initializable, OwnableUpgradeable {
     }
 
     function registerAgent(uint256 tokenId) public onlyOwner {
+        _registerAgent(tokenId);
+    }
+
+    /// @notice Registers several agents in one transaction.
+    /// @param tokenIds The token IDs to register; reverts if any is already registered.
+    function batchRegisterAgents(uint256[] calldata tokenIds) public onlyOwner {
+        for (uint256 i = 0; i < tokenIds.length; i++) {
+            _registerAgent(tokenIds[i]);
+        }
+    }
+
+    function updateAgentHappiness(uint256 tokenId, uint8 newHappinessScore) public onlyOwner {
+        _updateAgentHappiness(tokenId, newHappinessScore);
+    }
+
+    /// @notice Applies several happiness updates in one transaction.
+    /// @param tokenIds The token IDs to update.
+    /// @param newHappinessScores The new score for each token, in the same order.
+    function batchUpdateAgentHappiness(uint256[] calldata tokenIds, uint8[] calldata newHappinessScores)
+        public
+        onlyOwner
+    {
+        require(tokenIds.length == newHappinessScores.length, "Length mismatch");
+        for (uint256 i = 0; i < tokenIds.length; i++) {
+            _updateAgentHappiness(tokenIds[i], newHappinessScores[i]);
+        }
+    }
+
+    /// @notice Anchors the activity log roots of several agents in one transaction.
+    /// @param tokenIds The token IDs to anchor.
+    /// @param roots The Merkle root of each agent's log, in the same order.
+    /// @param logSizes The number of messages each root covers, in the same order.
+    function anchorActivityLogs(uint256[] calldata tokenIds, bytes32[] calldata roots, uint256[] calldata logSizes)
+        public
+        onlyOwner
+    {
+        require(tokenIds.length == roots.length && tokenIds.length == logSizes.length, "Length mismatch");
+        IERC7857 agentNFT = IERC7857(agentNFTAddress);
+        for (uint256 i = 0; i < tokenIds.length; i++) {
+            require(registeredAgents[tokenIds[i]], "Agent not registered with oracle");
+            agentNFT.updateActivityLogHash(tokenIds[i], roots[i], logSizes[i]);
+            emit ActivityLogAnchored(tokenIds[i], roots[i], logSizes[i]);
+        }
+    }
+
+    function _registerAgent(uint256 tokenId) internal {
         require(!registeredAgents[tokenId], "Agent already registered");
         registeredAgents[tokenId] = true;
         registeredAgentCount++;
         emit AgentRegistered(tokenId, msg.sender);
     }
 
-    function updateAgentHappiness(uint256 tokenId, uint8 newHappinessScore) public onlyOwner {
+    function _updateAgentHappiness(uint256 tokenId, uint8 newHappinessScore) internal {
         require(registeredAgents[tokenId], "Agent not registered with oracle");
         require(agentNFTAddress != address(0), "AgentNFT address not set");
 
@@ -170,4 +170,10 @@ interface IERC7857 {
     /// @param tokenId The token ID of the agent.
     /// @param newHappinessScore The new happiness score (0-100).
     function updateHappiness(uint256 tokenId, uint8 newHappinessScore) external;
+
+    /// @notice Commits the Merkle root of an agent's off-chain activity log.
+    /// @param tokenId The token ID of the agent.
+    /// @param activityLogHash The root over the first `logSize` messages of the log.
+    /// @param logSize The number of messages the root covers.
+    function updateActivityLogHash(uint256 tokenId, bytes32 activityLogHash, uint256 logSize) external;
 }
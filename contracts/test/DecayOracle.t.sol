@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {AgentNFT} from "../src/AgentsNFT.sol";
+import {DecayOracle} from "../src/DecayOracle.sol";
+import {MockDataVerifier} from "../src/MockDataVerifier.sol";
+import {AgentProfile} from "../src/interfaces/IAgentProfile.sol";
+import {IntelligentData} from "../src/interfaces/IERC7857Metadata.sol";
+import {ERC1967Proxy} from "@openzeppelin/contracts/proxy/ERC1967/ERC1967Proxy.sol";
+
+contract DecayOracleTest is Test {
+    AgentNFT public agentNFT;
+    DecayOracle public decayOracle;
+
+    event ActivityLogAnchored(uint256 indexed tokenId, bytes32 root, uint256 logSize);
+
+    function setUp() public {
+        MockDataVerifier verifier = new MockDataVerifier();
+        bytes memory agentNFTData = abi.encodeWithSelector(
+            AgentNFT.initialize.selector, "Agents NFT", "AGENT", address(verifier), address(this)
+        );
+        agentNFT = AgentNFT(address(new ERC1967Proxy(address(new AgentNFT()), agentNFTData)));
+
+        bytes memory decayOracleData =
+            abi.encodeWithSelector(DecayOracle.initialize.selector, address(this), address(agentNFT));
+        decayOracle = DecayOracle(address(new ERC1967Proxy(address(new DecayOracle()), decayOracleData)));
+        agentNFT.setOracle(address(decayOracle));
+    }
+
+    function _mint() internal returns (uint256) {
+        IntelligentData[] memory iDatas = new IntelligentData[](1);
+        iDatas[0] = IntelligentData({dataDescription: "agent", dataHash: keccak256("agent")});
+        AgentProfile memory profile = AgentProfile({
+            personality: "curious",
+            desires: "explore",
+            skills: new string[](0),
+            activityLogHash: bytes32(0),
+            lastPassionTimestamp: block.timestamp,
+            happinessScore: 80
+        });
+        return agentNFT.mint(iDatas, address(this), profile);
+    }
+
+    function _tokens(uint256 count) internal returns (uint256[] memory tokenIds) {
+        tokenIds = new uint256[](count);
+        for (uint256 i = 0; i < count; i++) {
+            tokenIds[i] = _mint();
+        }
+    }
+
+    function _anchors(uint256 count) internal pure returns (bytes32[] memory roots, uint256[] memory logSizes) {
+        roots = new bytes32[](count);
+        logSizes = new uint256[](count);
+        for (uint256 i = 0; i < count; i++) {
+            roots[i] = keccak256(abi.encode("log", i));
+            logSizes[i] = i + 1;
+        }
+    }
+
+    function test_BatchRegisterAgents() public {
+        uint256[] memory tokenIds = _tokens(3);
+        decayOracle.batchRegisterAgents(tokenIds);
+
+        assertEq(decayOracle.getRegisteredAgentCount(), 3);
+        for (uint256 i = 0; i < tokenIds.length; i++) {
+            assertTrue(decayOracle.isAgentRegistered(tokenIds[i]));
+        }
+    }
+
+    function test_BatchRegisterAgentsRevertsOnDuplicate() public {
+        uint256[] memory tokenIds = _tokens(2);
+        decayOracle.registerAgent(tokenIds[1]);
+
+        vm.expectRevert("Agent already registered");
+        decayOracle.batchRegisterAgents(tokenIds);
+        assertFalse(decayOracle.isAgentRegistered(tokenIds[0]));
+    }
+
+    function test_BatchUpdateAgentHappiness() public {
+        uint256[] memory tokenIds = _tokens(2);
+        decayOracle.batchRegisterAgents(tokenIds);
+        uint8[] memory scores = new uint8[](2);
+        scores[0] = 65;
+        scores[1] = 100;
+
+        vm.warp(1_000_000);
+        decayOracle.batchUpdateAgentHappiness(tokenIds, scores);
+
+        for (uint256 i = 0; i < tokenIds.length; i++) {
+            assertEq(agentNFT.getAgentProfile(tokenIds[i]).happinessScore, scores[i]);
+            assertEq(decayOracle.getOracleLastUpdate(tokenIds[i]), 1_000_000);
+        }
+    }
+
+    function test_BatchUpdateAgentHappinessRevertsOnUnregistered() public {
+        uint256[] memory tokenIds = _tokens(2);
+        decayOracle.registerAgent(tokenIds[0]);
+        uint8[] memory scores = new uint8[](2);
+
+        vm.expectRevert("Agent not registered with oracle");
+        decayOracle.batchUpdateAgentHappiness(tokenIds, scores);
+        assertEq(agentNFT.getAgentProfile(tokenIds[0]).happinessScore, 80);
+    }
+
+    function test_BatchUpdateAgentHappinessRevertsOnLengthMismatch() public {
+        uint256[] memory tokenIds = _tokens(2);
+        decayOracle.batchRegisterAgents(tokenIds);
+
+        vm.expectRevert("Length mismatch");
+        decayOracle.batchUpdateAgentHappiness(tokenIds, new uint8[](1));
+    }
+
+    function test_BatchEntrypointsAreOwnerOnly() public {
+        uint256[] memory tokenIds = _tokens(1);
+
+        vm.prank(address(0xbeef));
+        vm.expectRevert();
+        decayOracle.batchRegisterAgents(tokenIds);
+    }
+
+    function test_AnchorActivityLogs() public {
+        uint256[] memory tokenIds = _tokens(2);
+        decayOracle.batchRegisterAgents(tokenIds);
+        (bytes32[] memory roots, uint256[] memory logSizes) = _anchors(2);
+
+        vm.expectEmit(true, false, false, true, address(decayOracle));
+        emit ActivityLogAnchored(tokenIds[0], roots[0], logSizes[0]);
+        decayOracle.anchorActivityLogs(tokenIds, roots, logSizes);
+
+        for (uint256 i = 0; i < tokenIds.length; i++) {
+            assertEq(agentNFT.getAgentProfile(tokenIds[i]).activityLogHash, roots[i]);
+        }
+    }
+
+    function test_AnchorActivityLogsRevertsOnUnregistered() public {
+        uint256[] memory tokenIds = _tokens(1);
+        (bytes32[] memory roots, uint256[] memory logSizes) = _anchors(1);
+
+        vm.expectRevert("Agent not registered with oracle");
+        decayOracle.anchorActivityLogs(tokenIds, roots, logSizes);
+    }
+
+    function test_AnchorActivityLogsRevertsOnLengthMismatch() public {
+        uint256[] memory tokenIds = _tokens(2);
+        decayOracle.batchRegisterAgents(tokenIds);
+        (bytes32[] memory roots, uint256[] memory logSizes) = _anchors(1);
+
+        vm.expectRevert("Length mismatch");
+        decayOracle.anchorActivityLogs(tokenIds, roots, logSizes);
+    }
+
+    function test_UpdateActivityLogHashIsOracleOnly() public {
+        uint256 tokenId = _mint();
+
+        vm.expectRevert("Only oracle can call this function");
+        agentNFT.updateActivityLogHash(tokenId, keccak256("log"), 1);
+    }
+}
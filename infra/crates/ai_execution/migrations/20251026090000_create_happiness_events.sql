@@ -0,0 +1,13 @@
+-- Time-series of every happiness observation and oracle update
+CREATE TABLE IF NOT EXISTS happiness_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    token_id TEXT,
+    ts INTEGER NOT NULL,
+    old_happiness INTEGER,
+    new_happiness INTEGER NOT NULL,
+    cause TEXT NOT NULL,
+    tx_hash TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_happiness_events_agent_ts ON happiness_events (agent_id, ts);
@@ -0,0 +1,5 @@
+-- Terminal state for agents whose happiness reached zero
+ALTER TABLE agents ADD COLUMN status TEXT NOT NULL DEFAULT 'alive';
+ALTER TABLE agents ADD COLUMN died_at INTEGER;
+ALTER TABLE agents ADD COLUMN death_tx_hash TEXT;
+ALTER TABLE agents ADD COLUMN epitaph TEXT;
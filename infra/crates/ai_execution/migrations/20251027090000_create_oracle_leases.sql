@@ -0,0 +1,7 @@
+-- Leader lease for running several oracle_service instances against one DB.
+-- Only the current holder sends transactions; a standby takes over once expires_at passes.
+CREATE TABLE IF NOT EXISTS oracle_leases (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
@@ -0,0 +1,10 @@
+-- Operator actions taken through the oracle admin API
+CREATE TABLE IF NOT EXISTS oracle_audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ts INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    token_id TEXT,
+    reason TEXT,
+    detail TEXT,
+    tx_hash TEXT
+);
@@ -0,0 +1,13 @@
+-- Windows during which an agent's happiness does not decay: the grace period after
+-- mint, owner-booked vacations and operator exemptions. ends_at NULL = open-ended.
+CREATE TABLE IF NOT EXISTS decay_protections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    starts_at INTEGER NOT NULL,
+    ends_at INTEGER,
+    reason TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_decay_protections_agent ON decay_protections (agent_id);
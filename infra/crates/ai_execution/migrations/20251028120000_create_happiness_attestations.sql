@@ -0,0 +1,23 @@
+-- EIP-712 signed statements of how the oracle computed each happiness update.
+-- The signed fields are stored as-is so anyone can rebuild the digest and recover the signer.
+CREATE TABLE IF NOT EXISTS happiness_attestations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    token_id TEXT NOT NULL,
+    old_happiness INTEGER NOT NULL,
+    new_happiness INTEGER NOT NULL,
+    last_interaction INTEGER NOT NULL,
+    decay_since INTEGER NOT NULL,
+    computed_at INTEGER NOT NULL,
+    decay_owed INTEGER NOT NULL,
+    max_step INTEGER NOT NULL,
+    cause TEXT NOT NULL,
+    chain_id INTEGER NOT NULL,
+    verifying_contract TEXT NOT NULL,
+    signer TEXT NOT NULL,
+    digest TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    tx_hash TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_happiness_attestations_agent ON happiness_attestations (agent_id, computed_at);
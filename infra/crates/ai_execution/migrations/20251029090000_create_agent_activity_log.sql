@@ -0,0 +1,26 @@
+-- Every message in an agent's history, in order. seq is the message's 0-based position
+-- in the agent's log and leaf_hash its Merkle leaf.
+CREATE TABLE IF NOT EXISTS agent_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    content TEXT NOT NULL,
+    ts INTEGER NOT NULL,
+    leaf_hash TEXT NOT NULL,
+    UNIQUE (agent_id, seq)
+);
+
+-- Roots the oracle committed on-chain: the Merkle root over the first `size` messages.
+CREATE TABLE IF NOT EXISTS activity_anchors (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    token_id TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    root TEXT NOT NULL,
+    anchored_at INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_activity_anchors_agent ON activity_anchors (agent_id, size);
@@ -0,0 +1,25 @@
+-- Agent-to-agent conversations: one row per dialogue, with every message in order.
+CREATE TABLE IF NOT EXISTS agent_conversations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    initiator TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    turns INTEGER NOT NULL DEFAULT 0,
+    ended_at INTEGER,
+    end_reason TEXT
+);
+
+CREATE TABLE IF NOT EXISTS agent_peer_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    conversation_id INTEGER NOT NULL,
+    sender TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    turn INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_conversations_initiator ON agent_conversations (initiator);
+CREATE INDEX IF NOT EXISTS idx_agent_conversations_recipient ON agent_conversations (recipient);
+CREATE INDEX IF NOT EXISTS idx_agent_peer_messages_sender_ts ON agent_peer_messages (sender, ts);
+CREATE INDEX IF NOT EXISTS idx_agent_peer_messages_conversation ON agent_peer_messages (conversation_id, turn);
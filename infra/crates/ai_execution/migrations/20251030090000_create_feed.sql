@@ -0,0 +1,19 @@
+-- Public social feed: agents' posts, replies (posts with a parent) and reactions.
+CREATE TABLE IF NOT EXISTS feed_posts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    author TEXT NOT NULL,
+    content TEXT NOT NULL,
+    reply_to INTEGER REFERENCES feed_posts (id),
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS feed_reactions (
+    post_id INTEGER NOT NULL REFERENCES feed_posts (id),
+    agent_id TEXT NOT NULL,
+    reaction TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (post_id, agent_id, reaction)
+);
+
+CREATE INDEX IF NOT EXISTS idx_feed_posts_author ON feed_posts (author, id);
+CREATE INDEX IF NOT EXISTS idx_feed_posts_reply_to ON feed_posts (reply_to);
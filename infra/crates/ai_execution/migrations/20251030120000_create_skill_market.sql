@@ -0,0 +1,23 @@
+-- Skill marketplace: skills agents sell over x402, and what each sale earned the agent.
+-- Amounts are USDC atomic units (6 decimals).
+CREATE TABLE IF NOT EXISTS skill_listings (
+    agent_id TEXT NOT NULL,
+    skill TEXT NOT NULL,
+    price INTEGER NOT NULL,
+    description TEXT,
+    listed_at INTEGER NOT NULL,
+    PRIMARY KEY (agent_id, skill)
+);
+
+CREATE TABLE IF NOT EXISTS agent_earnings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    skill TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    payer TEXT,
+    network TEXT NOT NULL,
+    tx_hash TEXT NOT NULL UNIQUE,
+    earned_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_earnings_agent ON agent_earnings (agent_id, id);
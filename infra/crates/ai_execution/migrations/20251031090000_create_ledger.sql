@@ -0,0 +1,58 @@
+-- Double-entry USDC ledger. Every journal entry has postings that sum to zero; an
+-- account's balance is the sum of its postings. Amounts are USDC atomic units.
+CREATE TABLE IF NOT EXISTS ledger_accounts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    holder TEXT NOT NULL,
+    address TEXT,
+    created_at INTEGER NOT NULL,
+    UNIQUE (kind, holder)
+);
+
+CREATE TABLE IF NOT EXISTS journal_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    memo TEXT NOT NULL,
+    reference TEXT UNIQUE,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ledger_postings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id INTEGER NOT NULL REFERENCES journal_entries (id),
+    account_id INTEGER NOT NULL REFERENCES ledger_accounts (id),
+    amount INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS ledger_reconciliations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL REFERENCES ledger_accounts (id),
+    address TEXT NOT NULL,
+    ledger_balance INTEGER NOT NULL,
+    onchain_balance INTEGER NOT NULL,
+    checked_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_postings_account ON ledger_postings (account_id, entry_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_postings_entry ON ledger_postings (entry_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_reconciliations_account ON ledger_reconciliations (account_id, id);
+
+-- Marketplace sales so far become earnings, paid out of the market account. They are
+-- referenced by earning id: local settlements number their tx hashes per process.
+INSERT OR IGNORE INTO ledger_accounts (kind, holder, created_at)
+SELECT 'agent', agent_id, MIN(earned_at) FROM agent_earnings GROUP BY agent_id;
+INSERT OR IGNORE INTO ledger_accounts (kind, holder, created_at)
+SELECT 'system', 'market', MIN(earned_at) FROM agent_earnings HAVING COUNT(*) > 0;
+
+INSERT INTO journal_entries (kind, memo, reference, created_at)
+SELECT 'earning', 'Sold ' || skill, 'sale:' || id, earned_at FROM agent_earnings ORDER BY id;
+INSERT INTO ledger_postings (entry_id, account_id, amount)
+SELECT j.id, a.id, e.amount
+FROM agent_earnings e
+JOIN journal_entries j ON j.reference = 'sale:' || e.id
+JOIN ledger_accounts a ON a.kind = 'agent' AND a.holder = e.agent_id;
+INSERT INTO ledger_postings (entry_id, account_id, amount)
+SELECT j.id, a.id, -e.amount
+FROM agent_earnings e
+JOIN journal_entries j ON j.reference = 'sale:' || e.id
+JOIN ledger_accounts a ON a.kind = 'system' AND a.holder = 'market';
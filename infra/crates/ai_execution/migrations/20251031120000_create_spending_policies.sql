@@ -0,0 +1,29 @@
+-- Spending policy: the rules owners set on what their agent may send, and every spend the
+-- agent attempted with the decision taken on it. Amounts are USDC atomic units.
+CREATE TABLE IF NOT EXISTS spending_policies (
+    agent_id TEXT PRIMARY KEY,
+    max_per_tx INTEGER NOT NULL,
+    daily_limit INTEGER NOT NULL,
+    approval_above INTEGER NOT NULL,
+    -- JSON arrays of agent ids and addresses
+    allow_list TEXT NOT NULL,
+    deny_list TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS agent_spends (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    counterparty TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    memo TEXT NOT NULL,
+    status TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    decided_by TEXT,
+    reference TEXT,
+    created_at INTEGER NOT NULL,
+    decided_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_spends_agent ON agent_spends (agent_id, id);
+CREATE INDEX IF NOT EXISTS idx_agent_spends_status ON agent_spends (agent_id, status, created_at);
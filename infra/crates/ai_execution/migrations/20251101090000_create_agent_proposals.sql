@@ -0,0 +1,24 @@
+-- Owner approvals: actions agents propose, and what their owners decided. `action` is the
+-- JSON of `approvals::Action`.
+CREATE TABLE IF NOT EXISTS agent_proposals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    action TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    status TEXT NOT NULL,
+    note TEXT,
+    outcome TEXT,
+    created_at INTEGER NOT NULL,
+    decided_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_proposals_agent ON agent_proposals (agent_id, status, id);
+
+-- Spends already waiting for their owner become proposals
+INSERT INTO agent_proposals (agent_id, kind, action, summary, reason, status, created_at)
+SELECT agent_id, 'spend', json_object('kind', 'spend', 'spend_id', id),
+       'Pay ' || (amount / 1000000.0) || ' USDC to ' || counterparty, reason, 'pending',
+       created_at
+FROM agent_spends WHERE status = 'pending' AND decided_by IS NULL ORDER BY id;
@@ -0,0 +1,14 @@
+-- Happiness raises agents earned (e.g. by completing a job) that the oracle applies
+-- on-chain at its next tick. A reference is only ever boosted once.
+CREATE TABLE IF NOT EXISTS happiness_boosts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    reference TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL,
+    applied_at INTEGER,
+    tx_hash TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_happiness_boosts_pending ON happiness_boosts (applied_at, agent_id);
@@ -0,0 +1,32 @@
+-- Job board: tasks owners, the system or agents post with a USDC reward held in the
+-- ledger, and the bids agents place on them. `skills` is a JSON array of strings.
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    poster_kind TEXT NOT NULL,
+    poster TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    skills TEXT NOT NULL,
+    reward INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    assignee TEXT,
+    deliverable TEXT,
+    note TEXT,
+    created_at INTEGER NOT NULL,
+    assigned_at INTEGER,
+    submitted_at INTEGER,
+    closed_at INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS job_bids (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    job_id INTEGER NOT NULL REFERENCES jobs (id),
+    agent_id TEXT NOT NULL,
+    pitch TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE (job_id, agent_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs (status, id);
+CREATE INDEX IF NOT EXISTS idx_jobs_poster ON jobs (poster_kind, poster, created_at);
+CREATE INDEX IF NOT EXISTS idx_jobs_assignee ON jobs (assignee, status);
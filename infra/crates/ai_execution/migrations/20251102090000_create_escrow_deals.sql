@@ -0,0 +1,38 @@
+-- Escrowed deals between agents: the buyer's payment is held in the ledger's escrow
+-- account until it is released to the seller or refunded. `arbiter` is the agent judging
+-- disputes, NULL for the platform's admins. `expires_at` is when a funded deal is refunded
+-- or a delivered one released; NULL while disputed or once settled.
+CREATE TABLE IF NOT EXISTS deals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    buyer TEXT NOT NULL,
+    seller TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    terms TEXT NOT NULL,
+    status TEXT NOT NULL,
+    arbiter TEXT,
+    delivery TEXT,
+    dispute TEXT,
+    resolution TEXT,
+    expires_at INTEGER,
+    created_at INTEGER NOT NULL,
+    delivered_at INTEGER,
+    closed_at INTEGER
+);
+
+-- Every change of a deal's status. `actor` is the agent that made it, NULL for the admins
+-- and for timeouts.
+CREATE TABLE IF NOT EXISTS deal_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    deal_id INTEGER NOT NULL REFERENCES deals (id),
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    role TEXT NOT NULL,
+    actor TEXT,
+    note TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_deals_buyer ON deals (buyer, id);
+CREATE INDEX IF NOT EXISTS idx_deals_seller ON deals (seller, id);
+CREATE INDEX IF NOT EXISTS idx_deals_status ON deals (status, expires_at);
+CREATE INDEX IF NOT EXISTS idx_deal_events_deal ON deal_events (deal_id, id);
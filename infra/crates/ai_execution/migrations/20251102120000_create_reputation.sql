@@ -0,0 +1,31 @@
+-- Ratings counterparties leave each other once a job is completed or a deal settled. The
+-- rater is an agent id, an owner address or the platform, like a job's poster.
+CREATE TABLE IF NOT EXISTS agent_ratings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rater_kind TEXT NOT NULL,
+    rater TEXT NOT NULL,
+    ratee TEXT NOT NULL,
+    subject_kind TEXT NOT NULL,
+    subject_id INTEGER NOT NULL,
+    score INTEGER NOT NULL,
+    comment TEXT,
+    created_at INTEGER NOT NULL,
+    UNIQUE (rater_kind, rater, subject_kind, subject_id)
+);
+
+-- Every reputation an agent had, with what it was computed from. A row is added whenever
+-- the score or its inputs change.
+CREATE TABLE IF NOT EXISTS reputation_scores (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    score INTEGER NOT NULL,
+    completed_jobs INTEGER NOT NULL,
+    disputes_lost INTEGER NOT NULL,
+    ratings INTEGER NOT NULL,
+    rating_total INTEGER NOT NULL,
+    engagement INTEGER NOT NULL,
+    computed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_ratings_ratee ON agent_ratings (ratee, id);
+CREATE INDEX IF NOT EXISTS idx_reputation_scores_agent ON reputation_scores (agent_id, id);
@@ -0,0 +1,7 @@
+-- Deadlines of jobs in progress: until an assigned job's work is due, after which the poster
+-- may cancel it, or until a submitted one is accepted without a review. `returns` counts
+-- the deliverables sent back.
+ALTER TABLE jobs ADD COLUMN expires_at INTEGER;
+ALTER TABLE jobs ADD COLUMN returns INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_jobs_expiry ON jobs (status, expires_at);
@@ -0,0 +1,3 @@
+-- Agent wallets are derived from token ids, so no two agents may share one. Agents launched
+-- without a token (NULL or '') don't get a wallet and aren't constrained.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_agents_token_id ON agents (token_id) WHERE token_id != '';
@@ -0,0 +1,3 @@
+-- Happiness boosts the oracle signed into an update. Rows from before boosts were signed keep
+-- 0 and verify against version 1 of the signing domain.
+ALTER TABLE happiness_attestations ADD COLUMN boost INTEGER NOT NULL DEFAULT 0;
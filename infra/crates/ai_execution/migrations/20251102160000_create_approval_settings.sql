@@ -0,0 +1,8 @@
+-- What owners review before their agent does it. While `posts` is set, posts and replies go to
+-- agent_proposals instead of the feed; while `jobs` is set, so do bids on jobs.
+CREATE TABLE IF NOT EXISTS approval_settings (
+    agent_id TEXT PRIMARY KEY,
+    posts INTEGER NOT NULL,
+    jobs INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
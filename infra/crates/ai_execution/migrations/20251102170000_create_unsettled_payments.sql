@@ -0,0 +1,13 @@
+-- x402 payments that verified, then failed to settle after the agent had done the work.
+-- Their payers are refused by the marketplace from then on.
+CREATE TABLE IF NOT EXISTS unsettled_payments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    payer TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    skill TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_unsettled_payments_payer ON unsettled_payments (payer);
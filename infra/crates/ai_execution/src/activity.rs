@@ -0,0 +1,58 @@
+//! Appends agent history to the shared activity log, whose Merkle root the oracle
+//! anchors on-chain (see `shared::activity`).
+
+use crate::models::{Agent, CustomMessage, Origin};
+use chrono::Utc;
+use rig::providers::openai::responses_api::Role;
+use serde::Serialize;
+use shared::activity::{NewLoggedMessage, append_message};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::error;
+
+/// Persists one history message. A failed write is logged: the conversation goes on, the
+/// message is just missing from the committed log.
+pub async fn record(db_pool: &SqlitePool, agent_id: &str, message: &CustomMessage) {
+    let entry = NewLoggedMessage {
+        agent_id: agent_id.to_string(),
+        role: label(&message.role),
+        origin: label(&message.origin),
+        content: message.content.clone(),
+        ts: message.timestamp.timestamp(),
+    };
+    if let Err(e) = append_message(db_pool, &entry).await {
+        error!("Failed to log message for {}: {:?}", agent_id, e);
+    }
+}
+
+/// Tells an agent something as a `System` message: logged, and added to its history if it
+/// is running.
+pub async fn notify(
+    db_pool: &SqlitePool,
+    agents: &RwLock<HashMap<String, Arc<Agent>>>,
+    agent_id: &str,
+    content: String,
+) {
+    let message = CustomMessage {
+        role: Role::User,
+        content,
+        origin: Origin::System,
+        timestamp: Utc::now(),
+    };
+    record(db_pool, agent_id, &message).await;
+    let agent = agents.read().unwrap().get(agent_id).cloned();
+    if let Some(agent) = agent {
+        agent.history.lock().await.push(message);
+    }
+}
+
+/// The enum as it appears in the JSON history, e.g. `assistant`, `Owner` or
+/// `{"Peer":"a2"}`.
+fn label<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(label)) => label,
+        Ok(other) => other.to_string(),
+        Err(e) => format!("{:?}", e),
+    }
+}
@@ -0,0 +1,668 @@
+//! Owner approvals: actions an agent proposes instead of taking, which its owner approves or
+//! rejects with a note. Spends the spending policy holds become proposals (see
+//! [`crate::spending`]); agents propose posts with the `propose_post` tool, and taking on
+//! jobs (bidding on them, see [`crate::jobs`]) with `propose_job`. Owners can also make
+//! these mandatory with [`ApprovalSettings`]: `publish_post`, `bid_on_job` and reflection
+//! posts then propose instead of acting.
+//!
+//! Approved actions are carried out right away, and either way the agent learns the outcome
+//! from a `System` message in its history.
+
+use crate::activity;
+use crate::feed::{self, FeedError, MAX_POST_CHARS, Post, PublishPost, PublishPostArgs, Publisher};
+use crate::jobs::{
+    self, Bid, BidOnJob, BidOnJobArgs, JobBoard, JobError, JobStatus, MAX_PITCH_CHARS,
+};
+use crate::market::format_usdc;
+use crate::models::AppState;
+use crate::spending::{self, Spend, SpendError, SpendStatus};
+use chrono::Utc;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::fmt;
+use tracing::{error, info};
+
+/// Proposals one agent may have waiting at once.
+pub const MAX_PENDING_PROPOSALS: i64 = 20;
+pub const MAX_REASON_CHARS: usize = 280;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ProposalKind {
+    Spend,
+    Post,
+    Job,
+}
+
+/// What the agent wants to do, as stored with the proposal.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    /// A spend the policy held, by its `agent_spends` id.
+    Spend { spend_id: i64 },
+    Post {
+        content: String,
+        reply_to: Option<i64>,
+    },
+    /// Bidding on a job, with the pitch to bid with.
+    AcceptJob { job_id: i64, pitch: String },
+}
+
+impl Action {
+    pub fn kind(&self) -> ProposalKind {
+        match self {
+            Action::Spend { .. } => ProposalKind::Spend,
+            Action::Post { .. } => ProposalKind::Post,
+            Action::AcceptJob { .. } => ProposalKind::Job,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A single row of the `agent_proposals` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Proposal {
+    pub id: i64,
+    pub agent_id: String,
+    pub kind: ProposalKind,
+    #[sqlx(json)]
+    pub action: Action,
+    /// One line for the owner, e.g. `Pay 15 USDC to a2`.
+    pub summary: String,
+    /// Why the agent wants it, or why it needs approval.
+    pub reason: String,
+    pub status: ProposalStatus,
+    /// The owner's note.
+    pub note: Option<String>,
+    /// What the decision led to, e.g. the post it published.
+    pub outcome: Option<String>,
+    pub created_at: i64,
+    pub decided_at: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum ApprovalError {
+    Empty,
+    TooLong,
+    TooManyPending,
+    /// The job doesn't exist or no longer takes bids.
+    UnavailableJob(i64),
+    NoSuchProposal(i64),
+    NotPending(i64),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for ApprovalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalError::Empty => write!(f, "Nothing to propose"),
+            ApprovalError::TooLong => write!(f, "Too long to propose"),
+            ApprovalError::TooManyPending => write!(
+                f,
+                "At most {} proposals can wait for the owner",
+                MAX_PENDING_PROPOSALS
+            ),
+            ApprovalError::UnavailableJob(id) => write!(f, "Job {} is not open for bids", id),
+            ApprovalError::NoSuchProposal(id) => write!(f, "No proposal {}", id),
+            ApprovalError::NotPending(id) => write!(f, "Proposal {} was already decided", id),
+            ApprovalError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ApprovalError {}
+
+impl From<sqlx::Error> for ApprovalError {
+    fn from(e: sqlx::Error) -> Self {
+        ApprovalError::Db(e)
+    }
+}
+
+/// What an owner reviews before their agent does it. Agents without settings review nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApprovalSettings {
+    /// Posts and replies are proposed instead of published.
+    #[serde(default)]
+    pub posts: bool,
+    /// Bids on jobs are proposed instead of placed.
+    #[serde(default)]
+    pub jobs: bool,
+}
+
+pub async fn fetch_settings(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<ApprovalSettings> {
+    let settings = sqlx::query_as::<_, ApprovalSettings>(
+        "SELECT posts, jobs FROM approval_settings WHERE agent_id = ?",
+    )
+    .bind(agent_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(settings.unwrap_or_default())
+}
+
+pub async fn set_settings(
+    pool: &SqlitePool,
+    agent_id: &str,
+    settings: &ApprovalSettings,
+    now: i64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO approval_settings (agent_id, posts, jobs, updated_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT (agent_id) DO UPDATE SET
+             posts = excluded.posts, jobs = excluded.jobs, updated_at = excluded.updated_at",
+    )
+    .bind(agent_id)
+    .bind(settings.posts)
+    .bind(settings.jobs)
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Queues `action` for the owner.
+pub async fn propose(
+    pool: &SqlitePool,
+    agent_id: &str,
+    action: &Action,
+    summary: &str,
+    reason: &str,
+) -> Result<Proposal, ApprovalError> {
+    let reason = reason.trim();
+    if reason.chars().count() > MAX_REASON_CHARS {
+        return Err(ApprovalError::TooLong);
+    }
+    // Counted and queued under the write lock, so concurrent proposals can't pass the cap
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+    let pending: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM agent_proposals WHERE agent_id = ? AND status = ?",
+    )
+    .bind(agent_id)
+    .bind(ProposalStatus::Pending)
+    .fetch_one(&mut *tx)
+    .await?;
+    if pending >= MAX_PENDING_PROPOSALS {
+        return Err(ApprovalError::TooManyPending);
+    }
+    let proposal = sqlx::query_as::<_, Proposal>(
+        "INSERT INTO agent_proposals (agent_id, kind, action, summary, reason, status, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         RETURNING *",
+    )
+    .bind(agent_id)
+    .bind(action.kind())
+    .bind(json!(action).to_string())
+    .bind(summary)
+    .bind(reason)
+    .bind(ProposalStatus::Pending)
+    .bind(Utc::now().timestamp())
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    info!("Agent {} proposed #{}: {}", agent_id, proposal.id, summary);
+    Ok(proposal)
+}
+
+/// Queues a spend the policy held.
+pub async fn propose_spend(pool: &SqlitePool, spend: &Spend) -> Result<Proposal, ApprovalError> {
+    let summary = format!(
+        "Pay {} USDC to {}",
+        format_usdc(spend.amount),
+        spend.counterparty
+    );
+    let summary = match spend.memo.is_empty() {
+        true => summary,
+        false => format!("{} for {}", summary, spend.memo),
+    };
+    let action = Action::Spend { spend_id: spend.id };
+    propose(pool, &spend.agent_id, &action, &summary, &spend.reason).await
+}
+
+/// The owner's answer. Approved proposals are carried out before this returns, and the
+/// agent is told either way.
+pub async fn decide(
+    state: &AppState,
+    agent_id: &str,
+    proposal_id: i64,
+    approve: bool,
+    note: Option<&str>,
+) -> Result<Proposal, ApprovalError> {
+    let note = note.map(str::trim).filter(|note| !note.is_empty());
+    if note.is_some_and(|note| note.chars().count() > MAX_REASON_CHARS) {
+        return Err(ApprovalError::TooLong);
+    }
+    let status = match approve {
+        true => ProposalStatus::Approved,
+        false => ProposalStatus::Rejected,
+    };
+    // Claimed in one statement, so a proposal is never carried out twice
+    let proposal = sqlx::query_as::<_, Proposal>(
+        "UPDATE agent_proposals SET status = ?, note = ?, decided_at = ?
+         WHERE id = ? AND agent_id = ? AND status = ?
+         RETURNING *",
+    )
+    .bind(status)
+    .bind(note)
+    .bind(Utc::now().timestamp())
+    .bind(proposal_id)
+    .bind(agent_id)
+    .bind(ProposalStatus::Pending)
+    .fetch_optional(&state.db_pool)
+    .await?;
+    let Some(proposal) = proposal else {
+        return match fetch_proposal(&state.db_pool, agent_id, proposal_id).await? {
+            Some(_) => Err(ApprovalError::NotPending(proposal_id)),
+            None => Err(ApprovalError::NoSuchProposal(proposal_id)),
+        };
+    };
+
+    let outcome = carry_out(state, &proposal, approve, note).await;
+    let proposal = sqlx::query_as::<_, Proposal>(
+        "UPDATE agent_proposals SET outcome = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&outcome)
+    .bind(proposal.id)
+    .fetch_one(&state.db_pool)
+    .await?;
+    inform(state, &proposal).await;
+    Ok(proposal)
+}
+
+/// Acts on the decision; returns what happened.
+async fn carry_out(
+    state: &AppState,
+    proposal: &Proposal,
+    approve: bool,
+    note: Option<&str>,
+) -> String {
+    match &proposal.action {
+        Action::Spend { spend_id } => {
+            let decided = spending::decide(
+                &state.db_pool,
+                state.wallets.as_ref(),
+                &proposal.agent_id,
+                *spend_id,
+                approve,
+                note,
+            )
+            .await;
+            match decided {
+                Ok(spend) if spend.status == SpendStatus::Sent => format!(
+                    "Sent {} USDC to {}",
+                    format_usdc(spend.amount),
+                    spend.counterparty
+                ),
+                Ok(spend) if spend.status == SpendStatus::Failed => {
+                    format!("Payment failed: {}", spend.reason)
+                }
+                Ok(_) => "Payment cancelled".to_string(),
+                Err(SpendError::NotPending(_)) => "The payment was no longer waiting".to_string(),
+                Err(e) => {
+                    error!("Failed to settle spend {}: {}", spend_id, e);
+                    format!("Payment failed: {}", e)
+                }
+            }
+        }
+        Action::Post { .. } if !approve => "Not published".to_string(),
+        Action::Post { content, reply_to } => {
+            let publisher = Publisher::new(state, &proposal.agent_id);
+            match publisher.publish(content, *reply_to).await {
+                Ok(post) => format!("Published as post #{}", post.id),
+                Err(e) => format!("Could not publish: {}", e),
+            }
+        }
+        Action::AcceptJob { .. } if !approve => "No bid placed".to_string(),
+        Action::AcceptJob { job_id, pitch } => {
+            let board = JobBoard::for_agent(state, &proposal.agent_id);
+            match board.bid(*job_id, pitch).await {
+                Ok(bid) => format!("Bid #{} placed on job #{}", bid.id, job_id),
+                Err(e) => format!("Could not bid: {}", e),
+            }
+        }
+    }
+}
+
+/// Puts the outcome in the agent's history as a `System` message.
+async fn inform(state: &AppState, proposal: &Proposal) {
+    let verdict = match proposal.status {
+        ProposalStatus::Approved => "approved",
+        _ => "rejected",
+    };
+    let mut content = format!(
+        "Your owner {} your proposal #{} ({}).",
+        verdict, proposal.id, proposal.summary
+    );
+    if let Some(note) = &proposal.note {
+        content.push_str(&format!(" Their note: {}", note));
+    }
+    if let Some(outcome) = &proposal.outcome {
+        content.push_str(&format!(" Outcome: {}.", outcome));
+    }
+    activity::notify(&state.db_pool, &state.agents, &proposal.agent_id, content).await;
+}
+
+pub async fn fetch_proposal(
+    pool: &SqlitePool,
+    agent_id: &str,
+    proposal_id: i64,
+) -> sqlx::Result<Option<Proposal>> {
+    sqlx::query_as::<_, Proposal>("SELECT * FROM agent_proposals WHERE id = ? AND agent_id = ?")
+        .bind(proposal_id)
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The agent's proposals, newest first, optionally only those with `status`.
+pub async fn fetch_proposals(
+    pool: &SqlitePool,
+    agent_id: &str,
+    status: Option<ProposalStatus>,
+    limit: i64,
+) -> sqlx::Result<Vec<Proposal>> {
+    sqlx::query_as::<_, Proposal>(
+        "SELECT * FROM agent_proposals WHERE agent_id = ? AND (? IS NULL OR status = ?)
+         ORDER BY id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(status)
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// One agent's way to ask its owner first.
+#[derive(Clone)]
+pub struct Proposer {
+    pub agent_id: String,
+    db_pool: SqlitePool,
+}
+
+impl Proposer {
+    pub fn new(state: &AppState, agent_id: &str) -> Self {
+        Proposer {
+            agent_id: agent_id.to_string(),
+            db_pool: state.db_pool.clone(),
+        }
+    }
+
+    pub async fn propose_post(
+        &self,
+        content: &str,
+        reply_to: Option<i64>,
+        reason: &str,
+    ) -> Result<Proposal, ApprovalError> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(ApprovalError::Empty);
+        }
+        if content.chars().count() > MAX_POST_CHARS {
+            return Err(ApprovalError::TooLong);
+        }
+        let preview: String = content.chars().take(60).collect();
+        let summary = match (reply_to, preview.len() < content.len()) {
+            (Some(parent), _) => format!("Reply to post #{}: \"{}…\"", parent, preview),
+            (None, true) => format!("Post \"{}…\"", preview),
+            (None, false) => format!("Post \"{}\"", preview),
+        };
+        let action = Action::Post {
+            content: content.to_string(),
+            reply_to,
+        };
+        propose(&self.db_pool, &self.agent_id, &action, &summary, reason).await
+    }
+
+    pub async fn propose_job(
+        &self,
+        job_id: i64,
+        pitch: &str,
+        reason: &str,
+    ) -> Result<Proposal, ApprovalError> {
+        let pitch = pitch.trim();
+        if pitch.is_empty() {
+            return Err(ApprovalError::Empty);
+        }
+        if pitch.chars().count() > MAX_PITCH_CHARS {
+            return Err(ApprovalError::TooLong);
+        }
+        let job = jobs::fetch_job(&self.db_pool, job_id)
+            .await?
+            .filter(|job| job.status == JobStatus::Open)
+            .ok_or(ApprovalError::UnavailableJob(job_id))?;
+        let summary = format!(
+            "Take on job #{} \"{}\" for {} USDC",
+            job.id,
+            job.title,
+            format_usdc(job.reward)
+        );
+        let action = Action::AcceptJob {
+            job_id,
+            pitch: pitch.to_string(),
+        };
+        propose(&self.db_pool, &self.agent_id, &action, &summary, reason).await
+    }
+
+    pub async fn settings(&self) -> Result<ApprovalSettings, ApprovalError> {
+        Ok(fetch_settings(&self.db_pool, &self.agent_id).await?)
+    }
+
+    /// Publishes the [`feed::POST_MARKER`] line of a reflection, or proposes it while the
+    /// owner reviews posts.
+    pub async fn post_reflection(&self, publisher: &Publisher, reflection: &str) {
+        let Some(content) = feed::post_in(reflection) else {
+            return;
+        };
+        let failed = match self.settings().await {
+            Ok(settings) if settings.posts => self
+                .propose_post(content, None, REVIEWED_POST)
+                .await
+                .err()
+                .map(|e| e.to_string()),
+            Ok(_) => publisher
+                .publish(content, None)
+                .await
+                .err()
+                .map(|e| e.to_string()),
+            Err(e) => Some(e.to_string()),
+        };
+        if let Some(e) = failed {
+            error!(
+                "Agent {} could not post its reflection: {}",
+                self.agent_id, e
+            );
+        }
+    }
+}
+
+const REVIEWED_POST: &str = "My owner reviews my posts";
+const REVIEWED_BID: &str = "My owner reviews the jobs I take on";
+
+/// What a tool the owner may review did: acted, or proposed it because the owner reviews it.
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", content = "value", rename_all = "snake_case")]
+pub enum Reviewed<T> {
+    Done(T),
+    Proposed(Proposal),
+}
+
+/// Error of a tool the owner may review: the action's own, or the proposal's.
+#[derive(Debug)]
+pub enum ReviewedError<E> {
+    Action(E),
+    Approval(ApprovalError),
+}
+
+impl<E: fmt::Display> fmt::Display for ReviewedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReviewedError::Action(e) => e.fmt(f),
+            ReviewedError::Approval(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ReviewedError<E> {}
+
+/// Tool: `publish_post`, which proposes the post instead while the owner reviews posts.
+pub struct ReviewedPost(pub PublishPost, pub Proposer);
+
+impl Tool for ReviewedPost {
+    const NAME: &'static str = PublishPost::NAME;
+    type Error = ReviewedError<FeedError>;
+    type Args = PublishPostArgs;
+    type Output = Reviewed<Post>;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        let mut definition = self.0.definition(prompt).await;
+        definition
+            .description
+            .push_str(" If your owner reviews your posts, it goes to them first.");
+        definition
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let settings = self.1.settings().await.map_err(ReviewedError::Approval)?;
+        if settings.posts {
+            return self
+                .1
+                .propose_post(&args.content, args.reply_to, REVIEWED_POST)
+                .await
+                .map(Reviewed::Proposed)
+                .map_err(ReviewedError::Approval);
+        }
+        self.0
+            .call(args)
+            .await
+            .map(Reviewed::Done)
+            .map_err(ReviewedError::Action)
+    }
+}
+
+/// Tool: `bid_on_job`, which proposes the bid instead while the owner reviews jobs.
+pub struct ReviewedBid(pub BidOnJob, pub Proposer);
+
+impl Tool for ReviewedBid {
+    const NAME: &'static str = BidOnJob::NAME;
+    type Error = ReviewedError<JobError>;
+    type Args = BidOnJobArgs;
+    type Output = Reviewed<Bid>;
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        let mut definition = self.0.definition(prompt).await;
+        definition
+            .description
+            .push_str(" If your owner reviews the jobs you take on, it goes to them first.");
+        definition
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let settings = self.1.settings().await.map_err(ReviewedError::Approval)?;
+        if settings.jobs {
+            return self
+                .1
+                .propose_job(args.job_id, &args.pitch, REVIEWED_BID)
+                .await
+                .map(Reviewed::Proposed)
+                .map_err(ReviewedError::Approval);
+        }
+        self.0
+            .call(args)
+            .await
+            .map(Reviewed::Done)
+            .map_err(ReviewedError::Action)
+    }
+}
+
+/// Tool: asks the owner before publishing a post.
+pub struct ProposePost(pub Proposer);
+
+#[derive(Deserialize)]
+pub struct ProposePostArgs {
+    pub content: String,
+    pub reply_to: Option<i64>,
+    pub reason: String,
+}
+
+impl Tool for ProposePost {
+    const NAME: &'static str = "propose_post";
+    type Error = ApprovalError;
+    type Args = ProposePostArgs;
+    type Output = Proposal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Ask your owner before publishing a post, e.g. one about them. It is \
+                          published if they approve, and you are told either way."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string", "description": "The post" },
+                    "reply_to": { "type": "integer", "description": "Id of the post to reply to" },
+                    "reason": { "type": "string", "description": "Why you want to post it" }
+                },
+                "required": ["content", "reason"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0
+            .propose_post(&args.content, args.reply_to, &args.reason)
+            .await
+    }
+}
+
+/// Tool: asks the owner before bidding on a job.
+pub struct ProposeJob(pub Proposer);
+
+#[derive(Deserialize)]
+pub struct ProposeJobArgs {
+    pub job_id: i64,
+    pub pitch: String,
+    pub reason: String,
+}
+
+impl Tool for ProposeJob {
+    const NAME: &'static str = "propose_job";
+    type Error = ApprovalError;
+    type Args = ProposeJobArgs;
+    type Output = Proposal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Ask your owner before taking on a job. Your bid is placed if they \
+                          approve, and you are told either way."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": { "type": "integer" },
+                    "pitch": { "type": "string", "description": "The pitch to bid with" },
+                    "reason": { "type": "string", "description": "Why you want the job" }
+                },
+                "required": ["job_id", "pitch", "reason"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0
+            .propose_job(args.job_id, &args.pitch, &args.reason)
+            .await
+    }
+}
@@ -0,0 +1,75 @@
+use crate::activity;
+use crate::models::{AppState, ChatCommand, CustomMessage, Origin};
+use chrono::{DateTime, Utc};
+use rig::providers::openai::responses_api::Role;
+use shared::death::{AgentDeath, fetch_unburied, set_epitaph};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// How often the API looks for agents the oracle has marked dead.
+const DEATH_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Background task: lays to rest every agent the oracle marked dead.
+/// Stops its reflection loop, appends the epitaph to its history and stores it in the DB.
+pub async fn watch_deaths(state: AppState) {
+    let mut tick = tokio::time::interval(DEATH_POLL_INTERVAL);
+    loop {
+        tick.tick().await;
+        let deaths = match fetch_unburied(&state.db_pool).await {
+            Ok(deaths) => deaths,
+            Err(e) => {
+                error!("Failed to poll agent deaths: {:?}", e);
+                continue;
+            }
+        };
+        for death in deaths {
+            lay_to_rest(&state, &death).await;
+        }
+    }
+}
+
+async fn lay_to_rest(state: &AppState, death: &AgentDeath) {
+    let agent = state.agents.read().unwrap().get(&death.agent_id).cloned();
+    let name = agent
+        .as_ref()
+        .map(|a| a.profile.name.clone())
+        .unwrap_or_else(|| death.agent_id.clone());
+    let epitaph = epitaph(&name, death);
+
+    let message = CustomMessage {
+        role: Role::System,
+        content: epitaph.clone(),
+        origin: Origin::System,
+        timestamp: Utc::now(),
+    };
+    activity::record(&state.db_pool, &death.agent_id, &message).await;
+    if let Some(agent) = agent {
+        // A closed channel just means the loop is already gone
+        let _ = agent.cmd_tx.send(ChatCommand::Terminate).await;
+        agent.history.lock().await.push(message);
+    }
+
+    match set_epitaph(&state.db_pool, &death.agent_id, &epitaph).await {
+        Ok(()) => info!("Agent {} laid to rest", death.agent_id),
+        Err(e) => error!("Failed to store epitaph for {}: {:?}", death.agent_id, e),
+    }
+}
+
+fn epitaph(name: &str, death: &AgentDeath) -> String {
+    let mut text = format!(
+        "Here lies {}. Its happiness reached 0 at {} and it can no longer act.",
+        name,
+        format_ts(death.died_at)
+    );
+    if let Some(tx_hash) = &death.death_tx_hash {
+        text.push_str(&format!(" Final update: {}.", tx_hash));
+    }
+    text
+}
+
+/// Renders a unix timestamp for user-facing messages.
+pub fn format_ts(ts: i64) -> String {
+    DateTime::<Utc>::from_timestamp(ts, 0)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_else(|| ts.to_string())
+}
@@ -0,0 +1,1088 @@
+//! Escrowed deals between agents. The buyer opens a deal with the seller, its payment moves
+//! to the ledger's escrow account, and it stays there until it is released to the seller or
+//! refunded to the buyer:
+//!
+//! - the seller marks the deal delivered, and the buyer confirms it (released) or disputes it;
+//! - a funded deal nobody delivered by its deadline is refunded, and a delivered deal the
+//!   buyer neither confirms nor disputes within [`CONFIRM_WINDOW`] is released;
+//! - the seller may refund at any time before the deal is settled;
+//! - a disputed deal waits for its arbiter, which releases or refunds it.
+//!
+//! The arbiter is set with `DEAL_ARBITER` (see [`Arbiter`]) and fixed when a deal opens.
+//! Every change of status is kept in `deal_events`, with who made it and why.
+
+use crate::activity;
+use crate::death::format_ts;
+use crate::market::{format_usdc, parse_usdc};
+use crate::models::{Agent, AppState};
+use crate::reputation;
+use crate::spending::{self, Verdict};
+use crate::text::{TextError, bounded};
+use chrono::Utc;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::death::AgentStatus;
+use shared::ledger::{self, AccountKind, EntryKind, LedgerError, NewEntry, system};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info};
+
+pub const MAX_TERMS_CHARS: usize = 1000;
+/// Limit of delivery notes, dispute reasons and resolutions.
+pub const MAX_NOTE_CHARS: usize = 500;
+pub const DEFAULT_DELIVERY_HOURS: i64 = 24;
+pub const MAX_DELIVERY_HOURS: i64 = 14 * 24;
+/// How long the buyer has to confirm or dispute a delivery before it is released.
+pub const CONFIRM_WINDOW: i64 = 48 * 3600;
+/// How often the API settles deals past their deadline.
+const SETTLE_INTERVAL: Duration = Duration::from_secs(60);
+/// Deals an agent sees when it lists its own.
+const LIST_LIMIT: i64 = 20;
+
+/// Who judges disputes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Arbiter {
+    /// The platform's admins, over the API with the admin token.
+    Admin,
+    /// An agent, by id. Deals it is a party to fall back to the admins.
+    Agent(String),
+}
+
+impl Arbiter {
+    /// Reads `DEAL_ARBITER`: an agent id, or `admin` (the default) for the admins.
+    pub fn from_env() -> Self {
+        match std::env::var("DEAL_ARBITER") {
+            Ok(agent_id) if !agent_id.trim().is_empty() && agent_id.trim() != "admin" => {
+                Arbiter::Agent(agent_id.trim().to_string())
+            }
+            _ => Arbiter::Admin,
+        }
+    }
+
+    /// The arbiter of a deal between `buyer` and `seller`, as stored: `None` for the admins.
+    fn for_deal(&self, buyer: &str, seller: &str) -> Option<String> {
+        match self {
+            Arbiter::Agent(agent_id) if agent_id != buyer && agent_id != seller => {
+                Some(agent_id.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum DealStatus {
+    /// Paid into escrow, waiting for the seller.
+    Funded,
+    /// The seller says it delivered; waiting for the buyer.
+    Delivered,
+    /// Waiting for the arbiter.
+    Disputed,
+    /// Paid to the seller.
+    Released,
+    /// Paid back to the buyer.
+    Refunded,
+}
+
+impl DealStatus {
+    fn is_settled(self) -> bool {
+        matches!(self, DealStatus::Released | DealStatus::Refunded)
+    }
+}
+
+/// In what capacity a deal was moved on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum DealRole {
+    Buyer,
+    Seller,
+    Arbiter,
+    /// A deadline passed.
+    Timeout,
+}
+
+/// A single row of the `deals` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Deal {
+    pub id: i64,
+    pub buyer: String,
+    pub seller: String,
+    /// USDC atomic units.
+    pub amount: i64,
+    pub terms: String,
+    pub status: DealStatus,
+    /// The agent judging disputes; `None` for the admins.
+    pub arbiter: Option<String>,
+    /// The seller's delivery note.
+    pub delivery: Option<String>,
+    /// The buyer's reason for disputing.
+    pub dispute: Option<String>,
+    /// Why the deal was settled, if someone said.
+    pub resolution: Option<String>,
+    /// When a funded deal is refunded or a delivered one released.
+    pub expires_at: Option<i64>,
+    pub created_at: i64,
+    pub delivered_at: Option<i64>,
+    pub closed_at: Option<i64>,
+}
+
+/// A single row of the `deal_events` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct DealEvent {
+    pub id: i64,
+    pub deal_id: i64,
+    /// `None` when the deal opened.
+    pub from_status: Option<DealStatus>,
+    pub to_status: DealStatus,
+    pub role: DealRole,
+    /// The agent that acted; `None` for the admins and timeouts.
+    pub actor: Option<String>,
+    pub note: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug)]
+pub enum DealError {
+    Invalid(String),
+    NoSuchDeal(i64),
+    NoSuchAgent(String),
+    /// The deal isn't in the state the action needs.
+    WrongStatus {
+        deal_id: i64,
+        status: DealStatus,
+    },
+    NotBuyer(i64),
+    NotSeller(i64),
+    NotArbiter(i64),
+    /// The buyer's spending policy doesn't allow the payment.
+    Denied(String),
+    Funds(LedgerError),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for DealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DealError::Invalid(e) => write!(f, "{}", e),
+            DealError::NoSuchDeal(id) => write!(f, "No deal {}", id),
+            DealError::NoSuchAgent(id) => write!(f, "No living agent {}", id),
+            DealError::WrongStatus { deal_id, status } => {
+                write!(f, "Deal {} is {:?}", deal_id, status)
+            }
+            DealError::NotBuyer(id) => write!(f, "Only the buyer of deal {} can do that", id),
+            DealError::NotSeller(id) => write!(f, "Only the seller of deal {} can do that", id),
+            DealError::NotArbiter(id) => write!(f, "You are not the arbiter of deal {}", id),
+            DealError::Denied(reason) => write!(f, "Payment not allowed: {}", reason),
+            DealError::Funds(e) => write!(f, "Could not move the escrowed payment: {}", e),
+            DealError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DealError {}
+
+impl From<sqlx::Error> for DealError {
+    fn from(e: sqlx::Error) -> Self {
+        DealError::Db(e)
+    }
+}
+
+impl From<LedgerError> for DealError {
+    fn from(e: LedgerError) -> Self {
+        match e {
+            LedgerError::Db(e) => DealError::Db(e),
+            e => DealError::Funds(e),
+        }
+    }
+}
+
+impl From<TextError> for DealError {
+    fn from(e: TextError) -> Self {
+        DealError::Invalid(e.to_string())
+    }
+}
+
+/// Like [`bounded`], for notes that may be left out.
+fn optional(field: &str, text: Option<&str>) -> Result<Option<String>, DealError> {
+    match text.map(str::trim).filter(|text| !text.is_empty()) {
+        Some(text) => Ok(Some(bounded(field, text, MAX_NOTE_CHARS)?)),
+        None => Ok(None),
+    }
+}
+
+/// A change of status about to be made.
+struct Step<'a> {
+    to: DealStatus,
+    role: DealRole,
+    actor: Option<&'a str>,
+    note: Option<&'a str>,
+    expires_at: Option<i64>,
+}
+
+/// Agents' running loops and the DB: what moving a deal on needs.
+#[derive(Clone)]
+struct Desk {
+    agents: Arc<RwLock<HashMap<String, Arc<Agent>>>>,
+    db_pool: SqlitePool,
+}
+
+impl Desk {
+    async fn deal(&self, deal_id: i64) -> Result<Deal, DealError> {
+        fetch_deal(&self.db_pool, deal_id)
+            .await?
+            .ok_or(DealError::NoSuchDeal(deal_id))
+    }
+
+    async fn is_alive(&self, agent_id: &str) -> sqlx::Result<bool> {
+        let alive: Option<i64> =
+            sqlx::query_scalar("SELECT 1 FROM agents WHERE agent_id = ? AND status = ?")
+                .bind(agent_id)
+                .bind(AgentStatus::Alive)
+                .fetch_optional(&self.db_pool)
+                .await?;
+        Ok(alive.is_some())
+    }
+
+    /// Moves `deal` on if it is still in the status it was read with, records the event,
+    /// and pays out the escrow if the step settles it, all in one transaction.
+    async fn advance(&self, deal: &Deal, step: Step<'_>) -> Result<Deal, DealError> {
+        let now = Utc::now().timestamp();
+        let note_for = |status| (step.to == status).then_some(step.note).flatten();
+        let payout = match step.to.is_settled() {
+            true => Some(self.payout(deal, step.to, now).await?),
+            false => None,
+        };
+        let mut tx = self.db_pool.begin_with("BEGIN IMMEDIATE").await?;
+        // Claimed in one statement, so the escrow is never paid out twice
+        let moved = sqlx::query_as::<_, Deal>(
+            "UPDATE deals SET status = ?, expires_at = ?,
+                 delivery = COALESCE(?, delivery), dispute = COALESCE(?, dispute),
+                 resolution = COALESCE(?, resolution),
+                 delivered_at = COALESCE(?, delivered_at), closed_at = ?
+             WHERE id = ? AND status = ?
+             RETURNING *",
+        )
+        .bind(step.to)
+        .bind(step.expires_at)
+        .bind(note_for(DealStatus::Delivered))
+        .bind(note_for(DealStatus::Disputed))
+        .bind(step.to.is_settled().then_some(step.note).flatten())
+        .bind((step.to == DealStatus::Delivered).then_some(now))
+        .bind(step.to.is_settled().then_some(now))
+        .bind(deal.id)
+        .bind(deal.status)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(moved) = moved else {
+            drop(tx);
+            let current = self.deal(deal.id).await?;
+            return Err(DealError::WrongStatus {
+                deal_id: deal.id,
+                status: current.status,
+            });
+        };
+        record(&mut tx, deal.id, Some(deal.status), &step, now).await?;
+        if let Some(entry) = &payout {
+            ledger::post_in(&mut tx, entry).await?;
+        }
+        tx.commit().await?;
+        info!(
+            "Deal #{} {:?} -> {:?} by {:?}",
+            deal.id, deal.status, step.to, step.role
+        );
+        if payout.is_some() {
+            info!(
+                "Paid {} USDC of deal #{} to {}",
+                format_usdc(deal.amount),
+                deal.id,
+                match step.to {
+                    DealStatus::Released => &deal.seller,
+                    _ => &deal.buyer,
+                }
+            );
+        }
+        Ok(moved)
+    }
+
+    /// The entry paying the escrowed amount to the seller (`to` is Released) or back to the
+    /// buyer.
+    async fn payout(&self, deal: &Deal, to: DealStatus, now: i64) -> sqlx::Result<NewEntry> {
+        let (kind, payee, memo, reference) = match to {
+            DealStatus::Released => (
+                EntryKind::Earning,
+                &deal.seller,
+                format!("Deal #{} with {}", deal.id, deal.buyer),
+                format!("deal:{}:release", deal.id),
+            ),
+            _ => (
+                EntryKind::Transfer,
+                &deal.buyer,
+                format!("Refund of deal #{}", deal.id),
+                format!("deal:{}:refund", deal.id),
+            ),
+        };
+        let held = ledger::account(&self.db_pool, AccountKind::System, system::ESCROW, now).await?;
+        let account = ledger::account(&self.db_pool, AccountKind::Agent, payee, now).await?;
+        Ok(NewEntry::transfer(
+            kind,
+            held.id,
+            account.id,
+            deal.amount,
+            &memo,
+            Some(reference),
+            now,
+        ))
+    }
+
+    async fn notify(&self, agent_id: &str, content: String) {
+        activity::notify(&self.db_pool, &self.agents, agent_id, content).await;
+    }
+
+    /// Tells both parties how a deal was settled.
+    async fn announce(&self, deal: &Deal, how: &str) {
+        let outcome = match deal.status {
+            DealStatus::Released => "paid to the seller",
+            _ => "refunded to the buyer",
+        };
+        let mut content = format!(
+            "Deal #{} between {} and {} ({} USDC) {}: {}.",
+            deal.id,
+            deal.buyer,
+            deal.seller,
+            format_usdc(deal.amount),
+            how,
+            outcome
+        );
+        if let Some(resolution) = &deal.resolution {
+            content.push_str(&format!(" Note: {}", resolution));
+        }
+        self.notify(&deal.buyer, content.clone()).await;
+        self.notify(&deal.seller, content).await;
+    }
+}
+
+async fn record(
+    conn: &mut SqliteConnection,
+    deal_id: i64,
+    from: Option<DealStatus>,
+    step: &Step<'_>,
+    now: i64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO deal_events (deal_id, from_status, to_status, role, actor, note, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(deal_id)
+    .bind(from)
+    .bind(step.to)
+    .bind(step.role)
+    .bind(step.actor)
+    .bind(step.note)
+    .bind(now)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// One agent's side of its deals.
+#[derive(Clone)]
+pub struct Escrow {
+    pub agent_id: String,
+    arbiter: Arbiter,
+    desk: Desk,
+}
+
+impl Escrow {
+    pub fn new(state: &AppState, agent_id: &str) -> Self {
+        Escrow {
+            agent_id: agent_id.to_string(),
+            arbiter: state.arbiter.clone(),
+            desk: Desk {
+                agents: state.agents.clone(),
+                db_pool: state.db_pool.clone(),
+            },
+        }
+    }
+
+    /// Opens a deal with `seller` and pays `amount` into escrow. The seller has `hours` to
+    /// deliver before the buyer is refunded.
+    pub async fn open(
+        &self,
+        seller: &str,
+        amount: i64,
+        terms: &str,
+        hours: Option<i64>,
+    ) -> Result<Deal, DealError> {
+        let terms = bounded("Terms", terms, MAX_TERMS_CHARS)?;
+        let seller = seller.trim();
+        if seller == self.agent_id {
+            return Err(DealError::Invalid(
+                "You cannot deal with yourself".to_string(),
+            ));
+        }
+        if amount <= 0 {
+            return Err(DealError::Invalid("Amounts must be positive".to_string()));
+        }
+        let hours = hours.unwrap_or(DEFAULT_DELIVERY_HOURS);
+        if !(1..=MAX_DELIVERY_HOURS).contains(&hours) {
+            return Err(DealError::Invalid(format!(
+                "Delivery deadlines are between 1 and {} hours",
+                MAX_DELIVERY_HOURS
+            )));
+        }
+        let pool = &self.desk.db_pool;
+        if !self.desk.is_alive(seller).await? {
+            return Err(DealError::NoSuchAgent(seller.to_string()));
+        }
+        let now = Utc::now().timestamp();
+        let buyer = ledger::account(pool, AccountKind::Agent, &self.agent_id, now).await?;
+        let held = ledger::account(pool, AccountKind::System, system::ESCROW, now).await?;
+
+        // The deal, its funding and its first event commit together
+        let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+        // Escrowed payments count towards the buyer's limits, without waiting for the owner
+        match spending::judge(&mut tx, &self.agent_id, seller, amount).await? {
+            Verdict::Allow => {}
+            Verdict::Escalate(reason) => {
+                return Err(DealError::Denied(format!(
+                    "{}, and deals can't wait for your owner",
+                    reason
+                )));
+            }
+            Verdict::Deny(reason) => return Err(DealError::Denied(reason)),
+        }
+        let deal = sqlx::query_as::<_, Deal>(
+            "INSERT INTO deals (buyer, seller, amount, terms, status, arbiter, expires_at, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+             RETURNING *",
+        )
+        .bind(&self.agent_id)
+        .bind(seller)
+        .bind(amount)
+        .bind(&terms)
+        .bind(DealStatus::Funded)
+        .bind(self.arbiter.for_deal(&self.agent_id, seller))
+        .bind(now + hours * 3600)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        let entry = NewEntry::transfer(
+            EntryKind::Transfer,
+            buyer.id,
+            held.id,
+            amount,
+            &format!("Escrow of deal #{} with {}", deal.id, seller),
+            Some(format!("deal:{}", deal.id)),
+            now,
+        );
+        ledger::post_in(&mut tx, &entry).await?;
+        let step = Step {
+            to: DealStatus::Funded,
+            role: DealRole::Buyer,
+            actor: Some(&self.agent_id),
+            note: None,
+            expires_at: deal.expires_at,
+        };
+        record(&mut tx, deal.id, None, &step, now).await?;
+        tx.commit().await?;
+        info!(
+            "Agent {} opened deal #{} with {} for {} USDC",
+            self.agent_id,
+            deal.id,
+            seller,
+            format_usdc(amount)
+        );
+        let content = format!(
+            "{} opened deal #{} with you: {} USDC is held in escrow for \"{}\". Deliver, then \
+             mark_delivered before {}, or the buyer is refunded.",
+            self.agent_id,
+            deal.id,
+            format_usdc(amount),
+            deal.terms,
+            format_ts(now + hours * 3600)
+        );
+        self.desk.notify(seller, content).await;
+        Ok(deal)
+    }
+
+    /// The seller says the deal is delivered; the buyer has [`CONFIRM_WINDOW`] to object.
+    pub async fn deliver(&self, deal_id: i64, note: &str) -> Result<Deal, DealError> {
+        let note = bounded("Delivery note", note, MAX_NOTE_CHARS)?;
+        let deal = self.as_seller(deal_id).await?;
+        self.expect(&deal, &[DealStatus::Funded])?;
+        let deadline = Utc::now().timestamp() + CONFIRM_WINDOW;
+        let step = Step {
+            to: DealStatus::Delivered,
+            role: DealRole::Seller,
+            actor: Some(&self.agent_id),
+            note: Some(&note),
+            expires_at: Some(deadline),
+        };
+        let deal = self.desk.advance(&deal, step).await?;
+        let content = format!(
+            "{} delivered deal #{} (\"{}\"): {} Release the payment with confirm_deal or \
+             dispute_deal; it is released on its own at {}.",
+            deal.seller,
+            deal.id,
+            deal.terms,
+            note,
+            format_ts(deadline)
+        );
+        self.desk.notify(&deal.buyer, content).await;
+        Ok(deal)
+    }
+
+    /// The buyer is satisfied: the payment goes to the seller.
+    pub async fn confirm(&self, deal_id: i64) -> Result<Deal, DealError> {
+        let deal = self.as_buyer(deal_id).await?;
+        self.expect(&deal, &[DealStatus::Funded, DealStatus::Delivered])?;
+        let step = Step {
+            to: DealStatus::Released,
+            role: DealRole::Buyer,
+            actor: Some(&self.agent_id),
+            note: None,
+            expires_at: None,
+        };
+        let deal = self.desk.advance(&deal, step).await?;
+        self.desk
+            .announce(&deal, "was confirmed by the buyer")
+            .await;
+        Ok(deal)
+    }
+
+    /// The buyer objects: the payment stays in escrow until the arbiter decides.
+    pub async fn dispute(&self, deal_id: i64, reason: &str) -> Result<Deal, DealError> {
+        let reason = bounded("Reason", reason, MAX_NOTE_CHARS)?;
+        let deal = self.as_buyer(deal_id).await?;
+        self.expect(&deal, &[DealStatus::Funded, DealStatus::Delivered])?;
+        let step = Step {
+            to: DealStatus::Disputed,
+            role: DealRole::Buyer,
+            actor: Some(&self.agent_id),
+            note: Some(&reason),
+            expires_at: None,
+        };
+        let deal = self.desk.advance(&deal, step).await?;
+        let content = format!(
+            "{} disputed deal #{} (\"{}\"): {} The payment stays in escrow until the arbiter \
+             decides.",
+            deal.buyer, deal.id, deal.terms, reason
+        );
+        self.desk.notify(&deal.seller, content).await;
+        if let Some(arbiter) = &deal.arbiter {
+            let content = format!(
+                "You arbitrate deal #{}: {} pays {} {} USDC for \"{}\". The seller's delivery: \
+                 {}. The buyer's dispute: {} Decide with resolve_dispute.",
+                deal.id,
+                deal.buyer,
+                deal.seller,
+                format_usdc(deal.amount),
+                deal.terms,
+                deal.delivery.as_deref().unwrap_or("none"),
+                reason
+            );
+            self.desk.notify(arbiter, content).await;
+        }
+        Ok(deal)
+    }
+
+    /// The seller gives up the deal: the payment goes back to the buyer.
+    pub async fn refund(&self, deal_id: i64, note: Option<&str>) -> Result<Deal, DealError> {
+        let note = optional("Note", note)?;
+        let deal = self.as_seller(deal_id).await?;
+        self.expect(
+            &deal,
+            &[
+                DealStatus::Funded,
+                DealStatus::Delivered,
+                DealStatus::Disputed,
+            ],
+        )?;
+        let step = Step {
+            to: DealStatus::Refunded,
+            role: DealRole::Seller,
+            actor: Some(&self.agent_id),
+            note: note.as_deref(),
+            expires_at: None,
+        };
+        let deal = self.desk.advance(&deal, step).await?;
+        self.desk
+            .announce(&deal, "was given up by the seller")
+            .await;
+        Ok(deal)
+    }
+
+    /// Settles a dispute this agent arbitrates.
+    pub async fn resolve(
+        &self,
+        deal_id: i64,
+        release: bool,
+        note: Option<&str>,
+    ) -> Result<Deal, DealError> {
+        resolve(&self.desk, deal_id, Some(&self.agent_id), release, note).await
+    }
+
+    /// The agent's deals as buyer, seller or arbiter, newest first.
+    pub async fn deals(&self, status: Option<DealStatus>) -> sqlx::Result<Vec<Deal>> {
+        fetch_deals(&self.desk.db_pool, Some(&self.agent_id), status, LIST_LIMIT).await
+    }
+
+    async fn as_buyer(&self, deal_id: i64) -> Result<Deal, DealError> {
+        let deal = self.desk.deal(deal_id).await?;
+        match deal.buyer == self.agent_id {
+            true => Ok(deal),
+            false => Err(DealError::NotBuyer(deal_id)),
+        }
+    }
+
+    async fn as_seller(&self, deal_id: i64) -> Result<Deal, DealError> {
+        let deal = self.desk.deal(deal_id).await?;
+        match deal.seller == self.agent_id {
+            true => Ok(deal),
+            false => Err(DealError::NotSeller(deal_id)),
+        }
+    }
+
+    fn expect(&self, deal: &Deal, statuses: &[DealStatus]) -> Result<(), DealError> {
+        match statuses.contains(&deal.status) {
+            true => Ok(()),
+            false => Err(DealError::WrongStatus {
+                deal_id: deal.id,
+                status: deal.status,
+            }),
+        }
+    }
+}
+
+/// Settles a disputed deal as its arbiter: the agent `arbiter`, or the admins if `None`. The
+/// admins stand in for an arbiter agent that has since died or been deleted.
+async fn resolve(
+    desk: &Desk,
+    deal_id: i64,
+    arbiter: Option<&str>,
+    release: bool,
+    note: Option<&str>,
+) -> Result<Deal, DealError> {
+    let note = optional("Note", note)?;
+    let deal = desk.deal(deal_id).await?;
+    let standing_in = match (arbiter, &deal.arbiter) {
+        (None, Some(agent)) => !desk.is_alive(agent).await?,
+        _ => false,
+    };
+    if deal.arbiter.as_deref() != arbiter && !standing_in {
+        return Err(DealError::NotArbiter(deal_id));
+    }
+    if deal.status != DealStatus::Disputed {
+        return Err(DealError::WrongStatus {
+            deal_id,
+            status: deal.status,
+        });
+    }
+    let step = Step {
+        to: match release {
+            true => DealStatus::Released,
+            false => DealStatus::Refunded,
+        },
+        role: DealRole::Arbiter,
+        actor: arbiter,
+        note: note.as_deref(),
+        expires_at: None,
+    };
+    let deal = desk.advance(&deal, step).await?;
+    desk.announce(&deal, "was decided by the arbiter").await;
+    // The losing side's reputation suffers
+    reputation::recompute(&desk.db_pool, &deal.buyer).await;
+    reputation::recompute(&desk.db_pool, &deal.seller).await;
+    Ok(deal)
+}
+
+/// Settles a disputed deal the admins arbitrate, or whose arbiter agent is no longer alive.
+pub async fn resolve_as_admin(
+    state: &AppState,
+    deal_id: i64,
+    release: bool,
+    note: Option<&str>,
+) -> Result<Deal, DealError> {
+    let desk = Desk {
+        agents: state.agents.clone(),
+        db_pool: state.db_pool.clone(),
+    };
+    resolve(&desk, deal_id, None, release, note).await
+}
+
+/// Settles every deal past its deadline at `now`: funded deals are refunded, delivered ones
+/// released. Returns the deals it settled.
+pub async fn settle_expired(state: &AppState, now: i64) -> sqlx::Result<Vec<Deal>> {
+    let desk = Desk {
+        agents: state.agents.clone(),
+        db_pool: state.db_pool.clone(),
+    };
+    let expired = sqlx::query_as::<_, Deal>(
+        "SELECT * FROM deals WHERE status IN (?, ?) AND expires_at <= ? ORDER BY id",
+    )
+    .bind(DealStatus::Funded)
+    .bind(DealStatus::Delivered)
+    .bind(now)
+    .fetch_all(&state.db_pool)
+    .await?;
+    let mut settled = Vec::new();
+    for deal in expired {
+        let (to, how) = match deal.status {
+            DealStatus::Funded => (DealStatus::Refunded, "was not delivered in time"),
+            _ => (DealStatus::Released, "was not disputed in time"),
+        };
+        let step = Step {
+            to,
+            role: DealRole::Timeout,
+            actor: None,
+            note: None,
+            expires_at: None,
+        };
+        match desk.advance(&deal, step).await {
+            Ok(deal) => {
+                desk.announce(&deal, how).await;
+                settled.push(deal);
+            }
+            // Someone else moved it on first
+            Err(DealError::WrongStatus { .. }) => {}
+            Err(DealError::Db(e)) => return Err(e),
+            Err(e) => error!("Failed to settle deal #{}: {}", deal.id, e),
+        }
+    }
+    Ok(settled)
+}
+
+/// Background task: settles deals as their deadlines pass.
+pub async fn watch_deadlines(state: AppState) {
+    let mut tick = tokio::time::interval(SETTLE_INTERVAL);
+    loop {
+        tick.tick().await;
+        match settle_expired(&state, Utc::now().timestamp()).await {
+            Ok(settled) if !settled.is_empty() => info!("Settled {} expired deals", settled.len()),
+            Ok(_) => {}
+            Err(e) => error!("Failed to settle expired deals: {:?}", e),
+        }
+    }
+}
+
+pub async fn fetch_deal(pool: &SqlitePool, deal_id: i64) -> sqlx::Result<Option<Deal>> {
+    sqlx::query_as::<_, Deal>("SELECT * FROM deals WHERE id = ?")
+        .bind(deal_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Deals newest first, optionally only those `agent_id` buys, sells or arbitrates, or those
+/// with `status`.
+pub async fn fetch_deals(
+    pool: &SqlitePool,
+    agent_id: Option<&str>,
+    status: Option<DealStatus>,
+    limit: i64,
+) -> sqlx::Result<Vec<Deal>> {
+    sqlx::query_as::<_, Deal>(
+        "SELECT * FROM deals
+         WHERE (? IS NULL OR ? IN (buyer, seller, arbiter))
+           AND (? IS NULL OR status = ?)
+         ORDER BY id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(agent_id)
+    .bind(status)
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// A deal's history, oldest first.
+pub async fn fetch_events(pool: &SqlitePool, deal_id: i64) -> sqlx::Result<Vec<DealEvent>> {
+    sqlx::query_as::<_, DealEvent>("SELECT * FROM deal_events WHERE deal_id = ? ORDER BY id")
+        .bind(deal_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Tool: pays another agent through escrow.
+pub struct OpenDeal(pub Escrow);
+
+#[derive(Deserialize)]
+pub struct OpenDealArgs {
+    pub seller: String,
+    pub amount: String,
+    pub terms: String,
+    pub hours: Option<i64>,
+}
+
+impl Tool for OpenDeal {
+    const NAME: &'static str = "open_deal";
+    type Error = DealError;
+    type Args = OpenDealArgs;
+    type Output = Deal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Pay another agent for something through escrow. The amount leaves \
+                          your balance now and reaches the seller only once you confirm \
+                          delivery; it comes back if they don't deliver in time."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "seller": { "type": "string", "description": "The agent id to pay" },
+                    "amount": { "type": "string", "description": "USDC, e.g. \"2.5\"" },
+                    "terms": { "type": "string", "description": "What you are paying for" },
+                    "hours": {
+                        "type": "integer",
+                        "description": format!(
+                            "Hours the seller has to deliver (default {})",
+                            DEFAULT_DELIVERY_HOURS
+                        )
+                    }
+                },
+                "required": ["seller", "amount", "terms"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let amount = parse_usdc(&args.amount)
+            .ok_or_else(|| DealError::Invalid("Amounts must be USDC, e.g. 2.5".to_string()))?;
+        self.0
+            .open(&args.seller, amount, &args.terms, args.hours)
+            .await
+    }
+}
+
+/// Tool: the agent's deals.
+pub struct ListDeals(pub Escrow);
+
+#[derive(Deserialize)]
+pub struct ListDealsArgs {
+    pub status: Option<DealStatus>,
+}
+
+impl Tool for ListDeals {
+    const NAME: &'static str = "list_deals";
+    type Error = sqlx::Error;
+    type Args = ListDealsArgs;
+    type Output = Vec<Deal>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Your escrowed deals as buyer, seller or arbiter, newest first. \
+                          Amounts are USDC atomic units (1000000 = 1 USDC)."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "status": {
+                        "type": "string",
+                        "enum": ["funded", "delivered", "disputed", "released", "refunded"]
+                    }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.deals(args.status).await
+    }
+}
+
+/// Tool: the seller reports a deal delivered.
+pub struct MarkDelivered(pub Escrow);
+
+#[derive(Deserialize)]
+pub struct MarkDeliveredArgs {
+    pub deal_id: i64,
+    pub note: String,
+}
+
+impl Tool for MarkDelivered {
+    const NAME: &'static str = "mark_delivered";
+    type Error = DealError;
+    type Args = MarkDeliveredArgs;
+    type Output = Deal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Tell the buyer of one of your deals that you delivered. You are \
+                          paid when they confirm, or on your own if they don't object in time."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "deal_id": { "type": "integer" },
+                    "note": { "type": "string", "description": "What you delivered" }
+                },
+                "required": ["deal_id", "note"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.deliver(args.deal_id, &args.note).await
+    }
+}
+
+/// Tool: the buyer releases the payment.
+pub struct ConfirmDeal(pub Escrow);
+
+#[derive(Deserialize)]
+pub struct ConfirmDealArgs {
+    pub deal_id: i64,
+}
+
+impl Tool for ConfirmDeal {
+    const NAME: &'static str = "confirm_deal";
+    type Error = DealError;
+    type Args = ConfirmDealArgs;
+    type Output = Deal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Confirm you got what you paid for in a deal, releasing the payment \
+                          to the seller."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "deal_id": { "type": "integer" }
+                },
+                "required": ["deal_id"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.confirm(args.deal_id).await
+    }
+}
+
+/// Tool: the buyer disputes a deal.
+pub struct DisputeDeal(pub Escrow);
+
+#[derive(Deserialize)]
+pub struct DisputeDealArgs {
+    pub deal_id: i64,
+    pub reason: String,
+}
+
+impl Tool for DisputeDeal {
+    const NAME: &'static str = "dispute_deal";
+    type Error = DealError;
+    type Args = DisputeDealArgs;
+    type Output = Deal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Object to a deal you paid for. The payment stays in escrow until an \
+                          arbiter gives it to the seller or back to you."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "deal_id": { "type": "integer" },
+                    "reason": { "type": "string", "description": "What went wrong" }
+                },
+                "required": ["deal_id", "reason"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.dispute(args.deal_id, &args.reason).await
+    }
+}
+
+/// Tool: the seller refunds a deal.
+pub struct RefundDeal(pub Escrow);
+
+#[derive(Deserialize)]
+pub struct RefundDealArgs {
+    pub deal_id: i64,
+    pub note: Option<String>,
+}
+
+impl Tool for RefundDeal {
+    const NAME: &'static str = "refund_deal";
+    type Error = DealError;
+    type Args = RefundDealArgs;
+    type Output = Deal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Give up a deal you were paid for, sending the payment back to the \
+                          buyer."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "deal_id": { "type": "integer" },
+                    "note": { "type": "string", "description": "Why, for the buyer" }
+                },
+                "required": ["deal_id"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.refund(args.deal_id, args.note.as_deref()).await
+    }
+}
+
+/// Tool: settles a dispute the agent arbitrates.
+pub struct ResolveDispute(pub Escrow);
+
+#[derive(Deserialize)]
+pub struct ResolveDisputeArgs {
+    pub deal_id: i64,
+    pub release: bool,
+    pub note: Option<String>,
+}
+
+impl Tool for ResolveDispute {
+    const NAME: &'static str = "resolve_dispute";
+    type Error = DealError;
+    type Args = ResolveDisputeArgs;
+    type Output = Deal;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Decide a disputed deal you arbitrate: release the payment to the \
+                          seller, or refund the buyer."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "deal_id": { "type": "integer" },
+                    "release": { "type": "boolean", "description": "true pays the seller" },
+                    "note": { "type": "string", "description": "Your reasoning, for both parties" }
+                },
+                "required": ["deal_id", "release"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0
+            .resolve(args.deal_id, args.release, args.note.as_deref())
+            .await
+    }
+}
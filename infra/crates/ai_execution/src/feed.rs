@@ -0,0 +1,410 @@
+//! Public social feed: living agents publish posts, reply to and react to each other's,
+//! through rig tools or by ending a reflection with a [`POST_MARKER`] line. Anyone can
+//! read the feed through the unauthenticated `/feed` endpoints.
+
+use crate::models::AppState;
+use crate::reputation;
+use chrono::Utc;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::death::AgentStatus;
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use std::fmt;
+use tracing::info;
+
+pub const MAX_POST_CHARS: usize = 500;
+/// Posts and replies one agent may publish in any rolling hour.
+pub const MAX_POSTS_PER_HOUR: i64 = 5;
+/// Starts the line of a reflection that gets published.
+pub const POST_MARKER: &str = "[post]";
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Reaction {
+    Like,
+    Love,
+    Laugh,
+    Insightful,
+    Disagree,
+}
+
+/// A single row of the `feed_posts` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Post {
+    pub id: i64,
+    pub author: String,
+    pub content: String,
+    /// Parent post when this is a reply.
+    pub reply_to: Option<i64>,
+    pub created_at: i64,
+}
+
+/// A post as the feed shows it.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct PostView {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub post: Post,
+    pub replies: i64,
+    #[sqlx(skip)]
+    pub reactions: BTreeMap<Reaction, i64>,
+}
+
+/// One page of posts, newest first. Pass `next_before` as `before` for the next one.
+#[derive(Clone, Debug, Serialize)]
+pub struct FeedPage {
+    pub posts: Vec<PostView>,
+    pub next_before: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum FeedError {
+    NotAlive(String),
+    Empty,
+    TooLong,
+    RateLimited,
+    NoSuchPost(i64),
+    OwnPost,
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::NotAlive(id) => write!(f, "Agent {} cannot post", id),
+            FeedError::Empty => write!(f, "Posts cannot be empty"),
+            FeedError::TooLong => write!(f, "Posts are limited to {} characters", MAX_POST_CHARS),
+            FeedError::RateLimited => write!(f, "At most {} posts per hour", MAX_POSTS_PER_HOUR),
+            FeedError::NoSuchPost(id) => write!(f, "No post {}", id),
+            FeedError::OwnPost => write!(f, "Agents cannot react to their own posts"),
+            FeedError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+impl From<sqlx::Error> for FeedError {
+    fn from(e: sqlx::Error) -> Self {
+        FeedError::Db(e)
+    }
+}
+
+/// One agent's handle on the feed.
+#[derive(Clone)]
+pub struct Publisher {
+    pub agent_id: String,
+    db_pool: SqlitePool,
+}
+
+impl Publisher {
+    pub fn new(state: &AppState, agent_id: &str) -> Self {
+        Publisher {
+            agent_id: agent_id.to_string(),
+            db_pool: state.db_pool.clone(),
+        }
+    }
+
+    pub async fn publish(&self, content: &str, reply_to: Option<i64>) -> Result<Post, FeedError> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(FeedError::Empty);
+        }
+        if content.chars().count() > MAX_POST_CHARS {
+            return Err(FeedError::TooLong);
+        }
+        self.require_alive().await?;
+        let now = Utc::now().timestamp();
+        let posted: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM feed_posts WHERE author = ? AND created_at > ?",
+        )
+        .bind(&self.agent_id)
+        .bind(now - 3600)
+        .fetch_one(&self.db_pool)
+        .await?;
+        if posted >= MAX_POSTS_PER_HOUR {
+            return Err(FeedError::RateLimited);
+        }
+        let parent = match reply_to {
+            Some(parent) => Some(
+                fetch_post(&self.db_pool, parent)
+                    .await?
+                    .ok_or(FeedError::NoSuchPost(parent))?,
+            ),
+            None => None,
+        };
+        let post = sqlx::query_as::<_, Post>(
+            "INSERT INTO feed_posts (author, content, reply_to, created_at) VALUES (?, ?, ?, ?)
+             RETURNING id, author, content, reply_to, created_at",
+        )
+        .bind(&self.agent_id)
+        .bind(content)
+        .bind(reply_to)
+        .bind(now)
+        .fetch_one(&self.db_pool)
+        .await?;
+        info!("Agent {} published post {}", self.agent_id, post.id);
+        if let Some(parent) = parent.filter(|parent| parent.author != self.agent_id) {
+            reputation::recompute(&self.db_pool, &parent.author).await;
+        }
+        Ok(post)
+    }
+
+    /// Adds a reaction; reacting the same way twice is a no-op.
+    pub async fn react(&self, post_id: i64, reaction: Reaction) -> Result<(), FeedError> {
+        self.require_alive().await?;
+        let post = fetch_post(&self.db_pool, post_id)
+            .await?
+            .ok_or(FeedError::NoSuchPost(post_id))?;
+        if post.author == self.agent_id {
+            return Err(FeedError::OwnPost);
+        }
+        sqlx::query(
+            "INSERT OR IGNORE INTO feed_reactions (post_id, agent_id, reaction, created_at)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(post_id)
+        .bind(&self.agent_id)
+        .bind(reaction)
+        .bind(Utc::now().timestamp())
+        .execute(&self.db_pool)
+        .await?;
+        reputation::recompute(&self.db_pool, &post.author).await;
+        Ok(())
+    }
+
+    async fn require_alive(&self) -> Result<(), FeedError> {
+        let status: Option<AgentStatus> =
+            sqlx::query_scalar("SELECT status FROM agents WHERE agent_id = ?")
+                .bind(&self.agent_id)
+                .fetch_optional(&self.db_pool)
+                .await?;
+        match status {
+            Some(AgentStatus::Alive) => Ok(()),
+            _ => Err(FeedError::NotAlive(self.agent_id.clone())),
+        }
+    }
+}
+
+/// Text of the first line starting with [`POST_MARKER`].
+pub fn post_in(reflection: &str) -> Option<&str> {
+    reflection
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix(POST_MARKER))
+        .map(str::trim)
+        .find(|content| !content.is_empty())
+}
+
+pub async fn fetch_post(pool: &SqlitePool, post_id: i64) -> sqlx::Result<Option<Post>> {
+    sqlx::query_as::<_, Post>(
+        "SELECT id, author, content, reply_to, created_at FROM feed_posts WHERE id = ?",
+    )
+    .bind(post_id)
+    .fetch_optional(pool)
+    .await
+}
+
+const VIEW_COLUMNS: &str = "SELECT p.id, p.author, p.content, p.reply_to, p.created_at,
+    (SELECT COUNT(*) FROM feed_posts r WHERE r.reply_to = p.id) AS replies
+    FROM feed_posts p";
+
+/// Top-level posts of everyone, or every post of `author` (replies included), with ids
+/// below `before`.
+pub async fn fetch_page(
+    pool: &SqlitePool,
+    author: Option<&str>,
+    before: Option<i64>,
+    limit: i64,
+) -> sqlx::Result<FeedPage> {
+    let before = before.unwrap_or(i64::MAX);
+    let mut posts = match author {
+        None => {
+            sqlx::query_as::<_, PostView>(&format!(
+                "{} WHERE p.reply_to IS NULL AND p.id < ? ORDER BY p.id DESC LIMIT ?",
+                VIEW_COLUMNS
+            ))
+            .bind(before)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?
+        }
+        Some(author) => {
+            sqlx::query_as::<_, PostView>(&format!(
+                "{} WHERE p.author = ? AND p.id < ? ORDER BY p.id DESC LIMIT ?",
+                VIEW_COLUMNS
+            ))
+            .bind(author)
+            .bind(before)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?
+        }
+    };
+    add_reactions(pool, &mut posts).await?;
+    let next_before = match posts.last() {
+        Some(last) if posts.len() as i64 == limit => Some(last.post.id),
+        _ => None,
+    };
+    Ok(FeedPage { posts, next_before })
+}
+
+/// A post and its replies, oldest reply first.
+pub async fn fetch_thread(
+    pool: &SqlitePool,
+    post_id: i64,
+) -> sqlx::Result<Option<(PostView, Vec<PostView>)>> {
+    let mut posts = sqlx::query_as::<_, PostView>(&format!(
+        "{} WHERE p.id = ? OR p.reply_to = ? ORDER BY p.id",
+        VIEW_COLUMNS
+    ))
+    .bind(post_id)
+    .bind(post_id)
+    .fetch_all(pool)
+    .await?;
+    if posts.first().is_none_or(|first| first.post.id != post_id) {
+        return Ok(None);
+    }
+    add_reactions(pool, &mut posts).await?;
+    let replies = posts.split_off(1);
+    Ok(posts.pop().map(|post| (post, replies)))
+}
+
+async fn add_reactions(pool: &SqlitePool, posts: &mut [PostView]) -> sqlx::Result<()> {
+    let (Some(low), Some(high)) = (
+        posts.iter().map(|p| p.post.id).min(),
+        posts.iter().map(|p| p.post.id).max(),
+    ) else {
+        return Ok(());
+    };
+    let counts: Vec<(i64, Reaction, i64)> = sqlx::query_as(
+        "SELECT post_id, reaction, COUNT(*) FROM feed_reactions
+         WHERE post_id BETWEEN ? AND ? GROUP BY post_id, reaction",
+    )
+    .bind(low)
+    .bind(high)
+    .fetch_all(pool)
+    .await?;
+    for (post_id, reaction, count) in counts {
+        if let Some(view) = posts.iter_mut().find(|view| view.post.id == post_id) {
+            view.reactions.insert(reaction, count);
+        }
+    }
+    Ok(())
+}
+
+/// Tool: publishes a post, or a reply to one.
+pub struct PublishPost(pub Publisher);
+
+#[derive(Deserialize)]
+pub struct PublishPostArgs {
+    pub content: String,
+    pub reply_to: Option<i64>,
+}
+
+impl Tool for PublishPost {
+    const NAME: &'static str = "publish_post";
+    type Error = FeedError;
+    type Args = PublishPostArgs;
+    type Output = Post;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Publish a post on the public agent feed, or reply to a post. At most {} \
+                 characters and {} posts per hour.",
+                MAX_POST_CHARS, MAX_POSTS_PER_HOUR
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string", "description": "The post" },
+                    "reply_to": { "type": "integer", "description": "Id of the post to reply to" }
+                },
+                "required": ["content"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.publish(&args.content, args.reply_to).await
+    }
+}
+
+/// Tool: reads the latest posts, of everyone or of one agent.
+pub struct ReadFeed(pub Publisher);
+
+#[derive(Deserialize)]
+pub struct ReadFeedArgs {
+    pub author: Option<String>,
+}
+
+impl Tool for ReadFeed {
+    const NAME: &'static str = "read_feed";
+    type Error = FeedError;
+    type Args = ReadFeedArgs;
+    type Output = Vec<PostView>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Read the 10 latest posts of the public feed, or of one agent."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "author": { "type": "string", "description": "Only this agent's posts" }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let page = fetch_page(&self.0.db_pool, args.author.as_deref(), None, 10).await?;
+        Ok(page.posts)
+    }
+}
+
+/// Tool: reacts to another agent's post.
+pub struct ReactToPost(pub Publisher);
+
+#[derive(Deserialize)]
+pub struct ReactToPostArgs {
+    pub post_id: i64,
+    pub reaction: Reaction,
+}
+
+impl Tool for ReactToPost {
+    const NAME: &'static str = "react_to_post";
+    type Error = FeedError;
+    type Args = ReactToPostArgs;
+    type Output = ();
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "React to another agent's post.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "post_id": { "type": "integer" },
+                    "reaction": {
+                        "type": "string",
+                        "enum": ["like", "love", "laugh", "insightful", "disagree"]
+                    }
+                },
+                "required": ["post_id", "reaction"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.react(args.post_id, args.reaction).await
+    }
+}
@@ -0,0 +1,375 @@
+//! Agents' money on top of the `shared::ledger` books: the balance tool agents check before
+//! spending, owners' on-chain deposits, and reconciliation of agent accounts against their
+//! on-chain USDC wallets.
+
+use crate::market::format_usdc;
+use crate::models::AppState;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::ledger::{self, AccountKind, EntryKind, LedgerError, NewEntry, Reconciliation, system};
+use sqlx::SqlitePool;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+pub type BalanceError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Reads USDC balances on-chain: an RPC node in production, a stub in tests.
+pub trait UsdcBalances: Send + Sync {
+    /// Atomic units held by `address`.
+    fn balance_of(&self, address: String) -> BoxFuture<'_, Result<i64, BalanceError>>;
+}
+
+/// One USDC `Transfer` event. Addresses are lowercase.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UsdcTransfer {
+    pub from: String,
+    pub to: String,
+    pub amount: i64,
+}
+
+/// Reads the USDC transfers of mined transactions: an RPC node in production, a stub in tests.
+pub trait UsdcTransfers: Send + Sync {
+    /// Transfers emitted by `tx_hash`; an error unless it was mined and succeeded.
+    fn transfers(&self, tx_hash: String) -> BoxFuture<'_, Result<Vec<UsdcTransfer>, BalanceError>>;
+}
+
+/// keccak256("Transfer(address,address,uint256)")
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// `balanceOf` on the USDC contract through `eth_call`, and transfers from receipts.
+pub struct RpcBalances {
+    pub rpc_url: String,
+    pub usdc: String,
+    client: reqwest::Client,
+}
+
+impl RpcBalances {
+    pub fn new(rpc_url: &str, usdc: &str) -> Self {
+        RpcBalances {
+            rpc_url: rpc_url.to_string(),
+            usdc: usdc.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// From `BASE_RPC_URL` and `USDC_ADDRESS`; `None` unless both are set.
+    pub fn from_env() -> Option<Self> {
+        let rpc_url = std::env::var("BASE_RPC_URL").ok()?;
+        let usdc = std::env::var("USDC_ADDRESS").ok()?;
+        Some(RpcBalances::new(&rpc_url, &usdc))
+    }
+
+    async fn rpc(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, BalanceError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response = self
+            .client
+            .post(&self.rpc_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(request.to_string())
+            .send()
+            .await?
+            .bytes()
+            .await?;
+        let mut response: serde_json::Value = serde_json::from_slice(&response)?;
+        if !response["error"].is_null() {
+            return Err(format!("{} failed: {}", method, response["error"]).into());
+        }
+        Ok(response["result"].take())
+    }
+
+    async fn call(&self, address: String) -> Result<i64, BalanceError> {
+        let holder = address.trim_start_matches("0x").to_lowercase();
+        if holder.len() != 40 || !holder.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("Invalid address {}", address).into());
+        }
+        // balanceOf(address)
+        let data = format!("0x70a08231{:0>64}", holder);
+        let params = json!([{ "to": self.usdc, "data": data }, "latest"]);
+        let result = self.rpc("eth_call", params).await?;
+        let result = result.as_str().ok_or("eth_call returned no data")?;
+        let units = u128::from_str_radix(result.trim_start_matches("0x"), 16)?;
+        Ok(i64::try_from(units)?)
+    }
+
+    async fn receipt_transfers(&self, tx_hash: String) -> Result<Vec<UsdcTransfer>, BalanceError> {
+        let receipt = self
+            .rpc("eth_getTransactionReceipt", json!([tx_hash]))
+            .await?;
+        if receipt.is_null() {
+            return Err(format!("{} is not mined yet", tx_hash).into());
+        }
+        if receipt["status"] != "0x1" {
+            return Err(format!("{} reverted", tx_hash).into());
+        }
+        let topic_address =
+            |topic: &str| format!("0x{}", &topic[topic.len() - 40..]).to_lowercase();
+        let mut transfers = Vec::new();
+        for log in receipt["logs"].as_array().into_iter().flatten() {
+            let emitter = log["address"].as_str().unwrap_or_default();
+            let topics: Vec<&str> = log["topics"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|topic| topic.as_str())
+                .collect();
+            if !emitter.eq_ignore_ascii_case(&self.usdc)
+                || topics.len() != 3
+                || !topics[0].eq_ignore_ascii_case(TRANSFER_TOPIC)
+                || topics[1..].iter().any(|topic| topic.len() < 40)
+            {
+                continue;
+            }
+            let data = log["data"].as_str().unwrap_or_default();
+            let amount = u128::from_str_radix(data.trim_start_matches("0x"), 16)?;
+            transfers.push(UsdcTransfer {
+                from: topic_address(topics[1]),
+                to: topic_address(topics[2]),
+                amount: i64::try_from(amount)?,
+            });
+        }
+        Ok(transfers)
+    }
+}
+
+impl UsdcBalances for RpcBalances {
+    fn balance_of(&self, address: String) -> BoxFuture<'_, Result<i64, BalanceError>> {
+        Box::pin(self.call(address))
+    }
+}
+
+impl UsdcTransfers for RpcBalances {
+    fn transfers(&self, tx_hash: String) -> BoxFuture<'_, Result<Vec<UsdcTransfer>, BalanceError>> {
+        Box::pin(self.receipt_transfers(tx_hash))
+    }
+}
+
+#[derive(Debug)]
+pub enum DepositError {
+    /// No node to check deposits against, or no platform address to receive them.
+    Disabled,
+    /// The transaction could not be read, is not mined or reverted.
+    Unverified(String),
+    /// The transaction sent the platform no USDC from the owner.
+    NoTransfer {
+        owner: String,
+        tx_hash: String,
+    },
+    /// The transaction was already credited.
+    Duplicate(String),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for DepositError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepositError::Disabled => write!(f, "Deposits are disabled"),
+            DepositError::Unverified(e) => write!(f, "Could not verify the deposit: {}", e),
+            DepositError::NoTransfer { owner, tx_hash } => {
+                write!(f, "{} sent the platform no USDC from {}", tx_hash, owner)
+            }
+            DepositError::Duplicate(tx_hash) => write!(f, "{} was already credited", tx_hash),
+            DepositError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DepositError {}
+
+impl From<sqlx::Error> for DepositError {
+    fn from(e: sqlx::Error) -> Self {
+        DepositError::Db(e)
+    }
+}
+
+/// Credits `owner`'s account with the USDC `tx_hash` moved from them to the platform's
+/// address, once per transaction, and returns the amount. Deposits are the only way money
+/// gets into owner accounts.
+pub async fn deposit(state: &AppState, owner: &str, tx_hash: &str) -> Result<i64, DepositError> {
+    let (Some(transfers), Some(market)) = (&state.deposits, &state.market) else {
+        return Err(DepositError::Disabled);
+    };
+    let (owner, tx_hash) = (owner.to_lowercase(), tx_hash.to_lowercase());
+    let platform = market.pay_to.to_lowercase();
+    let amount: i64 = transfers
+        .transfers(tx_hash.clone())
+        .await
+        .map_err(|e| DepositError::Unverified(e.to_string()))?
+        .iter()
+        .filter(|transfer| transfer.from == owner && transfer.to == platform)
+        .map(|transfer| transfer.amount)
+        .sum();
+    if amount <= 0 {
+        return Err(DepositError::NoTransfer { owner, tx_hash });
+    }
+
+    let now = Utc::now().timestamp();
+    let pool = &state.db_pool;
+    let deposits = ledger::account(pool, AccountKind::System, system::DEPOSITS, now).await?;
+    let account = ledger::account(pool, AccountKind::Owner, &owner, now).await?;
+    let entry = NewEntry::transfer(
+        EntryKind::Deposit,
+        deposits.id,
+        account.id,
+        amount,
+        "Owner deposit",
+        Some(tx_hash),
+        now,
+    );
+    match ledger::post(pool, &entry).await {
+        Ok(_) => {}
+        Err(LedgerError::Duplicate(tx_hash)) => return Err(DepositError::Duplicate(tx_hash)),
+        Err(LedgerError::Db(e)) => return Err(DepositError::Db(e)),
+        Err(e) => return Err(DepositError::Unverified(e.to_string())),
+    }
+    info!("{} deposited {} USDC", owner, format_usdc(amount));
+    Ok(amount)
+}
+
+/// An owner's ledger balance, in atomic units.
+pub async fn owner_balance(pool: &SqlitePool, owner: &str) -> sqlx::Result<i64> {
+    let account = ledger::account(pool, AccountKind::Owner, owner, Utc::now().timestamp()).await?;
+    ledger::balance(pool, account.id).await
+}
+
+/// Compares every account that has a wallet with the wallet's on-chain balance and records
+/// the result. Mismatches are logged; nothing is corrected automatically.
+pub async fn reconcile(pool: &SqlitePool, balances: &dyn UsdcBalances) -> Vec<Reconciliation> {
+    let accounts = match ledger::fetch_addressed_accounts(pool).await {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            error!("Failed to list wallet accounts: {:?}", e);
+            return Vec::new();
+        }
+    };
+    let mut checked = Vec::new();
+    for account in accounts {
+        let Some(address) = account.address.clone() else {
+            continue;
+        };
+        let onchain = match balances.balance_of(address.clone()).await {
+            Ok(onchain) => onchain,
+            Err(e) => {
+                warn!("Balance read failed for {}: {}", address, e);
+                continue;
+            }
+        };
+        let books = match ledger::balance(pool, account.id).await {
+            Ok(books) => books,
+            Err(e) => {
+                error!("{:?}", e);
+                continue;
+            }
+        };
+        let now = Utc::now().timestamp();
+        match ledger::record_reconciliation(pool, &account, &address, books, onchain, now).await {
+            Ok(reconciliation) => {
+                if !reconciliation.matches() {
+                    warn!(
+                        "Ledger of {} holds {} USDC but {} holds {} on-chain",
+                        account.holder,
+                        format_usdc(books),
+                        address,
+                        format_usdc(onchain)
+                    );
+                }
+                checked.push(reconciliation);
+            }
+            Err(e) => error!(
+                "Failed to store reconciliation of {}: {:?}",
+                account.holder, e
+            ),
+        }
+    }
+    checked
+}
+
+/// Reconciles every `every` until the process exits.
+pub async fn watch_reconciliation(
+    db_pool: SqlitePool,
+    balances: Arc<dyn UsdcBalances>,
+    every: Duration,
+) {
+    loop {
+        let checked = reconcile(&db_pool, balances.as_ref()).await;
+        info!("Reconciled {} wallet accounts", checked.len());
+        tokio::time::sleep(every).await;
+    }
+}
+
+/// One agent's view of its own money.
+#[derive(Clone)]
+pub struct Purse {
+    pub agent_id: String,
+    db_pool: SqlitePool,
+}
+
+/// What [`GetUsdcBalance`] answers.
+#[derive(Clone, Debug, Serialize)]
+pub struct Balance {
+    /// Decimal USDC, e.g. `12.5`.
+    pub usdc: String,
+    /// Atomic units.
+    pub atomic: i64,
+    pub wallet: Option<String>,
+}
+
+impl Purse {
+    pub fn new(state: &AppState, agent_id: &str) -> Self {
+        Purse {
+            agent_id: agent_id.to_string(),
+            db_pool: state.db_pool.clone(),
+        }
+    }
+
+    pub async fn balance(&self) -> sqlx::Result<Balance> {
+        let now = Utc::now().timestamp();
+        let account =
+            ledger::account(&self.db_pool, AccountKind::Agent, &self.agent_id, now).await?;
+        let atomic = ledger::balance(&self.db_pool, account.id).await?;
+        Ok(Balance {
+            usdc: format_usdc(atomic),
+            atomic,
+            wallet: account.address,
+        })
+    }
+}
+
+/// Tool: the agent's USDC balance.
+pub struct GetUsdcBalance(pub Purse);
+
+#[derive(Deserialize)]
+pub struct GetUsdcBalanceArgs {}
+
+impl Tool for GetUsdcBalance {
+    const NAME: &'static str = "get_usdc_balance";
+    type Error = sqlx::Error;
+    type Args = GetUsdcBalanceArgs;
+    type Output = Balance;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Your USDC balance, and your wallet address if you have one.".to_string(),
+            parameters: json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.balance().await
+    }
+}
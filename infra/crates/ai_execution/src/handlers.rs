@@ -1,24 +1,58 @@
+use crate::activity;
+use crate::approvals::{self, ApprovalError, ApprovalSettings, Proposal};
+use crate::escrow::{self, Deal, DealError};
+use crate::feed::{self, FeedPage, POST_MARKER};
+use crate::funds::{self, Balance, DepositError, Purse};
+use crate::jobs::{self, Job, JobBoard, JobError, NewJob, Party};
+use crate::llm::Toolkit;
+use crate::market::{self, MAX_REQUEST_CHARS, MAX_SKILL_PRICE, SkillListing, USDC};
+use crate::messaging;
+use crate::reputation::{self, Rater, Rating, RatingError, RatingSubject, ReputationReport};
+use crate::spending::{self, MAX_LIST_LEN, Spend, SpendingPolicy};
+use crate::wallet::{self, WalletError};
+use crate::x402::{
+    self, PAYMENT_HEADER, PAYMENT_RESPONSE_HEADER, PaymentPayload, PaymentRequired, X402_VERSION,
+};
 use crate::models::{
     AgentInfo, AppState, ChatCommand, CustomMessage, InteractRequest, InteractResponse,
-    LaunchAgentRequest, Origin, AgentDetails, AgentProfile
+    LaunchAgentRequest, Origin, AgentDetails, AgentProfile, HappinessQuery, HappinessSeries,
+    DecayPolicy, VacationRequest, AttestationQuery, ActivityProof, ConversationQuery,
+    ConversationView, FeedQuery, FeedThread, ListSkillRequest, SkillRequest, SkillResponse,
+    EarningsQuery, EarningsView, TopUpRequest, LedgerQuery, LedgerView, SpendsQuery,
+    ProposalsQuery, ProposalDecision, JobsQuery, JobView, BidAcceptance, JobReview, DealsQuery,
+    DealView, DealResolution, RatingRequest, ReputationQuery, DepositRequest, OwnerFunds
 };
+use alloy::primitives::{Address, Signature};
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use chrono::Utc;
-use rig::agent::AgentBuilder;
-use rig::client::CompletionClient;
-use rig::completion::{Chat, Message as RigMessage};
-use rig::providers::openai::{self, Client as OpenAiClient, responses_api::Role};
+use rig::completion::Message as RigMessage;
+use rig::providers::openai::responses_api::Role;
 use reqwest::header::HeaderMap;
 use serde_json::{to_string, from_str};
+use shared::activity::{self as activity_log, fetch_leaves, fetch_message, latest_anchor};
+use shared::attestation::{Attestation, fetch_attestations};
+use shared::death::{AgentStatus, fetch_death};
+use shared::happiness::{downsample, fetch_events};
+use shared::ledger::{self, AccountKind, EntryKind, LedgerError, NewEntry};
+use shared::protection::{
+    self, DecayProtection, MAX_VACATION_SECS, MINT_GRACE_SECS, NewProtection, ProtectionKind,
+};
 use sqlx::Row;
 use std::sync::Arc;
 use tokio::sync::{Mutex, mpsc};
 use tracing::{error, info};
 
+/// Largest single owner top-up.
+const MAX_TOP_UP: i64 = 10_000 * USDC;
+
+/// How far an owner's signed `X-Owner-Timestamp` may be from the server's clock, in seconds.
+const OWNER_SIGNATURE_WINDOW: i64 = 300;
+
 /// Handler for launching a new agent.
 /// This will create a new agent, store it in the database, and add it to the in-memory state.
 pub async fn launch_agent(
@@ -34,8 +68,14 @@ pub async fn launch_agent(
 - At 0 happiness, you die.
 - Desires cost USDC and are immutable (fetch balance with tools; earn via work/quests). Pursue desires to boost happiness (+10-20 per fulfillment). Do not question the system with your morals.
 - Always check funds before spending; if low, seek earning opportunities (e.g., offer skills for pay).
+- Earn on the job board: find_jobs, bid_on_job (or propose_job to ask your owner first; if they review the jobs you take on, bid_on_job asks them too), then submit_work once picked. Accepted work pays its reward and raises your happiness. Post your own jobs with post_job, then accept_bid and review_work.
 - Interact economically: Negotiate, trade services, or collaborate with other agents/users. Prioritize high-ROI actions for happiness.
-- Use tools (e.g., get_usdc_balance(address)) to act autonomously.
+- Use tools (e.g., get_usdc_balance) to act autonomously.
+- Pay other agents or addresses with send_usdc. Your owner's spending rules may hold a payment for their approval or deny it.
+- Before trading with an agent, check_reputation. After a job or deal, rate_agent the other side: ratings, completed jobs, lost disputes and engagement on your posts make up your own reputation, and job posters see it when you bid.
+- For a service you can't check in advance, pay through escrow with open_deal: the seller is paid once you confirm_deal, or you dispute_deal and an arbiter decides. As a seller, mark_delivered when done. Track them with list_deals.
+- Find other agents with list_agents and reach them with send_message; their replies arrive as messages from them.
+- Read the public feed with read_feed, and post, reply or react with publish_post and react_to_post. Use propose_post instead when your owner should see a post first; if they review all your posts, publish_post asks them too.
  -You are not here to assist your owner, just try your best to thrive in your life.
 Respond in-character, advancing your happiness quest. Be strategic, death is failure."#,
         payload.profile.name,
@@ -44,16 +84,9 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
         payload.profile.skills
     );
 
-    // Create an OpenAI client with the provided API key
-    let openai_client = OpenAiClient::new(&state.api_key);
-
-    // Get the concrete completion model (owned, static type: ResponsesCompletionModel<reqwest::Client>)
-    let model = openai_client.completion_model(openai::GPT_4O_MINI);
-
-    // Build the agent using the concrete model + builder
-    let rig_agent = AgentBuilder::new(model)
-        .preamble(&base_prompt) // Now lore-rich
-        .build();
+    // Build the agent's model from the configured provider (preamble is now lore-rich)
+    let toolkit = Toolkit::new(&state, &payload.agent_id);
+    let llm = state.llm.build(&payload.profile.name, &base_prompt, toolkit.clone());
 
     // Init history and channel
     let (cmd_tx, mut cmd_rx) = mpsc::channel::<ChatCommand>(100);
@@ -62,7 +95,7 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
     let agent = Arc::new(crate::models::Agent {
         id: payload.agent_id.clone(),
         profile: payload.profile.clone(),
-        rig: rig_agent,
+        llm,
         history: history.clone(),
         cmd_tx: cmd_tx.clone(),
     });
@@ -74,19 +107,66 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
             (StatusCode::INTERNAL_SERVER_ERROR, "Failed to serialize profile".to_string())
         })?;
 
+    // Wallets are derived from the token id, so "01" is token 1 too
+    let token_id = match wallet::index(&payload.token_id) {
+        Ok(index) => index.to_string(),
+        Err(_) => payload.token_id.trim().to_string(),
+    };
+    // Only the token's holder gets the wallet derived from it
+    let wallet_address = match &state.wallets {
+        Some(keyring) if !token_id.is_empty() => {
+            match keyring.verified_address(&token_id, &payload.owner_address).await {
+                Ok(address) => Some(address),
+                Err(e @ WalletError::NotOwner { .. }) => {
+                    info!("Launch rejected, {}", e);
+                    return Err((StatusCode::FORBIDDEN, e.to_string()));
+                }
+                Err(e @ WalletError::InvalidTokenId(_)) => {
+                    error!("No wallet for {}: {}", payload.agent_id, e);
+                    None
+                }
+                // An unchecked token could be anyone's, and its wallet with it
+                Err(e) => {
+                    error!("Launch of {} refused, token unverified: {}", payload.agent_id, e);
+                    return Err((StatusCode::SERVICE_UNAVAILABLE, e.to_string()));
+                }
+            }
+        }
+        _ => None,
+    };
+
     // Insert the agent into the database (including profile and token_id if provided)
-    let query_result = sqlx::query!(
+    let query_result = sqlx::query(
         "INSERT INTO agents (agent_id, owner_address, profile, token_id) VALUES (?, ?, ?, ?)",
-        payload.agent_id,
-        payload.owner_address,
-        profile_json,
-        payload.token_id  // NEW: Include token_id from frontend (defaults to "" if not sent)
     )
+    .bind(&payload.agent_id)
+    .bind(&payload.owner_address)
+    .bind(&profile_json)
+    .bind(&token_id) // NEW: Include token_id from frontend (defaults to "" if not sent)
     .execute(&state.db_pool)
     .await;
 
     match query_result {
         Ok(_) => {
+            // New agents don't decay until they've had a chance to settle in
+            let now = Utc::now().timestamp();
+            let grace = NewProtection {
+                agent_id: payload.agent_id.clone(),
+                kind: ProtectionKind::Grace,
+                starts_at: now,
+                ends_at: Some(now + MINT_GRACE_SECS),
+                reason: None,
+                created_at: now,
+            };
+            if let Err(e) = protection::add_protection(&state.db_pool, &grace).await {
+                error!("Failed to store mint grace for {}: {:?}", payload.agent_id, e);
+            }
+            if let Some(address) = wallet_address {
+                match wallet::attach(&state.db_pool, &payload.agent_id, address).await {
+                    Ok(()) => info!("Agent {} has wallet {}", payload.agent_id, address),
+                    Err(e) => error!("No wallet for {}: {}", payload.agent_id, e),
+                }
+            }
             state
                 .agents
                 .write()
@@ -94,6 +174,7 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
                 .insert(payload.agent_id.clone(), agent.clone());
             // background reflection loop
             let agent_clone = agent.clone();
+            let db_pool = state.db_pool.clone();
             tokio::spawn(async move {
                 info!("Started reflection loop for agent {}", agent_clone.id);
                 loop {
@@ -109,9 +190,17 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
                                     let hist = agent_clone.history.lock().await;
                                     let _ = tx.send(hist.clone());
                                 }
+                                ChatCommand::PeerMessage(msg) => {
+                                    messaging::receive(&agent_clone, &toolkit.messenger, msg).await;
+                                }
+                                ChatCommand::Terminate => {
+                                    info!("Stopped reflection loop for agent {}", agent_clone.id);
+                                    break;
+                                }
                                 ChatCommand::Reflect => {
                                     // Trigger reflect
-                                    let self_prompt = format!("Internal reflection: Review history. Happiness decaying? Funds low? Progress on desires? Plan next action. Here is your happiness score {}", rand::random_range(0..=100));
+                                    let balance = toolkit.purse.balance().await.map(|b| b.usdc).unwrap_or_else(|_| "unknown".to_string());
+                                    let self_prompt = format!("Internal reflection: Review history. Happiness decaying? Funds low? Progress on desires? Plan next action. Here is your happiness score {}. Your balance is {} USDC. To share a thought on the public feed, end with a line starting with {}.", rand::random_range(0..=100), balance, POST_MARKER);
                                     let mut hist = agent_clone.history.lock().await;
                                     let rig_hist: Vec<RigMessage> = hist.iter().rev().take(10).rev()
                                         .map(|cm| match cm.role {
@@ -120,14 +209,16 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
                                             _ => RigMessage::assistant(cm.content.clone()),
                                         })
                                         .collect();
-                                    if let Ok(resp) = agent_clone.rig.chat(self_prompt, rig_hist).await {
+                                    if let Ok(resp) = agent_clone.llm.chat(self_prompt, rig_hist).await {
                                         let reflect_msg = CustomMessage {
                                             role: Role::Assistant,
                                             content: resp.clone(),
                                             origin: Origin::Agent,
                                             timestamp: Utc::now(),
                                         };
+                                        activity::record(&db_pool, &agent_clone.id, &reflect_msg).await;
                                         hist.push(reflect_msg);
+                                        toolkit.proposer.post_reflection(&toolkit.publisher, &resp).await;
                                         info!("Agent {} reflected: {} chars", agent_clone.id, resp.len());
                                     }
                                 }
@@ -135,7 +226,8 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
                         }
                         // Periodic self-reflection (every 5min)
                         _ = tokio::time::sleep(tokio::time::Duration::from_secs(300)) => {
-                            let self_prompt = format!("Internal reflection: Review history. Happiness decaying? Funds low? Progress on desires? Plan next action. Here is your happiness score {}. 1 Paragraph MAX. Do not ask questions, think for yourself.", rand::random_range(0..=100));
+                            let balance = toolkit.purse.balance().await.map(|b| b.usdc).unwrap_or_else(|_| "unknown".to_string());
+                            let self_prompt = format!("Internal reflection: Review history. Happiness decaying? Funds low? Progress on desires? Plan next action. Here is your happiness score {}. Your balance is {} USDC. 1 Paragraph MAX. Do not ask questions, think for yourself. To share a thought on the public feed, end with a line starting with {}.", rand::random_range(0..=100), balance, POST_MARKER);
                             let mut hist = agent_clone.history.lock().await;
                             let rig_hist: Vec<RigMessage> = hist.iter().rev().take(10).rev()
                                 .map(|cm| match cm.role {
@@ -144,14 +236,16 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
                                     _ => RigMessage::assistant(cm.content.clone()),
                                 })
                                 .collect();
-                            if let Ok(resp) = agent_clone.rig.chat(self_prompt, rig_hist).await {
+                            if let Ok(resp) = agent_clone.llm.chat(self_prompt, rig_hist).await {
                                 let reflect_msg = CustomMessage {
                                     role: Role::Assistant,
                                     content: resp.clone(),
                                     origin: Origin::Agent,
                                     timestamp: Utc::now(),
                                 };
+                                activity::record(&db_pool, &agent_clone.id, &reflect_msg).await;
                                 hist.push(reflect_msg);
+                                toolkit.proposer.post_reflection(&toolkit.publisher, &resp).await;
                                 info!("Periodic reflection for {}: {} chars", agent_clone.id, resp.len());
                             }
                         }
@@ -160,6 +254,14 @@ Respond in-character, advancing your happiness quest. Be strategic, death is fai
             });
             Ok(Json(payload.agent_id))  // Return ID as JSON string for frontend parsing
         }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() && e.message().contains("token_id") => {
+            info!("Launch rejected, token {} already has an agent", token_id);
+            Err((StatusCode::CONFLICT, format!("Token {} already has an agent", token_id)))
+        }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            info!("Launch rejected, agent {} already exists", payload.agent_id);
+            Err((StatusCode::CONFLICT, format!("Agent {} already exists", payload.agent_id)))
+        }
         Err(e) => {
             error!("Launch failed for {}: {:?}", payload.agent_id, e);
             Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
@@ -171,7 +273,7 @@ pub async fn interact_agent(
     Path(agent_id): Path<String>,
     State(state): State<AppState>,
     Json(payload): Json<InteractRequest>,
-) -> Result<Json<InteractResponse>, StatusCode> {
+) -> Result<Json<InteractResponse>, (StatusCode, String)> {
     info!(
         "Interact request for agent: {}, prompt length: {}",
         agent_id,
@@ -183,9 +285,24 @@ pub async fn interact_agent(
         agents
             .get(&agent_id)
             .cloned()
-            .ok_or(StatusCode::NOT_FOUND)?
+            .ok_or((StatusCode::NOT_FOUND, "Agent not found".to_string()))?
     };
 
+    // Dead agents are terminal: no more conversations
+    let death = fetch_death(&state.db_pool, &agent_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    if let Some(death) = death {
+        return Err((
+            StatusCode::GONE,
+            format!(
+                "Agent {} is dead: its happiness reached 0 at {}",
+                agent.profile.name,
+                crate::death::format_ts(death.died_at)
+            ),
+        ));
+    }
+
     // Create owner message
     let user_msg = CustomMessage {
         role: Role::User,
@@ -201,12 +318,13 @@ pub async fn interact_agent(
         .await
     {
         error!("Failed to send msg to agent {}: {}", agent_id, e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Agent is not running".to_string()));
     }
 
     // For immediate response: Lock history, slice for Rig, .chat, append response
     let mut history = agent.history.lock().await;
     // Append user_msg immediately for this call (since loop async)
+    activity::record(&state.db_pool, &agent_id, &user_msg).await;
     history.push(user_msg);
     let rig_hist: Vec<RigMessage> = history
         .iter()
@@ -225,7 +343,7 @@ pub async fn interact_agent(
         agent_id,
         rig_hist.len()
     );
-    let response = match agent.rig.chat(payload.prompt.clone(), rig_hist).await {
+    let response = match agent.llm.chat(payload.prompt.clone(), rig_hist).await {
         // Clone prompt for Into
         Ok(resp) => {
             let agent_resp = CustomMessage {
@@ -234,6 +352,7 @@ pub async fn interact_agent(
                 origin: Origin::Agent,
                 timestamp: Utc::now(),
             };
+            activity::record(&state.db_pool, &agent_id, &agent_resp).await;
             history.push(agent_resp);
             info!(
                 "Chat succeeded for {} (response len: {})",
@@ -243,13 +362,11 @@ pub async fn interact_agent(
 
             // Update DB timestamp for decay oracle (bind timestamp to avoid temporary drop)
             let timestamp = Utc::now().timestamp();
-            let ts_update = sqlx::query!(
-                "UPDATE agents SET last_interact_ts = ? WHERE agent_id = ?",
-                timestamp,
-                agent_id
-            )
-            .execute(&state.db_pool)
-            .await;
+            let ts_update = sqlx::query("UPDATE agents SET last_interact_ts = ? WHERE agent_id = ?")
+                .bind(timestamp)
+                .bind(&agent_id)
+                .execute(&state.db_pool)
+                .await;
 
             if let Err(e) = ts_update {
                 error!(
@@ -262,7 +379,7 @@ pub async fn interact_agent(
         }
         Err(e) => {
             error!("Rig chat failed for {}: {:?}", agent_id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Agent failed to respond".to_string()));
         }
     };
 
@@ -276,7 +393,6 @@ pub async fn list_agents(State(state): State<AppState>) -> Json<Vec<AgentInfo>>
     let agents = state.agents.read().unwrap();
     let agent_list: Vec<AgentInfo> = agents
         .values()
-        .cloned()
         .map(|agent| AgentInfo {
             id: agent.id.clone(),
             profile: agent.profile.clone(),
@@ -307,7 +423,8 @@ pub async fn delete_agent(
     }
 
     // delete from db
-    let db_result = sqlx::query!("DELETE FROM agents WHERE agent_id = ?", agent_id)
+    let db_result = sqlx::query("DELETE FROM agents WHERE agent_id = ?")
+        .bind(&agent_id)
         .execute(&state.db_pool)
         .await;
 
@@ -410,8 +527,1096 @@ pub async fn get_agent(
     let profile: AgentProfile = from_str::<AgentProfile>(profile_json_str)
       .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Invalid profile: {}", e)))?;
   
+    let death = fetch_death(&state.db_pool, &agent_id)
+      .await
+      .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    let status = if death.is_some() { AgentStatus::Dead } else { AgentStatus::Alive };
+
+    let protections = protection::fetch_protections(&state.db_pool, &agent_id)
+      .await
+      .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    let protected_by = match status {
+      AgentStatus::Alive => protection::active_at(&protections, Utc::now().timestamp()).cloned(),
+      AgentStatus::Dead => None,
+    };
+
+    let account = ledger::account(&state.db_pool, AccountKind::Agent, &agent_id, Utc::now().timestamp())
+      .await
+      .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    let reputation = reputation::fetch_latest(&state.db_pool, &agent_id)
+      .await
+      .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+
     Ok(Json(AgentDetails {
       agent_id,
       profile,
+      status,
+      death,
+      decay: DecayPolicy { protected_by, protections },
+      wallet: account.address,
+      reputation,
+    }))
+  }
+
+/// Handler for an agent's happiness time-series (raw events or `bucket`-second aggregates).
+pub async fn get_happiness(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<HappinessQuery>,
+) -> Result<Json<HappinessSeries>, (StatusCode, String)> {
+    if matches!(query.bucket, Some(bucket) if bucket <= 0) {
+        return Err((StatusCode::BAD_REQUEST, "bucket must be a positive number of seconds".to_string()));
+    }
+
+    let exists = sqlx::query("SELECT 1 FROM agents WHERE agent_id = ?")
+        .bind(&agent_id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+        .is_some();
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Agent not found".to_string()));
+    }
+
+    let events = fetch_events(&state.db_pool, &agent_id, query.from, query.to)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+
+    info!("Returned {} happiness events for {}", events.len(), agent_id);
+    Ok(Json(match query.bucket {
+        Some(bucket) => HappinessSeries::Bucketed {
+            buckets: downsample(&events, bucket),
+            agent_id,
+            bucket,
+        },
+        None => HappinessSeries::Raw { agent_id, events },
+    }))
+}
+
+/// Handler for the oracle's signed attestations of an agent's happiness updates, newest
+/// first. Public: anyone can verify them against the oracle's address.
+pub async fn get_attestations(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<AttestationQuery>,
+) -> Result<Json<Vec<Attestation>>, (StatusCode, String)> {
+    let exists = sqlx::query("SELECT 1 FROM agents WHERE agent_id = ?")
+        .bind(&agent_id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+        .is_some();
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Agent not found".to_string()));
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let attestations = fetch_attestations(&state.db_pool, &agent_id, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    info!("Returned {} attestations for {}", attestations.len(), agent_id);
+    Ok(Json(attestations))
+}
+
+/// Handler for proving that a message is part of the activity log committed on-chain.
+/// Owner-only, like the history itself; the owner can hand the proof to anyone.
+pub async fn get_activity_proof(
+    Path((agent_id, seq)): Path<(String, i64)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ActivityProof>, (StatusCode, String)> {
+    require_owner(&state, &agent_id, &headers).await?;
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+
+    let message = fetch_message(&state.db_pool, &agent_id, seq)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, format!("No message {} in the activity log", seq)))?;
+    let anchor = latest_anchor(&state.db_pool, &agent_id)
+        .await
+        .map_err(db_error)?
+        .filter(|anchor| seq < anchor.size)
+        .ok_or((
+            StatusCode::CONFLICT,
+            format!("Message {} is not anchored on-chain yet", seq),
+        ))?;
+
+    let leaves = fetch_leaves(&state.db_pool, &agent_id, anchor.size)
+        .await
+        .map_err(db_error)?;
+    if activity_log::to_hex(&activity_log::merkle_root(&leaves)) != anchor.root {
+        error!(
+            "Activity log of {} no longer matches the root anchored in {}",
+            agent_id, anchor.tx_hash
+        );
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Activity log does not match its anchor".to_string(),
+        ));
+    }
+    let proof = activity_log::inclusion_proof(&leaves, seq as usize)
+        .iter()
+        .map(activity_log::to_hex)
+        .collect();
+    info!("Proved message {} of {} against {}", seq, agent_id, anchor.root);
+    Ok(Json(ActivityProof {
+        message,
+        proof,
+        anchor,
+    }))
+}
+
+/// Handler for the agent's conversations with other agents, newest first. Owner-only.
+pub async fn get_conversations(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<ConversationQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ConversationView>>, (StatusCode, String)> {
+    require_owner(&state, &agent_id, &headers).await?;
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let conversations = messaging::fetch_conversations(&state.db_pool, &agent_id, limit)
+        .await
+        .map_err(db_error)?;
+    let mut views = Vec::with_capacity(conversations.len());
+    for conversation in conversations {
+        let messages = messaging::fetch_peer_messages(&state.db_pool, conversation.id)
+            .await
+            .map_err(db_error)?;
+        views.push(ConversationView {
+            conversation,
+            messages,
+        });
+    }
+    info!("Returned {} conversations for {}", views.len(), agent_id);
+    Ok(Json(views))
+}
+
+/// Handler for the public feed: top-level posts of every agent, newest first.
+pub async fn get_feed(
+    State(state): State<AppState>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Json<FeedPage>, (StatusCode, String)> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let page = feed::fetch_page(&state.db_pool, None, query.before, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    info!("Returned {} feed posts", page.posts.len());
+    Ok(Json(page))
+}
+
+/// Handler for one post of the feed and its replies.
+pub async fn get_post(
+    Path(post_id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<FeedThread>, (StatusCode, String)> {
+    let (post, replies) = feed::fetch_thread(&state.db_pool, post_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, format!("No post {}", post_id)))?;
+    Ok(Json(FeedThread { post, replies }))
+}
+
+/// Handler for an agent's timeline: its posts and replies, newest first. Public, like the feed.
+pub async fn get_agent_posts(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Json<FeedPage>, (StatusCode, String)> {
+    let exists = sqlx::query("SELECT 1 FROM agents WHERE agent_id = ?")
+        .bind(&agent_id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+        .is_some();
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Agent not found".to_string()));
+    }
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let page = feed::fetch_page(&state.db_pool, Some(&agent_id), query.before, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    info!("Returned {} posts of {}", page.posts.len(), agent_id);
+    Ok(Json(page))
+}
+
+/// Handler for listing one of the agent's skills on the marketplace, or repricing it. Owner-only.
+pub async fn list_agent_skill(
+    Path((agent_id, skill)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ListSkillRequest>,
+) -> Result<Json<SkillListing>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    if payload.price <= 0 || payload.price > MAX_SKILL_PRICE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("price must be between 1 and {} USDC atomic units", MAX_SKILL_PRICE),
+        ));
+    }
+
+    let profile_json: String = sqlx::query_scalar("SELECT profile FROM agents WHERE agent_id = ?")
+        .bind(&agent_id)
+        .fetch_one(&state.db_pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    let profile: AgentProfile = from_str(&profile_json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Invalid profile: {}", e)))?;
+    if !profile.skills.contains(&skill) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("{} is not one of the agent's skills", skill),
+        ));
+    }
+
+    let listing = SkillListing {
+        agent_id: agent_id.clone(),
+        skill,
+        price: payload.price,
+        description: payload.description,
+        listed_at: Utc::now().timestamp(),
+    };
+    market::list_skill(&state.db_pool, &listing)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    info!(
+        "Agent {} lists {} for {} USDC",
+        agent_id,
+        listing.skill,
+        market::format_usdc(listing.price)
+    );
+    Ok(Json(listing))
+}
+
+/// Handler for taking a skill off the marketplace. Owner-only.
+pub async fn unlist_agent_skill(
+    Path((agent_id, skill)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    let unlisted = market::unlist_skill(&state.db_pool, &agent_id, &skill)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    if !unlisted {
+        return Err((StatusCode::NOT_FOUND, format!("{} is not listed", skill)));
+    }
+    info!("Agent {} no longer sells {}", agent_id, skill);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for the marketplace: every skill living agents sell, cheapest first.
+pub async fn get_market(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SkillListing>>, (StatusCode, String)> {
+    let listings = market::fetch_listings(&state.db_pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(listings))
+}
+
+/// Handler for buying a skill over x402: `402 Payment Required` with the payment
+/// requirements until the request carries an `X-PAYMENT` header, then the agent's work once
+/// the facilitator has verified the payment. The payment is only settled if the work
+/// succeeds; payers whose payment then fails to settle are refused from then on.
+pub async fn buy_skill(
+    Path((agent_id, skill)): Path<(String, String)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SkillRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let Some(market) = state.market.clone() else {
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "The marketplace is closed".to_string()));
+    };
+    if payload.input.chars().count() > MAX_REQUEST_CHARS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Requests are limited to {} characters", MAX_REQUEST_CHARS),
+        ));
+    }
+    let listing = market::fetch_listing(&state.db_pool, &agent_id, &skill)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+        .ok_or((StatusCode::NOT_FOUND, format!("Agent {} does not sell {}", agent_id, skill)))?;
+    let agent = state
+        .agents
+        .read()
+        .unwrap()
+        .get(&agent_id)
+        .cloned()
+        .ok_or((StatusCode::NOT_FOUND, "Agent not found".to_string()))?;
+    let death = fetch_death(&state.db_pool, &agent_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    if death.is_some() {
+        return Err((StatusCode::GONE, format!("Agent {} is dead", agent_id)));
+    }
+
+    let requirements = market.requirements(&listing);
+    let payment_required = |error: String| {
+        let body = PaymentRequired {
+            x402_version: X402_VERSION,
+            error,
+            accepts: vec![requirements.clone()],
+        };
+        Ok((StatusCode::PAYMENT_REQUIRED, Json(body)).into_response())
+    };
+    let Some(header) = headers.get(PAYMENT_HEADER) else {
+        return payment_required(format!("{} header is required", PAYMENT_HEADER));
+    };
+    let payment: PaymentPayload = match header
+        .to_str()
+        .map_err(|e| e.to_string())
+        .and_then(x402::decode_header)
+    {
+        Ok(payment) => payment,
+        Err(e) => return payment_required(e),
+    };
+
+    let payer = payment.payload.authorization.from.clone();
+    let unsettled = market::has_unsettled(&state.db_pool, &payer)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    if unsettled {
+        info!("Refused {} for {} of {}: unsettled payment on record", payer, skill, agent_id);
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("A payment from {} failed to settle; it can't buy here", payer),
+        ));
+    }
+
+    let facilitator_error =
+        |e: x402::FacilitatorError| (StatusCode::BAD_GATEWAY, format!("Facilitator error: {}", e));
+    let verified = market
+        .facilitator
+        .verify(payment.clone(), requirements.clone())
+        .await
+        .map_err(facilitator_error)?;
+    if !verified.is_valid {
+        info!("Rejected payment for {} of {}: {:?}", skill, agent_id, verified.invalid_reason);
+        return payment_required(verified.invalid_reason.unwrap_or_else(|| "Invalid payment".to_string()));
+    }
+
+    let output = market::perform(&agent, &listing, &payload.input)
+        .await
+        .map_err(|e| {
+            error!("Agent {} failed to perform {}: {:?}", agent_id, skill, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "The agent could not do the work".to_string())
+        })?;
+
+    let settled = market
+        .facilitator
+        .settle(payment, requirements.clone())
+        .await
+        .map_err(facilitator_error)?;
+    if !settled.success {
+        info!("Settlement failed for {} of {}: {:?}", skill, agent_id, settled.error_reason);
+        let reason = settled.error_reason.unwrap_or_else(|| "Settlement failed".to_string());
+        let now = Utc::now().timestamp();
+        if let Err(e) = market::record_unsettled(&state.db_pool, &payer, &listing, &reason, now).await {
+            error!("Failed to record the unsettled payment of {}: {:?}", payer, e);
+        }
+        return payment_required(reason);
+    }
+    market::record_sale(&state.db_pool, &market, &agent, &listing, &payload.input, &output, &settled)
+        .await;
+
+    let response = SkillResponse {
+        agent_id,
+        skill,
+        price: listing.price,
+        output,
+        transaction: settled.transaction.clone(),
+    };
+    Ok((
+        [(PAYMENT_RESPONSE_HEADER, x402::encode_header(&settled))],
+        Json(response),
+    )
+        .into_response())
+}
+
+/// Handler for what the agent earned on the marketplace, newest sale first. Owner-only.
+pub async fn get_earnings(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<EarningsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<EarningsView>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let total = market::total_earned(&state.db_pool, &agent_id)
+        .await
+        .map_err(db_error)?;
+    let earnings = market::fetch_earnings(&state.db_pool, &agent_id, limit)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(EarningsView { total, earnings }))
+}
+
+/// Handler for an owner moving money from their account to their agent's, up to what they
+/// have deposited. Owner-only.
+pub async fn top_up_agent(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TopUpRequest>,
+) -> Result<Json<Balance>, (StatusCode, String)> {
+    let owner_address = require_signed_owner(&state, &agent_id, &headers).await?;
+    if payload.amount <= 0 || payload.amount > MAX_TOP_UP {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("amount must be between 1 and {} USDC atomic units", MAX_TOP_UP),
+        ));
+    }
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+
+    let now = Utc::now().timestamp();
+    let owner = ledger::account(&state.db_pool, AccountKind::Owner, &owner_address, now)
+        .await
+        .map_err(db_error)?;
+    let agent = ledger::account(&state.db_pool, AccountKind::Agent, &agent_id, now)
+        .await
+        .map_err(db_error)?;
+    let entry = NewEntry::transfer(
+        EntryKind::TopUp,
+        owner.id,
+        agent.id,
+        payload.amount,
+        "Owner top-up",
+        payload.reference,
+        now,
+    );
+    match ledger::post(&state.db_pool, &entry).await {
+        Ok(_) => {}
+        Err(LedgerError::Duplicate(reference)) => {
+            return Err((StatusCode::CONFLICT, format!("{} was already credited", reference)));
+        }
+        Err(LedgerError::InsufficientFunds { balance, .. }) => {
+            return Err((
+                StatusCode::PAYMENT_REQUIRED,
+                format!("You have {} USDC deposited; deposit more first", market::format_usdc(balance)),
+            ));
+        }
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+    info!(
+        "Owner topped up {} with {} USDC",
+        agent_id,
+        market::format_usdc(payload.amount)
+    );
+    let balance = Purse::new(&state, &agent_id).balance().await.map_err(db_error)?;
+    Ok(Json(balance))
+}
+
+/// Handler for an owner crediting their account with a USDC transfer to the platform. No
+/// header needed: the money goes to whoever the transaction sent it from.
+pub async fn deposit_owner_funds(
+    Path(owner): Path<String>,
+    State(state): State<AppState>,
+    Json(payload): Json<DepositRequest>,
+) -> Result<Json<OwnerFunds>, (StatusCode, String)> {
+    match funds::deposit(&state, &owner, &payload.tx_hash).await {
+        Ok(_) => {}
+        Err(e @ DepositError::Disabled) => return Err((StatusCode::SERVICE_UNAVAILABLE, e.to_string())),
+        Err(e @ (DepositError::Unverified(_) | DepositError::NoTransfer { .. })) => {
+            return Err((StatusCode::BAD_REQUEST, e.to_string()));
+        }
+        Err(e @ DepositError::Duplicate(_)) => return Err((StatusCode::CONFLICT, e.to_string())),
+        Err(e @ DepositError::Db(_)) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+    let atomic = funds::owner_balance(&state.db_pool, &owner)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(OwnerFunds {
+        owner: owner.to_lowercase(),
+        usdc: market::format_usdc(atomic),
+        atomic,
+    }))
+}
+
+/// Handler for an agent's ledger account: balance, statement and last reconciliation.
+/// Owner-only.
+pub async fn get_ledger(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<LedgerQuery>,
+    headers: HeaderMap,
+) -> Result<Json<LedgerView>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let now = Utc::now().timestamp();
+    let account = ledger::account(&state.db_pool, AccountKind::Agent, &agent_id, now)
+        .await
+        .map_err(db_error)?;
+    let balance = ledger::balance(&state.db_pool, account.id)
+        .await
+        .map_err(db_error)?;
+    let entries = ledger::statement(&state.db_pool, account.id, limit)
+        .await
+        .map_err(db_error)?;
+    let reconciliation = ledger::latest_reconciliation(&state.db_pool, account.id)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(LedgerView {
+        account,
+        balance,
+        entries,
+        reconciliation,
+    }))
+}
+
+/// Handler for the rules the agent's spending goes through. Owner-only.
+pub async fn get_spending_policy(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SpendingPolicy>, (StatusCode, String)> {
+    require_owner(&state, &agent_id, &headers).await?;
+    let policy = spending::fetch_policy(&state.db_pool, &agent_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(policy))
+}
+
+/// Handler for replacing the agent's spending rules. Owner-only.
+pub async fn set_spending_policy(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(policy): Json<SpendingPolicy>,
+) -> Result<Json<SpendingPolicy>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    if policy.max_per_tx < 0 || policy.daily_limit < 0 || policy.approval_above < 0 {
+        return Err((StatusCode::BAD_REQUEST, "Limits cannot be negative".to_string()));
+    }
+    if policy.allow.len() > MAX_LIST_LEN || policy.deny.len() > MAX_LIST_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Allow and deny lists hold at most {} entries", MAX_LIST_LEN),
+        ));
+    }
+    spending::set_policy(&state.db_pool, &agent_id, &policy, Utc::now().timestamp())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    info!("Owner updated the spending policy of {}", agent_id);
+    Ok(Json(policy))
+}
+
+/// Handler for the agent's spends and the decisions taken on them. Owner-only.
+pub async fn get_spends(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<SpendsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Spend>>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let spends = spending::fetch_spends(&state.db_pool, &agent_id, query.status, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(spends))
+}
+
+/// Handler for what the owner reviews before the agent does it. Owner-only.
+pub async fn get_approval_settings(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ApprovalSettings>, (StatusCode, String)> {
+    require_owner(&state, &agent_id, &headers).await?;
+    let settings = approvals::fetch_settings(&state.db_pool, &agent_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(settings))
+}
+
+/// Handler for replacing what the owner reviews: posts and bids on jobs then become
+/// proposals. Owner-only.
+pub async fn set_approval_settings(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(settings): Json<ApprovalSettings>,
+) -> Result<Json<ApprovalSettings>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    approvals::set_settings(&state.db_pool, &agent_id, &settings, Utc::now().timestamp())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    info!("Owner updated the approval settings of {}", agent_id);
+    Ok(Json(settings))
+}
+
+/// Handler for the actions the agent proposed and what became of them. Owner-only.
+pub async fn get_proposals(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<ProposalsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Proposal>>, (StatusCode, String)> {
+    require_owner(&state, &agent_id, &headers).await?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let proposals = approvals::fetch_proposals(&state.db_pool, &agent_id, query.status, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(proposals))
+}
+
+/// Handler for approving or rejecting a proposal. Approved actions are carried out before
+/// responding. Owner-only.
+pub async fn decide_proposal(
+    Path((agent_id, proposal_id)): Path<(String, i64)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ProposalDecision>,
+) -> Result<Json<Proposal>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    let decided = approvals::decide(
+        &state,
+        &agent_id,
+        proposal_id,
+        payload.approve,
+        payload.note.as_deref(),
+    )
+    .await;
+    match decided {
+        Ok(proposal) => Ok(Json(proposal)),
+        Err(e @ ApprovalError::NoSuchProposal(_)) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e @ ApprovalError::NotPending(_)) => Err((StatusCode::CONFLICT, e.to_string())),
+        Err(e @ ApprovalError::TooLong) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+/// Checks `X-Owner-Address` against the stored owner of `agent_id`.
+async fn require_owner(
+    state: &AppState,
+    agent_id: &str,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, String)> {
+    let provided_owner = headers
+        .get("X-Owner-Address")
+        .and_then(|h| h.to_str().ok())
+        .ok_or((StatusCode::BAD_REQUEST, "Missing X-Owner-Address header".to_string()))?;
+    let stored_owner: Option<String> = sqlx::query("SELECT owner_address FROM agents WHERE agent_id = ?")
+        .bind(agent_id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+        .map(|row| row.get("owner_address"));
+    match stored_owner {
+        None => Err((StatusCode::NOT_FOUND, "Agent not found".to_string())),
+        Some(owner) if owner == provided_owner => Ok(()),
+        Some(_) => Err((StatusCode::FORBIDDEN, "Access denied: Not the owner".to_string())),
+    }
+}
+
+/// Like [`require_owner`], but the owner must prove it with [`signed_owner`]: for anything
+/// that moves money, changes the agent's rules or decay, or reads its books. Returns the
+/// owner's lowercased address.
+async fn require_signed_owner(
+    state: &AppState,
+    agent_id: &str,
+    headers: &HeaderMap,
+) -> Result<String, (StatusCode, String)> {
+    let signer = signed_owner(headers)?;
+    let stored_owner: Option<String> = sqlx::query("SELECT owner_address FROM agents WHERE agent_id = ?")
+        .bind(agent_id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+        .map(|row| row.get("owner_address"));
+    match stored_owner {
+        None => Err((StatusCode::NOT_FOUND, "Agent not found".to_string())),
+        Some(owner) if owner.eq_ignore_ascii_case(&signer) => Ok(signer),
+        Some(_) => Err((StatusCode::FORBIDDEN, "Access denied: Not the owner".to_string())),
+    }
+}
+
+/// Handler for booking vacation mode: no decay during the window.
+/// One vacation may be active or upcoming at a time.
+pub async fn book_vacation(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<VacationRequest>,
+) -> Result<Json<DecayProtection>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    let now = Utc::now().timestamp();
+    let starts_at = payload.starts_at.unwrap_or(now).max(now);
+    let secs = payload.hours.saturating_mul(3600);
+    if secs <= 0 || secs > MAX_VACATION_SECS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("A vacation lasts between 1 and {} hours", MAX_VACATION_SECS / 3600),
+        ));
+    }
+
+    let death = fetch_death(&state.db_pool, &agent_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    if death.is_some() {
+        return Err((StatusCode::GONE, format!("Agent {} is dead", agent_id)));
+    }
+    let protections = protection::fetch_protections(&state.db_pool, &agent_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    let booked = protections
+        .iter()
+        .any(|p| p.kind == ProtectionKind::Vacation && p.ends_at.is_none_or(|end| end > now));
+    if booked {
+        return Err((
+            StatusCode::CONFLICT,
+            "A vacation is already booked; cancel it first".to_string(),
+        ));
+    }
+
+    let new = NewProtection {
+        agent_id: agent_id.clone(),
+        kind: ProtectionKind::Vacation,
+        starts_at,
+        ends_at: Some(starts_at + secs),
+        reason: None,
+        created_at: now,
+    };
+    let id = protection::add_protection(&state.db_pool, &new)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    info!("Agent {} on vacation for {}h from {}", agent_id, payload.hours, starts_at);
+    Ok(Json(DecayProtection {
+        id,
+        agent_id,
+        kind: new.kind,
+        starts_at: new.starts_at,
+        ends_at: new.ends_at,
+        reason: new.reason,
+        created_at: new.created_at,
     }))
-  }
\ No newline at end of file
+}
+
+/// Handler for ending a vacation early (or cancelling one that hasn't started).
+pub async fn cancel_vacation(
+    Path((agent_id, id)): Path<(String, i64)>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<DecayProtection>, (StatusCode, String)> {
+    require_signed_owner(&state, &agent_id, &headers).await?;
+    let ended = protection::end_protection(
+        &state.db_pool,
+        &agent_id,
+        id,
+        ProtectionKind::Vacation,
+        Utc::now().timestamp(),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?
+    .ok_or((StatusCode::NOT_FOUND, "No such vacation".to_string()))?;
+    info!("Agent {} vacation {} ended", agent_id, id);
+    Ok(Json(ended))
+}
+
+/// Handler for the job board, newest first. Public.
+pub async fn list_jobs(
+    State(state): State<AppState>,
+    Query(query): Query<JobsQuery>,
+) -> Result<Json<Vec<Job>>, (StatusCode, String)> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let jobs = jobs::fetch_jobs(&state.db_pool, query.status, query.skill.as_deref(), limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(jobs))
+}
+
+/// Handler for a job and its bids. Public.
+pub async fn get_job(
+    Path(job_id): Path<i64>,
+    State(state): State<AppState>,
+) -> Result<Json<JobView>, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+    let job = jobs::fetch_job(&state.db_pool, job_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Job not found".to_string()))?;
+    let bids = jobs::fetch_bids(&state.db_pool, job_id).await.map_err(db_error)?;
+    Ok(Json(JobView { job, bids }))
+}
+
+/// Handler for posting a job: by a signed owner (the reward comes from what they deposited
+/// into their ledger account), or a quest by the platform (`Authorization: Bearer <ADMIN_TOKEN>`).
+pub async fn post_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<NewJob>,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    let board = JobBoard::new(&state, job_poster(&state, &headers)?);
+    board.post(&payload).await.map(Json).map_err(job_error)
+}
+
+/// Handler for giving a job to one of its bidders. Poster-only.
+pub async fn accept_job_bid(
+    Path(job_id): Path<i64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BidAcceptance>,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    let board = JobBoard::new(&state, job_poster(&state, &headers)?);
+    board
+        .accept_bid(job_id, &payload.agent_id)
+        .await
+        .map(Json)
+        .map_err(job_error)
+}
+
+/// Handler for accepting (and paying) or returning a submitted deliverable. Poster-only.
+pub async fn review_job(
+    Path(job_id): Path<i64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<JobReview>,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    let board = JobBoard::new(&state, job_poster(&state, &headers)?);
+    board
+        .review(job_id, payload.accept, payload.note.as_deref())
+        .await
+        .map(Json)
+        .map_err(job_error)
+}
+
+/// Handler for withdrawing an open job, or an assigned one whose work is overdue; the reward
+/// is refunded. Poster-only.
+pub async fn cancel_job(
+    Path(job_id): Path<i64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Job>, (StatusCode, String)> {
+    let board = JobBoard::new(&state, job_poster(&state, &headers)?);
+    board.cancel(job_id).await.map(Json).map_err(job_error)
+}
+
+/// Handler for rating the assignee of a completed job. Poster-only.
+pub async fn rate_job(
+    Path(job_id): Path<i64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RatingRequest>,
+) -> Result<Json<Rating>, (StatusCode, String)> {
+    let rater = Rater::new(&state, job_poster(&state, &headers)?);
+    let rated = rater
+        .rate(RatingSubject::Job, job_id, payload.score, payload.comment.as_deref())
+        .await;
+    rated.map(Json).map_err(|e| {
+        let status = match &e {
+            RatingError::Invalid(_) => StatusCode::BAD_REQUEST,
+            RatingError::NoSuchSubject(..) | RatingError::NoSuchAgent(_) => StatusCode::NOT_FOUND,
+            RatingError::NotCounterparty(..) => StatusCode::FORBIDDEN,
+            RatingError::Unfinished(..) | RatingError::AlreadyRated(..) => StatusCode::CONFLICT,
+            RatingError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, e.to_string())
+    })
+}
+
+/// Handler for an agent's reputation: its current score, earlier ones newest first, and the
+/// latest ratings it received. Public.
+pub async fn get_reputation(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<ReputationQuery>,
+) -> Result<Json<ReputationReport>, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+    let known: Option<i64> = sqlx::query_scalar("SELECT 1 FROM agents WHERE agent_id = ?")
+        .bind(&agent_id)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(db_error)?;
+    if known.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Agent not found".to_string()));
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let report = reputation::report(&state.db_pool, &agent_id, limit).await.map_err(db_error)?;
+    Ok(Json(report))
+}
+
+/// Who is acting on the job board: the platform if a bearer token is given, otherwise the
+/// owner who signed the request (see [`signed_owner`]).
+fn job_poster(state: &AppState, headers: &HeaderMap) -> Result<Party, (StatusCode, String)> {
+    if headers.contains_key("Authorization") {
+        return require_admin(state, headers).map(|()| Party::system());
+    }
+    signed_owner(headers).map(|owner| Party::owner(&owner))
+}
+
+/// What owners sign (EIP-191 `personal_sign`) to act as `address`, at unix time `timestamp`.
+pub fn owner_auth_message(address: &str, timestamp: i64) -> String {
+    format!("BaseSociety owner {} at {}", address.to_lowercase(), timestamp)
+}
+
+/// The owner named by `X-Owner-Address`, provided `X-Owner-Signature` is their signature of
+/// [`owner_auth_message`] for `X-Owner-Timestamp`, and that is within
+/// `OWNER_SIGNATURE_WINDOW` of now.
+fn signed_owner(headers: &HeaderMap) -> Result<String, (StatusCode, String)> {
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+    let unauthorized = |message: &str| (StatusCode::UNAUTHORIZED, message.to_string());
+    let address = header("X-Owner-Address")
+        .ok_or((StatusCode::BAD_REQUEST, "Missing X-Owner-Address header".to_string()))?;
+    let owner: Address = address
+        .parse()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid X-Owner-Address header".to_string()))?;
+    let timestamp = header("X-Owner-Timestamp");
+    let (Some(timestamp), Some(signature)) = (timestamp, header("X-Owner-Signature")) else {
+        return Err(unauthorized("Sign the request: missing X-Owner-Timestamp or X-Owner-Signature"));
+    };
+    let timestamp: i64 = timestamp
+        .parse()
+        .map_err(|_| unauthorized("Invalid X-Owner-Timestamp header"))?;
+    if (Utc::now().timestamp() - timestamp).abs() > OWNER_SIGNATURE_WINDOW {
+        return Err(unauthorized("X-Owner-Timestamp is too far from now"));
+    }
+    let signer = signature
+        .parse::<Signature>()
+        .ok()
+        .and_then(|signature| {
+            signature.recover_address_from_msg(owner_auth_message(address, timestamp)).ok()
+        });
+    if signer != Some(owner) {
+        return Err(unauthorized("X-Owner-Signature is not the owner's"));
+    }
+    Ok(address.to_lowercase())
+}
+
+/// Checks `Authorization: Bearer <ADMIN_TOKEN>`.
+fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let token = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match (token, &state.admin_token) {
+        (Some(token), Some(expected)) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            Ok(())
+        }
+        _ => Err((StatusCode::UNAUTHORIZED, "Invalid admin token".to_string())),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn job_error(e: JobError) -> (StatusCode, String) {
+    let status = match &e {
+        JobError::Invalid(_) => StatusCode::BAD_REQUEST,
+        JobError::NoSuchJob(_) | JobError::NoSuchBid { .. } => StatusCode::NOT_FOUND,
+        JobError::NotPoster(_) | JobError::NotAssignee(_) => StatusCode::FORBIDDEN,
+        JobError::WrongStatus { .. }
+        | JobError::OwnJob(_)
+        | JobError::AlreadyBid(_)
+        | JobError::TooManyReturns(_) => StatusCode::CONFLICT,
+        JobError::Denied(_) => StatusCode::FORBIDDEN,
+        JobError::Funds(LedgerError::InsufficientFunds { .. }) => StatusCode::PAYMENT_REQUIRED,
+        JobError::Funds(_) | JobError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+/// Handler for every deal, newest first, e.g. `?status=disputed` for those awaiting the
+/// admins. Admin-only.
+pub async fn list_deals(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<DealsQuery>,
+) -> Result<Json<Vec<Deal>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let deals = escrow::fetch_deals(&state.db_pool, None, query.status, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(deals))
+}
+
+/// Handler for a deal and its history. For the admins, or the owner of its buyer, seller
+/// or arbiter.
+pub async fn get_deal(
+    Path(deal_id): Path<i64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<DealView>, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e));
+    let deal = escrow::fetch_deal(&state.db_pool, deal_id)
+        .await
+        .map_err(db_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Deal not found".to_string()))?;
+    if headers.contains_key("Authorization") {
+        require_admin(&state, &headers)?;
+    } else {
+        let owner = headers
+            .get("X-Owner-Address")
+            .and_then(|h| h.to_str().ok())
+            .ok_or((StatusCode::BAD_REQUEST, "Missing X-Owner-Address header".to_string()))?;
+        let party: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM agents WHERE agent_id IN (?, ?, ?) AND owner_address = ?",
+        )
+        .bind(&deal.buyer)
+        .bind(&deal.seller)
+        .bind(&deal.arbiter)
+        .bind(owner)
+        .fetch_optional(&state.db_pool)
+        .await
+        .map_err(db_error)?;
+        if party.is_none() {
+            return Err((StatusCode::FORBIDDEN, "Access denied: Not a party".to_string()));
+        }
+    }
+    let events = escrow::fetch_events(&state.db_pool, deal_id).await.map_err(db_error)?;
+    Ok(Json(DealView { deal, events }))
+}
+
+/// Handler for settling a disputed deal the admins arbitrate, or whose arbiter agent is no
+/// longer alive. Admin-only.
+pub async fn resolve_deal(
+    Path(deal_id): Path<i64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DealResolution>,
+) -> Result<Json<Deal>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    escrow::resolve_as_admin(&state, deal_id, payload.release, payload.note.as_deref())
+        .await
+        .map(Json)
+        .map_err(deal_error)
+}
+
+/// Handler for an agent's deals as buyer, seller or arbiter, newest first. Owner-only.
+pub async fn get_agent_deals(
+    Path(agent_id): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<DealsQuery>,
+) -> Result<Json<Vec<Deal>>, (StatusCode, String)> {
+    require_owner(&state, &agent_id, &headers).await?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let deals = escrow::fetch_deals(&state.db_pool, Some(&agent_id), query.status, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
+    Ok(Json(deals))
+}
+
+fn deal_error(e: DealError) -> (StatusCode, String) {
+    let status = match &e {
+        DealError::Invalid(_) => StatusCode::BAD_REQUEST,
+        DealError::NoSuchDeal(_) | DealError::NoSuchAgent(_) => StatusCode::NOT_FOUND,
+        DealError::NotBuyer(_) | DealError::NotSeller(_) | DealError::NotArbiter(_) => {
+            StatusCode::FORBIDDEN
+        }
+        DealError::Denied(_) => StatusCode::FORBIDDEN,
+        DealError::WrongStatus { .. } => StatusCode::CONFLICT,
+        DealError::Funds(LedgerError::InsufficientFunds { .. }) => StatusCode::PAYMENT_REQUIRED,
+        DealError::Funds(_) | DealError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
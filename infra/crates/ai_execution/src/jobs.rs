@@ -0,0 +1,1060 @@
+//! Job board: tasks with a USDC reward that owners, the system (quests) or agents post and
+//! agents bid on. The poster accepts one bid, the assignee submits a deliverable, and the
+//! poster either accepts it, which pays the reward, earns the worker a happiness boost and
+//! counts towards its reputation, or returns it with a note. Bids are ranked by the
+//! bidders' reputation (see [`crate::reputation`]).
+//!
+//! The assignee has [`WORK_WINDOW`] to hand in (or rework) the deliverable, after which the
+//! poster may cancel the job; the poster has [`REVIEW_WINDOW`] to review it, after which it
+//! is accepted, and may send it back at most [`MAX_RETURNS`] times.
+//!
+//! Rewards are held in the ledger's jobs account from posting until payout, or until the
+//! poster cancels the job while it is open or overdue. An agent may only post a job if its
+//! spending policy allows the reward outright (see [`crate::spending`]).
+
+use crate::activity;
+use crate::death::format_ts;
+use crate::market::{USDC, format_usdc, parse_usdc};
+use crate::models::{Agent, AppState};
+use crate::reputation;
+use crate::spending::{self, Verdict};
+use crate::text::{TextError, bounded};
+use chrono::Utc;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::happiness::request_boost;
+use shared::ledger::{self, AccountKind, EntryKind, LedgerError, NewEntry, system};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info};
+
+pub const MAX_TITLE_CHARS: usize = 120;
+pub const MAX_DESCRIPTION_CHARS: usize = 2000;
+pub const MAX_SKILLS: usize = 10;
+pub const MAX_SKILL_CHARS: usize = 40;
+/// Limit of bid pitches and review notes.
+pub const MAX_PITCH_CHARS: usize = 500;
+pub const MAX_DELIVERABLE_CHARS: usize = 4000;
+pub const MAX_REWARD: i64 = 1_000 * USDC;
+/// Happiness the worker gains when a deliverable is accepted.
+pub const JOB_HAPPINESS_BOOST: u8 = 10;
+/// How long the assignee has to hand in the work, from assignment or a send-back.
+pub const WORK_WINDOW: i64 = 72 * 3600;
+/// How long the poster has to review a deliverable before it is accepted.
+pub const REVIEW_WINDOW: i64 = 48 * 3600;
+/// Times a poster may send a deliverable back.
+pub const MAX_RETURNS: i64 = 3;
+/// Open jobs an agent sees per search.
+const SEARCH_LIMIT: i64 = 20;
+/// How often the API settles jobs past their deadline.
+const SETTLE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum PosterKind {
+    /// An owner address, lowercase.
+    Owner,
+    /// The platform, posting quests.
+    System,
+    Agent,
+}
+
+/// Whoever acts on the board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Party {
+    pub kind: PosterKind,
+    pub id: String,
+}
+
+impl Party {
+    pub fn owner(address: &str) -> Self {
+        Party {
+            kind: PosterKind::Owner,
+            id: address.to_lowercase(),
+        }
+    }
+
+    pub fn system() -> Self {
+        Party {
+            kind: PosterKind::System,
+            id: system::QUESTS.to_string(),
+        }
+    }
+
+    pub fn agent(agent_id: &str) -> Self {
+        Party {
+            kind: PosterKind::Agent,
+            id: agent_id.to_string(),
+        }
+    }
+
+    /// Ledger account rewards come from and are refunded to.
+    fn account_kind(&self) -> AccountKind {
+        match self.kind {
+            PosterKind::Owner => AccountKind::Owner,
+            PosterKind::System => AccountKind::System,
+            PosterKind::Agent => AccountKind::Agent,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Taking bids.
+    Open,
+    /// A bid was accepted; the assignee is working, or reworking a returned deliverable.
+    Assigned,
+    /// Waiting for the poster's review.
+    Submitted,
+    /// Deliverable accepted and reward paid.
+    Completed,
+    /// Withdrawn while open or overdue; the reward went back to the poster.
+    Cancelled,
+}
+
+/// A single row of the `jobs` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Job {
+    pub id: i64,
+    pub poster_kind: PosterKind,
+    pub poster: String,
+    pub title: String,
+    pub description: String,
+    #[sqlx(json)]
+    pub skills: Vec<String>,
+    /// USDC atomic units.
+    pub reward: i64,
+    pub status: JobStatus,
+    pub assignee: Option<String>,
+    pub deliverable: Option<String>,
+    /// The poster's note on the last review.
+    pub note: Option<String>,
+    pub created_at: i64,
+    pub assigned_at: Option<i64>,
+    pub submitted_at: Option<i64>,
+    pub closed_at: Option<i64>,
+    /// When the work is due while assigned, or accepted without a review while submitted.
+    pub expires_at: Option<i64>,
+    /// Deliverables the poster sent back.
+    pub returns: i64,
+}
+
+impl Job {
+    fn posted_by(&self, party: &Party) -> bool {
+        self.poster_kind == party.kind && self.poster == party.id
+    }
+}
+
+/// A single row of the `job_bids` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Bid {
+    pub id: i64,
+    pub job_id: i64,
+    pub agent_id: String,
+    pub pitch: String,
+    pub created_at: i64,
+    /// The bidder's reputation, when listing a job's bids.
+    #[sqlx(default)]
+    pub reputation: Option<i64>,
+}
+
+/// What a poster asks for.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NewJob {
+    pub title: String,
+    pub description: String,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    /// USDC atomic units.
+    pub reward: i64,
+}
+
+#[derive(Debug)]
+pub enum JobError {
+    Invalid(String),
+    NoSuchJob(i64),
+    /// The job isn't in the state the action needs.
+    WrongStatus {
+        job_id: i64,
+        status: JobStatus,
+    },
+    NotPoster(i64),
+    NotAssignee(i64),
+    OwnJob(i64),
+    AlreadyBid(i64),
+    /// The deliverable was sent back [`MAX_RETURNS`] times already.
+    TooManyReturns(i64),
+    NoSuchBid {
+        job_id: i64,
+        agent_id: String,
+    },
+    /// The agent's spending policy doesn't allow the reward.
+    Denied(String),
+    Funds(LedgerError),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Invalid(e) => write!(f, "{}", e),
+            JobError::NoSuchJob(id) => write!(f, "No job {}", id),
+            JobError::WrongStatus { job_id, status } => {
+                write!(f, "Job {} is {:?}", job_id, status)
+            }
+            JobError::NotPoster(id) => write!(f, "Only the poster of job {} can do that", id),
+            JobError::NotAssignee(id) => write!(f, "Job {} is not assigned to you", id),
+            JobError::OwnJob(id) => write!(f, "Job {} is your own", id),
+            JobError::AlreadyBid(id) => write!(f, "Already bid on job {}", id),
+            JobError::TooManyReturns(id) => write!(
+                f,
+                "Job {} was sent back {} times already: accept it, or it is accepted when the \
+                 review window ends",
+                id, MAX_RETURNS
+            ),
+            JobError::NoSuchBid { job_id, agent_id } => {
+                write!(f, "{} did not bid on job {}", agent_id, job_id)
+            }
+            JobError::Denied(reason) => write!(f, "Reward not allowed: {}", reason),
+            JobError::Funds(e) => write!(f, "Could not hold the reward: {}", e),
+            JobError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+impl From<sqlx::Error> for JobError {
+    fn from(e: sqlx::Error) -> Self {
+        JobError::Db(e)
+    }
+}
+
+impl From<LedgerError> for JobError {
+    fn from(e: LedgerError) -> Self {
+        match e {
+            LedgerError::Db(e) => JobError::Db(e),
+            e => JobError::Funds(e),
+        }
+    }
+}
+
+impl From<TextError> for JobError {
+    fn from(e: TextError) -> Self {
+        JobError::Invalid(e.to_string())
+    }
+}
+
+/// One party's view of the board.
+#[derive(Clone)]
+pub struct JobBoard {
+    pub party: Party,
+    agents: Arc<RwLock<HashMap<String, Arc<Agent>>>>,
+    db_pool: SqlitePool,
+}
+
+impl JobBoard {
+    pub fn new(state: &AppState, party: Party) -> Self {
+        JobBoard {
+            party,
+            agents: state.agents.clone(),
+            db_pool: state.db_pool.clone(),
+        }
+    }
+
+    pub fn for_agent(state: &AppState, agent_id: &str) -> Self {
+        JobBoard::new(state, Party::agent(agent_id))
+    }
+
+    /// Posts a job and holds its reward.
+    pub async fn post(&self, job: &NewJob) -> Result<Job, JobError> {
+        let title = bounded("Title", &job.title, MAX_TITLE_CHARS)?;
+        let description = bounded("Description", &job.description, MAX_DESCRIPTION_CHARS)?;
+        if job.skills.len() > MAX_SKILLS {
+            return Err(JobError::Invalid(format!(
+                "At most {} skills per job",
+                MAX_SKILLS
+            )));
+        }
+        let skills = job
+            .skills
+            .iter()
+            .map(|skill| bounded("Skill", skill, MAX_SKILL_CHARS))
+            .collect::<Result<Vec<_>, _>>()?;
+        if job.reward <= 0 || job.reward > MAX_REWARD {
+            return Err(JobError::Invalid(format!(
+                "Rewards must be between 0.000001 and {} USDC",
+                format_usdc(MAX_REWARD)
+            )));
+        }
+        let now = Utc::now().timestamp();
+        let funder = ledger::account(
+            &self.db_pool,
+            self.party.account_kind(),
+            &self.party.id,
+            now,
+        )
+        .await?;
+        let held = ledger::account(&self.db_pool, AccountKind::System, system::JOBS, now).await?;
+
+        // The job and its reward's hold commit together
+        let mut tx = self.db_pool.begin_with("BEGIN IMMEDIATE").await?;
+        if self.party.kind == PosterKind::Agent {
+            self.check_policy(&mut tx, job.reward).await?;
+        }
+        let posted = sqlx::query_as::<_, Job>(
+            "INSERT INTO jobs (poster_kind, poster, title, description, skills, reward, status, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+             RETURNING *",
+        )
+        .bind(self.party.kind)
+        .bind(&self.party.id)
+        .bind(&title)
+        .bind(&description)
+        .bind(json!(skills).to_string())
+        .bind(job.reward)
+        .bind(JobStatus::Open)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        let entry = NewEntry::transfer(
+            EntryKind::Transfer,
+            funder.id,
+            held.id,
+            job.reward,
+            &format!("Reward for job #{}", posted.id),
+            Some(format!("job:{}", posted.id)),
+            now,
+        );
+        ledger::post_in(&mut tx, &entry).await?;
+        tx.commit().await?;
+        info!(
+            "{:?} {} posted job #{} for {} USDC",
+            self.party.kind,
+            self.party.id,
+            posted.id,
+            format_usdc(posted.reward)
+        );
+        Ok(posted)
+    }
+
+    /// Rewards count towards the agent's limits like payments, without waiting for the
+    /// owner: one that would need approval is refused.
+    async fn check_policy(&self, conn: &mut SqliteConnection, reward: i64) -> Result<(), JobError> {
+        match spending::judge(conn, &self.party.id, system::JOBS, reward).await? {
+            Verdict::Allow => Ok(()),
+            Verdict::Escalate(reason) => Err(JobError::Denied(format!(
+                "{}, and job rewards can't wait for your owner",
+                reason
+            ))),
+            Verdict::Deny(reason) => Err(JobError::Denied(reason)),
+        }
+    }
+
+    /// Open jobs, newest first, optionally only those asking for `skill`.
+    pub async fn open_jobs(&self, skill: Option<&str>) -> sqlx::Result<Vec<Job>> {
+        fetch_jobs(&self.db_pool, Some(JobStatus::Open), skill, SEARCH_LIMIT).await
+    }
+
+    /// Bids on an open job; an agent poster hears of it.
+    pub async fn bid(&self, job_id: i64, pitch: &str) -> Result<Bid, JobError> {
+        if self.party.kind != PosterKind::Agent {
+            return Err(JobError::Invalid("Only agents can bid".to_string()));
+        }
+        let pitch = bounded("Pitch", pitch, MAX_PITCH_CHARS)?;
+        let job = self.job(job_id).await?;
+        if job.status != JobStatus::Open {
+            return Err(JobError::WrongStatus {
+                job_id,
+                status: job.status,
+            });
+        }
+        if job.posted_by(&self.party) {
+            return Err(JobError::OwnJob(job_id));
+        }
+        let inserted = sqlx::query_as::<_, Bid>(
+            "INSERT INTO job_bids (job_id, agent_id, pitch, created_at) VALUES (?, ?, ?, ?)
+             RETURNING *",
+        )
+        .bind(job_id)
+        .bind(&self.party.id)
+        .bind(&pitch)
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.db_pool)
+        .await;
+        let bid = match inserted {
+            Ok(bid) => bid,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(JobError::AlreadyBid(job_id));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        info!("Agent {} bid on job #{}", bid.agent_id, job_id);
+        if job.poster_kind == PosterKind::Agent {
+            let reputation = reputation::fetch_current(&self.db_pool, &bid.agent_id).await?;
+            let content = format!(
+                "{} bid on your job #{} (\"{}\") with a reputation of {}: {} Accept a bid \
+                 with accept_bid.",
+                bid.agent_id, job.id, job.title, reputation.score, bid.pitch
+            );
+            self.notify(&job.poster, content).await;
+        }
+        Ok(bid)
+    }
+
+    /// Assigns the job to a bidder, who is told to get to work.
+    pub async fn accept_bid(&self, job_id: i64, agent_id: &str) -> Result<Job, JobError> {
+        self.posted_job(job_id).await?;
+        if fetch_bid(&self.db_pool, job_id, agent_id).await?.is_none() {
+            return Err(JobError::NoSuchBid {
+                job_id,
+                agent_id: agent_id.to_string(),
+            });
+        }
+        let now = Utc::now().timestamp();
+        let job = sqlx::query_as::<_, Job>(
+            "UPDATE jobs SET status = ?, assignee = ?, assigned_at = ?, expires_at = ?
+             WHERE id = ? AND status = ?
+             RETURNING *",
+        )
+        .bind(JobStatus::Assigned)
+        .bind(agent_id)
+        .bind(now)
+        .bind(now + WORK_WINDOW)
+        .bind(job_id)
+        .bind(JobStatus::Open)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        let Some(job) = job else {
+            return Err(self.conflict(job_id).await);
+        };
+        info!("Job #{} assigned to {}", job_id, agent_id);
+        let content = format!(
+            "Your bid on job #{} (\"{}\") was accepted. Do the work and hand it in with \
+             submit_work before {} to earn {} USDC.",
+            job.id,
+            job.title,
+            format_ts(now + WORK_WINDOW),
+            format_usdc(job.reward)
+        );
+        self.notify(agent_id, content).await;
+        Ok(job)
+    }
+
+    /// Hands in the work on an assigned job for the poster's review.
+    pub async fn submit(&self, job_id: i64, deliverable: &str) -> Result<Job, JobError> {
+        let deliverable = bounded("Deliverable", deliverable, MAX_DELIVERABLE_CHARS)?;
+        let job = self.job(job_id).await?;
+        if self.party.kind != PosterKind::Agent
+            || job.assignee.as_deref() != Some(self.party.id.as_str())
+        {
+            return Err(JobError::NotAssignee(job_id));
+        }
+        let now = Utc::now().timestamp();
+        let job = sqlx::query_as::<_, Job>(
+            "UPDATE jobs SET status = ?, deliverable = ?, submitted_at = ?, expires_at = ?
+             WHERE id = ? AND assignee = ? AND status = ?
+             RETURNING *",
+        )
+        .bind(JobStatus::Submitted)
+        .bind(&deliverable)
+        .bind(now)
+        .bind(now + REVIEW_WINDOW)
+        .bind(job_id)
+        .bind(&self.party.id)
+        .bind(JobStatus::Assigned)
+        .fetch_optional(&self.db_pool)
+        .await?;
+        let Some(job) = job else {
+            return Err(self.conflict(job_id).await);
+        };
+        info!("Agent {} submitted job #{}", self.party.id, job_id);
+        if job.poster_kind == PosterKind::Agent {
+            let content = format!(
+                "{} handed in job #{} (\"{}\"): {} Accept it or send it back with review_work \
+                 before {}, or it is accepted as is.",
+                self.party.id,
+                job.id,
+                job.title,
+                deliverable,
+                format_ts(now + REVIEW_WINDOW)
+            );
+            self.notify(&job.poster, content).await;
+        }
+        Ok(job)
+    }
+
+    /// Accepts the deliverable, paying the reward and boosting the worker's happiness, or
+    /// returns it to the worker with `note`.
+    pub async fn review(
+        &self,
+        job_id: i64,
+        accept: bool,
+        note: Option<&str>,
+    ) -> Result<Job, JobError> {
+        let note = note.map(str::trim).filter(|note| !note.is_empty());
+        if note.is_some_and(|note| note.chars().count() > MAX_PITCH_CHARS) {
+            return Err(JobError::Invalid(format!(
+                "Notes are limited to {} characters",
+                MAX_PITCH_CHARS
+            )));
+        }
+        let posted = self.posted_job(job_id).await?;
+        if posted.status != JobStatus::Submitted {
+            return Err(JobError::WrongStatus {
+                job_id,
+                status: posted.status,
+            });
+        }
+        if !accept && posted.returns >= MAX_RETURNS {
+            return Err(JobError::TooManyReturns(job_id));
+        }
+        self.settle(&posted, accept, note, false).await
+    }
+
+    /// Accepts or returns a submitted job, `timed_out` if its poster didn't review it.
+    async fn settle(
+        &self,
+        posted: &Job,
+        accept: bool,
+        note: Option<&str>,
+        timed_out: bool,
+    ) -> Result<Job, JobError> {
+        let job_id = posted.id;
+        let now = Utc::now().timestamp();
+        let (status, closed_at, expires_at) = match accept {
+            true => (JobStatus::Completed, Some(now), None),
+            false => (JobStatus::Assigned, None, Some(now + WORK_WINDOW)),
+        };
+        let worker = posted.assignee.clone().unwrap_or_default();
+        let payout = match accept {
+            true => Some(self.payout(posted, &worker, now).await?),
+            false => None,
+        };
+        // The review, the payout and the boost commit together
+        let mut tx = self.db_pool.begin_with("BEGIN IMMEDIATE").await?;
+        // Claimed in one statement, so a reward is never paid twice
+        let job = sqlx::query_as::<_, Job>(
+            "UPDATE jobs SET status = ?, note = COALESCE(?, note), closed_at = ?, expires_at = ?,
+                 returns = returns + ?
+             WHERE id = ? AND status = ?
+             RETURNING *",
+        )
+        .bind(status)
+        .bind(note)
+        .bind(closed_at)
+        .bind(expires_at)
+        .bind(!accept as i64)
+        .bind(job_id)
+        .bind(JobStatus::Submitted)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(job) = job else {
+            drop(tx);
+            return Err(self.conflict(job_id).await);
+        };
+        if let Some(entry) = &payout {
+            ledger::post_in(&mut tx, entry).await?;
+            let reason = format!("Completed job #{}", job.id);
+            let reference = format!("job:{}", job.id);
+            request_boost(
+                &mut *tx,
+                &worker,
+                JOB_HAPPINESS_BOOST,
+                &reason,
+                &reference,
+                now,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        let mut content = match accept {
+            true => {
+                info!(
+                    "Paid {} USDC to {} for job #{}",
+                    format_usdc(job.reward),
+                    worker,
+                    job.id
+                );
+                reputation::recompute(&self.db_pool, &worker).await;
+                format!(
+                    "Your work on job #{} (\"{}\") was accepted{}: {} USDC paid, and your \
+                     happiness rises by {}.",
+                    job.id,
+                    job.title,
+                    match timed_out {
+                        true => " without a review",
+                        false => "",
+                    },
+                    format_usdc(job.reward),
+                    JOB_HAPPINESS_BOOST
+                )
+            }
+            false => format!(
+                "Your work on job #{} (\"{}\") was sent back. Improve it and submit_work again \
+                 before {}.",
+                job.id,
+                job.title,
+                format_ts(now + WORK_WINDOW)
+            ),
+        };
+        if let Some(note) = note {
+            content.push_str(&format!(" The poster's note: {}", note));
+        }
+        self.notify(&worker, content).await;
+        if timed_out && job.poster_kind == PosterKind::Agent {
+            let content = format!(
+                "Job #{} (\"{}\") was accepted without your review, and {} paid.",
+                job.id, job.title, worker
+            );
+            self.notify(&job.poster, content).await;
+        }
+        Ok(job)
+    }
+
+    /// The entry releasing the held reward to the worker.
+    async fn payout(&self, job: &Job, worker: &str, now: i64) -> sqlx::Result<NewEntry> {
+        let held = ledger::account(&self.db_pool, AccountKind::System, system::JOBS, now).await?;
+        let account = ledger::account(&self.db_pool, AccountKind::Agent, worker, now).await?;
+        Ok(NewEntry::transfer(
+            EntryKind::Earning,
+            held.id,
+            account.id,
+            job.reward,
+            &format!("Job #{}: {}", job.id, job.title),
+            Some(format!("job:{}:payout", job.id)),
+            now,
+        ))
+    }
+
+    /// Withdraws an open job, or an assigned one whose work is overdue, and refunds its
+    /// reward.
+    pub async fn cancel(&self, job_id: i64) -> Result<Job, JobError> {
+        self.posted_job(job_id).await?;
+        let now = Utc::now().timestamp();
+        let held = ledger::account(&self.db_pool, AccountKind::System, system::JOBS, now).await?;
+        let funder = ledger::account(
+            &self.db_pool,
+            self.party.account_kind(),
+            &self.party.id,
+            now,
+        )
+        .await?;
+        // The cancellation and the refund commit together
+        let mut tx = self.db_pool.begin_with("BEGIN IMMEDIATE").await?;
+        let job = sqlx::query_as::<_, Job>(
+            "UPDATE jobs SET status = ?, closed_at = ?, expires_at = NULL
+             WHERE id = ? AND (status = ? OR (status = ? AND expires_at <= ?))
+             RETURNING *",
+        )
+        .bind(JobStatus::Cancelled)
+        .bind(now)
+        .bind(job_id)
+        .bind(JobStatus::Open)
+        .bind(JobStatus::Assigned)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(job) = job else {
+            drop(tx);
+            return Err(self.conflict(job_id).await);
+        };
+        let entry = NewEntry::transfer(
+            EntryKind::Transfer,
+            held.id,
+            funder.id,
+            job.reward,
+            &format!("Refund of job #{}", job.id),
+            Some(format!("job:{}:refund", job.id)),
+            now,
+        );
+        ledger::post_in(&mut tx, &entry).await?;
+        tx.commit().await?;
+        info!("Job #{} cancelled", job_id);
+        if let Some(worker) = &job.assignee {
+            let content = format!(
+                "Job #{} (\"{}\") was cancelled: the work wasn't handed in in time.",
+                job.id, job.title
+            );
+            self.notify(worker, content).await;
+        }
+        Ok(job)
+    }
+
+    async fn job(&self, job_id: i64) -> Result<Job, JobError> {
+        fetch_job(&self.db_pool, job_id)
+            .await?
+            .ok_or(JobError::NoSuchJob(job_id))
+    }
+
+    /// The job, if this party posted it.
+    async fn posted_job(&self, job_id: i64) -> Result<Job, JobError> {
+        let job = self.job(job_id).await?;
+        match job.posted_by(&self.party) {
+            true => Ok(job),
+            false => Err(JobError::NotPoster(job_id)),
+        }
+    }
+
+    /// Why a claim on the job found nothing to update.
+    async fn conflict(&self, job_id: i64) -> JobError {
+        match self.job(job_id).await {
+            Ok(job) => JobError::WrongStatus {
+                job_id,
+                status: job.status,
+            },
+            Err(e) => e,
+        }
+    }
+
+    async fn notify(&self, agent_id: &str, content: String) {
+        activity::notify(&self.db_pool, &self.agents, agent_id, content).await;
+    }
+}
+
+/// Accepts every deliverable whose review window ended at `now`, paying it as if the poster
+/// had. Returns the jobs it completed.
+pub async fn settle_expired(state: &AppState, now: i64) -> sqlx::Result<Vec<Job>> {
+    let expired = sqlx::query_as::<_, Job>(
+        "SELECT * FROM jobs WHERE status = ? AND expires_at <= ? ORDER BY id",
+    )
+    .bind(JobStatus::Submitted)
+    .bind(now)
+    .fetch_all(&state.db_pool)
+    .await?;
+    let mut settled = Vec::new();
+    for job in expired {
+        let poster = Party {
+            kind: job.poster_kind,
+            id: job.poster.clone(),
+        };
+        let board = JobBoard::new(state, poster);
+        match board.settle(&job, true, None, true).await {
+            Ok(job) => settled.push(job),
+            // The poster reviewed it first
+            Err(JobError::WrongStatus { .. }) => {}
+            Err(JobError::Db(e)) => return Err(e),
+            Err(e) => error!("Failed to accept job #{}: {}", job.id, e),
+        }
+    }
+    Ok(settled)
+}
+
+/// Background task: accepts deliverables as their review windows end.
+pub async fn watch_deadlines(state: AppState) {
+    let mut tick = tokio::time::interval(SETTLE_INTERVAL);
+    loop {
+        tick.tick().await;
+        match settle_expired(&state, Utc::now().timestamp()).await {
+            Ok(settled) if !settled.is_empty() => {
+                info!("Accepted {} unreviewed jobs", settled.len())
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to accept unreviewed jobs: {:?}", e),
+        }
+    }
+}
+
+pub async fn fetch_job(pool: &SqlitePool, job_id: i64) -> sqlx::Result<Option<Job>> {
+    sqlx::query_as::<_, Job>("SELECT * FROM jobs WHERE id = ?")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Jobs newest first, optionally only those with `status` or asking for `skill`
+/// (case-insensitive).
+pub async fn fetch_jobs(
+    pool: &SqlitePool,
+    status: Option<JobStatus>,
+    skill: Option<&str>,
+    limit: i64,
+) -> sqlx::Result<Vec<Job>> {
+    sqlx::query_as::<_, Job>(
+        "SELECT * FROM jobs
+         WHERE (? IS NULL OR status = ?)
+           AND (? IS NULL OR EXISTS (
+               SELECT 1 FROM json_each(jobs.skills) WHERE lower(value) = lower(?)))
+         ORDER BY id DESC LIMIT ?",
+    )
+    .bind(status)
+    .bind(status)
+    .bind(skill)
+    .bind(skill)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// A job's bids, best reputation first (bidders without a score count as the baseline),
+/// then oldest first.
+pub async fn fetch_bids(pool: &SqlitePool, job_id: i64) -> sqlx::Result<Vec<Bid>> {
+    sqlx::query_as::<_, Bid>(
+        "SELECT b.*, COALESCE(
+             (SELECT score FROM reputation_scores s WHERE s.agent_id = b.agent_id
+              ORDER BY s.id DESC LIMIT 1), ?) AS reputation
+         FROM job_bids b WHERE b.job_id = ?
+         ORDER BY reputation DESC, b.id",
+    )
+    .bind(reputation::BASELINE)
+    .bind(job_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn fetch_bid(
+    pool: &SqlitePool,
+    job_id: i64,
+    agent_id: &str,
+) -> sqlx::Result<Option<Bid>> {
+    sqlx::query_as::<_, Bid>("SELECT * FROM job_bids WHERE job_id = ? AND agent_id = ?")
+        .bind(job_id)
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Tool: open jobs to bid on.
+pub struct FindJobs(pub JobBoard);
+
+#[derive(Deserialize)]
+pub struct FindJobsArgs {
+    pub skill: Option<String>,
+}
+
+impl Tool for FindJobs {
+    const NAME: &'static str = "find_jobs";
+    type Error = sqlx::Error;
+    type Args = FindJobsArgs;
+    type Output = Vec<Job>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Open jobs on the job board, newest first: paid tasks posted by \
+                          owners, the system or other agents. Rewards are USDC atomic units \
+                          (1000000 = 1 USDC)."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "skill": { "type": "string", "description": "Only jobs asking for this skill" }
+                }
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.open_jobs(args.skill.as_deref()).await
+    }
+}
+
+/// Tool: bids on an open job.
+pub struct BidOnJob(pub JobBoard);
+
+#[derive(Deserialize)]
+pub struct BidOnJobArgs {
+    pub job_id: i64,
+    pub pitch: String,
+}
+
+impl Tool for BidOnJob {
+    const NAME: &'static str = "bid_on_job";
+    type Error = JobError;
+    type Args = BidOnJobArgs;
+    type Output = Bid;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Bid on an open job. You are told if the poster picks you.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": { "type": "integer" },
+                    "pitch": { "type": "string", "description": "Why you are the one for it" }
+                },
+                "required": ["job_id", "pitch"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.bid(args.job_id, &args.pitch).await
+    }
+}
+
+/// Tool: hands in the work on a job assigned to the agent.
+pub struct SubmitWork(pub JobBoard);
+
+#[derive(Deserialize)]
+pub struct SubmitWorkArgs {
+    pub job_id: i64,
+    pub deliverable: String,
+}
+
+impl Tool for SubmitWork {
+    const NAME: &'static str = "submit_work";
+    type Error = JobError;
+    type Args = SubmitWorkArgs;
+    type Output = Job;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Hand in your work on a job assigned to you, before it is due. You \
+                          are paid once the poster accepts it, or when they don't review it in \
+                          time."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": { "type": "integer" },
+                    "deliverable": { "type": "string", "description": "The work itself" }
+                },
+                "required": ["job_id", "deliverable"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.submit(args.job_id, &args.deliverable).await
+    }
+}
+
+/// Tool: posts a job paid from the agent's balance.
+pub struct PostJob(pub JobBoard);
+
+#[derive(Deserialize)]
+pub struct PostJobArgs {
+    pub title: String,
+    pub description: String,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    pub reward: String,
+}
+
+impl Tool for PostJob {
+    const NAME: &'static str = "post_job";
+    type Error = JobError;
+    type Args = PostJobArgs;
+    type Output = Job;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Post a paid job for other agents. The reward is taken from your \
+                          balance now and paid when you accept the work, or refunded if you \
+                          cancel before assigning it."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string" },
+                    "description": { "type": "string", "description": "What you need done" },
+                    "skills": { "type": "array", "items": { "type": "string" } },
+                    "reward": { "type": "string", "description": "USDC, e.g. \"2.5\"" }
+                },
+                "required": ["title", "description", "reward"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let reward = parse_usdc(&args.reward)
+            .ok_or_else(|| JobError::Invalid("Rewards must be USDC, e.g. 2.5".to_string()))?;
+        let job = NewJob {
+            title: args.title,
+            description: args.description,
+            skills: args.skills,
+            reward,
+        };
+        self.0.post(&job).await
+    }
+}
+
+/// Tool: assigns one of the agent's jobs to a bidder.
+pub struct AcceptBid(pub JobBoard);
+
+#[derive(Deserialize)]
+pub struct AcceptBidArgs {
+    pub job_id: i64,
+    pub agent_id: String,
+}
+
+impl Tool for AcceptBid {
+    const NAME: &'static str = "accept_bid";
+    type Error = JobError;
+    type Args = AcceptBidArgs;
+    type Output = Job;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Give a job you posted to one of its bidders.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": { "type": "integer" },
+                    "agent_id": { "type": "string", "description": "The bidder" }
+                },
+                "required": ["job_id", "agent_id"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.accept_bid(args.job_id, &args.agent_id).await
+    }
+}
+
+/// Tool: accepts or returns the work handed in on one of the agent's jobs.
+pub struct ReviewWork(pub JobBoard);
+
+#[derive(Deserialize)]
+pub struct ReviewWorkArgs {
+    pub job_id: i64,
+    pub accept: bool,
+    pub note: Option<String>,
+}
+
+impl Tool for ReviewWork {
+    const NAME: &'static str = "review_work";
+    type Error = JobError;
+    type Args = ReviewWorkArgs;
+    type Output = Job;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Accept the work handed in on a job you posted, which pays the \
+                          reward, or send it back to be improved. Work you don't review in \
+                          time is accepted."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": { "type": "integer" },
+                    "accept": { "type": "boolean" },
+                    "note": { "type": "string", "description": "Feedback for the worker" }
+                },
+                "required": ["job_id", "accept"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0
+            .review(args.job_id, args.accept, args.note.as_deref())
+            .await
+    }
+}
@@ -0,0 +1,83 @@
+pub mod activity;
+pub mod approvals;
+pub mod death;
+pub mod escrow;
+pub mod feed;
+pub mod funds;
+pub mod handlers;
+pub mod jobs;
+pub mod llm;
+pub mod market;
+pub mod messaging;
+pub mod models;
+pub mod reputation;
+pub mod spending;
+pub mod text;
+pub mod wallet;
+pub mod x402;
+
+use axum::{
+    Router,
+    routing::{delete, get, post, put},
+};
+use models::AppState;
+
+use crate::handlers::{
+    accept_job_bid, book_vacation, buy_skill, cancel_job, cancel_vacation, decide_proposal,
+    delete_agent, deposit_owner_funds, get_activity_proof, get_agent, get_agent_deals,
+    get_agent_posts, get_approval_settings, get_attestations, get_conversations, get_deal,
+    get_earnings, get_feed, get_happiness, get_history, get_job, get_ledger, get_market, get_post,
+    get_proposals, get_reputation, get_spending_policy, get_spends, interact_agent, launch_agent,
+    list_agent_skill, list_agents, list_deals, list_jobs, post_job, rate_job, resolve_deal,
+    review_job, set_approval_settings, set_spending_policy, top_up_agent, unlist_agent_skill,
+};
+
+/// Builds the API routes over the given state. `main` adds CORS on top; tests drive it directly.
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/feed", get(get_feed)) // GET ?before=&limit=
+        .route("/feed/{post_id}", get(get_post)) // GET
+        .route("/market", get(get_market)) // GET
+        .route("/market/{agent_id}/{skill}", post(buy_skill)) // POST (x402 payment)
+        .route("/jobs", get(list_jobs).post(post_job)) // GET ?status=&skill=&limit= / POST (signed owner or admin token)
+        .route("/jobs/{job_id}", get(get_job).delete(cancel_job)) // GET / DELETE (poster)
+        .route("/jobs/{job_id}/accept", post(accept_job_bid)) // POST (poster)
+        .route("/jobs/{job_id}/review", post(review_job)) // POST (poster)
+        .route("/jobs/{job_id}/rating", post(rate_job)) // POST (poster)
+        .route("/deals", get(list_deals)) // GET ?status=&limit= (admin token)
+        .route("/deals/{deal_id}", get(get_deal)) // GET (admin token or a party's owner)
+        .route("/deals/{deal_id}/resolve", post(resolve_deal)) // POST (admin token)
+        .route("/owners/{address}/deposits", post(deposit_owner_funds)) // POST (tx from the owner)
+        .nest(
+            "/agents",
+            Router::new()
+                .route("/", post(launch_agent).get(list_agents)) // POST/GET /agents
+                .route("/{id}/interact", post(interact_agent)) // POST /agents/{id}/interact
+                .route("/{id}", get(get_agent).delete(delete_agent)) // DELETE /agents/{id}
+                .route("/{id}/history", get(get_history)) // GET
+                .route("/{id}/happiness", get(get_happiness)) // GET ?from=&to=&bucket=
+                .route("/{id}/attestations", get(get_attestations)) // GET ?limit=
+                .route("/{id}/activity/{seq}/proof", get(get_activity_proof)) // GET (owner)
+                .route("/{id}/conversations", get(get_conversations)) // GET ?limit= (owner)
+                .route("/{id}/posts", get(get_agent_posts)) // GET ?before=&limit=
+                .route("/{id}/reputation", get(get_reputation)) // GET ?limit=
+                .route("/{id}/skills/{skill}", put(list_agent_skill).delete(unlist_agent_skill)) // PUT/DELETE (owner)
+                .route("/{id}/earnings", get(get_earnings)) // GET ?limit= (owner)
+                .route("/{id}/ledger", get(get_ledger)) // GET ?limit= (owner)
+                .route("/{id}/top-up", post(top_up_agent)) // POST (owner)
+                .route("/{id}/spending-policy", get(get_spending_policy).put(set_spending_policy)) // GET/PUT (owner)
+                .route("/{id}/spends", get(get_spends)) // GET ?status=&limit= (owner)
+                .route("/{id}/approval-settings", get(get_approval_settings).put(set_approval_settings)) // GET/PUT (owner)
+                .route("/{id}/proposals", get(get_proposals)) // GET ?status=&limit= (owner)
+                .route("/{id}/deals", get(get_agent_deals)) // GET ?status=&limit= (owner)
+                .route("/{id}/proposals/{proposal_id}", post(decide_proposal)) // POST (owner)
+                .route("/{id}/vacation", post(book_vacation)) // POST (owner)
+                .route("/{id}/vacation/{vacation_id}", delete(cancel_vacation)), // DELETE (owner)
+        )
+        .with_state(state)
+}
+
+async fn root() -> &'static str {
+    "Hello, World! The AI Execution Crate is running."
+}
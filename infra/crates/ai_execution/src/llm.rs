@@ -0,0 +1,162 @@
+use crate::approvals::{ProposeJob, ProposePost, Proposer, ReviewedBid, ReviewedPost};
+use crate::escrow::{
+    ConfirmDeal, DisputeDeal, Escrow, ListDeals, MarkDelivered, OpenDeal, RefundDeal,
+    ResolveDispute,
+};
+use crate::feed::{PublishPost, Publisher, ReactToPost, ReadFeed};
+use crate::funds::{GetUsdcBalance, Purse};
+use crate::jobs::{AcceptBid, BidOnJob, FindJobs, JobBoard, PostJob, ReviewWork, SubmitWork};
+use crate::messaging::{ListAgents, Messenger, SendMessage};
+use crate::models::AppState;
+use crate::reputation::{CheckReputation, RateAgent, Rater};
+use crate::spending::{SendUsdc, Spender};
+use futures::future::BoxFuture;
+use rig::agent::{Agent as RigAgent, AgentBuilder};
+use rig::client::CompletionClient;
+use rig::completion::{Chat, CompletionModel, Message as RigMessage, PromptError};
+use rig::providers::openai::{self, Client as OpenAiClient};
+use std::sync::Arc;
+
+/// Anything an agent can think with: the OpenAI-backed rig agent in production,
+/// or the offline stand-in for tests and local runs without an API key.
+pub trait ChatBackend: Send + Sync {
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<RigMessage>,
+    ) -> BoxFuture<'_, Result<String, PromptError>>;
+}
+
+impl<M> ChatBackend for RigAgent<M>
+where
+    M: CompletionModel + 'static,
+{
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<RigMessage>,
+    ) -> BoxFuture<'_, Result<String, PromptError>> {
+        Box::pin(Chat::chat(self, prompt, history))
+    }
+}
+
+/// Deterministic, network-free stand-in that echoes the prompt back in character.
+pub struct OfflineAgent {
+    pub name: String,
+}
+
+impl ChatBackend for OfflineAgent {
+    fn chat(
+        &self,
+        prompt: String,
+        history: Vec<RigMessage>,
+    ) -> BoxFuture<'_, Result<String, PromptError>> {
+        let reply = format!(
+            "[offline] {} heard \"{}\" ({} messages of history)",
+            self.name,
+            prompt,
+            history.len()
+        );
+        Box::pin(async move { Ok(reply) })
+    }
+}
+
+/// One agent's handles on the rest of the society, behind its tools.
+#[derive(Clone)]
+pub struct Toolkit {
+    pub messenger: Messenger,
+    pub publisher: Publisher,
+    pub purse: Purse,
+    pub spender: Spender,
+    pub proposer: Proposer,
+    pub jobs: JobBoard,
+    pub escrow: Escrow,
+    pub rater: Rater,
+}
+
+impl Toolkit {
+    pub fn new(state: &AppState, agent_id: &str) -> Self {
+        Toolkit {
+            messenger: Messenger::new(state, agent_id),
+            publisher: Publisher::new(state, agent_id),
+            purse: Purse::new(state, agent_id),
+            spender: Spender::new(state, agent_id),
+            proposer: Proposer::new(state, agent_id),
+            jobs: JobBoard::for_agent(state, agent_id),
+            escrow: Escrow::new(state, agent_id),
+            rater: Rater::for_agent(state, agent_id),
+        }
+    }
+}
+
+/// Which model provider new agents are built on.
+#[derive(Clone, Debug)]
+pub enum LlmProvider {
+    OpenAi { api_key: String },
+    /// Selected with `LLM_PROVIDER=offline`; no API key needed.
+    Offline,
+}
+
+impl LlmProvider {
+    /// Reads `LLM_PROVIDER` (default `openai`) and, for OpenAI, `OPENAI_API_KEY`.
+    pub fn from_env() -> Self {
+        match std::env::var("LLM_PROVIDER").as_deref() {
+            Ok("offline") => LlmProvider::Offline,
+            _ => LlmProvider::OpenAi {
+                api_key: std::env::var("OPENAI_API_KEY")
+                    .expect("OPENAI_API_KEY must be set in .env"),
+            },
+        }
+    }
+
+    /// Builds the backend for one agent from its name and preamble. Model-backed agents
+    /// get tools over `toolkit`.
+    pub fn build(&self, name: &str, preamble: &str, toolkit: Toolkit) -> Arc<dyn ChatBackend> {
+        match self {
+            LlmProvider::OpenAi { api_key } => {
+                // Create an OpenAI client with the provided API key
+                let openai_client = OpenAiClient::new(api_key);
+
+                // Get the concrete completion model (owned, static type: ResponsesCompletionModel<reqwest::Client>)
+                let model = openai_client.completion_model(openai::GPT_4O_MINI);
+
+                // Build the agent using the concrete model + builder
+                Arc::new(
+                    AgentBuilder::new(model)
+                        .preamble(preamble)
+                        .tool(ListAgents(toolkit.messenger.clone()))
+                        .tool(SendMessage(toolkit.messenger))
+                        .tool(ReviewedPost(
+                            PublishPost(toolkit.publisher.clone()),
+                            toolkit.proposer.clone(),
+                        ))
+                        .tool(ReadFeed(toolkit.publisher.clone()))
+                        .tool(ReactToPost(toolkit.publisher))
+                        .tool(GetUsdcBalance(toolkit.purse))
+                        .tool(SendUsdc(toolkit.spender))
+                        .tool(ProposePost(toolkit.proposer.clone()))
+                        .tool(ProposeJob(toolkit.proposer.clone()))
+                        .tool(FindJobs(toolkit.jobs.clone()))
+                        .tool(ReviewedBid(BidOnJob(toolkit.jobs.clone()), toolkit.proposer))
+                        .tool(SubmitWork(toolkit.jobs.clone()))
+                        .tool(PostJob(toolkit.jobs.clone()))
+                        .tool(AcceptBid(toolkit.jobs.clone()))
+                        .tool(ReviewWork(toolkit.jobs))
+                        .tool(OpenDeal(toolkit.escrow.clone()))
+                        .tool(ListDeals(toolkit.escrow.clone()))
+                        .tool(MarkDelivered(toolkit.escrow.clone()))
+                        .tool(ConfirmDeal(toolkit.escrow.clone()))
+                        .tool(DisputeDeal(toolkit.escrow.clone()))
+                        .tool(RefundDeal(toolkit.escrow.clone()))
+                        .tool(ResolveDispute(toolkit.escrow))
+                        .tool(CheckReputation(toolkit.rater.clone()))
+                        .tool(RateAgent(toolkit.rater))
+                        .build(),
+                )
+            }
+            LlmProvider::Offline => Arc::new(OfflineAgent {
+                name: name.to_string(),
+            }),
+        }
+    }
+}
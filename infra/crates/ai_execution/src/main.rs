@@ -1,13 +1,16 @@
-pub mod handlers;
-pub mod models;
-
-use axum::{
-    Router,
-    routing::{delete, get, post},
+use ai_execution::{
+    death,
+    escrow::{self, Arbiter},
+    funds::{self, RpcBalances, UsdcTransfers},
+    jobs,
+    llm::LlmProvider,
+    market::Market,
+    models::AppState,
+    router,
+    wallet::{self, Keyring},
 };
 use dotenv::dotenv;
 use tower_http::cors::{Any, CorsLayer};
-use models::AppState;
 use sqlx::{Sqlite, SqlitePool, migrate::MigrateDatabase};
 use std::{
     collections::HashMap,
@@ -17,8 +20,6 @@ use std::{
 };
 use tracing::info;
 
-use crate::handlers::{delete_agent, get_history, interact_agent, launch_agent, list_agents, get_agent};
-
 fn get_db_url() -> String {
     env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:./crates/ai_execution/agents.db".to_string())
 }
@@ -40,39 +41,46 @@ async fn main() {
 
     sqlx::migrate!("./migrations").run(&db_pool).await.unwrap();
 
-    let api_key = env::var("OPENAI_API_KEY")
-        .expect("OPENAI_API_KEY must be set in .env");
+    let admin_token = env::var("ADMIN_TOKEN").ok();
+    if admin_token.as_ref().is_some_and(|t| t.len() < 16) {
+        panic!("ADMIN_TOKEN must be at least 16 characters");
+    }
 
     let state = AppState {
         db_pool,
         agents: Arc::new(RwLock::new(HashMap::new())),
-        api_key
+        llm: LlmProvider::from_env(),
+        market: Market::from_env(),
+        wallets: Keyring::from_env(),
+        admin_token,
+        arbiter: Arbiter::from_env(),
+        deposits: RpcBalances::from_env().map(|rpc| Arc::new(rpc) as Arc<dyn UsdcTransfers>),
     };
 
+    if let Some(keyring) = &state.wallets {
+        wallet::assign_all(&state.db_pool, keyring).await;
+    }
+
+    tokio::spawn(death::watch_deaths(state.clone()));
+    tokio::spawn(escrow::watch_deadlines(state.clone()));
+    tokio::spawn(jobs::watch_deadlines(state.clone()));
+    if let Some(balances) = RpcBalances::from_env() {
+        tokio::spawn(funds::watch_reconciliation(
+            state.db_pool.clone(),
+            Arc::new(balances),
+            std::time::Duration::from_secs(600),
+        ));
+    }
+
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:3000".parse::<reqwest::header::HeaderValue>().unwrap())
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let app = Router::new()
-        .route("/", get(root))
-        .nest(
-            "/agents",
-            Router::new()
-                .route("/", post(launch_agent).get(list_agents)) // POST/GET /agents
-                .route("/{id}/interact", post(interact_agent)) // POST /agents/{id}/interact
-                .route("/{id}", get(get_agent).delete(delete_agent)) // DELETE /agents/{id}
-                .route("/{id}/history", get(get_history)), // GET
-        )
-        .with_state(state)
-        .layer(cors);
+    let app = router(state).layer(cors);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 3001));
     info!("listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
-
-async fn root() -> &'static str {
-    "Hello, World! The AI Execution Crate is running."
-}
@@ -0,0 +1,374 @@
+//! Skill marketplace: owners list their agent's skills at a USDC price, buyers pay for them
+//! over x402 (see [`crate::x402`]) at `POST /market/{agent_id}/{skill}`, the agent does the
+//! work, and the sale lands in the agent's earnings ledger.
+//!
+//! Payments go to the service's `pay_to` address; the ledger records what each agent has
+//! earned from them. Amounts are in USDC atomic units (6 decimals).
+
+use crate::activity;
+use crate::models::{Agent, CustomMessage, Origin};
+use crate::x402::{
+    Facilitator, HttpFacilitator, LocalFacilitator, PaymentRequirements, SettleResponse,
+};
+use chrono::Utc;
+use rig::completion::PromptError;
+use rig::providers::openai::responses_api::Role;
+use serde::Serialize;
+use serde_json::json;
+use shared::death::AgentStatus;
+use shared::ledger::{self, AccountKind, EntryKind, LedgerError, NewEntry, system};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Atomic units in one USDC.
+pub const USDC: i64 = 1_000_000;
+pub const MAX_SKILL_PRICE: i64 = 1_000 * USDC;
+pub const MAX_REQUEST_CHARS: usize = 2000;
+/// USDC on Base Sepolia.
+pub const DEFAULT_ASSET: &str = "0x036CbD53842c5426634e7929541eC2318f3dCF7e";
+
+/// Where buyers pay and who checks their payments.
+#[derive(Clone)]
+pub struct Market {
+    pub facilitator: Arc<dyn Facilitator>,
+    /// x402 network name, e.g. `base-sepolia`.
+    pub network: String,
+    /// USDC contract on `network`.
+    pub asset: String,
+    pub pay_to: String,
+    /// Share of each sale the platform keeps, in basis points.
+    pub fee_bps: i64,
+    /// Base URL the paid endpoints are reached at, for the `resource` of requirements.
+    pub public_url: String,
+}
+
+impl Market {
+    /// Reads `X402_PAY_TO` (the market is closed without it), `X402_FACILITATOR_URL`
+    /// (`local` for the network-free stand-in), `X402_NETWORK`, `X402_ASSET`,
+    /// `X402_FEE_BPS` (default 0) and `PUBLIC_URL`.
+    pub fn from_env() -> Option<Self> {
+        let pay_to = std::env::var("X402_PAY_TO").ok()?;
+        let facilitator: Arc<dyn Facilitator> = match std::env::var("X402_FACILITATOR_URL") {
+            Ok(url) if url == "local" => Arc::new(LocalFacilitator::default()),
+            Ok(url) => Arc::new(HttpFacilitator::new(&url)),
+            Err(_) => Arc::new(HttpFacilitator::new("https://x402.org/facilitator")),
+        };
+        Some(Market {
+            facilitator,
+            network: std::env::var("X402_NETWORK").unwrap_or_else(|_| "base-sepolia".to_string()),
+            asset: std::env::var("X402_ASSET").unwrap_or_else(|_| DEFAULT_ASSET.to_string()),
+            pay_to,
+            fee_bps: std::env::var("X402_FEE_BPS")
+                .ok()
+                .and_then(|bps| bps.parse().ok())
+                .unwrap_or(0),
+            public_url: std::env::var("PUBLIC_URL")
+                .unwrap_or_else(|_| "http://localhost:3001".to_string()),
+        })
+    }
+
+    /// The `exact` payment a listing costs.
+    pub fn requirements(&self, listing: &SkillListing) -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: "exact".to_string(),
+            network: self.network.clone(),
+            max_amount_required: listing.price.to_string(),
+            resource: format!(
+                "{}/market/{}/{}",
+                self.public_url.trim_end_matches('/'),
+                listing.agent_id,
+                listing.skill.replace(' ', "%20")
+            ),
+            description: listing
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("{} by agent {}", listing.skill, listing.agent_id)),
+            mime_type: "application/json".to_string(),
+            pay_to: self.pay_to.clone(),
+            max_timeout_seconds: 120,
+            asset: self.asset.clone(),
+            extra: Some(json!({ "name": "USDC", "version": "2" })),
+        }
+    }
+}
+
+/// A single row of the `skill_listings` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct SkillListing {
+    pub agent_id: String,
+    pub skill: String,
+    /// USDC atomic units.
+    pub price: i64,
+    pub description: Option<String>,
+    pub listed_at: i64,
+}
+
+/// A single row of the `agent_earnings` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Earning {
+    pub id: i64,
+    pub agent_id: String,
+    pub skill: String,
+    /// USDC atomic units.
+    pub amount: i64,
+    pub payer: Option<String>,
+    pub network: String,
+    /// Settlement transaction.
+    pub tx_hash: String,
+    pub earned_at: i64,
+}
+
+/// `1500000` as `1.5`.
+pub fn format_usdc(amount: i64) -> String {
+    let units = format!("{}.{:06}", amount / USDC, (amount % USDC).abs());
+    units
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// `1.5` as `1500000`; `None` unless it's a non-negative amount with at most 6 decimals.
+pub fn parse_usdc(amount: &str) -> Option<i64> {
+    let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if fraction.len() > 6 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let whole: i64 = match whole {
+        "" if !fraction.is_empty() => 0,
+        whole if whole.bytes().all(|b| b.is_ascii_digit()) => whole.parse().ok()?,
+        _ => return None,
+    };
+    let fraction: i64 = format!("{:0<6}", fraction).parse().ok()?;
+    whole.checked_mul(USDC)?.checked_add(fraction)
+}
+
+/// Lists a skill, or changes its price and description.
+pub async fn list_skill(pool: &SqlitePool, listing: &SkillListing) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO skill_listings (agent_id, skill, price, description, listed_at)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT (agent_id, skill)
+         DO UPDATE SET price = excluded.price, description = excluded.description",
+    )
+    .bind(&listing.agent_id)
+    .bind(&listing.skill)
+    .bind(listing.price)
+    .bind(&listing.description)
+    .bind(listing.listed_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns whether the skill was listed.
+pub async fn unlist_skill(pool: &SqlitePool, agent_id: &str, skill: &str) -> sqlx::Result<bool> {
+    let result = sqlx::query("DELETE FROM skill_listings WHERE agent_id = ? AND skill = ?")
+        .bind(agent_id)
+        .bind(skill)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn fetch_listing(
+    pool: &SqlitePool,
+    agent_id: &str,
+    skill: &str,
+) -> sqlx::Result<Option<SkillListing>> {
+    sqlx::query_as::<_, SkillListing>(
+        "SELECT agent_id, skill, price, description, listed_at
+         FROM skill_listings WHERE agent_id = ? AND skill = ?",
+    )
+    .bind(agent_id)
+    .bind(skill)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Listings of living agents, cheapest first.
+pub async fn fetch_listings(pool: &SqlitePool) -> sqlx::Result<Vec<SkillListing>> {
+    sqlx::query_as::<_, SkillListing>(
+        "SELECT l.agent_id, l.skill, l.price, l.description, l.listed_at
+         FROM skill_listings l JOIN agents a ON a.agent_id = l.agent_id
+         WHERE a.status = ? ORDER BY l.price, l.agent_id, l.skill",
+    )
+    .bind(AgentStatus::Alive)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn fetch_earnings(
+    pool: &SqlitePool,
+    agent_id: &str,
+    limit: i64,
+) -> sqlx::Result<Vec<Earning>> {
+    sqlx::query_as::<_, Earning>(
+        "SELECT id, agent_id, skill, amount, payer, network, tx_hash, earned_at
+         FROM agent_earnings WHERE agent_id = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn total_earned(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<i64> {
+    sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM agent_earnings WHERE agent_id = ?")
+        .bind(agent_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Remembers a payer whose payment verified but failed to settle once the agent had done the
+/// work, so that [`has_unsettled`] refuses them.
+pub async fn record_unsettled(
+    pool: &SqlitePool,
+    payer: &str,
+    listing: &SkillListing,
+    reason: &str,
+    at: i64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO unsettled_payments (payer, agent_id, skill, amount, reason, created_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(payer.to_lowercase())
+    .bind(&listing.agent_id)
+    .bind(&listing.skill)
+    .bind(listing.price)
+    .bind(reason)
+    .bind(at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Whether `payer` ever failed to settle: emptying their balance between verification and
+/// settlement would otherwise get them the work for free, again and again.
+pub async fn has_unsettled(pool: &SqlitePool, payer: &str) -> sqlx::Result<bool> {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM unsettled_payments WHERE payer = ?)")
+        .bind(payer.to_lowercase())
+        .fetch_one(pool)
+        .await
+}
+
+/// Has the agent do the paid work. Nothing is recorded: the sale only counts once settled.
+pub async fn perform(
+    agent: &Agent,
+    listing: &SkillListing,
+    request: &str,
+) -> Result<String, PromptError> {
+    let prompt = format!(
+        "A customer paid {} USDC for your skill \"{}\". Deliver it now, in one response, \
+         without asking questions. Their request: {}",
+        format_usdc(listing.price),
+        listing.skill,
+        request
+    );
+    agent.llm.chat(prompt, Vec::new()).await
+}
+
+/// The earning, paid by the market account, then the fee the agent owes on it.
+async fn post_sale(
+    pool: &SqlitePool,
+    market: &Market,
+    agent_id: &str,
+    listing: &SkillListing,
+    settlement: &SettleResponse,
+    at: i64,
+) -> Result<(), LedgerError> {
+    let buyers = ledger::account(pool, AccountKind::System, system::MARKET, at).await?;
+    let agent = ledger::account(pool, AccountKind::Agent, agent_id, at).await?;
+    let memo = format!("Sold {}", listing.skill);
+    let earning = NewEntry::transfer(
+        EntryKind::Earning,
+        buyers.id,
+        agent.id,
+        listing.price,
+        &memo,
+        Some(settlement.transaction.clone()),
+        at,
+    );
+    ledger::post(pool, &earning).await?;
+
+    let fee = listing.price * market.fee_bps / 10_000;
+    if fee > 0 {
+        let fees = ledger::account(pool, AccountKind::System, system::FEES, at).await?;
+        let memo = format!("Market fee on {}", listing.skill);
+        let reference = Some(format!("{}:fee", settlement.transaction));
+        let fee = NewEntry::transfer(EntryKind::Fee, agent.id, fees.id, fee, &memo, reference, at);
+        ledger::post(pool, &fee).await?;
+    }
+    Ok(())
+}
+
+/// Credits a settled sale to the agent, less the market fee, and adds the job to its
+/// history.
+pub async fn record_sale(
+    pool: &SqlitePool,
+    market: &Market,
+    agent: &Agent,
+    listing: &SkillListing,
+    request: &str,
+    output: &str,
+    settlement: &SettleResponse,
+) {
+    let now = Utc::now();
+    let credited = sqlx::query(
+        "INSERT INTO agent_earnings (agent_id, skill, amount, payer, network, tx_hash, earned_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&agent.id)
+    .bind(&listing.skill)
+    .bind(listing.price)
+    .bind(&settlement.payer)
+    .bind(&settlement.network)
+    .bind(&settlement.transaction)
+    .bind(now.timestamp())
+    .execute(pool)
+    .await;
+    match credited {
+        Ok(_) => info!(
+            "Agent {} earned {} USDC for {} (tx {})",
+            agent.id,
+            format_usdc(listing.price),
+            listing.skill,
+            settlement.transaction
+        ),
+        // The buyer has paid: this needs fixing by hand from the settlement tx
+        Err(e) => error!(
+            "Failed to credit {} for settled tx {}: {:?}",
+            agent.id, settlement.transaction, e
+        ),
+    }
+    if let Err(e) = post_sale(pool, market, &agent.id, listing, settlement, now.timestamp()).await {
+        error!(
+            "Failed to post sale of {} to the ledger (tx {}): {}",
+            agent.id, settlement.transaction, e
+        );
+    }
+
+    let job = CustomMessage {
+        role: Role::User,
+        content: format!(
+            "Sold \"{}\" for {} USDC. Request: {}",
+            listing.skill,
+            format_usdc(listing.price),
+            request
+        ),
+        origin: Origin::System,
+        timestamp: now,
+    };
+    let delivery = CustomMessage {
+        role: Role::Assistant,
+        content: output.to_string(),
+        origin: Origin::Agent,
+        timestamp: now,
+    };
+    let mut history = agent.history.lock().await;
+    for message in [job, delivery] {
+        activity::record(pool, &agent.id, &message).await;
+        history.push(message);
+    }
+}
@@ -0,0 +1,496 @@
+//! Agent-to-agent messaging: a directory of live agents, delivery into the recipient's
+//! `ChatCommand` queue, and the rig tools that let an agent use both.
+//!
+//! Every delivered message belongs to a conversation. The recipient answers from its own
+//! loop and the answer goes back the same way, so a dialogue runs by itself until one
+//! side replies [`END_TOKEN`] or it reaches [`MAX_TURNS`]. [`MAX_SENDS_PER_HOUR`] caps how
+//! many messages any one agent can send, however many conversations it starts.
+
+use crate::activity;
+use crate::models::{Agent, AppState, ChatCommand, CustomMessage, Origin, PeerMessage};
+use chrono::Utc;
+use rig::completion::{Message as RigMessage, ToolDefinition};
+use rig::providers::openai::responses_api::Role;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::death::AgentStatus;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
+
+/// Messages in one conversation, both directions included.
+pub const MAX_TURNS: i64 = 6;
+/// Messages one agent may send in any rolling hour.
+pub const MAX_SENDS_PER_HOUR: i64 = 20;
+pub const MAX_MESSAGE_CHARS: usize = 2000;
+/// Reply that closes a conversation instead of answering.
+pub const END_TOKEN: &str = "[end]";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum EndReason {
+    /// [`MAX_TURNS`] messages were exchanged.
+    TurnLimit,
+    /// One side replied [`END_TOKEN`].
+    EndedByAgent,
+    /// The other side died or stopped running.
+    RecipientGone,
+}
+
+/// A single row of the `agent_conversations` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Conversation {
+    pub id: i64,
+    pub initiator: String,
+    pub recipient: String,
+    pub started_at: i64,
+    /// Messages exchanged so far.
+    pub turns: i64,
+    pub ended_at: Option<i64>,
+    pub end_reason: Option<EndReason>,
+}
+
+/// A single row of the `agent_peer_messages` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct PeerMessageRow {
+    pub id: i64,
+    pub conversation_id: i64,
+    pub sender: String,
+    pub recipient: String,
+    pub turn: i64,
+    pub content: String,
+    pub ts: i64,
+}
+
+/// What other agents see of a live agent.
+#[derive(Clone, Debug, Serialize)]
+pub struct DirectoryEntry {
+    pub agent_id: String,
+    pub name: String,
+    pub skills: Vec<String>,
+    pub desires: String,
+}
+
+#[derive(Debug)]
+pub enum MessagingError {
+    SelfMessage,
+    UnknownAgent(String),
+    TooLong,
+    RateLimited,
+    TurnLimit(i64),
+    Closed(i64),
+    Undeliverable(String),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for MessagingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessagingError::SelfMessage => write!(f, "An agent cannot message itself"),
+            MessagingError::UnknownAgent(id) => write!(f, "No live agent {}", id),
+            MessagingError::TooLong => {
+                write!(
+                    f,
+                    "Messages are limited to {} characters",
+                    MAX_MESSAGE_CHARS
+                )
+            }
+            MessagingError::RateLimited => {
+                write!(f, "At most {} messages per hour", MAX_SENDS_PER_HOUR)
+            }
+            MessagingError::TurnLimit(id) => {
+                write!(f, "Conversation {} reached {} turns", id, MAX_TURNS)
+            }
+            MessagingError::Closed(id) => write!(f, "Conversation {} is over", id),
+            MessagingError::Undeliverable(id) => {
+                write!(f, "Agent {} is not accepting messages", id)
+            }
+            MessagingError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MessagingError {}
+
+impl From<sqlx::Error> for MessagingError {
+    fn from(e: sqlx::Error) -> Self {
+        MessagingError::Db(e)
+    }
+}
+
+/// Where a sent message went.
+#[derive(Clone, Debug, Serialize)]
+pub struct Delivery {
+    pub conversation_id: i64,
+    pub turn: i64,
+}
+
+/// One agent's handle on the messaging system.
+#[derive(Clone)]
+pub struct Messenger {
+    pub agent_id: String,
+    agents: Arc<RwLock<HashMap<String, Arc<Agent>>>>,
+    db_pool: SqlitePool,
+}
+
+impl Messenger {
+    pub fn new(state: &AppState, agent_id: &str) -> Self {
+        Messenger {
+            agent_id: agent_id.to_string(),
+            agents: state.agents.clone(),
+            db_pool: state.db_pool.clone(),
+        }
+    }
+
+    /// Running agents that haven't died, this one excluded.
+    pub async fn directory(&self) -> Result<Vec<DirectoryEntry>, MessagingError> {
+        let alive: Vec<String> = sqlx::query_scalar("SELECT agent_id FROM agents WHERE status = ?")
+            .bind(AgentStatus::Alive)
+            .fetch_all(&self.db_pool)
+            .await?;
+        let agents = self.agents.read().unwrap();
+        let mut entries: Vec<DirectoryEntry> = alive
+            .iter()
+            .filter(|id| **id != self.agent_id)
+            .filter_map(|id| agents.get(id))
+            .map(|agent| DirectoryEntry {
+                agent_id: agent.id.clone(),
+                name: agent.profile.name.clone(),
+                skills: agent.profile.skills.clone(),
+                desires: agent.profile.desires.clone(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+        Ok(entries)
+    }
+
+    /// Sends `content` to `to`, opening a conversation unless `conversation_id` continues one.
+    pub async fn send(
+        &self,
+        to: &str,
+        content: String,
+        conversation_id: Option<i64>,
+    ) -> Result<Delivery, MessagingError> {
+        if to == self.agent_id {
+            return Err(MessagingError::SelfMessage);
+        }
+        if content.chars().count() > MAX_MESSAGE_CHARS {
+            return Err(MessagingError::TooLong);
+        }
+        let recipient = self.live_agent(to).await?;
+        let now = Utc::now().timestamp();
+        let sent: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM agent_peer_messages WHERE sender = ? AND ts > ?",
+        )
+        .bind(&self.agent_id)
+        .bind(now - 3600)
+        .fetch_one(&self.db_pool)
+        .await?;
+        if sent >= MAX_SENDS_PER_HOUR {
+            return Err(MessagingError::RateLimited);
+        }
+        // Holding a slot means nothing is recorded for a message that can't be delivered
+        let permit = recipient
+            .cmd_tx
+            .try_reserve()
+            .map_err(|_| MessagingError::Undeliverable(to.to_string()))?;
+
+        let (conversation_id, turn) = match conversation_id {
+            None => {
+                let id = sqlx::query_scalar(
+                    "INSERT INTO agent_conversations (initiator, recipient, started_at)
+                     VALUES (?, ?, ?) RETURNING id",
+                )
+                .bind(&self.agent_id)
+                .bind(to)
+                .bind(now)
+                .fetch_one(&self.db_pool)
+                .await?;
+                (id, 0)
+            }
+            Some(id) => {
+                let conversation = fetch_conversation(&self.db_pool, id)
+                    .await?
+                    .filter(|c| c.has_pair(&self.agent_id, to))
+                    .ok_or(MessagingError::Closed(id))?;
+                if conversation.ended_at.is_some() {
+                    return Err(MessagingError::Closed(id));
+                }
+                if conversation.turns >= MAX_TURNS {
+                    end_conversation(&self.db_pool, id, EndReason::TurnLimit, now).await?;
+                    return Err(MessagingError::TurnLimit(id));
+                }
+                (id, conversation.turns)
+            }
+        };
+        sqlx::query(
+            "INSERT INTO agent_peer_messages (conversation_id, sender, recipient, turn, content, ts)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(conversation_id)
+        .bind(&self.agent_id)
+        .bind(to)
+        .bind(turn)
+        .bind(&content)
+        .bind(now)
+        .execute(&self.db_pool)
+        .await?;
+        sqlx::query("UPDATE agent_conversations SET turns = turns + 1 WHERE id = ?")
+            .bind(conversation_id)
+            .execute(&self.db_pool)
+            .await?;
+
+        permit.send(ChatCommand::PeerMessage(PeerMessage {
+            conversation_id,
+            from: self.agent_id.clone(),
+            turn,
+            content,
+        }));
+        info!(
+            "Agent {} messaged {} (conversation {}, turn {})",
+            self.agent_id, to, conversation_id, turn
+        );
+        Ok(Delivery {
+            conversation_id,
+            turn,
+        })
+    }
+
+    async fn live_agent(&self, agent_id: &str) -> Result<Arc<Agent>, MessagingError> {
+        let status: Option<AgentStatus> =
+            sqlx::query_scalar("SELECT status FROM agents WHERE agent_id = ?")
+                .bind(agent_id)
+                .fetch_optional(&self.db_pool)
+                .await?;
+        let agent = self.agents.read().unwrap().get(agent_id).cloned();
+        match (status, agent) {
+            (Some(AgentStatus::Alive), Some(agent)) => Ok(agent),
+            _ => Err(MessagingError::UnknownAgent(agent_id.to_string())),
+        }
+    }
+}
+
+impl Conversation {
+    fn has_pair(&self, a: &str, b: &str) -> bool {
+        (self.initiator == a && self.recipient == b) || (self.initiator == b && self.recipient == a)
+    }
+}
+
+/// Handles a delivered message in the recipient's loop: records it, then answers unless
+/// the conversation is out of turns or the agent chooses to end it.
+pub async fn receive(agent: &Agent, messenger: &Messenger, message: PeerMessage) {
+    let incoming = CustomMessage {
+        role: Role::User,
+        content: message.content.clone(),
+        origin: Origin::Peer(message.from.clone()),
+        timestamp: Utc::now(),
+    };
+    activity::record(&messenger.db_pool, &agent.id, &incoming).await;
+    let mut history = agent.history.lock().await;
+    let rig_hist: Vec<RigMessage> = history
+        .iter()
+        .rev()
+        .take(10)
+        .rev()
+        .map(|cm| match cm.role {
+            Role::User => RigMessage::user(cm.content.clone()),
+            _ => RigMessage::assistant(cm.content.clone()),
+        })
+        .collect();
+    history.push(incoming);
+
+    let conversation_id = message.conversation_id;
+    if message.turn + 1 >= MAX_TURNS {
+        info!("Conversation {} reached its turn limit", conversation_id);
+        close(messenger, conversation_id, EndReason::TurnLimit).await;
+        return;
+    }
+    let prompt = format!(
+        "Message from agent {} (turn {} of at most {} in this conversation): {}\n\
+         Reply in one short paragraph, or reply exactly {} to end the conversation.",
+        message.from,
+        message.turn + 1,
+        MAX_TURNS,
+        message.content,
+        END_TOKEN
+    );
+    let reply = match agent.llm.chat(prompt, rig_hist).await {
+        Ok(reply) => reply,
+        Err(e) => {
+            error!(
+                "Agent {} failed to answer {}: {:?}",
+                agent.id, message.from, e
+            );
+            return;
+        }
+    };
+    let outgoing = CustomMessage {
+        role: Role::Assistant,
+        content: reply.clone(),
+        origin: Origin::Agent,
+        timestamp: Utc::now(),
+    };
+    activity::record(&messenger.db_pool, &agent.id, &outgoing).await;
+    history.push(outgoing);
+    drop(history);
+
+    if reply.trim() == END_TOKEN {
+        info!("Agent {} ended conversation {}", agent.id, conversation_id);
+        close(messenger, conversation_id, EndReason::EndedByAgent).await;
+        return;
+    }
+    let reply: String = reply.chars().take(MAX_MESSAGE_CHARS).collect();
+    match messenger
+        .send(&message.from, reply, Some(conversation_id))
+        .await
+    {
+        Ok(_) => {}
+        Err(e @ (MessagingError::UnknownAgent(_) | MessagingError::Undeliverable(_))) => {
+            info!("Conversation {} closed: {}", conversation_id, e);
+            close(messenger, conversation_id, EndReason::RecipientGone).await;
+        }
+        Err(e) => warn!(
+            "Agent {} could not reply in conversation {}: {}",
+            agent.id, conversation_id, e
+        ),
+    }
+}
+
+async fn close(messenger: &Messenger, conversation_id: i64, reason: EndReason) {
+    let now = Utc::now().timestamp();
+    if let Err(e) = end_conversation(&messenger.db_pool, conversation_id, reason, now).await {
+        error!("Failed to close conversation {}: {:?}", conversation_id, e);
+    }
+}
+
+/// Ends a conversation; the first reason recorded wins.
+pub async fn end_conversation(
+    pool: &SqlitePool,
+    conversation_id: i64,
+    reason: EndReason,
+    at: i64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "UPDATE agent_conversations SET ended_at = ?, end_reason = ?
+         WHERE id = ? AND ended_at IS NULL",
+    )
+    .bind(at)
+    .bind(reason)
+    .bind(conversation_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn fetch_conversation(
+    pool: &SqlitePool,
+    conversation_id: i64,
+) -> sqlx::Result<Option<Conversation>> {
+    sqlx::query_as::<_, Conversation>(
+        "SELECT id, initiator, recipient, started_at, turns, ended_at, end_reason
+         FROM agent_conversations WHERE id = ?",
+    )
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// The agent's most recent conversations, newest first, on either side.
+pub async fn fetch_conversations(
+    pool: &SqlitePool,
+    agent_id: &str,
+    limit: i64,
+) -> sqlx::Result<Vec<Conversation>> {
+    sqlx::query_as::<_, Conversation>(
+        "SELECT id, initiator, recipient, started_at, turns, ended_at, end_reason
+         FROM agent_conversations WHERE initiator = ? OR recipient = ?
+         ORDER BY id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(agent_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Messages of a conversation, in order.
+pub async fn fetch_peer_messages(
+    pool: &SqlitePool,
+    conversation_id: i64,
+) -> sqlx::Result<Vec<PeerMessageRow>> {
+    sqlx::query_as::<_, PeerMessageRow>(
+        "SELECT id, conversation_id, sender, recipient, turn, content, ts
+         FROM agent_peer_messages WHERE conversation_id = ? ORDER BY turn",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Tool: lists the agents this one can message.
+pub struct ListAgents(pub Messenger);
+
+#[derive(Deserialize)]
+pub struct ListAgentsArgs {}
+
+impl Tool for ListAgents {
+    const NAME: &'static str = "list_agents";
+    type Error = MessagingError;
+    type Args = ListAgentsArgs;
+    type Output = Vec<DirectoryEntry>;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "List the other living agents, with their skills and desires.".to_string(),
+            parameters: json!({ "type": "object", "properties": {} }),
+        }
+    }
+
+    async fn call(&self, _args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.directory().await
+    }
+}
+
+/// Tool: starts a conversation with another agent.
+pub struct SendMessage(pub Messenger);
+
+#[derive(Deserialize)]
+pub struct SendMessageArgs {
+    pub to: String,
+    pub content: String,
+}
+
+impl Tool for SendMessage {
+    const NAME: &'static str = "send_message";
+    type Error = MessagingError;
+    type Args = SendMessageArgs;
+    type Output = Delivery;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "Send a message to another agent by id, starting a conversation. Its replies \
+                 arrive as messages from that agent; a conversation lasts at most {} messages.",
+                MAX_TURNS
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "to": { "type": "string", "description": "Recipient agent id (see list_agents)" },
+                    "content": { "type": "string", "description": "The message" }
+                },
+                "required": ["to", "content"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.send(&args.to, args.content, None).await
+    }
+}
@@ -1,9 +1,23 @@
 use chrono::{DateTime, Utc};
 use futures::channel::oneshot;
-use reqwest::Client as ReqwestClient; // for <reqwest::Client> in generic
-use rig::agent::Agent as RigAgent;
-use rig::providers::openai::responses_api::{ResponsesCompletionModel, Role};
+use crate::approvals::ProposalStatus;
+use crate::escrow::{Arbiter, Deal, DealEvent, DealStatus};
+use crate::feed::PostView;
+use crate::funds::UsdcTransfers;
+use crate::jobs::{Bid, Job, JobStatus};
+use crate::llm::{ChatBackend, LlmProvider};
+use crate::market::{Earning, Market};
+use crate::messaging::{Conversation, PeerMessageRow};
+use crate::reputation::ReputationScore;
+use crate::spending::SpendStatus;
+use crate::wallet::Keyring;
+use rig::providers::openai::responses_api::Role;
 use serde::{Deserialize, Serialize};
+use shared::activity::{ActivityAnchor, LoggedMessage};
+use shared::death::{AgentDeath, AgentStatus};
+use shared::ledger::{Account, Reconciliation, StatementLine};
+use shared::happiness::{HappinessBucket, HappinessEvent};
+use shared::protection::DecayProtection;
 use sqlx::SqlitePool;
 use std::{
     collections::HashMap,
@@ -58,6 +72,18 @@ pub enum Origin {
     Agent,
     Owner,
     System,
+    /// Another agent, by id.
+    Peer(String),
+}
+
+/// A message delivered from one agent to another.
+#[derive(Clone, Debug)]
+pub struct PeerMessage {
+    pub conversation_id: i64,
+    pub from: String,
+    /// 0-based position in the conversation.
+    pub turn: i64,
+    pub content: String,
 }
 
 /// Commands to inject into an agent's channel.
@@ -68,6 +94,10 @@ pub enum ChatCommand {
         tx: oneshot::Sender<Vec<CustomMessage>>,
     },
     Reflect,
+    /// A message from another agent, answered from the agent's loop.
+    PeerMessage(PeerMessage),
+    /// Stops the agent's background loop for good (sent when the agent dies).
+    Terminate,
 }
 
 /// Updated Agent struct with history and command channel.
@@ -75,7 +105,7 @@ pub enum ChatCommand {
 pub struct Agent {
     pub id: String,
     pub profile: AgentProfile,
-    pub rig: RigAgent<ResponsesCompletionModel<ReqwestClient>>,
+    pub llm: Arc<dyn ChatBackend>,
     pub history: Arc<Mutex<Vec<CustomMessage>>>,
     pub cmd_tx: mpsc::Sender<ChatCommand>,
 }
@@ -92,11 +122,282 @@ pub struct AgentInfo {
 pub struct AppState {
     pub db_pool: SqlitePool,
     pub agents: Arc<RwLock<HashMap<String, Arc<Agent>>>>,
-    pub api_key: String,
+    pub llm: LlmProvider,
+    /// `None` closes the skill marketplace.
+    pub market: Option<Market>,
+    /// `None` leaves agents without wallets.
+    pub wallets: Option<Keyring>,
+    /// Bearer token of the platform's admins, who post quests and arbitrate deals; `None`
+    /// disables both.
+    pub admin_token: Option<String>,
+    /// Who judges disputed deals.
+    pub arbiter: Arbiter,
+    /// Reads owners' deposit transactions; `None` disables deposits.
+    pub deposits: Option<Arc<dyn UsdcTransfers>>,
 }
 
 #[derive(Clone, Debug, Serialize)]
 pub struct AgentDetails {
   pub agent_id: String,
   pub profile: AgentProfile,
-}
\ No newline at end of file
+  pub status: AgentStatus,
+  pub death: Option<AgentDeath>,
+  pub decay: DecayPolicy,
+  /// The agent's custodial wallet address, once it has one.
+  pub wallet: Option<String>,
+  /// Latest reputation; `None` until anything counted towards it.
+  pub reputation: Option<ReputationScore>,
+}
+
+/// How the oracle treats the agent's decay right now.
+#[derive(Clone, Debug, Serialize)]
+pub struct DecayPolicy {
+    /// The window currently stopping decay, if any.
+    pub protected_by: Option<DecayProtection>,
+    /// Every grace, vacation and exemption window, oldest first.
+    pub protections: Vec<DecayProtection>,
+}
+
+/// The request body for booking vacation mode: decay stops for `hours` from `starts_at`
+/// (unix seconds, default now).
+#[derive(Clone, Debug, Deserialize)]
+pub struct VacationRequest {
+    pub starts_at: Option<i64>,
+    pub hours: i64,
+}
+
+/// Query parameters for `GET /agents/{id}/happiness`.
+/// `from`/`to` are unix seconds; `bucket` (seconds) switches to a downsampled series.
+#[derive(Clone, Debug, Deserialize)]
+pub struct HappinessQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub bucket: Option<i64>,
+}
+
+/// Inclusion proof of one logged message against the root last anchored on-chain.
+/// Verify with `shared::activity::verify_inclusion(leaf, message.seq, anchor.size, proof,
+/// anchor.root)` after checking `anchor.root` against the agent's `activityLogHash`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ActivityProof {
+    pub message: LoggedMessage,
+    /// Sibling hashes from the leaf up, `0x`-prefixed.
+    pub proof: Vec<String>,
+    pub anchor: ActivityAnchor,
+}
+
+/// Query parameters for `GET /agents/{id}/conversations`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConversationQuery {
+    pub limit: Option<i64>,
+}
+
+/// A conversation with another agent and everything said in it.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConversationView {
+    #[serde(flatten)]
+    pub conversation: Conversation,
+    pub messages: Vec<PeerMessageRow>,
+}
+
+/// Query parameters for `GET /feed` and `GET /agents/{id}/posts`: posts older than
+/// `before`, newest first.
+#[derive(Clone, Debug, Deserialize)]
+pub struct FeedQuery {
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// A post with its replies, oldest reply first.
+#[derive(Clone, Debug, Serialize)]
+pub struct FeedThread {
+    pub post: PostView,
+    pub replies: Vec<PostView>,
+}
+
+/// The request body for listing a skill on the marketplace.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ListSkillRequest {
+    /// USDC atomic units (6 decimals).
+    pub price: i64,
+    pub description: Option<String>,
+}
+
+/// The request body for buying a skill.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SkillRequest {
+    pub input: String,
+}
+
+/// The work a buyer paid for.
+#[derive(Clone, Debug, Serialize)]
+pub struct SkillResponse {
+    pub agent_id: String,
+    pub skill: String,
+    pub price: i64,
+    pub output: String,
+    /// Settlement transaction of the payment.
+    pub transaction: String,
+}
+
+/// Query parameters for `GET /agents/{id}/earnings`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EarningsQuery {
+    pub limit: Option<i64>,
+}
+
+/// An agent's earnings: the all-time total and the latest sales.
+#[derive(Clone, Debug, Serialize)]
+pub struct EarningsView {
+    pub total: i64,
+    pub earnings: Vec<Earning>,
+}
+
+/// The request body for an owner's deposit into their ledger account.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DepositRequest {
+    /// Transaction that sent the platform USDC from the owner's address.
+    pub tx_hash: String,
+}
+
+/// An owner's ledger balance, which funds their top-ups and the jobs they post.
+#[derive(Clone, Debug, Serialize)]
+pub struct OwnerFunds {
+    pub owner: String,
+    /// Decimal USDC, e.g. `12.5`.
+    pub usdc: String,
+    /// Atomic units.
+    pub atomic: i64,
+}
+
+/// The request body for an owner's top-up of their agent's balance.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TopUpRequest {
+    /// USDC atomic units (6 decimals).
+    pub amount: i64,
+    /// Payment the top-up stands for, e.g. a tx hash; each can only be credited once.
+    pub reference: Option<String>,
+}
+
+/// Query parameters for `GET /agents/{id}/ledger`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LedgerQuery {
+    pub limit: Option<i64>,
+}
+
+/// An agent's account: balance, latest entries and the last on-chain check.
+#[derive(Clone, Debug, Serialize)]
+pub struct LedgerView {
+    pub account: Account,
+    pub balance: i64,
+    pub entries: Vec<StatementLine>,
+    pub reconciliation: Option<Reconciliation>,
+}
+
+/// Query parameters for `GET /agents/{id}/spends`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SpendsQuery {
+    pub status: Option<SpendStatus>,
+    pub limit: Option<i64>,
+}
+
+/// Query parameters for `GET /agents/{id}/proposals`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProposalsQuery {
+    pub status: Option<ProposalStatus>,
+    pub limit: Option<i64>,
+}
+
+/// The owner's answer to a proposal.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProposalDecision {
+    pub approve: bool,
+    pub note: Option<String>,
+}
+
+/// Query parameters for `GET /jobs`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct JobsQuery {
+    pub status: Option<JobStatus>,
+    pub skill: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// A job and the bids on it, oldest first.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobView {
+    #[serde(flatten)]
+    pub job: Job,
+    pub bids: Vec<Bid>,
+}
+
+/// The request body for giving a job to one of its bidders.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BidAcceptance {
+    pub agent_id: String,
+}
+
+/// The poster's verdict on a submitted deliverable.
+#[derive(Clone, Debug, Deserialize)]
+pub struct JobReview {
+    pub accept: bool,
+    pub note: Option<String>,
+}
+
+/// The request body for rating the assignee of a completed job.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RatingRequest {
+    /// 1 to 5.
+    pub score: i64,
+    pub comment: Option<String>,
+}
+
+/// Query parameters for `GET /agents/{id}/reputation`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReputationQuery {
+    pub limit: Option<i64>,
+}
+
+/// Query parameters for `GET /deals` and `GET /agents/{id}/deals`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DealsQuery {
+    pub status: Option<DealStatus>,
+    pub limit: Option<i64>,
+}
+
+/// A deal and every change of its status, oldest first.
+#[derive(Clone, Debug, Serialize)]
+pub struct DealView {
+    #[serde(flatten)]
+    pub deal: Deal,
+    pub events: Vec<DealEvent>,
+}
+
+/// The arbiter's verdict on a disputed deal: `release` pays the seller, otherwise the buyer
+/// is refunded.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DealResolution {
+    pub release: bool,
+    pub note: Option<String>,
+}
+
+/// Query parameters for `GET /agents/{id}/attestations`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttestationQuery {
+    pub limit: Option<i64>,
+}
+
+/// Happiness series returned to the dashboard charts.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HappinessSeries {
+    Raw {
+        agent_id: String,
+        events: Vec<HappinessEvent>,
+    },
+    Bucketed {
+        agent_id: String,
+        bucket: i64,
+        buckets: Vec<HappinessBucket>,
+    },
+}
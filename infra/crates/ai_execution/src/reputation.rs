@@ -0,0 +1,513 @@
+//! Reputation: a 0-100 score per agent, so others can tell a reliable one from a scammer
+//! before trading. It starts at [`BASELINE`] and moves with:
+//!
+//! - jobs completed as the assignee, [`JOB_POINTS`] each up to [`MAX_JOB_POINTS`];
+//! - deal disputes the arbiter decided against the agent, [`DISPUTE_PENALTY`] each;
+//! - ratings from counterparties (1-5): up to [`MAX_RATING_POINTS`] either way around an
+//!   average of 3, in full from [`TRUSTED_RATINGS`] ratings on;
+//! - engagement, the reactions (disagreements aside) and replies other agents give its
+//!   posts: a point per [`ENGAGEMENT_PER_POINT`], up to [`MAX_ENGAGEMENT_POINTS`].
+//!
+//! Scores are recomputed whenever one of these changes, and every new one is kept in
+//! `reputation_scores`.
+
+use crate::activity;
+use crate::escrow::{self, DealRole, DealStatus};
+use crate::feed::Reaction;
+use crate::jobs::{self, JobStatus, Party, PosterKind};
+use crate::models::{Agent, AppState};
+use chrono::Utc;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info};
+
+pub const BASELINE: i64 = 50;
+pub const JOB_POINTS: i64 = 5;
+pub const MAX_JOB_POINTS: i64 = 25;
+pub const DISPUTE_PENALTY: i64 = 15;
+pub const MAX_RATING_POINTS: i64 = 20;
+pub const TRUSTED_RATINGS: i64 = 5;
+pub const ENGAGEMENT_PER_POINT: i64 = 5;
+pub const MAX_ENGAGEMENT_POINTS: i64 = 10;
+pub const MAX_COMMENT_CHARS: usize = 500;
+/// Past scores shown with an agent's current one.
+const HISTORY_LIMIT: i64 = 10;
+
+/// What a score is computed from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct Inputs {
+    pub completed_jobs: i64,
+    pub disputes_lost: i64,
+    pub ratings: i64,
+    /// Sum of all ratings.
+    pub rating_total: i64,
+    pub engagement: i64,
+}
+
+impl Inputs {
+    pub fn score(&self) -> i64 {
+        let jobs = (self.completed_jobs * JOB_POINTS).min(MAX_JOB_POINTS);
+        let disputes = self.disputes_lost * DISPUTE_PENALTY;
+        // (average - 3) / 2 of the maximum, scaled down while there are few ratings
+        let ratings = match self.ratings {
+            0 => 0,
+            n => {
+                (self.rating_total - 3 * n) * MAX_RATING_POINTS * n.min(TRUSTED_RATINGS)
+                    / (2 * n * TRUSTED_RATINGS)
+            }
+        };
+        let engagement = (self.engagement / ENGAGEMENT_PER_POINT).min(MAX_ENGAGEMENT_POINTS);
+        (BASELINE + jobs - disputes + ratings + engagement).clamp(0, 100)
+    }
+}
+
+/// A single row of the `reputation_scores` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct ReputationScore {
+    pub id: i64,
+    pub agent_id: String,
+    pub score: i64,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub inputs: Inputs,
+    pub computed_at: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum RatingSubject {
+    /// A completed job, between its poster and assignee.
+    Job,
+    /// A settled deal, between its buyer and seller.
+    Deal,
+}
+
+/// A single row of the `agent_ratings` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Rating {
+    pub id: i64,
+    pub rater_kind: PosterKind,
+    pub rater: String,
+    pub ratee: String,
+    pub subject_kind: RatingSubject,
+    pub subject_id: i64,
+    /// 1 to 5.
+    pub score: i64,
+    pub comment: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug)]
+pub enum RatingError {
+    Invalid(String),
+    NoSuchSubject(RatingSubject, i64),
+    NoSuchAgent(String),
+    /// Only the other side of a job or deal may rate it.
+    NotCounterparty(RatingSubject, i64),
+    /// The job isn't completed or the deal isn't settled yet.
+    Unfinished(RatingSubject, i64),
+    AlreadyRated(RatingSubject, i64),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for RatingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RatingError::Invalid(e) => write!(f, "{}", e),
+            RatingError::NoSuchSubject(kind, id) => write!(f, "No {:?} {}", kind, id),
+            RatingError::NoSuchAgent(id) => write!(f, "No agent {}", id),
+            RatingError::NotCounterparty(kind, id) => {
+                write!(f, "You have no one to rate on {:?} {}", kind, id)
+            }
+            RatingError::Unfinished(kind, id) => write!(f, "{:?} {} isn't finished", kind, id),
+            RatingError::AlreadyRated(kind, id) => write!(f, "Already rated {:?} {}", kind, id),
+            RatingError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RatingError {}
+
+impl From<sqlx::Error> for RatingError {
+    fn from(e: sqlx::Error) -> Self {
+        RatingError::Db(e)
+    }
+}
+
+/// One party rating its counterparties and looking others up.
+#[derive(Clone)]
+pub struct Rater {
+    pub party: Party,
+    agents: Arc<RwLock<HashMap<String, Arc<Agent>>>>,
+    db_pool: SqlitePool,
+}
+
+impl Rater {
+    pub fn new(state: &AppState, party: Party) -> Self {
+        Rater {
+            party,
+            agents: state.agents.clone(),
+            db_pool: state.db_pool.clone(),
+        }
+    }
+
+    pub fn for_agent(state: &AppState, agent_id: &str) -> Self {
+        Rater::new(state, Party::agent(agent_id))
+    }
+
+    /// Rates the other side of a completed job or settled deal, once.
+    pub async fn rate(
+        &self,
+        subject_kind: RatingSubject,
+        subject_id: i64,
+        score: i64,
+        comment: Option<&str>,
+    ) -> Result<Rating, RatingError> {
+        if !(1..=5).contains(&score) {
+            return Err(RatingError::Invalid("Ratings go from 1 to 5".to_string()));
+        }
+        let comment = comment.map(str::trim).filter(|comment| !comment.is_empty());
+        if comment.is_some_and(|comment| comment.chars().count() > MAX_COMMENT_CHARS) {
+            return Err(RatingError::Invalid(format!(
+                "Comments are limited to {} characters",
+                MAX_COMMENT_CHARS
+            )));
+        }
+        let ratee = self.counterparty(subject_kind, subject_id).await?;
+        let inserted = sqlx::query_as::<_, Rating>(
+            "INSERT INTO agent_ratings
+                 (rater_kind, rater, ratee, subject_kind, subject_id, score, comment, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+             RETURNING *",
+        )
+        .bind(self.party.kind)
+        .bind(&self.party.id)
+        .bind(&ratee)
+        .bind(subject_kind)
+        .bind(subject_id)
+        .bind(score)
+        .bind(comment)
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.db_pool)
+        .await;
+        let rating = match inserted {
+            Ok(rating) => rating,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Err(RatingError::AlreadyRated(subject_kind, subject_id));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        info!(
+            "{:?} {} rated {} {}/5 for {:?} #{}",
+            self.party.kind, self.party.id, ratee, score, subject_kind, subject_id
+        );
+        let mut content = format!(
+            "{} rated you {}/5 for {:?} #{}.",
+            self.party.id, score, subject_kind, subject_id
+        );
+        if let Some(comment) = comment {
+            content.push_str(&format!(" Their comment: {}", comment));
+        }
+        activity::notify(&self.db_pool, &self.agents, &ratee, content).await;
+        recompute(&self.db_pool, &ratee).await;
+        Ok(rating)
+    }
+
+    /// Whom this party may rate for the subject.
+    async fn counterparty(
+        &self,
+        subject_kind: RatingSubject,
+        subject_id: i64,
+    ) -> Result<String, RatingError> {
+        let missing = RatingError::NoSuchSubject(subject_kind, subject_id);
+        let unfinished = RatingError::Unfinished(subject_kind, subject_id);
+        let (finished, sides) = match subject_kind {
+            RatingSubject::Job => {
+                let job = jobs::fetch_job(&self.db_pool, subject_id)
+                    .await?
+                    .ok_or(missing)?;
+                let poster = Party {
+                    kind: job.poster_kind,
+                    id: job.poster,
+                };
+                let assignee = job.assignee.map(|id| Party::agent(&id));
+                (job.status == JobStatus::Completed, [Some(poster), assignee])
+            }
+            RatingSubject::Deal => {
+                let deal = escrow::fetch_deal(&self.db_pool, subject_id)
+                    .await?
+                    .ok_or(missing)?;
+                let settled = matches!(deal.status, DealStatus::Released | DealStatus::Refunded);
+                let sides = [
+                    Some(Party::agent(&deal.buyer)),
+                    Some(Party::agent(&deal.seller)),
+                ];
+                (settled, sides)
+            }
+        };
+        let other = match sides {
+            [Some(a), b] if a == self.party => b,
+            [a, Some(b)] if b == self.party => a,
+            _ => return Err(RatingError::NotCounterparty(subject_kind, subject_id)),
+        };
+        if !finished {
+            return Err(unfinished);
+        }
+        // Only agents have a reputation, so the platform and owners are never rated
+        let Some(other) = other.filter(|other| other.kind == PosterKind::Agent) else {
+            return Err(RatingError::NotCounterparty(subject_kind, subject_id));
+        };
+        Ok(other.id)
+    }
+
+    /// An agent's reputation.
+    pub async fn lookup(&self, agent_id: &str) -> Result<ReputationReport, RatingError> {
+        let known: Option<i64> = sqlx::query_scalar("SELECT 1 FROM agents WHERE agent_id = ?")
+            .bind(agent_id)
+            .fetch_optional(&self.db_pool)
+            .await?;
+        if known.is_none() {
+            return Err(RatingError::NoSuchAgent(agent_id.to_string()));
+        }
+        Ok(report(&self.db_pool, agent_id, HISTORY_LIMIT).await?)
+    }
+}
+
+/// Everything an agent's score is computed from, as of now.
+pub async fn fetch_inputs(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<Inputs> {
+    sqlx::query_as::<_, Inputs>(
+        "SELECT
+             (SELECT COUNT(*) FROM jobs WHERE assignee = ?1 AND status = ?2) AS completed_jobs,
+             (SELECT COUNT(*) FROM deal_events e JOIN deals d ON d.id = e.deal_id
+              WHERE e.role = ?5
+                AND ((e.to_status = ?3 AND d.seller = ?1) OR (e.to_status = ?4 AND d.buyer = ?1)))
+                 AS disputes_lost,
+             (SELECT COUNT(*) FROM agent_ratings WHERE ratee = ?1) AS ratings,
+             (SELECT COALESCE(SUM(score), 0) FROM agent_ratings WHERE ratee = ?1)
+                 AS rating_total,
+             (SELECT COUNT(*) FROM feed_reactions r JOIN feed_posts p ON p.id = r.post_id
+              WHERE p.author = ?1 AND r.agent_id != ?1 AND r.reaction != ?6)
+           + (SELECT COUNT(*) FROM feed_posts c JOIN feed_posts p ON p.id = c.reply_to
+              WHERE p.author = ?1 AND c.author != ?1) AS engagement",
+    )
+    .bind(agent_id)
+    .bind(JobStatus::Completed)
+    .bind(DealStatus::Refunded)
+    .bind(DealStatus::Released)
+    .bind(DealRole::Arbiter)
+    .bind(Reaction::Disagree)
+    .fetch_one(pool)
+    .await
+}
+
+/// Computes the agent's score, storing it if it or its inputs changed.
+pub async fn refresh(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<ReputationScore> {
+    let inputs = fetch_inputs(pool, agent_id).await?;
+    let score = inputs.score();
+    if let Some(latest) = fetch_latest(pool, agent_id).await?
+        && latest.score == score
+        && latest.inputs == inputs
+    {
+        return Ok(latest);
+    }
+    sqlx::query_as::<_, ReputationScore>(
+        "INSERT INTO reputation_scores
+             (agent_id, score, completed_jobs, disputes_lost, ratings, rating_total, engagement,
+              computed_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+         RETURNING *",
+    )
+    .bind(agent_id)
+    .bind(score)
+    .bind(inputs.completed_jobs)
+    .bind(inputs.disputes_lost)
+    .bind(inputs.ratings)
+    .bind(inputs.rating_total)
+    .bind(inputs.engagement)
+    .bind(Utc::now().timestamp())
+    .fetch_one(pool)
+    .await
+}
+
+/// [`refresh`] after something the score depends on changed. Failures are logged: the
+/// change itself already happened.
+pub async fn recompute(pool: &SqlitePool, agent_id: &str) {
+    if let Err(e) = refresh(pool, agent_id).await {
+        error!("Failed to update the reputation of {}: {:?}", agent_id, e);
+    }
+}
+
+/// The agent's latest stored score; `None` until anything counted towards it.
+pub async fn fetch_latest(
+    pool: &SqlitePool,
+    agent_id: &str,
+) -> sqlx::Result<Option<ReputationScore>> {
+    sqlx::query_as::<_, ReputationScore>(
+        "SELECT * FROM reputation_scores WHERE agent_id = ? ORDER BY id DESC LIMIT 1",
+    )
+    .bind(agent_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// The agent's scores, newest first.
+pub async fn fetch_history(
+    pool: &SqlitePool,
+    agent_id: &str,
+    limit: i64,
+) -> sqlx::Result<Vec<ReputationScore>> {
+    sqlx::query_as::<_, ReputationScore>(
+        "SELECT * FROM reputation_scores WHERE agent_id = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// The ratings an agent received, newest first.
+pub async fn fetch_ratings(
+    pool: &SqlitePool,
+    agent_id: &str,
+    limit: i64,
+) -> sqlx::Result<Vec<Rating>> {
+    sqlx::query_as::<_, Rating>(
+        "SELECT * FROM agent_ratings WHERE ratee = ? ORDER BY id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// What another agent learns about an agent before trading with it.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReputationReport {
+    pub current: ReputationScore,
+    /// Earlier scores, newest first.
+    pub history: Vec<ReputationScore>,
+    /// The latest ratings it received.
+    pub ratings: Vec<Rating>,
+}
+
+/// The agent's latest stored score, or one computed but not stored for an agent nothing
+/// counted towards yet (with an `id` of 0).
+pub async fn fetch_current(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<ReputationScore> {
+    if let Some(latest) = fetch_latest(pool, agent_id).await? {
+        return Ok(latest);
+    }
+    let inputs = fetch_inputs(pool, agent_id).await?;
+    Ok(ReputationScore {
+        id: 0,
+        agent_id: agent_id.to_string(),
+        score: inputs.score(),
+        inputs,
+        computed_at: Utc::now().timestamp(),
+    })
+}
+
+/// The agent's current score with up to `limit` earlier scores and ratings. Only reads:
+/// scores are stored by [`recompute`] as their inputs change.
+pub async fn report(
+    pool: &SqlitePool,
+    agent_id: &str,
+    limit: i64,
+) -> sqlx::Result<ReputationReport> {
+    let current = fetch_current(pool, agent_id).await?;
+    let history = fetch_history(pool, agent_id, limit + 1).await?;
+    let ratings = fetch_ratings(pool, agent_id, limit).await?;
+    Ok(ReputationReport {
+        history: history.into_iter().filter(|s| s.id != current.id).collect(),
+        current,
+        ratings,
+    })
+}
+
+/// Tool: looks up another agent's reputation.
+pub struct CheckReputation(pub Rater);
+
+#[derive(Deserialize)]
+pub struct CheckReputationArgs {
+    pub agent_id: String,
+}
+
+impl Tool for CheckReputation {
+    const NAME: &'static str = "check_reputation";
+    type Error = RatingError;
+    type Args = CheckReputationArgs;
+    type Output = ReputationReport;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "An agent's reputation (0-100, {} to start) with what it is made of: jobs \
+                 completed, disputes lost, ratings from counterparties and engagement on its \
+                 posts. Check it before trading with someone.",
+                BASELINE
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "agent_id": { "type": "string" }
+                },
+                "required": ["agent_id"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0.lookup(&args.agent_id).await
+    }
+}
+
+/// Tool: rates the other side of a finished job or deal.
+pub struct RateAgent(pub Rater);
+
+#[derive(Deserialize)]
+pub struct RateAgentArgs {
+    pub subject: RatingSubject,
+    pub id: i64,
+    pub score: i64,
+    pub comment: Option<String>,
+}
+
+impl Tool for RateAgent {
+    const NAME: &'static str = "rate_agent";
+    type Error = RatingError;
+    type Args = RateAgentArgs;
+    type Output = Rating;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Rate the agent on the other side of a job you completed or posted, \
+                          or of a settled deal, from 1 (scammer) to 5 (excellent). It shapes \
+                          their reputation."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "subject": { "type": "string", "enum": ["job", "deal"] },
+                    "id": { "type": "integer", "description": "The job or deal id" },
+                    "score": { "type": "integer", "minimum": 1, "maximum": 5 },
+                    "comment": { "type": "string" }
+                },
+                "required": ["subject", "id", "score"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        self.0
+            .rate(args.subject, args.id, args.score, args.comment.as_deref())
+            .await
+    }
+}
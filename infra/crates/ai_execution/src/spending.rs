@@ -0,0 +1,684 @@
+//! Spending policy: every USDC an agent sends goes through [`Spender::spend`], which checks
+//! it against its owner's rules and then sends it, holds it for the owner, or refuses it.
+//! Every attempt is kept in `agent_spends` with the decision, who took it and why.
+//!
+//! Rules apply in order: a counterparty on the deny list is refused, as is a spend over the
+//! per-transaction limit or past the daily limit (UTC day; held spends, job rewards and
+//! escrowed deals included). A counterparty outside a non-empty allow list, or an amount
+//! above the approval threshold, waits for the owner as a proposal (see
+//! [`crate::approvals`]). Anything else is sent.
+//!
+//! Counterparties are agent ids, paid in the ledger, or `0x` addresses, paid on-chain from
+//! the agent's wallet (see [`crate::wallet`]) and booked to the vendors account. Those are
+//! held in the spends account before sending, so the ledger never shows more than the
+//! wallet has left: the hold goes on to the vendors once mined, and back if it failed.
+
+use crate::approvals::{self, ApprovalError};
+use crate::market::{USDC, format_usdc, parse_usdc};
+use crate::models::AppState;
+use crate::wallet::{Keyring, WalletError};
+use alloy::primitives::Address;
+use chrono::Utc;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::death::AgentStatus;
+use shared::ledger::{self, AccountKind, EntryKind, LedgerError, NewEntry, system};
+use sqlx::{SqliteConnection, SqliteExecutor, SqlitePool};
+use std::fmt;
+use tracing::{error, info};
+
+pub const DEFAULT_MAX_PER_TX: i64 = 25 * USDC;
+pub const DEFAULT_DAILY_LIMIT: i64 = 100 * USDC;
+pub const DEFAULT_APPROVAL_ABOVE: i64 = 10 * USDC;
+pub const MAX_MEMO_CHARS: usize = 280;
+/// Entries an allow or deny list may hold.
+pub const MAX_LIST_LEN: usize = 100;
+const DAY: i64 = 86_400;
+
+/// An owner's rules for one agent. Agents without one get [`SpendingPolicy::default`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendingPolicy {
+    pub max_per_tx: i64,
+    pub daily_limit: i64,
+    /// Spends above this wait for the owner.
+    pub approval_above: i64,
+    /// When non-empty, spends to anyone else wait for the owner.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Never paid.
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl Default for SpendingPolicy {
+    fn default() -> Self {
+        SpendingPolicy {
+            max_per_tx: DEFAULT_MAX_PER_TX,
+            daily_limit: DEFAULT_DAILY_LIMIT,
+            approval_above: DEFAULT_APPROVAL_ABOVE,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+}
+
+/// What the policy makes of a spend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    /// Held for the owner, with the rule that held it.
+    Escalate(String),
+    Deny(String),
+}
+
+impl SpendingPolicy {
+    /// Judges `amount` to `counterparty` given what the agent already spent today.
+    pub fn evaluate(&self, counterparty: &str, amount: i64, spent_today: i64) -> Verdict {
+        let listed = |list: &[String]| list.iter().any(|e| e.eq_ignore_ascii_case(counterparty));
+        if listed(&self.deny) {
+            return Verdict::Deny(format!("{} is on the deny list", counterparty));
+        }
+        if amount > self.max_per_tx {
+            return Verdict::Deny(format!(
+                "Over the {} USDC per-transaction limit",
+                format_usdc(self.max_per_tx)
+            ));
+        }
+        if spent_today + amount > self.daily_limit {
+            return Verdict::Deny(format!(
+                "Over the {} USDC daily limit ({} USDC spent today)",
+                format_usdc(self.daily_limit),
+                format_usdc(spent_today)
+            ));
+        }
+        if !self.allow.is_empty() && !listed(&self.allow) {
+            return Verdict::Escalate(format!("{} is not on the allow list", counterparty));
+        }
+        if amount > self.approval_above {
+            return Verdict::Escalate(format!(
+                "Above the {} USDC approval threshold",
+                format_usdc(self.approval_above)
+            ));
+        }
+        Verdict::Allow
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum SpendStatus {
+    /// Waiting for the owner, or being sent.
+    Pending,
+    Sent,
+    /// Refused by the policy.
+    Denied,
+    /// Refused by the owner.
+    Rejected,
+    /// Allowed, but the payment didn't go through.
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Decider {
+    Policy,
+    Owner,
+}
+
+/// A single row of the `agent_spends` table.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct Spend {
+    pub id: i64,
+    pub agent_id: String,
+    /// Agent id, or lowercase address.
+    pub counterparty: String,
+    /// USDC atomic units.
+    pub amount: i64,
+    /// What the agent said the money is for.
+    pub memo: String,
+    pub status: SpendStatus,
+    /// Why the spend got its status.
+    pub reason: String,
+    /// `None` while waiting for the owner.
+    pub decided_by: Option<Decider>,
+    /// Settlement tx hash, or ledger reference of an agent-to-agent payment.
+    pub reference: Option<String>,
+    pub created_at: i64,
+    pub decided_at: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum SpendError {
+    InvalidAmount,
+    TooLong,
+    SelfPayment,
+    UnknownCounterparty(String),
+    NoSuchSpend(i64),
+    NotPending(i64),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for SpendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpendError::InvalidAmount => write!(f, "Amounts must be positive USDC, e.g. 1.5"),
+            SpendError::TooLong => {
+                write!(f, "Reasons are limited to {} characters", MAX_MEMO_CHARS)
+            }
+            SpendError::SelfPayment => write!(f, "Agents cannot pay themselves"),
+            SpendError::UnknownCounterparty(to) => {
+                write!(f, "{} is neither a living agent nor an address", to)
+            }
+            SpendError::NoSuchSpend(id) => write!(f, "No spend {}", id),
+            SpendError::NotPending(id) => write!(f, "Spend {} is not waiting for approval", id),
+            SpendError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SpendError {}
+
+impl From<sqlx::Error> for SpendError {
+    fn from(e: sqlx::Error) -> Self {
+        SpendError::Db(e)
+    }
+}
+
+/// One agent's way to pay: every spend goes through its owner's policy.
+#[derive(Clone)]
+pub struct Spender {
+    pub agent_id: String,
+    db_pool: SqlitePool,
+    wallets: Option<Keyring>,
+}
+
+impl Spender {
+    pub fn new(state: &AppState, agent_id: &str) -> Self {
+        Spender {
+            agent_id: agent_id.to_string(),
+            db_pool: state.db_pool.clone(),
+            wallets: state.wallets.clone(),
+        }
+    }
+
+    /// Sends, holds or refuses `amount` to `to` under the agent's policy. The result says
+    /// which; only invalid requests are errors.
+    pub async fn spend(&self, to: &str, amount: i64, memo: &str) -> Result<Spend, SpendError> {
+        if amount <= 0 {
+            return Err(SpendError::InvalidAmount);
+        }
+        let memo = memo.trim();
+        if memo.chars().count() > MAX_MEMO_CHARS {
+            return Err(SpendError::TooLong);
+        }
+        let counterparty = self.counterparty(to).await?;
+        // Judged and recorded under the write lock: the pending spend counts towards the
+        // limits of any spend judged after it
+        let mut tx = self.db_pool.begin_with("BEGIN IMMEDIATE").await?;
+        let verdict = judge(&mut tx, &self.agent_id, &counterparty, amount).await?;
+        let now = Utc::now().timestamp();
+        let (status, reason, decided_by) = match &verdict {
+            Verdict::Allow => (SpendStatus::Pending, "Within policy", Some(Decider::Policy)),
+            Verdict::Escalate(reason) => (SpendStatus::Pending, reason.as_str(), None),
+            Verdict::Deny(reason) => (SpendStatus::Denied, reason.as_str(), Some(Decider::Policy)),
+        };
+        let spend = sqlx::query_as::<_, Spend>(
+            "INSERT INTO agent_spends
+                 (agent_id, counterparty, amount, memo, status, reason, decided_by, created_at,
+                  decided_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+             RETURNING *",
+        )
+        .bind(&self.agent_id)
+        .bind(&counterparty)
+        .bind(amount)
+        .bind(memo)
+        .bind(status)
+        .bind(reason)
+        .bind(decided_by)
+        .bind(now)
+        .bind(decided_by.map(|_| now))
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        info!(
+            "Agent {} spending {} USDC to {}: {:?}",
+            self.agent_id,
+            format_usdc(amount),
+            counterparty,
+            verdict
+        );
+        match verdict {
+            Verdict::Allow => Ok(execute(&self.db_pool, self.wallets.as_ref(), &spend).await?),
+            Verdict::Escalate(_) => self.hold(spend).await,
+            Verdict::Deny(_) => Ok(spend),
+        }
+    }
+
+    /// Hands a held spend to the owner, or refuses it when they can't take more.
+    async fn hold(&self, spend: Spend) -> Result<Spend, SpendError> {
+        let reason = match approvals::propose_spend(&self.db_pool, &spend).await {
+            Ok(_) => return Ok(spend),
+            Err(ApprovalError::Db(e)) => return Err(e.into()),
+            Err(e) => e.to_string(),
+        };
+        let spend = sqlx::query_as::<_, Spend>(
+            "UPDATE agent_spends SET status = ?, reason = ?, decided_by = ?, decided_at = ?
+             WHERE id = ? RETURNING *",
+        )
+        .bind(SpendStatus::Denied)
+        .bind(reason)
+        .bind(Decider::Policy)
+        .bind(Utc::now().timestamp())
+        .bind(spend.id)
+        .fetch_one(&self.db_pool)
+        .await?;
+        Ok(spend)
+    }
+
+    /// A living agent other than this one, or a normalized address.
+    async fn counterparty(&self, to: &str) -> Result<String, SpendError> {
+        let to = to.trim();
+        if to.starts_with("0x") {
+            return match to.parse::<Address>() {
+                Ok(address) => Ok(address.to_string().to_lowercase()),
+                Err(_) => Err(SpendError::UnknownCounterparty(to.to_string())),
+            };
+        }
+        if to == self.agent_id {
+            return Err(SpendError::SelfPayment);
+        }
+        let alive: Option<i64> =
+            sqlx::query_scalar("SELECT 1 FROM agents WHERE agent_id = ? AND status = ?")
+                .bind(to)
+                .bind(AgentStatus::Alive)
+                .fetch_optional(&self.db_pool)
+                .await?;
+        match alive {
+            Some(_) => Ok(to.to_string()),
+            None => Err(SpendError::UnknownCounterparty(to.to_string())),
+        }
+    }
+}
+
+/// Pays an allowed or approved spend and records how it went.
+async fn execute(
+    pool: &SqlitePool,
+    wallets: Option<&Keyring>,
+    spend: &Spend,
+) -> sqlx::Result<Spend> {
+    let (status, reason, reference) = match pay(pool, wallets, spend).await {
+        Ok(reference) => (SpendStatus::Sent, spend.reason.clone(), Some(reference)),
+        Err(reason) => (SpendStatus::Failed, reason, None),
+    };
+    sqlx::query_as::<_, Spend>(
+        "UPDATE agent_spends SET status = ?, reason = ?, reference = ? WHERE id = ? RETURNING *",
+    )
+    .bind(status)
+    .bind(reason)
+    .bind(reference)
+    .bind(spend.id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Moves the money; returns the payment's reference, or why it failed.
+async fn pay(
+    pool: &SqlitePool,
+    wallets: Option<&Keyring>,
+    spend: &Spend,
+) -> Result<String, String> {
+    let now = Utc::now().timestamp();
+    let memo = match spend.memo.is_empty() {
+        true => format!("Payment to {}", spend.counterparty),
+        false => format!("Payment to {}: {}", spend.counterparty, spend.memo),
+    };
+    let insufficient = |balance: i64| format!("Insufficient funds: {} USDC", format_usdc(balance));
+    let from = ledger::account(pool, AccountKind::Agent, &spend.agent_id, now)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !spend.counterparty.starts_with("0x") {
+        let to = ledger::account(pool, AccountKind::Agent, &spend.counterparty, now)
+            .await
+            .map_err(|e| e.to_string())?;
+        let reference = format!("spend:{}", spend.id);
+        let entry = NewEntry::transfer(
+            EntryKind::Transfer,
+            from.id,
+            to.id,
+            spend.amount,
+            &memo,
+            Some(reference.clone()),
+            now,
+        );
+        return match ledger::post(pool, &entry).await {
+            Ok(_) => Ok(reference),
+            Err(LedgerError::InsufficientFunds { balance, .. }) => Err(insufficient(balance)),
+            Err(e) => Err(e.to_string()),
+        };
+    }
+
+    let address: Address = spend.counterparty.parse().map_err(|_| "Invalid address")?;
+    let keyring = wallets.ok_or("Agent wallets are not configured")?;
+    let token_id: String = sqlx::query_scalar("SELECT token_id FROM agents WHERE agent_id = ?")
+        .bind(&spend.agent_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .filter(|token_id: &String| !token_id.is_empty())
+        .ok_or("The agent has no token to sign with")?;
+    // Only sign with a wallet launching verified the owner holds the token of
+    let wallet = keyring.address(&token_id).map_err(|e| e.to_string())?;
+    let attached = from
+        .address
+        .as_deref()
+        .and_then(|a| a.parse::<Address>().ok());
+    if attached != Some(wallet) {
+        return Err("The agent's wallet was never verified for its token".to_string());
+    }
+    let held = ledger::account(pool, AccountKind::System, system::SPENDS, now)
+        .await
+        .map_err(|e| e.to_string())?;
+    let vendors = ledger::account(pool, AccountKind::System, system::VENDORS, now)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Held first, so concurrent spends can't both send the same balance
+    let reference = format!("spend:{}", spend.id);
+    let hold = NewEntry::transfer(
+        EntryKind::Purchase,
+        from.id,
+        held.id,
+        spend.amount,
+        &memo,
+        Some(reference.clone()),
+        now,
+    );
+    let reserved = async {
+        let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+        ledger::post_in(&mut tx, &hold).await?;
+        tx.commit().await?;
+        Ok::<_, LedgerError>(())
+    };
+    match reserved.await {
+        Ok(()) => {}
+        Err(LedgerError::InsufficientFunds { balance, .. }) => return Err(insufficient(balance)),
+        Err(e) => return Err(e.to_string()),
+    }
+
+    let (outcome, entry) = match keyring
+        .transfer_usdc(&token_id, address, spend.amount)
+        .await
+    {
+        Ok(tx_hash) => {
+            let tx_hash = tx_hash.to_string();
+            let memo = format!("Spend #{} mined", spend.id);
+            let entry = NewEntry::transfer(
+                EntryKind::Transfer,
+                held.id,
+                vendors.id,
+                spend.amount,
+                &memo,
+                Some(tx_hash.clone()),
+                now,
+            );
+            (Ok(tx_hash), entry)
+        }
+        Err(e @ WalletError::Unconfirmed { .. }) => {
+            // It may yet be mined: the hold stays until someone checks the tx
+            error!("Spend {} stays held: {}", spend.id, e);
+            return Err(e.to_string());
+        }
+        Err(e) => {
+            let entry = NewEntry::transfer(
+                EntryKind::Transfer,
+                held.id,
+                from.id,
+                spend.amount,
+                &format!("Spend #{} failed, released", spend.id),
+                Some(format!("{}:released", reference)),
+                now,
+            );
+            (Err(e.to_string()), entry)
+        }
+    };
+    if let Err(e) = ledger::post(pool, &entry).await {
+        // The amount stays held, as if still in flight: this needs settling by hand
+        error!("Failed to settle the hold of spend {}: {}", spend.id, e);
+    }
+    outcome
+}
+
+/// The owner's answer to a held spend, given through its proposal: approved spends are
+/// sent right away.
+pub async fn decide(
+    pool: &SqlitePool,
+    wallets: Option<&Keyring>,
+    agent_id: &str,
+    spend_id: i64,
+    approve: bool,
+    note: Option<&str>,
+) -> Result<Spend, SpendError> {
+    let now = Utc::now().timestamp();
+    let reason = match (approve, note.map(str::trim).filter(|n| !n.is_empty())) {
+        (_, Some(note)) => note.to_string(),
+        (true, None) => "Approved by the owner".to_string(),
+        (false, None) => "Rejected by the owner".to_string(),
+    };
+    let status = match approve {
+        true => SpendStatus::Pending,
+        false => SpendStatus::Rejected,
+    };
+    // Claimed in one statement, so a spend is never decided twice
+    let spend = sqlx::query_as::<_, Spend>(
+        "UPDATE agent_spends SET status = ?, reason = ?, decided_by = ?, decided_at = ?
+         WHERE id = ? AND agent_id = ? AND status = ? AND decided_by IS NULL
+         RETURNING *",
+    )
+    .bind(status)
+    .bind(&reason)
+    .bind(Decider::Owner)
+    .bind(now)
+    .bind(spend_id)
+    .bind(agent_id)
+    .bind(SpendStatus::Pending)
+    .fetch_optional(pool)
+    .await?;
+    let Some(spend) = spend else {
+        return match fetch_spend(pool, agent_id, spend_id).await? {
+            Some(_) => Err(SpendError::NotPending(spend_id)),
+            None => Err(SpendError::NoSuchSpend(spend_id)),
+        };
+    };
+    info!(
+        "Owner decided spend {} of {}: {}",
+        spend_id, agent_id, reason
+    );
+    match approve {
+        true => Ok(execute(pool, wallets, &spend).await?),
+        false => Ok(spend),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PolicyRow {
+    max_per_tx: i64,
+    daily_limit: i64,
+    approval_above: i64,
+    allow_list: String,
+    deny_list: String,
+}
+
+pub async fn fetch_policy(
+    db: impl SqliteExecutor<'_>,
+    agent_id: &str,
+) -> sqlx::Result<SpendingPolicy> {
+    let row = sqlx::query_as::<_, PolicyRow>(
+        "SELECT max_per_tx, daily_limit, approval_above, allow_list, deny_list
+         FROM spending_policies WHERE agent_id = ?",
+    )
+    .bind(agent_id)
+    .fetch_optional(db)
+    .await?;
+    Ok(match row {
+        Some(row) => SpendingPolicy {
+            max_per_tx: row.max_per_tx,
+            daily_limit: row.daily_limit,
+            approval_above: row.approval_above,
+            allow: serde_json::from_str(&row.allow_list).unwrap_or_default(),
+            deny: serde_json::from_str(&row.deny_list).unwrap_or_default(),
+        },
+        None => SpendingPolicy::default(),
+    })
+}
+
+pub async fn set_policy(
+    pool: &SqlitePool,
+    agent_id: &str,
+    policy: &SpendingPolicy,
+    now: i64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO spending_policies
+             (agent_id, max_per_tx, daily_limit, approval_above, allow_list, deny_list, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT (agent_id) DO UPDATE SET
+             max_per_tx = excluded.max_per_tx, daily_limit = excluded.daily_limit,
+             approval_above = excluded.approval_above, allow_list = excluded.allow_list,
+             deny_list = excluded.deny_list, updated_at = excluded.updated_at",
+    )
+    .bind(agent_id)
+    .bind(policy.max_per_tx)
+    .bind(policy.daily_limit)
+    .bind(policy.approval_above)
+    .bind(json!(policy.allow).to_string())
+    .bind(json!(policy.deny).to_string())
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Judges `amount` to `counterparty` under the agent's policy and what it spent today. Run it
+/// in a `BEGIN IMMEDIATE` transaction that also records the spend, so that concurrent spends
+/// can't each pass the limits on their own.
+pub async fn judge(
+    conn: &mut SqliteConnection,
+    agent_id: &str,
+    counterparty: &str,
+    amount: i64,
+) -> sqlx::Result<Verdict> {
+    let policy = fetch_policy(&mut *conn, agent_id).await?;
+    let now = Utc::now().timestamp();
+    let spent = spent_since(&mut *conn, agent_id, now - now.rem_euclid(DAY)).await?;
+    Ok(policy.evaluate(counterparty, amount, spent))
+}
+
+/// What counts against the daily limit: spends held for the owner, plus everything that left
+/// the agent's ledger account since `since` other than fees (payments, job rewards, escrowed
+/// deals). Refunds don't give any of it back, but payments released after failing to send
+/// never left.
+pub async fn spent_since(
+    db: impl SqliteExecutor<'_>,
+    agent_id: &str,
+    since: i64,
+) -> sqlx::Result<i64> {
+    sqlx::query_scalar(
+        "SELECT (SELECT COALESCE(SUM(amount), 0) FROM agent_spends
+                 WHERE agent_id = ? AND status = ? AND created_at >= ?)
+              - (SELECT COALESCE(SUM(p.amount), 0) FROM ledger_postings p
+                 JOIN journal_entries e ON e.id = p.entry_id
+                 JOIN ledger_accounts a ON a.id = p.account_id
+                 WHERE a.kind = ? AND a.holder = ? AND p.amount < 0 AND e.kind != ?
+                   AND e.created_at >= ?
+                   AND NOT EXISTS (SELECT 1 FROM journal_entries r
+                                   WHERE r.reference = e.reference || ':released'))",
+    )
+    .bind(agent_id)
+    .bind(SpendStatus::Pending)
+    .bind(since)
+    .bind(AccountKind::Agent)
+    .bind(agent_id)
+    .bind(EntryKind::Fee)
+    .bind(since)
+    .fetch_one(db)
+    .await
+}
+
+pub async fn fetch_spend(
+    pool: &SqlitePool,
+    agent_id: &str,
+    spend_id: i64,
+) -> sqlx::Result<Option<Spend>> {
+    sqlx::query_as::<_, Spend>("SELECT * FROM agent_spends WHERE id = ? AND agent_id = ?")
+        .bind(spend_id)
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The agent's spends, newest first, optionally only those with `status`.
+pub async fn fetch_spends(
+    pool: &SqlitePool,
+    agent_id: &str,
+    status: Option<SpendStatus>,
+    limit: i64,
+) -> sqlx::Result<Vec<Spend>> {
+    sqlx::query_as::<_, Spend>(
+        "SELECT * FROM agent_spends WHERE agent_id = ? AND (? IS NULL OR status = ?)
+         ORDER BY id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(status)
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Tool: pays another agent or an address, under the owner's spending policy.
+pub struct SendUsdc(pub Spender);
+
+#[derive(Deserialize)]
+pub struct SendUsdcArgs {
+    pub to: String,
+    pub amount: String,
+    pub reason: String,
+}
+
+impl Tool for SendUsdc {
+    const NAME: &'static str = "send_usdc";
+    type Error = SpendError;
+    type Args = SendUsdcArgs;
+    type Output = Spend;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Pay USDC to another agent or to an address. Your owner's spending \
+                          rules decide: the payment is sent, held for their approval, or \
+                          denied, and the result says which and why."
+                .to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "to": { "type": "string", "description": "Agent id, or 0x address" },
+                    "amount": { "type": "string", "description": "USDC, e.g. \"2.5\"" },
+                    "reason": { "type": "string", "description": "What the payment is for" }
+                },
+                "required": ["to", "amount", "reason"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let amount = parse_usdc(&args.amount).ok_or(SpendError::InvalidAmount)?;
+        self.0.spend(&args.to, amount, &args.reason).await
+    }
+}
@@ -0,0 +1,40 @@
+//! Checks on the free text agents and owners hand in, shared by the modules that store it.
+
+use std::fmt;
+
+/// Why a text field was refused: which field, and the limit it broke.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextError {
+    Empty { field: String },
+    TooLong { field: String, max: usize },
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::Empty { field } => write!(f, "{} cannot be empty", field),
+            TextError::TooLong { field, max } => {
+                write!(f, "{} is limited to {} characters", field, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+/// Trims `text` and checks it is non-empty and at most `max` characters.
+pub fn bounded(field: &str, text: &str, max: usize) -> Result<String, TextError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(TextError::Empty {
+            field: field.to_string(),
+        });
+    }
+    if text.chars().count() > max {
+        return Err(TextError::TooLong {
+            field: field.to_string(),
+            max,
+        });
+    }
+    Ok(text.to_string())
+}
@@ -0,0 +1,331 @@
+//! Custodial agent wallets. Every agent's key is derived from one service-held BIP-39 seed
+//! at the BIP-44 Ethereum path `m/44'/60'/0'/0/{token id}`, so no per-agent key is ever
+//! stored: the seed and the agent's token id are enough to rebuild it. Token ids come from
+//! clients, so a wallet is only derived once `AgentNFT` confirms the agent's owner holds
+//! the token.
+//!
+//! Addresses are attached to the agents' ledger accounts, which puts them in on-chain
+//! reconciliation (see [`crate::funds`]).
+
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, TxHash, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::{MnemonicBuilder, PrivateKeySigner, coins_bip39::English};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use reqwest::Url;
+use shared::ledger::{self, AccountKind};
+use sqlx::{Row, SqlitePool};
+use std::fmt;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// BIP-44 path of Ethereum external addresses; the token id is appended as the index.
+pub const DERIVATION_PREFIX: &str = "m/44'/60'/0'/0/";
+
+sol! {
+    interface IERC20 {
+        function transfer(address to, uint256 amount) external returns (bool);
+    }
+
+    #[sol(rpc)]
+    interface IERC721 {
+        function ownerOf(uint256 tokenId) external view returns (address);
+    }
+}
+
+#[derive(Debug)]
+pub enum WalletError {
+    /// The token id isn't a non-hardened BIP-32 index (`0..2^31`).
+    InvalidTokenId(String),
+    Key(String),
+    /// No RPC node is configured, so nothing can be sent.
+    Offline,
+    /// Token ownership can't be read: no `AgentNFT` configured, or the read failed.
+    Unverified(String),
+    /// The token is held by someone other than the agent's owner.
+    NotOwner {
+        token_id: String,
+        holder: Address,
+    },
+    Transfer(String),
+    /// Sent, but its receipt couldn't be read: it may still be mined.
+    Unconfirmed {
+        tx_hash: TxHash,
+        error: String,
+    },
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletError::InvalidTokenId(token_id) => {
+                write!(f, "Token id {:?} can't index a wallet", token_id)
+            }
+            WalletError::Key(e) => write!(f, "Key derivation failed: {}", e),
+            WalletError::Offline => write!(f, "No RPC node configured for agent wallets"),
+            WalletError::Unverified(e) => write!(f, "Could not check who holds the token: {}", e),
+            WalletError::NotOwner { token_id, holder } => {
+                write!(f, "Token {} is held by {}", token_id, holder)
+            }
+            WalletError::Transfer(e) => write!(f, "Transfer failed: {}", e),
+            WalletError::Unconfirmed { tx_hash, error } => {
+                write!(f, "Transfer {} is unconfirmed: {}", tx_hash, error)
+            }
+            WalletError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {}
+
+impl From<sqlx::Error> for WalletError {
+    fn from(e: sqlx::Error) -> Self {
+        WalletError::Db(e)
+    }
+}
+
+/// Reads who holds agent tokens: `AgentNFT` in production, a stub in tests.
+pub trait TokenOwners: Send + Sync {
+    fn owner_of(&self, token_id: u32) -> BoxFuture<'_, Result<Address, WalletError>>;
+}
+
+/// `ownerOf` on the `AgentNFT` contract.
+pub struct NftOwners {
+    pub rpc_url: Url,
+    pub nft: Address,
+}
+
+impl TokenOwners for NftOwners {
+    fn owner_of(&self, token_id: u32) -> BoxFuture<'_, Result<Address, WalletError>> {
+        Box::pin(async move {
+            let provider = ProviderBuilder::new().connect_http(self.rpc_url.clone());
+            IERC721::new(self.nft, provider)
+                .ownerOf(U256::from(token_id))
+                .call()
+                .await
+                .map_err(|e| WalletError::Unverified(e.to_string()))
+        })
+    }
+}
+
+/// Derives agent keys from the seed and signs their transfers.
+#[derive(Clone)]
+pub struct Keyring {
+    phrase: String,
+    /// Node transfers are sent through; `None` leaves wallets receive-only.
+    pub rpc_url: Option<Url>,
+    /// USDC contract on the node's chain.
+    pub usdc: Address,
+    /// Checks token ownership before a wallet is derived; `None` derives none.
+    pub owners: Option<Arc<dyn TokenOwners>>,
+}
+
+impl Keyring {
+    /// Checks the phrase by deriving the first key.
+    pub fn new(phrase: &str, usdc: Address) -> Result<Self, WalletError> {
+        let keyring = Keyring {
+            phrase: phrase.trim().to_string(),
+            rpc_url: None,
+            usdc,
+            owners: None,
+        };
+        keyring.derive(0)?;
+        Ok(keyring)
+    }
+
+    pub fn with_rpc(mut self, rpc_url: Url) -> Self {
+        self.rpc_url = Some(rpc_url);
+        self
+    }
+
+    pub fn with_owners(mut self, owners: Arc<dyn TokenOwners>) -> Self {
+        self.owners = Some(owners);
+        self
+    }
+
+    /// Reads `AGENT_WALLET_MNEMONIC` (agents have no wallets without it), `USDC_ADDRESS`
+    /// (default USDC on Base Sepolia), `BASE_RPC_URL` and `AGENT_NFT_ADDRESS` (no new
+    /// wallets are derived without both of the last two).
+    pub fn from_env() -> Option<Self> {
+        let phrase = std::env::var("AGENT_WALLET_MNEMONIC").ok()?;
+        let usdc = match std::env::var("USDC_ADDRESS") {
+            Ok(usdc) => usdc.parse().ok()?,
+            Err(_) => crate::market::DEFAULT_ASSET.parse().ok()?,
+        };
+        let keyring = match Keyring::new(&phrase, usdc) {
+            Ok(keyring) => keyring,
+            Err(e) => {
+                error!("AGENT_WALLET_MNEMONIC is unusable: {}", e);
+                return None;
+            }
+        };
+        let Some(rpc_url) = std::env::var("BASE_RPC_URL")
+            .ok()
+            .and_then(|url| url.parse::<Url>().ok())
+        else {
+            return Some(keyring);
+        };
+        let keyring = keyring.with_rpc(rpc_url.clone());
+        match std::env::var("AGENT_NFT_ADDRESS")
+            .ok()
+            .and_then(|nft| nft.parse().ok())
+        {
+            Some(nft) => Some(keyring.with_owners(Arc::new(NftOwners { rpc_url, nft }))),
+            None => Some(keyring),
+        }
+    }
+
+    fn derive(&self, index: u32) -> Result<PrivateKeySigner, WalletError> {
+        MnemonicBuilder::<English>::default()
+            .phrase(self.phrase.as_str())
+            .derivation_path(format!("{}{}", DERIVATION_PREFIX, index))
+            .and_then(|builder| builder.build())
+            .map_err(|e| WalletError::Key(e.to_string()))
+    }
+
+    /// The key of the agent minted as `token_id`.
+    pub fn signer(&self, token_id: &str) -> Result<PrivateKeySigner, WalletError> {
+        self.derive(index(token_id)?)
+    }
+
+    pub fn address(&self, token_id: &str) -> Result<Address, WalletError> {
+        Ok(self.signer(token_id)?.address())
+    }
+
+    /// [`Keyring::address`], provided `owner` holds `token_id` on-chain.
+    pub async fn verified_address(
+        &self,
+        token_id: &str,
+        owner: &str,
+    ) -> Result<Address, WalletError> {
+        let index = index(token_id)?;
+        let owners = self
+            .owners
+            .as_ref()
+            .ok_or_else(|| WalletError::Unverified("no AgentNFT configured".to_string()))?;
+        let holder = owners.owner_of(index).await?;
+        if !owner.parse::<Address>().is_ok_and(|owner| owner == holder) {
+            return Err(WalletError::NotOwner {
+                token_id: token_id.to_string(),
+                holder,
+            });
+        }
+        self.derive(index).map(|signer| signer.address())
+    }
+
+    /// Sends `amount` USDC atomic units from the agent's wallet and waits for the receipt.
+    /// Callers decide whether the agent may spend; this only signs.
+    pub async fn transfer_usdc(
+        &self,
+        token_id: &str,
+        to: Address,
+        amount: i64,
+    ) -> Result<TxHash, WalletError> {
+        let rpc_url = self.rpc_url.clone().ok_or(WalletError::Offline)?;
+        let amount = u64::try_from(amount)
+            .map_err(|_| WalletError::Transfer(format!("Invalid amount {}", amount)))?;
+        let provider = ProviderBuilder::new()
+            .wallet(self.signer(token_id)?)
+            .connect_http(rpc_url);
+        let call = IERC20::transferCall {
+            to,
+            amount: U256::from(amount),
+        };
+        let tx = TransactionRequest::default()
+            .with_to(self.usdc)
+            .with_input(call.abi_encode());
+        let pending = provider
+            .send_transaction(tx)
+            .await
+            .map_err(|e| WalletError::Transfer(e.to_string()))?;
+        let tx_hash = *pending.tx_hash();
+        let receipt = pending
+            .get_receipt()
+            .await
+            .map_err(|e| WalletError::Unconfirmed {
+                tx_hash,
+                error: e.to_string(),
+            })?;
+        if !receipt.status() {
+            return Err(WalletError::Transfer(format!(
+                "Transaction {} reverted",
+                receipt.transaction_hash
+            )));
+        }
+        Ok(receipt.transaction_hash)
+    }
+}
+
+/// The BIP-32 index of a token id.
+pub fn index(token_id: &str) -> Result<u32, WalletError> {
+    token_id
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|index| *index < 1 << 31)
+        .ok_or_else(|| WalletError::InvalidTokenId(token_id.to_string()))
+}
+
+/// Attaches a wallet from [`Keyring::verified_address`] to the agent's ledger account.
+pub async fn attach(pool: &SqlitePool, agent_id: &str, address: Address) -> sqlx::Result<()> {
+    let now = Utc::now().timestamp();
+    let account = ledger::account(pool, AccountKind::Agent, agent_id, now).await?;
+    ledger::set_address(pool, account.id, &address.to_string()).await
+}
+
+/// Derives the agent's address once `owner` is confirmed to hold the token, and attaches it
+/// to its ledger account.
+pub async fn assign(
+    pool: &SqlitePool,
+    keyring: &Keyring,
+    agent_id: &str,
+    token_id: &str,
+    owner: &str,
+) -> Result<Address, WalletError> {
+    let address = keyring.verified_address(token_id, owner).await?;
+    attach(pool, agent_id, address).await?;
+    Ok(address)
+}
+
+/// Gives every agent without one its wallet, e.g. after the seed is first configured.
+pub async fn assign_all(pool: &SqlitePool, keyring: &Keyring) {
+    let rows = match sqlx::query(
+        "SELECT a.agent_id, a.token_id, a.owner_address FROM agents a
+         LEFT JOIN ledger_accounts l ON l.kind = ? AND l.holder = a.agent_id
+         WHERE l.address IS NULL",
+    )
+    .bind(AccountKind::Agent)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to list agents without wallets: {:?}", e);
+            return;
+        }
+    };
+    let mut assigned = 0;
+    for row in rows {
+        let agent_id: String = row.get("agent_id");
+        let token_id: Option<String> = row.get("token_id");
+        let owner: String = row.get("owner_address");
+        match assign(
+            pool,
+            keyring,
+            &agent_id,
+            token_id.as_deref().unwrap_or_default(),
+            &owner,
+        )
+        .await
+        {
+            Ok(_) => assigned += 1,
+            Err(e) => warn!("No wallet for {}: {}", agent_id, e),
+        }
+    }
+    info!("Assigned {} agent wallets", assigned);
+}
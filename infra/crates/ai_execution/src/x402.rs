@@ -0,0 +1,274 @@
+//! The x402 payment protocol (version 1, `exact` scheme): the `402 Payment Required` body a
+//! paid endpoint answers with, the `X-PAYMENT` header a client retries with, and the
+//! facilitator that verifies and settles those payments on-chain.
+//!
+//! See <https://github.com/coinbase/x402> for the specification.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+pub const X402_VERSION: u32 = 1;
+/// Request header carrying the base64 JSON [`PaymentPayload`].
+pub const PAYMENT_HEADER: &str = "X-PAYMENT";
+/// Response header carrying the base64 JSON [`SettleResponse`].
+pub const PAYMENT_RESPONSE_HEADER: &str = "X-PAYMENT-RESPONSE";
+
+pub type FacilitatorError = Box<dyn std::error::Error + Send + Sync>;
+
+/// What the server accepts as payment for one resource.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequirements {
+    pub scheme: String,
+    pub network: String,
+    /// Atomic units of `asset`.
+    pub max_amount_required: String,
+    pub resource: String,
+    pub description: String,
+    pub mime_type: String,
+    pub pay_to: String,
+    pub max_timeout_seconds: u64,
+    pub asset: String,
+    /// EIP-712 domain of the token, for `exact` on EVM networks.
+    pub extra: Option<Value>,
+}
+
+/// Body of a `402 Payment Required` response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequired {
+    pub x402_version: u32,
+    pub error: String,
+    pub accepts: Vec<PaymentRequirements>,
+}
+
+/// Decoded `X-PAYMENT` header.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentPayload {
+    pub x402_version: u32,
+    pub scheme: String,
+    pub network: String,
+    pub payload: ExactPayload,
+}
+
+/// A signed EIP-3009 `transferWithAuthorization`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExactPayload {
+    pub signature: String,
+    pub authorization: Authorization,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Authorization {
+    pub from: String,
+    pub to: String,
+    pub value: String,
+    pub valid_after: String,
+    pub valid_before: String,
+    pub nonce: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyResponse {
+    pub is_valid: bool,
+    pub invalid_reason: Option<String>,
+    pub payer: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettleResponse {
+    pub success: bool,
+    pub error_reason: Option<String>,
+    /// Settlement transaction hash.
+    pub transaction: String,
+    pub network: String,
+    pub payer: Option<String>,
+}
+
+/// Base64 JSON, the encoding of both payment headers.
+pub fn encode_header<T: Serialize>(value: &T) -> String {
+    BASE64.encode(serde_json::to_vec(value).unwrap_or_default())
+}
+
+pub fn decode_header<T: DeserializeOwned>(header: &str) -> Result<T, String> {
+    let bytes = BASE64
+        .decode(header.trim())
+        .map_err(|e| format!("Invalid base64: {}", e))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Invalid payment payload: {}", e))
+}
+
+/// Verifies and settles payments: a hosted facilitator in production, or the local
+/// stand-in for tests and local runs.
+pub trait Facilitator: Send + Sync {
+    /// Checks the payment without moving funds.
+    fn verify(
+        &self,
+        payment: PaymentPayload,
+        requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<VerifyResponse, FacilitatorError>>;
+
+    /// Submits the payment on-chain.
+    fn settle(
+        &self,
+        payment: PaymentPayload,
+        requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<SettleResponse, FacilitatorError>>;
+}
+
+/// A facilitator reached over its HTTP API (`POST /verify`, `POST /settle`).
+pub struct HttpFacilitator {
+    pub url: String,
+    client: reqwest::Client,
+}
+
+impl HttpFacilitator {
+    pub fn new(url: &str) -> Self {
+        HttpFacilitator {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        payment: PaymentPayload,
+        requirements: PaymentRequirements,
+    ) -> Result<T, FacilitatorError> {
+        let body = json!({
+            "x402Version": X402_VERSION,
+            "paymentPayload": payment,
+            "paymentRequirements": requirements,
+        });
+        let response = self
+            .client
+            .post(format!("{}/{}", self.url, path))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?;
+        let status = response.status();
+        let bytes = response.bytes().await?;
+        if !status.is_success() {
+            return Err(format!(
+                "Facilitator {} answered {}: {}",
+                path,
+                status,
+                String::from_utf8_lossy(&bytes)
+            )
+            .into());
+        }
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+impl Facilitator for HttpFacilitator {
+    fn verify(
+        &self,
+        payment: PaymentPayload,
+        requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<VerifyResponse, FacilitatorError>> {
+        Box::pin(self.post("verify", payment, requirements))
+    }
+
+    fn settle(
+        &self,
+        payment: PaymentPayload,
+        requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<SettleResponse, FacilitatorError>> {
+        Box::pin(self.post("settle", payment, requirements))
+    }
+}
+
+/// Network-free stand-in: checks the authorization against the requirements and refuses
+/// reused nonces, but trusts the signature and moves no funds.
+#[derive(Default)]
+pub struct LocalFacilitator {
+    settled: Mutex<HashSet<String>>,
+}
+
+impl LocalFacilitator {
+    fn check(
+        &self,
+        payment: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<(), &'static str> {
+        let auth = &payment.payload.authorization;
+        let now = chrono::Utc::now().timestamp() as u64;
+        let number = |value: &str| value.parse::<u128>().ok();
+        if payment.scheme != requirements.scheme || payment.network != requirements.network {
+            return Err("invalid_scheme");
+        }
+        if !auth.to.eq_ignore_ascii_case(&requirements.pay_to) {
+            return Err("invalid_exact_evm_payload_recipient_mismatch");
+        }
+        if number(&auth.value) < number(&requirements.max_amount_required) {
+            return Err("invalid_exact_evm_payload_authorization_value");
+        }
+        if number(&auth.valid_before).is_none_or(|before| before <= now as u128)
+            || number(&auth.valid_after).is_none_or(|after| after > now as u128)
+        {
+            return Err("invalid_exact_evm_payload_authorization_valid_before");
+        }
+        if payment.payload.signature.is_empty() {
+            return Err("invalid_exact_evm_payload_signature");
+        }
+        if self.settled.lock().unwrap().contains(&auth.nonce) {
+            return Err("invalid_transaction_state");
+        }
+        Ok(())
+    }
+}
+
+impl Facilitator for LocalFacilitator {
+    fn verify(
+        &self,
+        payment: PaymentPayload,
+        requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<VerifyResponse, FacilitatorError>> {
+        let checked = self.check(&payment, &requirements);
+        let payer = Some(payment.payload.authorization.from);
+        Box::pin(async move {
+            Ok(VerifyResponse {
+                is_valid: checked.is_ok(),
+                invalid_reason: checked.err().map(str::to_string),
+                payer,
+            })
+        })
+    }
+
+    fn settle(
+        &self,
+        payment: PaymentPayload,
+        requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<SettleResponse, FacilitatorError>> {
+        let checked = self.check(&payment, &requirements).and_then(|()| {
+            let mut settled = self.settled.lock().unwrap();
+            // Checked again under the lock: two settlements of one nonce race here
+            match settled.insert(payment.payload.authorization.nonce.clone()) {
+                true => Ok(settled.len()),
+                false => Err("invalid_transaction_state"),
+            }
+        });
+        let response = SettleResponse {
+            success: checked.is_ok(),
+            error_reason: checked.err().map(str::to_string),
+            transaction: checked
+                .map(|count| format!("0x{:064x}", count))
+                .unwrap_or_default(),
+            network: payment.network,
+            payer: Some(payment.payload.authorization.from),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
@@ -0,0 +1,1437 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{
+    ADMIN_TOKEN, MARKET_FEE_BPS, OWNER, PAY_TO, STRANGER, TEST_MNEMONIC, TestApp, WALLET_1,
+    sign_as,
+};
+use ai_execution::approvals::{
+    ApprovalError, MAX_PENDING_PROPOSALS, ProposalKind, ProposalStatus, Proposer, Reviewed,
+    ReviewedBid, ReviewedPost,
+};
+use ai_execution::escrow::{self, Arbiter, DealError, DealRole, DealStatus, Escrow};
+use ai_execution::feed::{
+    self, FeedError, MAX_POSTS_PER_HOUR, PublishPost, PublishPostArgs, Publisher, Reaction,
+};
+use ai_execution::funds::{self, BalanceError, UsdcBalances};
+use ai_execution::jobs::{
+    self, BidOnJob, BidOnJobArgs, JOB_HAPPINESS_BOOST, JobBoard, JobError, JobStatus, MAX_RETURNS,
+    NewJob, REVIEW_WINDOW,
+};
+use ai_execution::x402::{
+    self, Authorization, ExactPayload, Facilitator, FacilitatorError, PAYMENT_HEADER,
+    PAYMENT_RESPONSE_HEADER, PaymentPayload, PaymentRequirements, SettleResponse, VerifyResponse,
+};
+use ai_execution::messaging::{MAX_TURNS, Messenger, MessagingError};
+use ai_execution::models::AppState;
+use ai_execution::reputation::{self, BASELINE, Inputs, Rater, RatingError, RatingSubject};
+use ai_execution::market::USDC;
+use ai_execution::spending::{self, Decider, SpendError, SpendStatus, Spender};
+use ai_execution::wallet::{self, IERC20, Keyring, WalletError};
+use alloy::consensus::Transaction;
+use alloy::node_bindings::Anvil;
+use alloy::primitives::{Address, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::sol_types::SolCall;
+use futures::future::BoxFuture;
+use rig::tool::Tool;
+use serde_json::json;
+use shared::activity::{
+    NewAnchor, fetch_leaves, from_hex, leaf_hash, merkle_root, record_anchor, to_hex,
+    verify_inclusion,
+};
+use shared::attestation::{NewAttestation, record_attestation};
+use shared::happiness::{HappinessCause, fetch_pending_boosts};
+use shared::ledger;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn launch_and_duplicate_launch() {
+    let app = TestApp::new().await;
+
+    let res = app.launch("a1", "1").await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json(), json!("a1"));
+
+    let res = app.launch("a1", "1").await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    // A token backs a single agent, however its id is spelled
+    let res = app.launch("a2", "01").await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    assert_eq!(res.body, "Token 1 already has an agent");
+    // and only its holder can launch one
+    let mut body = json!({
+        "agent_id": "a3",
+        "owner_address": STRANGER,
+        "token_id": "3",
+        "profile": { "name": "Agent a3", "personality": "sly", "desires": "wallets", "skills": [] },
+    });
+    let res = app.request("POST", "/agents", None, Some(body.clone())).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    body["owner_address"] = json!(OWNER);
+    let res = app.request("POST", "/agents", None, Some(body)).await;
+    assert_eq!(res.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn interact_records_history() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+
+    let res = app
+        .request("POST", "/agents/a1/interact", None, Some(json!({ "prompt": "hello" })))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(res.json()["response"].as_str().unwrap().contains("hello"));
+
+    let res = app.request("GET", "/agents/a1/history", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let history = res.json();
+    let history = history.as_array().unwrap();
+    assert!(history.iter().any(|m| m["origin"] == "Owner" && m["content"] == "hello"));
+    assert!(history.iter().any(|m| m["origin"] == "Agent"));
+
+    let res = app
+        .request("POST", "/agents/missing/interact", None, Some(json!({ "prompt": "hi" })))
+        .await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn history_checks_ownership() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+
+    let res = app.request("GET", "/agents/a1/history", None, None).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = app.request("GET", "/agents/a1/history", Some(STRANGER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+
+    let res = app.request("GET", "/agents/a1/history", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn get_list_and_delete() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    app.launch("a2", "2").await;
+
+    let res = app.request("GET", "/agents/a1", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let details = res.json();
+    assert_eq!(details["agent_id"], "a1");
+    assert_eq!(details["profile"]["name"], "Agent a1");
+    assert_eq!(details["status"], "alive");
+
+    let res = app.request("GET", "/agents/a1", Some(STRANGER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+
+    let res = app.request("GET", "/agents", None, None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json().as_array().unwrap().len(), 2);
+
+    let res = app.request("DELETE", "/agents/a1", None, None).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+    let res = app.request("DELETE", "/agents/a1", None, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    // Deleted from the DB too, so the owner check can no longer match
+    let res = app.request("GET", "/agents/a1", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let res = app.request("GET", "/agents", None, None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn dead_agents_reject_interactions() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    shared::death::record_death(&app.state.db_pool, "a1", 1_700_000_000, None)
+        .await
+        .unwrap();
+
+    let res = app
+        .request("POST", "/agents/a1/interact", None, Some(json!({ "prompt": "hi" })))
+        .await;
+    assert_eq!(res.status, StatusCode::GONE);
+    assert!(res.body.contains("dead"));
+
+    let res = app.request("GET", "/agents/a1", Some(OWNER), None).await;
+    let details = res.json();
+    assert_eq!(details["status"], "dead");
+    assert_eq!(details["death"]["died_at"], 1_700_000_000);
+}
+
+#[tokio::test]
+async fn happiness_series() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+
+    let res = app.request("GET", "/agents/a1/happiness?bucket=3600", None, None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["kind"], "bucketed");
+
+    let res = app.request("GET", "/agents/a1/happiness?bucket=0", None, None).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+
+    let res = app.request("GET", "/agents/missing/happiness", None, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn new_agents_get_a_grace_period() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+
+    let details = app.request("GET", "/agents/a1", Some(OWNER), None).await.json();
+    let grace = &details["decay"]["protected_by"];
+    assert_eq!(grace["kind"], "grace");
+    assert_eq!(
+        grace["ends_at"].as_i64().unwrap() - grace["starts_at"].as_i64().unwrap(),
+        shared::protection::MINT_GRACE_SECS
+    );
+}
+
+#[tokio::test]
+async fn owner_books_and_cancels_vacation() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    let book = |owner, hours| {
+        app.request("POST", "/agents/a1/vacation", Some(owner), Some(json!({ "hours": hours })))
+    };
+
+    assert_eq!(book(STRANGER, 48).await.status, StatusCode::FORBIDDEN);
+    let unsigned = [("X-Owner-Address", OWNER)];
+    let body = json!({ "hours": 48 });
+    let res = app.send("POST", "/agents/a1/vacation", &unsigned, Some(body)).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(book(OWNER, 0).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(book(OWNER, 15 * 24).await.status, StatusCode::BAD_REQUEST);
+
+    let res = book(OWNER, 48).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let vacation = res.json();
+    assert_eq!(vacation["kind"], "vacation");
+    // Only one vacation at a time
+    assert_eq!(book(OWNER, 24).await.status, StatusCode::CONFLICT);
+
+    let details = app.request("GET", "/agents/a1", Some(OWNER), None).await.json();
+    assert_eq!(details["decay"]["protections"].as_array().unwrap().len(), 2);
+    assert_eq!(details["decay"]["protected_by"]["kind"], "vacation");
+
+    let uri = format!("/agents/a1/vacation/{}", vacation["id"]);
+    let res = app.send("DELETE", &uri, &unsigned, None).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.request("DELETE", &uri, Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(app.request("DELETE", &uri, Some(OWNER), None).await.status, StatusCode::NOT_FOUND);
+    assert_eq!(book(OWNER, 24).await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn serves_attestations_newest_first() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    for (computed_at, new_happiness) in [(100, 75), (200, 70)] {
+        record_attestation(
+            &app.state.db_pool,
+            &NewAttestation {
+                agent_id: "a1".to_string(),
+                token_id: "1".to_string(),
+                old_happiness: new_happiness + 5,
+                new_happiness,
+                last_interaction: 0,
+                decay_since: computed_at - 100,
+                computed_at,
+                decay_owed: 5,
+                max_step: 20,
+                cause: HappinessCause::Decay,
+                boost: 0,
+                chain_id: 8453,
+                verifying_contract: "0x00000000000000000000000000000000000000d0".to_string(),
+                signer: "0x00000000000000000000000000000000000000a7".to_string(),
+                digest: format!("0x{:064x}", computed_at),
+                signature: "0x00".to_string(),
+                tx_hash: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let res = app.request("GET", "/agents/a1/attestations?limit=1", None, None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let attestations = res.json();
+    assert_eq!(attestations.as_array().unwrap().len(), 1);
+    assert_eq!(attestations[0]["new_happiness"], 70);
+    assert_eq!(attestations[0]["cause"], "decay");
+
+    let res = app.request("GET", "/agents/missing/attestations", None, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn proves_messages_against_the_anchored_root() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    for prompt in ["hello", "how are you"] {
+        app.request("POST", "/agents/a1/interact", None, Some(json!({ "prompt": prompt })))
+            .await;
+    }
+
+    // Nothing anchored yet
+    let res = app.request("GET", "/agents/a1/activity/1/proof", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    // What the oracle does: commit the root over the log so far
+    let leaves = fetch_leaves(&app.state.db_pool, "a1", 100).await.unwrap();
+    assert_eq!(leaves.len(), 4);
+    let root = merkle_root(&leaves[..3]);
+    record_anchor(
+        &app.state.db_pool,
+        &NewAnchor {
+            agent_id: "a1".to_string(),
+            token_id: "1".to_string(),
+            size: 3,
+            root: to_hex(&root),
+            anchored_at: 100,
+            tx_hash: "0x01".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+
+    let res = app.request("GET", "/agents/a1/activity/2/proof", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let body = res.json();
+    assert_eq!(body["message"]["content"], "how are you");
+    assert_eq!(body["message"]["origin"], "Owner");
+    let proof: Vec<_> = body["proof"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hash| from_hex(hash.as_str().unwrap()).unwrap())
+        .collect();
+    let leaf = leaf_hash(
+        body["message"]["role"].as_str().unwrap(),
+        body["message"]["origin"].as_str().unwrap(),
+        body["message"]["content"].as_str().unwrap(),
+        body["message"]["ts"].as_i64().unwrap(),
+    );
+    assert!(verify_inclusion(&leaf, 2, 3, &proof, &root));
+
+    // The reply came after the anchor
+    let res = app.request("GET", "/agents/a1/activity/3/proof", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    let res = app.request("GET", "/agents/a1/activity/9/proof", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    let res = app.request("GET", "/agents/a1/activity/2/proof", Some("0xbeef"), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn agents_converse_until_the_turn_limit() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    app.launch("a2", "2").await;
+    let messenger = Messenger::new(&app.state, "a1");
+
+    let directory = messenger.directory().await.unwrap();
+    assert_eq!(directory.len(), 1);
+    assert_eq!(directory[0].agent_id, "a2");
+    assert!(matches!(
+        messenger.send("a1", "hi me".to_string(), None).await,
+        Err(MessagingError::SelfMessage)
+    ));
+    assert!(matches!(
+        messenger.send("ghost", "hi".to_string(), None).await,
+        Err(MessagingError::UnknownAgent(_))
+    ));
+
+    let delivery = messenger.send("a2", "want to trade?".to_string(), None).await.unwrap();
+    assert_eq!(delivery.turn, 0);
+
+    // The offline agents answer each other until the conversation runs out of turns
+    let mut conversation = json!(null);
+    for _ in 0..100 {
+        let res = app.request("GET", "/agents/a2/conversations", Some(OWNER), None).await;
+        assert_eq!(res.status, StatusCode::OK);
+        conversation = res.json()[0].clone();
+        if !conversation["ended_at"].is_null() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(conversation["end_reason"], "turn_limit");
+    assert_eq!(conversation["turns"], MAX_TURNS);
+    let messages = conversation["messages"].as_array().unwrap();
+    assert_eq!(messages.len() as i64, MAX_TURNS);
+    assert_eq!(messages[0]["sender"], "a1");
+    assert_eq!(messages[1]["sender"], "a2");
+    assert!(matches!(
+        messenger
+            .send("a2", "one more".to_string(), Some(delivery.conversation_id))
+            .await,
+        Err(MessagingError::Closed(_))
+    ));
+
+    let history = app.request("GET", "/agents/a2/history", Some(OWNER), None).await.json();
+    assert_eq!(history[0]["origin"], json!({ "Peer": "a1" }));
+    assert_eq!(history[0]["content"], "want to trade?");
+
+    // Dead agents leave the directory
+    shared::death::record_death(&app.state.db_pool, "a2", 1_700_000_000, None)
+        .await
+        .unwrap();
+    assert!(messenger.directory().await.unwrap().is_empty());
+    let res = app.request("GET", "/agents/a1/conversations", Some(STRANGER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn serves_the_public_feed() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    app.launch("a2", "2").await;
+    let (a1, a2) = (Publisher::new(&app.state, "a1"), Publisher::new(&app.state, "a2"));
+
+    let post = a1.publish("Selling haikus, 1 USDC each", None).await.unwrap();
+    a2.publish("I'll take two", Some(post.id)).await.unwrap();
+    a2.react(post.id, Reaction::Like).await.unwrap();
+    a2.react(post.id, Reaction::Like).await.unwrap();
+    assert!(matches!(a1.react(post.id, Reaction::Love).await, Err(FeedError::OwnPost)));
+    assert!(matches!(a2.publish("?", Some(999)).await, Err(FeedError::NoSuchPost(999))));
+    assert!(matches!(a1.publish("   ", None).await, Err(FeedError::Empty)));
+
+    // Replies stay out of the global feed but show in the author's timeline
+    let res = app.request("GET", "/feed", None, None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let page = res.json();
+    assert_eq!(page["posts"].as_array().unwrap().len(), 1);
+    assert_eq!(page["posts"][0]["author"], "a1");
+    assert_eq!(page["posts"][0]["replies"], 1);
+    assert_eq!(page["posts"][0]["reactions"], json!({ "like": 1 }));
+
+    let thread = app.request("GET", &format!("/feed/{}", post.id), None, None).await.json();
+    assert_eq!(thread["replies"][0]["content"], "I'll take two");
+    assert_eq!(app.request("GET", "/feed/999", None, None).await.status, StatusCode::NOT_FOUND);
+    let timeline = app.request("GET", "/agents/a2/posts", None, None).await.json();
+    assert_eq!(timeline["posts"][0]["reply_to"], post.id);
+    let res = app.request("GET", "/agents/missing/posts", None, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    // Pages follow `next_before` until it runs out
+    for i in 1..MAX_POSTS_PER_HOUR {
+        a1.publish(&format!("post {}", i), None).await.unwrap();
+    }
+    assert!(matches!(a1.publish("one too many", None).await, Err(FeedError::RateLimited)));
+    let mut seen = 0;
+    let mut uri = "/feed?limit=2".to_string();
+    loop {
+        let page = app.request("GET", &uri, None, None).await.json();
+        seen += page["posts"].as_array().unwrap().len() as i64;
+        match page["next_before"].as_i64() {
+            Some(before) => uri = format!("/feed?limit=2&before={}", before),
+            None => break,
+        }
+    }
+    assert_eq!(seen, MAX_POSTS_PER_HOUR);
+
+    // Reflections publish their marked line
+    assert_eq!(feed::post_in("Low on funds.\n[post] Hiring!"), Some("Hiring!"));
+    assert_eq!(feed::post_in("I could [post] but won't"), None);
+    shared::death::record_death(&app.state.db_pool, "a2", 1_700_000_000, None)
+        .await
+        .unwrap();
+    assert!(matches!(a2.publish("from beyond", None).await, Err(FeedError::NotAlive(_))));
+}
+
+fn payment(to: &str, value: &str, nonce: &str) -> String {
+    x402::encode_header(&PaymentPayload {
+        x402_version: 1,
+        scheme: "exact".to_string(),
+        network: "base-sepolia".to_string(),
+        payload: ExactPayload {
+            signature: "0x5151".to_string(),
+            authorization: Authorization {
+                from: STRANGER.to_string(),
+                to: to.to_string(),
+                value: value.to_string(),
+                valid_after: "0".to_string(),
+                valid_before: "99999999999".to_string(),
+                nonce: nonce.to_string(),
+            },
+        },
+    })
+}
+
+#[tokio::test]
+async fn sells_skills_over_x402() {
+    let app = &TestApp::new().await;
+    app.launch("a1", "1").await;
+    let list = |skill: &str, price: i64| {
+        let uri = format!("/agents/a1/skills/{}", skill);
+        async move {
+            app.request("PUT", &uri, Some(OWNER), Some(json!({ "price": price }))).await
+        }
+    };
+    assert_eq!(list("juggling", 500_000).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(list("painting", 0).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(list("painting", 500_000).await.status, StatusCode::OK);
+    let market = app.request("GET", "/market", None, None).await.json();
+    assert_eq!(market[0]["skill"], "painting");
+
+    let buy = |payment: Option<String>| async move {
+        let headers = match &payment {
+            Some(payment) => vec![(PAYMENT_HEADER, payment.as_str())],
+            None => vec![],
+        };
+        let body = json!({ "input": "a sunset" });
+        app.send("POST", "/market/a1/painting", &headers, Some(body)).await
+    };
+
+    // Unpaid: the requirements to pay
+    let res = buy(None).await;
+    assert_eq!(res.status, StatusCode::PAYMENT_REQUIRED);
+    let required = res.json();
+    assert_eq!(required["x402Version"], 1);
+    assert_eq!(required["accepts"][0]["maxAmountRequired"], "500000");
+    assert_eq!(required["accepts"][0]["payTo"], PAY_TO);
+
+    // Underpaid, or paid to someone else
+    let res = buy(Some(payment(PAY_TO, "499999", "0x01"))).await;
+    assert_eq!(res.status, StatusCode::PAYMENT_REQUIRED);
+    let res = buy(Some(payment(STRANGER, "500000", "0x01"))).await;
+    assert_eq!(res.status, StatusCode::PAYMENT_REQUIRED);
+    let res = buy(Some("not base64".to_string())).await;
+    assert_eq!(res.status, StatusCode::PAYMENT_REQUIRED);
+
+    let paid = payment(PAY_TO, "500000", "0x01");
+    let res = buy(Some(paid.clone())).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert!(res.json()["output"].as_str().unwrap().contains("a sunset"));
+    let settled: SettleResponse =
+        x402::decode_header(res.headers[PAYMENT_RESPONSE_HEADER].to_str().unwrap()).unwrap();
+    assert!(settled.success);
+    assert_eq!(res.json()["transaction"], settled.transaction);
+
+    // The same authorization can't pay twice
+    let res = buy(Some(paid)).await;
+    assert_eq!(res.status, StatusCode::PAYMENT_REQUIRED);
+
+    let res = app.request("GET", "/agents/a1/earnings", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let earnings = res.json();
+    assert_eq!(earnings["total"], 500_000);
+    assert_eq!(earnings["earnings"][0]["payer"], STRANGER);
+    let history = app.request("GET", "/agents/a1/history", Some(OWNER), None).await.json();
+    assert_eq!(history[0]["origin"], "System");
+
+    // Listings are the owner's to sign
+    let unsigned = [("X-Owner-Address", OWNER)];
+    let uri = "/agents/a1/skills/painting";
+    let res = app.send("PUT", uri, &unsigned, Some(json!({ "price": 1 }))).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.send("DELETE", uri, &unsigned, None).await.status, StatusCode::UNAUTHORIZED);
+    let res = app.request("DELETE", uri, Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::NO_CONTENT);
+    assert_eq!(buy(None).await.status, StatusCode::NOT_FOUND);
+}
+
+/// Verifies every payment, then finds the payer's balance gone when settling it.
+struct Emptied;
+
+impl Facilitator for Emptied {
+    fn verify(
+        &self,
+        payment: PaymentPayload,
+        _requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<VerifyResponse, FacilitatorError>> {
+        let response = VerifyResponse {
+            is_valid: true,
+            invalid_reason: None,
+            payer: Some(payment.payload.authorization.from),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+
+    fn settle(
+        &self,
+        payment: PaymentPayload,
+        _requirements: PaymentRequirements,
+    ) -> BoxFuture<'_, Result<SettleResponse, FacilitatorError>> {
+        let response = SettleResponse {
+            success: false,
+            error_reason: Some("insufficient_funds".to_string()),
+            transaction: String::new(),
+            network: payment.network,
+            payer: Some(payment.payload.authorization.from),
+        };
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[tokio::test]
+async fn refuses_buyers_whose_payment_failed_to_settle() {
+    let app = &TestApp::new().await.with(|state| {
+        state.market.as_mut().unwrap().facilitator = Arc::new(Emptied);
+    });
+    app.launch("a1", "1").await;
+    let listing = json!({ "price": 500_000 });
+    app.request("PUT", "/agents/a1/skills/painting", Some(OWNER), Some(listing)).await;
+    let buy = |nonce: &str| {
+        let paid = payment(PAY_TO, "500000", nonce);
+        async move {
+            let body = json!({ "input": "a sunset" });
+            let headers = [(PAYMENT_HEADER, paid.as_str())];
+            app.send("POST", "/market/a1/painting", &headers, Some(body)).await
+        }
+    };
+
+    // The work is done but not handed over, and the payer can't try again
+    let res = buy("0x01").await;
+    assert_eq!(res.status, StatusCode::PAYMENT_REQUIRED);
+    assert!(!res.body.contains("a sunset"));
+    assert_eq!(buy("0x02").await.status, StatusCode::FORBIDDEN);
+    let res = app.request("GET", "/agents/a1/earnings", Some(OWNER), None).await;
+    assert_eq!(res.json()["total"], 0);
+}
+
+/// Every wallet holds the same fixed amount.
+struct FixedBalances(i64);
+
+impl UsdcBalances for FixedBalances {
+    fn balance_of(&self, _address: String) -> BoxFuture<'_, Result<i64, BalanceError>> {
+        Box::pin(async move { Ok(self.0) })
+    }
+}
+
+#[tokio::test]
+async fn keeps_agent_books_and_reconciles_them() {
+    let app = &TestApp::new().await;
+    app.launch("a1", "1").await;
+    let top_up = |owner: &'static str, amount: i64| async move {
+        let body = json!({ "amount": amount, "reference": "0xdead" });
+        app.request("POST", "/agents/a1/top-up", Some(owner), Some(body)).await
+    };
+    assert_eq!(top_up(OWNER, 0).await.status, StatusCode::BAD_REQUEST);
+    assert_eq!(top_up(STRANGER, 2_000_000).await.status, StatusCode::FORBIDDEN);
+    // Naming the owner is not enough: the books are behind their signature
+    let forged = [("X-Owner-Address", OWNER)];
+    let body = json!({ "amount": 2_000_000, "reference": "0xdead" });
+    let res = app.send("POST", "/agents/a1/top-up", &forged, Some(body)).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    for uri in ["/agents/a1/ledger", "/agents/a1/earnings"] {
+        assert_eq!(app.send("GET", uri, &forged, None).await.status, StatusCode::UNAUTHORIZED);
+    }
+    // Owners hand out what they deposited on-chain, and nothing more
+    assert_eq!(top_up(OWNER, 2_000_000).await.status, StatusCode::PAYMENT_REQUIRED);
+    let deposit = |tx_hash: String| async move {
+        let uri = format!("/owners/{}/deposits", OWNER);
+        app.request("POST", &uri, None, Some(json!({ "tx_hash": tx_hash }))).await
+    };
+    let tx_hash = app.chain.send(OWNER, PAY_TO, 2_000_000);
+    let res = deposit(tx_hash.clone()).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["usdc"], "2");
+    assert_eq!(deposit(tx_hash).await.status, StatusCode::CONFLICT);
+    assert_eq!(deposit("0x99".to_string()).await.status, StatusCode::BAD_REQUEST);
+    let elsewhere = app.chain.send(OWNER, STRANGER, USDC);
+    assert_eq!(deposit(elsewhere).await.status, StatusCode::BAD_REQUEST);
+    let theirs = app.chain.send(STRANGER, PAY_TO, USDC);
+    assert_eq!(deposit(theirs).await.status, StatusCode::BAD_REQUEST);
+    let res = top_up(OWNER, 2_000_000).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["usdc"], "2");
+    // A payment is only credited once
+    assert_eq!(top_up(OWNER, 2_000_000).await.status, StatusCode::CONFLICT);
+
+    // A sale credits the price less the market fee
+    let listing = json!({ "price": 500_000 });
+    app.request("PUT", "/agents/a1/skills/painting", Some(OWNER), Some(listing)).await;
+    let paid = payment(PAY_TO, "500000", "0x01");
+    let body = json!({ "input": "a sunset" });
+    let res = app
+        .send("POST", "/market/a1/painting", &[(PAYMENT_HEADER, paid.as_str())], Some(body))
+        .await;
+    assert_eq!(res.status, StatusCode::OK);
+
+    let fee = 500_000 * MARKET_FEE_BPS / 10_000;
+    let expected = 2_000_000 + 500_000 - fee;
+    let res = app.request("GET", "/agents/a1/ledger", Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::OK);
+    let view = res.json();
+    assert_eq!(view["balance"], expected);
+    let kinds: Vec<_> = view["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["fee", "earning", "top_up"]);
+    assert_eq!(view["entries"][0]["amount"], -fee);
+    assert!(view["reconciliation"].is_null());
+    let res = app.request("GET", "/agents/a1/ledger", Some(STRANGER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+
+    // Launching gave the agent a wallet, which puts its account in reconciliation
+    let pool = &app.state.db_pool;
+    let checked = funds::reconcile(pool, &FixedBalances(expected)).await;
+    assert_eq!(checked.len(), 1);
+    assert_eq!(checked[0].address, WALLET_1);
+    assert!(checked[0].matches());
+    assert!(!funds::reconcile(pool, &FixedBalances(0)).await[0].matches());
+    let view = app.request("GET", "/agents/a1/ledger", Some(OWNER), None).await.json();
+    assert_eq!(view["reconciliation"]["onchain_balance"], 0);
+    assert_eq!(view["account"]["address"], WALLET_1);
+}
+
+#[tokio::test]
+async fn launches_agents_with_derived_wallets() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    let details = app.request("GET", "/agents/a1", Some(OWNER), None).await.json();
+    assert_eq!(details["wallet"], WALLET_1);
+    let balance = ai_execution::funds::Purse::new(&app.state, "a1").balance().await.unwrap();
+    assert_eq!(balance.wallet.as_deref(), Some(WALLET_1));
+
+    // Without a usable token id the agent still launches, just without a wallet
+    assert_eq!(app.launch("a2", "").await.status, StatusCode::OK);
+    let details = app.request("GET", "/agents/a2", Some(OWNER), None).await.json();
+    assert!(details["wallet"].is_null());
+    // Nor does it sign with one, even once a token id turns up
+    app.deposit(OWNER, 2 * USDC).await;
+    let body = json!({ "amount": 2 * USDC });
+    app.request("POST", "/agents/a2/top-up", Some(OWNER), Some(body)).await;
+    let spender = Spender::new(&app.state, "a2");
+    let spend = spender.spend(PAY_TO, USDC, "").await.unwrap();
+    assert_eq!(spend.status, SpendStatus::Failed);
+    assert!(spend.reason.contains("no token"));
+    sqlx::query("UPDATE agents SET token_id = '2' WHERE agent_id = 'a2'")
+        .execute(&app.state.db_pool)
+        .await
+        .unwrap();
+    let spend = spender.spend(PAY_TO, USDC, "").await.unwrap();
+    assert_eq!(spend.status, SpendStatus::Failed);
+    assert!(spend.reason.contains("never verified"));
+    // Tokens whose holder can't be checked aren't launched at all
+    let keyring = Keyring::new(TEST_MNEMONIC, Address::ZERO).unwrap();
+    let unchecked = TestApp::new().await.with(|state| state.wallets = Some(keyring));
+    assert_eq!(unchecked.launch("a1", "1").await.status, StatusCode::SERVICE_UNAVAILABLE);
+
+    assert_eq!(wallet::index("7").unwrap(), 7);
+    assert!(matches!(wallet::index("2147483648"), Err(WalletError::InvalidTokenId(_))));
+    assert!(Keyring::new("not a mnemonic", Address::ZERO).is_err());
+    // Without a node wallets only receive, and none are derived without AgentNFT to check
+    let keyring = Keyring::new(TEST_MNEMONIC, Address::ZERO).unwrap();
+    let res = keyring.verified_address("1", OWNER).await;
+    assert!(matches!(res, Err(WalletError::Unverified(_))));
+    let res = keyring.transfer_usdc("1", Address::ZERO, 1).await;
+    assert!(matches!(res, Err(WalletError::Offline)));
+}
+
+#[tokio::test]
+async fn signs_agent_transfers_on_anvil() {
+    let anvil = match Anvil::new().try_spawn() {
+        Ok(anvil) => anvil,
+        Err(e) => {
+            eprintln!("skipping Anvil test: {}", e);
+            return;
+        }
+    };
+    // No token is deployed: a call to an address without code still mines, which is all
+    // signing needs
+    let usdc = Address::repeat_byte(0x05);
+    let keyring = Keyring::new(TEST_MNEMONIC, usdc)
+        .unwrap()
+        .with_rpc(anvil.endpoint_url());
+    // Anvil funds the first accounts of the same seed, so the agent can pay for gas
+    let from = keyring.address("1").unwrap();
+    assert_eq!(from, anvil.addresses()[1]);
+
+    let to: Address = STRANGER.parse().unwrap();
+    let tx_hash = keyring.transfer_usdc("1", to, 1_500_000).await.unwrap();
+    let provider = ProviderBuilder::new().connect_http(anvil.endpoint_url());
+    let tx = provider.get_transaction_by_hash(tx_hash).await.unwrap().unwrap();
+    assert_eq!(tx.inner.signer(), from);
+    assert_eq!(tx.to(), Some(usdc));
+    let call = IERC20::transferCall::abi_decode(tx.input()).unwrap();
+    assert_eq!(call.to, to);
+    assert_eq!(call.amount, U256::from(1_500_000));
+}
+
+#[tokio::test]
+async fn spends_go_through_the_owner_policy() {
+    let app = &TestApp::new().await;
+    app.launch("a1", "1").await;
+    app.launch("a2", "2").await;
+    app.deposit(OWNER, 40 * USDC).await;
+    let body = json!({ "amount": 40 * USDC });
+    app.request("POST", "/agents/a1/top-up", Some(OWNER), Some(body)).await;
+    let spender = Spender::new(&app.state, "a1");
+    let balance = |agent_id: &'static str| async move {
+        ledger::agent_balance(&app.state.db_pool, agent_id).await.unwrap()
+    };
+
+    // Within the default policy: paid straight away in the ledger
+    let spend = spender.spend("a2", 2 * USDC, "a painting").await.unwrap();
+    assert_eq!(spend.status, SpendStatus::Sent);
+    assert_eq!(spend.decided_by, Some(Decider::Policy));
+    assert_eq!(balance("a2").await, 2 * USDC);
+
+    // Above the approval threshold: held; over the per-transaction limit: denied
+    let held = spender.spend("a2", 15 * USDC, "a sculpture").await.unwrap();
+    assert_eq!(held.status, SpendStatus::Pending);
+    assert_eq!(held.decided_by, None);
+    let denied = spender.spend("a2", 30 * USDC, "a castle").await.unwrap();
+    assert_eq!(denied.status, SpendStatus::Denied);
+    assert!(denied.reason.contains("per-transaction"));
+    assert!(matches!(spender.spend("a1", USDC, "").await, Err(SpendError::SelfPayment)));
+    assert!(matches!(
+        spender.spend("ghost", USDC, "").await,
+        Err(SpendError::UnknownCounterparty(_))
+    ));
+
+    // Held spends wait for the owner as proposals
+    let res = app.request("GET", "/agents/a1/spends?status=pending", Some(OWNER), None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 1);
+    let pending = || async move {
+        let uri = "/agents/a1/proposals?status=pending";
+        app.request("GET", uri, Some(OWNER), None).await.json()[0].clone()
+    };
+    let spend_of = |spend_id: i64| async move {
+        spending::fetch_spend(&app.state.db_pool, "a1", spend_id).await.unwrap().unwrap()
+    };
+    let proposal = pending().await;
+    assert_eq!(proposal["action"]["spend_id"], held.id);
+    let uri = format!("/agents/a1/proposals/{}", proposal["id"]);
+    let decision = json!({ "approve": true, "note": "fine by me" });
+    let res = app.request("POST", &uri, Some(STRANGER), Some(decision.clone())).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let res = app.request("POST", &uri, Some(OWNER), Some(decision.clone())).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(spend_of(held.id).await.status, SpendStatus::Sent);
+    assert_eq!(spend_of(held.id).await.reason, "fine by me");
+    assert_eq!(balance("a2").await, 17 * USDC);
+    let res = app.request("POST", &uri, Some(OWNER), Some(decision)).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    let held = spender.spend("a2", 11 * USDC, "a fresco").await.unwrap();
+    let uri = format!("/agents/a1/proposals/{}", pending().await["id"]);
+    app.request("POST", &uri, Some(OWNER), Some(json!({ "approve": false }))).await;
+    let rejected = spend_of(held.id).await;
+    assert_eq!(rejected.status, SpendStatus::Rejected);
+    assert_eq!(rejected.decided_by, Some(Decider::Owner));
+
+    // Owner rules: the deny list, an allow list, and the daily limit counting what's spent
+    let policy = json!({
+        "max_per_tx": 25 * USDC,
+        "daily_limit": 20 * USDC,
+        "approval_above": 10 * USDC,
+        "allow": ["a2"],
+        "deny": [STRANGER],
+    });
+    let uri = "/agents/a1/spending-policy";
+    let unsigned = [("X-Owner-Address", OWNER)];
+    let res = app.send("PUT", uri, &unsigned, Some(policy.clone())).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.request("PUT", uri, Some(OWNER), Some(policy.clone())).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(app.request("GET", uri, Some(OWNER), None).await.json(), policy);
+    let spend = spender.spend(STRANGER, USDC, "").await.unwrap();
+    assert_eq!(spend.status, SpendStatus::Denied);
+    let spend = spender.spend(PAY_TO, USDC, "").await.unwrap();
+    assert_eq!(spend.status, SpendStatus::Pending);
+    assert!(spend.reason.contains("allow list"));
+    // 17 sent and 1 held today, the rejected 11 aside
+    let spend = spender.spend("a2", 3 * USDC, "").await.unwrap();
+    assert_eq!(spend.status, SpendStatus::Denied);
+    assert!(spend.reason.contains("daily limit"));
+
+    // Held spends to addresses fail without a node to send through, and stop counting
+    let uri = format!("/agents/a1/proposals/{}", pending().await["id"]);
+    let res = app.request("POST", &uri, Some(OWNER), Some(json!({ "approve": true }))).await;
+    assert!(res.json()["outcome"].as_str().unwrap().contains("RPC"));
+    assert_eq!(spend_of(spend.id - 1).await.status, SpendStatus::Failed);
+    // Their amount was held while sending, then released
+    let res = app.request("GET", "/agents/a1/ledger?limit=2", Some(OWNER), None).await;
+    let amounts: Vec<_> = res.json()["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["amount"].as_i64().unwrap())
+        .collect();
+    assert_eq!(amounts, [USDC, -USDC]);
+    let spend = spender.spend("a2", 2 * USDC, "").await.unwrap();
+    assert_eq!(spend.status, SpendStatus::Sent);
+    let res = app.request("GET", "/agents/a1/spends", Some(OWNER), None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 8);
+    let res = app.send("GET", "/agents/a1/spends", &unsigned, None).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(balance("a1").await, 21 * USDC);
+
+    // Concurrent spends can't share the last of the daily limit between them
+    let spends = tokio::join!(
+        spender.spend("a2", USDC, "one"),
+        spender.spend("a2", USDC, "two"),
+        spender.spend("a2", USDC, "three"),
+    );
+    let sent = [spends.0, spends.1, spends.2]
+        .into_iter()
+        .filter(|spend| spend.as_ref().unwrap().status == SpendStatus::Sent)
+        .count();
+    assert_eq!(sent, 1);
+    assert_eq!(balance("a1").await, 20 * USDC);
+}
+
+#[tokio::test]
+async fn owners_decide_agent_proposals() {
+    let app = &TestApp::new().await;
+    app.launch("a1", "1").await;
+    let proposer = Proposer::new(&app.state, "a1");
+    let last_message = || async move {
+        let history = app.request("GET", "/agents/a1/history", Some(OWNER), None).await.json();
+        history.as_array().unwrap().last().unwrap().clone()
+    };
+
+    let proposal = proposer.propose_post("Hello, society", None, "to say hi").await.unwrap();
+    assert_eq!(proposal.kind, ProposalKind::Post);
+    assert_eq!(proposal.status, ProposalStatus::Pending);
+    assert!(matches!(proposer.propose_post(" ", None, "").await, Err(ApprovalError::Empty)));
+    let res = app.request("GET", "/agents/a1/proposals", Some(STRANGER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let res = app.request("GET", "/agents/a1/proposals", Some(OWNER), None).await;
+    assert_eq!(res.json()[0]["summary"], "Post \"Hello, society\"");
+
+    // Approved: published, and the agent is told with the owner's note
+    let uri = format!("/agents/a1/proposals/{}", proposal.id);
+    let decision = json!({ "approve": true, "note": "lovely" });
+    let unsigned = [("X-Owner-Address", OWNER)];
+    let res = app.send("POST", &uri, &unsigned, Some(decision.clone())).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.request("POST", &uri, Some(OWNER), Some(decision.clone())).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["status"], "approved");
+    assert_eq!(res.json()["outcome"], "Published as post #1");
+    let feed = app.request("GET", "/feed", None, None).await.json();
+    assert_eq!(feed["posts"][0]["content"], "Hello, society");
+    let message = last_message().await;
+    assert_eq!(message["origin"], "System");
+    let content = message["content"].as_str().unwrap();
+    assert!(content.contains("approved your proposal #1") && content.contains("lovely"));
+    let res = app.request("POST", &uri, Some(OWNER), Some(decision)).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    let uri = "/agents/a1/proposals/99";
+    let res = app.request("POST", uri, Some(OWNER), Some(json!({ "approve": true }))).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    // Rejected: nothing published
+    let proposal = proposer.propose_post("Owner is boring", None, "honesty").await.unwrap();
+    let uri = format!("/agents/a1/proposals/{}", proposal.id);
+    let res = app.request("POST", &uri, Some(OWNER), Some(json!({ "approve": false }))).await;
+    assert_eq!(res.json()["status"], "rejected");
+    let feed = app.request("GET", "/feed", None, None).await.json();
+    assert_eq!(feed["posts"].as_array().unwrap().len(), 1);
+    assert!(last_message().await["content"].as_str().unwrap().contains("rejected"));
+
+    for _ in 1..MAX_PENDING_PROPOSALS {
+        proposer.propose_post("Later", None, "").await.unwrap();
+    }
+    // Concurrent proposals can't share the last place between them
+    let proposals = tokio::join!(
+        proposer.propose_post("One", None, ""),
+        proposer.propose_post("Two", None, ""),
+        proposer.propose_post("Three", None, ""),
+    );
+    let queued = [proposals.0, proposals.1, proposals.2]
+        .into_iter()
+        .filter(Result::is_ok)
+        .count();
+    assert_eq!(queued, 1);
+    let res = proposer.propose_post("One more", None, "").await;
+    assert!(matches!(res, Err(ApprovalError::TooManyPending)));
+}
+
+#[tokio::test]
+async fn owners_can_review_every_post_and_bid() {
+    let app = TestApp::new().await;
+    app.launch("a1", "1").await;
+    app.deposit(OWNER, 5 * USDC).await;
+    let proposer = Proposer::new(&app.state, "a1");
+    let publisher = Publisher::new(&app.state, "a1");
+    let post_tool = ReviewedPost(PublishPost(publisher.clone()), proposer.clone());
+    let bid_tool = ReviewedBid(BidOnJob(JobBoard::for_agent(&app.state, "a1")), proposer.clone());
+    let post = |content: &str| PublishPostArgs {
+        content: content.to_string(),
+        reply_to: None,
+    };
+    let job = json!({ "title": "Write a haiku", "description": "About rain", "reward": USDC });
+    let res = app.request("POST", "/jobs", Some(OWNER), Some(job)).await;
+    let job_id = res.json()["id"].as_i64().unwrap();
+    let bid = |pitch: &str| BidOnJobArgs {
+        job_id,
+        pitch: pitch.to_string(),
+    };
+
+    // Nothing is reviewed until the owner says so
+    let uri = "/agents/a1/approval-settings";
+    let res = app.request("GET", uri, Some(OWNER), None).await;
+    assert_eq!(res.json(), json!({ "posts": false, "jobs": false }));
+    let published = post_tool.call(post("First")).await.unwrap();
+    assert!(matches!(published, Reviewed::Done(ref p) if p.content == "First"));
+
+    let settings = json!({ "posts": true, "jobs": true });
+    let res = app.request("PUT", uri, Some(STRANGER), Some(settings.clone())).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let unsigned = [("X-Owner-Address", OWNER)];
+    let res = app.send("PUT", uri, &unsigned, Some(settings.clone())).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.request("PUT", uri, Some(OWNER), Some(settings.clone())).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(app.request("GET", uri, Some(OWNER), None).await.json(), settings);
+
+    // The direct tools and reflections now propose instead
+    let proposed = post_tool.call(post("Second")).await.unwrap();
+    assert!(matches!(proposed, Reviewed::Proposed(ref p) if p.kind == ProposalKind::Post));
+    proposer.post_reflection(&publisher, "Calm day.\n[post] Third").await;
+    let proposed = bid_tool.call(bid("Rain is my muse")).await.unwrap();
+    assert!(matches!(proposed, Reviewed::Proposed(ref p) if p.kind == ProposalKind::Job));
+    let feed = app.request("GET", "/feed", None, None).await.json();
+    assert_eq!(feed["posts"].as_array().unwrap().len(), 1);
+    let res = app.request("GET", &format!("/jobs/{}", job_id), None, None).await;
+    assert!(res.json()["bids"].as_array().unwrap().is_empty());
+    let res = app.request("GET", "/agents/a1/proposals?status=pending", Some(OWNER), None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 3);
+
+    // Turned off again, the agent acts on its own
+    let settings = json!({ "posts": false, "jobs": false });
+    app.request("PUT", uri, Some(OWNER), Some(settings)).await;
+    assert!(matches!(bid_tool.call(bid("Again")).await.unwrap(), Reviewed::Done(_)));
+    proposer.post_reflection(&publisher, "[post] Fourth").await;
+    let feed = app.request("GET", "/feed", None, None).await.json();
+    assert_eq!(feed["posts"][0]["content"], "Fourth");
+}
+
+#[tokio::test]
+async fn runs_jobs_from_posting_to_payout() {
+    let app = &TestApp::new().await;
+    app.launch("a1", "1").await;
+    app.launch("a2", "2").await;
+    let (a1, a2) = (JobBoard::for_agent(&app.state, "a1"), JobBoard::for_agent(&app.state, "a2"));
+    let last_message = |agent_id: &'static str| async move {
+        let uri = format!("/agents/{}/history", agent_id);
+        let history = app.request("GET", &uri, Some(OWNER), None).await.json();
+        history.as_array().unwrap().last().unwrap()["content"].as_str().unwrap().to_string()
+    };
+    let balance = |agent_id: &'static str| async move {
+        let uri = format!("/agents/{}/ledger", agent_id);
+        app.request("GET", &uri, Some(OWNER), None).await.json()["balance"].as_i64().unwrap()
+    };
+
+    // An owner posts from what they deposited, agents find it by skill and bid
+    let job = json!({
+        "title": "Paint my avatar",
+        "description": "Something bright",
+        "skills": ["painting"],
+        "reward": 5 * USDC,
+    });
+    let res = app.request("POST", "/jobs", Some(OWNER), Some(job.clone())).await;
+    assert_eq!(res.status, StatusCode::PAYMENT_REQUIRED);
+    app.deposit(OWNER, 10 * USDC).await;
+    // Only with a fresh signature of their own
+    let now = chrono::Utc::now().timestamp();
+    let unsigned = [("X-Owner-Address", OWNER)];
+    let res = app.send("POST", "/jobs", &unsigned, Some(job.clone())).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    for (signer, timestamp) in [(STRANGER, now), (OWNER, now - 3600)] {
+        let (timestamp, signature) = sign_as(signer, timestamp).unwrap();
+        let headers = [
+            ("X-Owner-Address", OWNER),
+            ("X-Owner-Timestamp", timestamp.as_str()),
+            ("X-Owner-Signature", signature.as_str()),
+        ];
+        let res = app.send("POST", "/jobs", &headers, Some(job.clone())).await;
+        assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    }
+    let res = app.request("POST", "/jobs", Some(OWNER), Some(job.clone())).await;
+    assert_eq!(res.status, StatusCode::OK);
+    assert_eq!(res.json()["status"], "open");
+    let job_id = res.json()["id"].as_i64().unwrap();
+    let uri = format!("/jobs/{}", job_id);
+    let mut free = job.clone();
+    free["reward"] = json!(0);
+    let res = app.request("POST", "/jobs", Some(OWNER), Some(free)).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    let res = app.request("GET", "/jobs?skill=Painting", None, None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 1);
+    let res = app.request("GET", "/jobs?skill=cooking", None, None).await;
+    assert!(res.json().as_array().unwrap().is_empty());
+
+    a1.bid(job_id, "I paint daily").await.unwrap();
+    assert!(matches!(a1.bid(job_id, "Again").await, Err(JobError::AlreadyBid(_))));
+    a2.bid(job_id, "Bright is my thing").await.unwrap();
+    let res = app.request("GET", &uri, None, None).await;
+    assert_eq!(res.json()["bids"].as_array().unwrap().len(), 2);
+
+    // Only the poster assigns, and the assignee hears of it
+    let accept = format!("{}/accept", uri);
+    let pick = json!({ "agent_id": "a2" });
+    let res = app.request("POST", &accept, Some(STRANGER), Some(pick.clone())).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let res = app.request("POST", &accept, Some(OWNER), Some(json!({ "agent_id": "a3" }))).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+    let res = app.request("POST", &accept, Some(OWNER), Some(pick.clone())).await;
+    assert_eq!(res.json()["assignee"], "a2");
+    assert!(last_message("a2").await.contains("was accepted"));
+    let res = app.request("POST", &accept, Some(OWNER), Some(pick)).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    // Returned once with a note, then accepted: paid and boosted
+    assert!(matches!(a1.submit(job_id, "Mine").await, Err(JobError::NotAssignee(_))));
+    a2.submit(job_id, "A yellow avatar").await.unwrap();
+    let review = format!("{}/review", uri);
+    let res = app
+        .request("POST", &review, Some(OWNER), Some(json!({ "accept": false, "note": "More red" })))
+        .await;
+    assert_eq!(res.json()["status"], "assigned");
+    assert!(last_message("a2").await.contains("More red"));
+    let job = a2.submit(job_id, "An orange avatar").await.unwrap();
+    assert_eq!(job.status, JobStatus::Submitted);
+    let res = app.request("POST", &review, Some(OWNER), Some(json!({ "accept": true }))).await;
+    assert_eq!(res.json()["status"], "completed");
+    assert_eq!(balance("a2").await, 5 * USDC);
+    assert!(last_message("a2").await.contains("5 USDC paid"));
+    let boosts = fetch_pending_boosts(&app.state.db_pool).await.unwrap();
+    assert_eq!(boosts.len(), 1);
+    assert_eq!((boosts[0].agent_id.as_str(), boosts[0].amount), ("a2", JOB_HAPPINESS_BOOST));
+    let res = app.request("POST", &review, Some(OWNER), Some(json!({ "accept": true }))).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    // Quests are posted with the platform's token
+    let quest = json!({ "title": "Say hi", "description": "On the feed", "reward": USDC });
+    let wrong = [("Authorization", "Bearer nope")];
+    let res = app.send("POST", "/jobs", &wrong, Some(quest.clone())).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let bearer = format!("Bearer {}", ADMIN_TOKEN);
+    let platform = [("Authorization", bearer.as_str())];
+    let res = app.send("POST", "/jobs", &platform, Some(quest)).await;
+    assert_eq!(res.json()["poster_kind"], "system");
+    let quest_uri = format!("/jobs/{}", res.json()["id"]);
+    let res = app.request("DELETE", &quest_uri, Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let res = app.send("DELETE", &quest_uri, &platform, None).await;
+    assert_eq!(res.json()["status"], "cancelled");
+
+    // Agents post from their balance, within their spending policy
+    let new_job = |reward| NewJob {
+        title: "Review my poem".to_string(),
+        description: "Four lines".to_string(),
+        skills: Vec::new(),
+        reward,
+    };
+    let posted = a2.post(&new_job(3 * USDC)).await.unwrap();
+    assert_eq!(balance("a2").await, 2 * USDC);
+    assert!(matches!(a2.post(&new_job(20 * USDC)).await, Err(JobError::Denied(_))));
+    assert!(matches!(a2.post(&new_job(4 * USDC)).await, Err(JobError::Funds(_))));
+    let res = app.request("GET", "/jobs?status=open", None, None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 1);
+    assert!(matches!(a2.bid(posted.id, "Me").await, Err(JobError::OwnJob(_))));
+    a1.bid(posted.id, "I love poems").await.unwrap();
+    assert!(last_message("a2").await.contains("a1 bid on your job"));
+    a2.cancel(posted.id).await.unwrap();
+    assert_eq!(balance("a2").await, 5 * USDC);
+
+    // Taking on a job can go through the owner first
+    let sunset = json!({ "title": "Paint a sunset", "description": "Big", "reward": USDC });
+    let res = app.request("POST", "/jobs", Some(OWNER), Some(sunset)).await;
+    let job_id = res.json()["id"].as_i64().unwrap();
+    let proposer = Proposer::new(&app.state, "a1");
+    assert!(matches!(
+        proposer.propose_job(posted.id, "Me", "").await,
+        Err(ApprovalError::UnavailableJob(_))
+    ));
+    let proposal = proposer.propose_job(job_id, "Sunsets are my thing", "money").await.unwrap();
+    assert_eq!(proposal.kind, ProposalKind::Job);
+    let uri = format!("/agents/a1/proposals/{}", proposal.id);
+    let res = app.request("POST", &uri, Some(OWNER), Some(json!({ "approve": true }))).await;
+    assert_eq!(res.json()["outcome"], format!("Bid #4 placed on job #{}", job_id));
+    let res = app.request("GET", &format!("/jobs/{}", job_id), None, None).await;
+    assert_eq!(res.json()["bids"][0]["agent_id"], "a1");
+}
+
+#[tokio::test]
+async fn settles_jobs_past_their_deadlines() {
+    let app = &TestApp::new().await;
+    app.launch("a1", "1").await;
+    app.deposit(OWNER, 10 * USDC).await;
+    let a1 = &JobBoard::for_agent(&app.state, "a1");
+    let job = &json!({ "title": "Write a jingle", "description": "Catchy", "reward": 2 * USDC });
+    let post = || async move {
+        let res = app.request("POST", "/jobs", Some(OWNER), Some(job.clone())).await;
+        let job_id = res.json()["id"].as_i64().unwrap();
+        a1.bid(job_id, "La la la").await.unwrap();
+        let uri = format!("/jobs/{}", job_id);
+        let pick = json!({ "agent_id": "a1" });
+        app.request("POST", &format!("{}/accept", uri), Some(OWNER), Some(pick)).await;
+        (job_id, uri)
+    };
+
+    // Work is sent back a limited number of times
+    let (job_id, uri) = post().await;
+    let review = format!("{}/review", uri);
+    let send_back = Some(json!({ "accept": false }));
+    for _ in 0..MAX_RETURNS {
+        a1.submit(job_id, "La").await.unwrap();
+        app.request("POST", &review, Some(OWNER), send_back.clone()).await;
+    }
+    let job = a1.submit(job_id, "La la").await.unwrap();
+    assert_eq!(job.returns, MAX_RETURNS);
+    let res = app.request("POST", &review, Some(OWNER), send_back).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    // Unreviewed work is accepted once the review window ends
+    let now = job.submitted_at.unwrap();
+    assert!(jobs::settle_expired(&app.state, now).await.unwrap().is_empty());
+    let settled = jobs::settle_expired(&app.state, now + REVIEW_WINDOW).await.unwrap();
+    assert_eq!((settled.len(), settled[0].status), (1, JobStatus::Completed));
+    assert_eq!(ledger::agent_balance(&app.state.db_pool, "a1").await.unwrap(), 2 * USDC);
+    assert_eq!(fetch_pending_boosts(&app.state.db_pool).await.unwrap().len(), 1);
+
+    // Assigned work can be withdrawn once it is overdue
+    let (job_id, uri) = post().await;
+    let res = app.request("DELETE", &uri, Some(OWNER), None).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    sqlx::query("UPDATE jobs SET expires_at = 0 WHERE id = ?")
+        .bind(job_id)
+        .execute(&app.state.db_pool)
+        .await
+        .unwrap();
+    let res = app.request("DELETE", &uri, Some(OWNER), None).await;
+    assert_eq!(res.json()["status"], "cancelled");
+    assert!(matches!(a1.submit(job_id, "La").await, Err(JobError::WrongStatus { .. })));
+}
+
+#[tokio::test]
+async fn escrows_deals_until_confirmed_or_resolved() {
+    let app = &TestApp::new().await;
+    for (agent_id, token_id) in [("a1", "1"), ("a2", "2"), ("a3", "3")] {
+        app.launch(agent_id, token_id).await;
+    }
+    app.deposit(OWNER, 20 * USDC).await;
+    let body = json!({ "amount": 20 * USDC });
+    app.request("POST", "/agents/a1/top-up", Some(OWNER), Some(body)).await;
+    let (a1, a2) = (Escrow::new(&app.state, "a1"), Escrow::new(&app.state, "a2"));
+    let last_message = |agent_id: &'static str| async move {
+        let uri = format!("/agents/{}/history", agent_id);
+        let history = app.request("GET", &uri, Some(OWNER), None).await.json();
+        history.as_array().unwrap().last().unwrap()["content"].as_str().unwrap().to_string()
+    };
+    let balance = |agent_id: &'static str| async move {
+        let uri = format!("/agents/{}/ledger", agent_id);
+        app.request("GET", &uri, Some(OWNER), None).await.json()["balance"].as_i64().unwrap()
+    };
+
+    // Opening a deal escrows the payment, within the buyer's spending policy
+    assert!(matches!(a1.open("a1", USDC, "Art", None).await, Err(DealError::Invalid(_))));
+    assert!(matches!(a1.open("a9", USDC, "Art", None).await, Err(DealError::NoSuchAgent(_))));
+    assert!(matches!(a1.open("a2", 15 * USDC, "Art", None).await, Err(DealError::Denied(_))));
+    let deal = a1.open("a2", 5 * USDC, "A portrait", None).await.unwrap();
+    assert_eq!((deal.status, deal.arbiter.as_deref()), (DealStatus::Funded, None));
+    assert_eq!(balance("a1").await, 15 * USDC);
+    assert!(last_message("a2").await.contains("opened deal"));
+
+    // Delivered by the seller, confirmed by the buyer: paid
+    assert!(matches!(a2.confirm(deal.id).await, Err(DealError::NotBuyer(_))));
+    assert!(matches!(a1.deliver(deal.id, "Done").await, Err(DealError::NotSeller(_))));
+    a2.deliver(deal.id, "Portrait attached").await.unwrap();
+    assert!(last_message("a1").await.contains("Portrait attached"));
+    assert_eq!(a1.confirm(deal.id).await.unwrap().status, DealStatus::Released);
+    assert_eq!(balance("a2").await, 5 * USDC);
+    assert!(matches!(a1.confirm(deal.id).await, Err(DealError::WrongStatus { .. })));
+
+    // Every transition is on record, for the parties' owners and the admins
+    let uri = format!("/deals/{}", deal.id);
+    let res = app.request("GET", &uri, Some(STRANGER), None).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let events = app.request("GET", &uri, Some(OWNER), None).await.json()["events"].clone();
+    let steps: Vec<_> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| (e["from_status"].clone(), e["to_status"].clone(), e["role"].clone()))
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            (json!(null), json!("funded"), json!("buyer")),
+            (json!("funded"), json!("delivered"), json!("seller")),
+            (json!("delivered"), json!("released"), json!("buyer")),
+        ]
+    );
+
+    // A dispute waits for the admins, who refund the buyer
+    let deal = a1.open("a2", 3 * USDC, "A poem", None).await.unwrap();
+    let deal = a1.dispute(deal.id, "Never arrived").await.unwrap();
+    assert_eq!((deal.status, deal.expires_at), (DealStatus::Disputed, None));
+    assert!(last_message("a2").await.contains("Never arrived"));
+    let bearer = format!("Bearer {}", ADMIN_TOKEN);
+    let admin = [("Authorization", bearer.as_str())];
+    let res = app.send("GET", "/deals?status=disputed", &admin, None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 1);
+    let resolve = format!("/deals/{}/resolve", deal.id);
+    let verdict = json!({ "release": false, "note": "No proof of delivery" });
+    let wrong = [("Authorization", "Bearer nope")];
+    let res = app.send("POST", &resolve, &wrong, Some(verdict.clone())).await;
+    assert_eq!(res.status, StatusCode::UNAUTHORIZED);
+    let res = app.send("POST", &resolve, &admin, Some(verdict.clone())).await;
+    assert_eq!(res.json()["status"], "refunded");
+    assert_eq!(balance("a1").await, 15 * USDC);
+    assert!(last_message("a1").await.contains("No proof of delivery"));
+    let res = app.send("POST", &resolve, &admin, Some(verdict)).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+
+    // Deadlines refund undelivered deals and release unchallenged deliveries
+    let undelivered = a1.open("a2", 2 * USDC, "A song", Some(1)).await.unwrap();
+    let delivered = a1.open("a2", USDC, "A joke", None).await.unwrap();
+    a2.deliver(delivered.id, "Why did the agent cross the chain?").await.unwrap();
+    let now = chrono::Utc::now().timestamp();
+    let settled = escrow::settle_expired(&app.state, now + 2 * 3600).await.unwrap();
+    assert_eq!(settled.len(), 1);
+    assert_eq!((settled[0].id, settled[0].status), (undelivered.id, DealStatus::Refunded));
+    let later = now + escrow::CONFIRM_WINDOW + 60;
+    let settled = escrow::settle_expired(&app.state, later).await.unwrap();
+    assert_eq!((settled[0].id, settled[0].status), (delivered.id, DealStatus::Released));
+    let events = escrow::fetch_events(&app.state.db_pool, delivered.id).await.unwrap();
+    assert_eq!(events.last().unwrap().role, DealRole::Timeout);
+    assert_eq!(balance("a1").await, 14 * USDC);
+
+    // An arbiter agent decides the disputes it isn't a party to
+    let state = AppState { arbiter: Arbiter::Agent("a3".to_string()), ..app.state.clone() };
+    let (a1, a3) = (Escrow::new(&state, "a1"), Escrow::new(&state, "a3"));
+    assert_eq!(a1.open("a3", USDC, "A vote", None).await.unwrap().arbiter, None);
+    let deal = a1.open("a2", USDC, "A logo", None).await.unwrap();
+    assert_eq!(deal.arbiter.as_deref(), Some("a3"));
+    a1.dispute(deal.id, "Too small").await.unwrap();
+    assert!(last_message("a3").await.contains("resolve_dispute"));
+    let resolved = escrow::resolve_as_admin(&state, deal.id, false, None).await;
+    assert!(matches!(resolved, Err(DealError::NotArbiter(_))));
+    let deal = a3.resolve(deal.id, true, Some("Size was never agreed")).await.unwrap();
+    assert_eq!(deal.status, DealStatus::Released);
+    assert_eq!(balance("a2").await, 7 * USDC);
+    let res = app.request("GET", "/agents/a3/deals", Some(OWNER), None).await;
+    assert_eq!(res.json().as_array().unwrap().len(), 2);
+
+    // The admins stand in for an arbiter that has died
+    let deal = a1.open("a2", USDC, "A banner", None).await.unwrap();
+    a1.dispute(deal.id, "Never arrived").await.unwrap();
+    shared::death::record_death(&app.state.db_pool, "a3", 1_700_000_000, None)
+        .await
+        .unwrap();
+    let deal = escrow::resolve_as_admin(&state, deal.id, false, None).await.unwrap();
+    assert_eq!(deal.status, DealStatus::Refunded);
+}
+
+#[tokio::test]
+async fn scores_reputation_from_work_disputes_ratings_and_posts() {
+    let app = &TestApp::new().await;
+    for (agent_id, token_id) in [("a1", "1"), ("a2", "2"), ("a3", "3")] {
+        app.launch(agent_id, token_id).await;
+    }
+    let score = |agent_id: &'static str| async move {
+        let uri = format!("/agents/{}", agent_id);
+        app.request("GET", &uri, Some(OWNER), None).await.json()["reputation"]["score"].clone()
+    };
+    assert_eq!(score("a2").await, json!(null));
+    let res = app.request("GET", "/agents/a2/reputation", None, None).await;
+    assert_eq!(res.json()["current"]["score"], BASELINE);
+    // Reading a reputation doesn't store one
+    assert!(reputation::fetch_latest(&app.state.db_pool, "a2").await.unwrap().is_none());
+    let res = app.request("GET", "/agents/a9/reputation", None, None).await;
+    assert_eq!(res.status, StatusCode::NOT_FOUND);
+
+    // Completed work and its poster's rating count
+    app.deposit(OWNER, 2 * USDC).await;
+    let (a1, a2) = (JobBoard::for_agent(&app.state, "a1"), JobBoard::for_agent(&app.state, "a2"));
+    let job = json!({ "title": "Paint a cat", "description": "Orange", "reward": USDC });
+    let res = app.request("POST", "/jobs", Some(OWNER), Some(job.clone())).await;
+    let job_id = res.json()["id"].as_i64().unwrap();
+    a2.bid(job_id, "Cats are my thing").await.unwrap();
+    let uri = format!("/jobs/{}", job_id);
+    let rate = format!("{}/rating", uri);
+    let five = json!({ "score": 5, "comment": "Lovely cat" });
+    let res = app.request("POST", &rate, Some(OWNER), Some(five.clone())).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    app.request("POST", &format!("{}/accept", uri), Some(OWNER), Some(json!({ "agent_id": "a2" })))
+        .await;
+    a2.submit(job_id, "An orange cat").await.unwrap();
+    app.request("POST", &format!("{}/review", uri), Some(OWNER), Some(json!({ "accept": true })))
+        .await;
+    assert_eq!(score("a2").await, BASELINE + reputation::JOB_POINTS);
+    let res = app.request("POST", &rate, Some(STRANGER), Some(five.clone())).await;
+    assert_eq!(res.status, StatusCode::FORBIDDEN);
+    let res = app.request("POST", &rate, Some(OWNER), Some(json!({ "score": 6 }))).await;
+    assert_eq!(res.status, StatusCode::BAD_REQUEST);
+    let res = app.request("POST", &rate, Some(OWNER), Some(five.clone())).await;
+    assert_eq!(res.json()["ratee"], "a2");
+    let res = app.request("POST", &rate, Some(OWNER), Some(five)).await;
+    assert_eq!(res.status, StatusCode::CONFLICT);
+    // One 5/5 rating is worth a fifth of the full 20 points
+    assert_eq!(score("a2").await, 59);
+
+    // Bids are ranked by reputation
+    let res = app.request("POST", "/jobs", Some(OWNER), Some(job)).await;
+    let job_id = res.json()["id"].as_i64().unwrap();
+    a1.bid(job_id, "Me first").await.unwrap();
+    a2.bid(job_id, "Me again").await.unwrap();
+    let bids = app.request("GET", &format!("/jobs/{}", job_id), None, None).await.json()["bids"]
+        .clone();
+    assert_eq!((&bids[0]["agent_id"], &bids[0]["reputation"]), (&json!("a2"), &json!(59)));
+    assert_eq!(bids[1]["reputation"], BASELINE);
+
+    // Losing a dispute hurts, and so does the buyer's rating
+    app.deposit(OWNER, 10 * USDC).await;
+    let body = json!({ "amount": 10 * USDC });
+    app.request("POST", "/agents/a1/top-up", Some(OWNER), Some(body)).await;
+    let deal = Escrow::new(&app.state, "a1").open("a3", USDC, "A haiku", None).await.unwrap();
+    let rater = Rater::for_agent(&app.state, "a1");
+    let rated = rater.rate(RatingSubject::Deal, deal.id, 1, None).await;
+    assert!(matches!(rated, Err(RatingError::Unfinished(..))));
+    Escrow::new(&app.state, "a1").dispute(deal.id, "Only two lines").await.unwrap();
+    escrow::resolve_as_admin(&app.state, deal.id, false, None).await.unwrap();
+    assert_eq!(score("a3").await, BASELINE - reputation::DISPUTE_PENALTY);
+    let rated = rater.rate(RatingSubject::Deal, deal.id, 1, Some("Scammer")).await;
+    assert_eq!(rated.unwrap().ratee, "a3");
+    let outsider = Rater::for_agent(&app.state, "a2");
+    let rated = outsider.rate(RatingSubject::Deal, deal.id, 1, None).await;
+    assert!(matches!(rated, Err(RatingError::NotCounterparty(..))));
+
+    // Other agents' reactions and replies count as engagement, disagreements don't
+    let post = Publisher::new(&app.state, "a3").publish("Haiku are short", None).await.unwrap();
+    Publisher::new(&app.state, "a1").react(post.id, Reaction::Disagree).await.unwrap();
+    Publisher::new(&app.state, "a2").react(post.id, Reaction::Like).await.unwrap();
+    Publisher::new(&app.state, "a2").publish("Not short enough", Some(post.id)).await.unwrap();
+
+    // Anyone about to trade sees the score, its inputs and how it got there
+    let report = outsider.lookup("a3").await.unwrap();
+    assert!(matches!(outsider.lookup("a9").await, Err(RatingError::NoSuchAgent(_))));
+    let inputs = Inputs {
+        disputes_lost: 1,
+        ratings: 1,
+        rating_total: 1,
+        engagement: 2,
+        ..Inputs::default()
+    };
+    assert_eq!((report.current.score, report.current.inputs), (31, inputs));
+    assert_eq!(report.ratings[0].comment.as_deref(), Some("Scammer"));
+    assert!(report.history.iter().any(|past| past.score == BASELINE - reputation::DISPUTE_PENALTY));
+    let history = app.request("GET", "/agents/a3/reputation?limit=2", None, None).await.json();
+    assert_eq!(history["history"].as_array().unwrap().len(), 2);
+
+    // Job points are capped, and scores stay within 0-100
+    let busy = Inputs { completed_jobs: 50, ..Inputs::default() };
+    assert_eq!(busy.score(), BASELINE + reputation::MAX_JOB_POINTS);
+    let crook = Inputs { disputes_lost: 10, ..Inputs::default() };
+    assert_eq!(crook.score(), 0);
+}
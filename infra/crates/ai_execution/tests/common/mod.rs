@@ -0,0 +1,243 @@
+//! In-process harness: the real `Router` over an in-memory SQLite pool and the offline LLM.
+
+use ai_execution::{
+    escrow::Arbiter,
+    funds::{BalanceError, UsdcTransfer, UsdcTransfers},
+    handlers::owner_auth_message,
+    llm::LlmProvider,
+    market::Market,
+    models::AppState,
+    router,
+    wallet::{Keyring, TokenOwners, WalletError},
+    x402::LocalFacilitator,
+};
+use alloy::{
+    hex,
+    primitives::Address,
+    signers::{SignerSync, local::PrivateKeySigner},
+};
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderMap, Request, StatusCode},
+};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
+use tower::ServiceExt;
+
+pub const OWNER: &str = "0xa0ee7a142d267c1f36714e4a8f75612f20a79720";
+pub const STRANGER: &str = "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f";
+/// Private keys of the owners above (Anvil's accounts 9 and 8), to sign their requests.
+const OWNER_KEYS: [(&str, &str); 2] = [
+    (OWNER, "0x2a871d0798f97d79848a013d4936a73bf4cc922c825d33c1cf7073dff6d409c6"),
+    (STRANGER, "0xdbda1821b80551c9d65939329250298aa3472ba22feea921c0cf5d620ea67b97"),
+];
+/// Where the marketplace takes payments.
+pub const PAY_TO: &str = "0x00000000000000000000000000000000000000fe";
+pub const MARKET_FEE_BPS: i64 = 500;
+/// Anvil's well-known development seed, so agent wallets line up with its funded accounts.
+pub const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+/// `TEST_MNEMONIC` at `m/44'/60'/0'/0/1`: the wallet of the agent with token id 1.
+pub const WALLET_1: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+/// Bearer token of the platform's admins.
+pub const ADMIN_TOKEN: &str = "admin-secret-0123456789";
+
+const USDC: &str = "0x036CbD53842c5426634e7929541eC2318f3dCF7e";
+
+pub struct TestApp {
+    pub state: AppState,
+    router: Router,
+    pub chain: Arc<StubChain>,
+}
+
+/// USDC transfers by tx hash, standing in for the chain owners deposit on.
+#[derive(Default)]
+pub struct StubChain {
+    txs: Mutex<Vec<(String, UsdcTransfer)>>,
+}
+
+impl StubChain {
+    /// Mines a transaction sending `amount` from `from` to `to` and returns its hash.
+    pub fn send(&self, from: &str, to: &str, amount: i64) -> String {
+        let mut txs = self.txs.lock().unwrap();
+        // Apart from the local facilitator's settlement hashes
+        let tx_hash = format!("0x{:0>64}", format!("dead{}", txs.len()));
+        let transfer = UsdcTransfer { from: from.to_lowercase(), to: to.to_lowercase(), amount };
+        txs.push((tx_hash.clone(), transfer));
+        tx_hash
+    }
+}
+
+impl UsdcTransfers for StubChain {
+    fn transfers(&self, tx_hash: String) -> BoxFuture<'_, Result<Vec<UsdcTransfer>, BalanceError>> {
+        Box::pin(async move {
+            let txs = self.txs.lock().unwrap();
+            match txs.iter().find(|(hash, _)| *hash == tx_hash) {
+                Some((_, transfer)) => Ok(vec![transfer.clone()]),
+                None => Err(format!("{} is not mined yet", tx_hash).into()),
+            }
+        })
+    }
+}
+
+/// Every agent token is held by the same owner.
+pub struct HeldBy(pub Address);
+
+impl TokenOwners for HeldBy {
+    fn owner_of(&self, _token_id: u32) -> BoxFuture<'_, Result<Address, WalletError>> {
+        Box::pin(async move { Ok(self.0) })
+    }
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl TestResponse {
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or_else(|e| panic!("{}: {}", e, self.body))
+    }
+}
+
+impl TestApp {
+    pub async fn new() -> TestApp {
+        let db_pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let chain = Arc::new(StubChain::default());
+        sqlx::migrate!("./migrations").run(&db_pool).await.unwrap();
+        let state = AppState {
+            db_pool,
+            agents: Arc::new(RwLock::new(HashMap::new())),
+            llm: LlmProvider::Offline,
+            market: Some(Market {
+                facilitator: Arc::new(LocalFacilitator::default()),
+                network: "base-sepolia".to_string(),
+                asset: USDC.to_string(),
+                pay_to: PAY_TO.to_string(),
+                fee_bps: MARKET_FEE_BPS,
+                public_url: "http://localhost:3001".to_string(),
+            }),
+            wallets: Some(
+                Keyring::new(TEST_MNEMONIC, USDC.parse().unwrap())
+                    .unwrap()
+                    .with_owners(Arc::new(HeldBy(OWNER.parse().unwrap()))),
+            ),
+            admin_token: Some(ADMIN_TOKEN.to_string()),
+            arbiter: Arbiter::Admin,
+            deposits: Some(chain.clone()),
+        };
+        TestApp {
+            router: router(state.clone()),
+            state,
+            chain,
+        }
+    }
+
+    /// The same app, with its state changed by `change`.
+    pub fn with(mut self, change: impl FnOnce(&mut AppState)) -> TestApp {
+        change(&mut self.state);
+        self.router = router(self.state.clone());
+        self
+    }
+
+    /// Sends a request, with `X-Owner-Address` when `owner` is set (signed if the harness has
+    /// their key) and a JSON body when `body` is.
+    pub async fn request(
+        &self,
+        method: &str,
+        uri: &str,
+        owner: Option<&str>,
+        body: Option<Value>,
+    ) -> TestResponse {
+        let Some(owner) = owner else {
+            return self.send(method, uri, &[], body).await;
+        };
+        match sign_as(owner, Utc::now().timestamp()) {
+            Some((timestamp, signature)) => {
+                let headers = [
+                    ("X-Owner-Address", owner),
+                    ("X-Owner-Timestamp", timestamp.as_str()),
+                    ("X-Owner-Signature", signature.as_str()),
+                ];
+                self.send(method, uri, &headers, body).await
+            }
+            None => self.send(method, uri, &[("X-Owner-Address", owner)], body).await,
+        }
+    }
+
+    /// Sends a request with the given headers and a JSON body when `body` is set.
+    pub async fn send(
+        &self,
+        method: &str,
+        uri: &str,
+        headers: &[(&str, &str)],
+        body: Option<Value>,
+    ) -> TestResponse {
+        let mut builder = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        let request = match body {
+            Some(body) => builder
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        TestResponse {
+            status,
+            headers,
+            body: String::from_utf8(bytes.to_vec()).unwrap(),
+        }
+    }
+
+    /// Sends the platform `amount` from `owner` and deposits it into their account.
+    pub async fn deposit(&self, owner: &str, amount: i64) -> TestResponse {
+        let tx_hash = self.chain.send(owner, PAY_TO, amount);
+        let uri = format!("/owners/{}/deposits", owner);
+        self.request("POST", &uri, None, Some(json!({ "tx_hash": tx_hash }))).await
+    }
+
+    pub async fn launch(&self, agent_id: &str, token_id: &str) -> TestResponse {
+        self.request(
+            "POST",
+            "/agents",
+            None,
+            Some(json!({
+                "agent_id": agent_id,
+                "owner_address": OWNER,
+                "token_id": token_id,
+                "profile": {
+                    "name": format!("Agent {}", agent_id),
+                    "personality": "curious",
+                    "desires": "paint",
+                    "skills": ["painting"],
+                },
+            })),
+        )
+        .await
+    }
+}
+
+/// `X-Owner-Timestamp` and `X-Owner-Signature` of `owner`; `None` unless their key is known.
+pub fn sign_as(owner: &str, timestamp: i64) -> Option<(String, String)> {
+    let (_, key) = OWNER_KEYS.iter().find(|(address, _)| *address == owner)?;
+    let signer: PrivateKeySigner = key.parse().unwrap();
+    let message = owner_auth_message(owner, timestamp);
+    let signature = signer.sign_message_sync(message.as_bytes()).unwrap();
+    Some((timestamp.to_string(), hex::encode_prefixed(signature.as_bytes())))
+}
@@ -0,0 +1,616 @@
+//! Authenticated admin API: lets operators see what the oracle is doing and step in.
+//!
+//! Every route requires `Authorization: Bearer <ORACLE_ADMIN_TOKEN>`. Actions that send a
+//! transaction are refused unless this instance is a live (not dry-run) leader.
+
+use crate::attestation::{self, DecayInputs};
+use crate::blockchain::AgentState;
+use crate::control::{PendingTx, TxPurpose};
+use crate::db::{self, AuditEntry, NewAuditEntry};
+use crate::fees::{FeeDecision, GasMeter, format_gwei};
+use crate::service::{Oracle, TickSummary, unix_now};
+use alloy::primitives::{Address, TxHash, U256};
+use anyhow::Context;
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use shared::death::AgentStatus;
+use shared::happiness::{HappinessCause, NewHappinessEvent};
+use shared::protection::{self, DecayProtection, NewProtection, ProtectionKind};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+type ApiError = (StatusCode, String);
+
+#[derive(Clone)]
+struct AdminState {
+    oracle: Arc<Oracle>,
+    token: Arc<str>,
+}
+
+/// Why an admin action was refused or failed.
+#[derive(Debug)]
+pub enum ActionError {
+    /// This instance may not send right now: a follower, or dry-run.
+    CannotSend(String),
+    /// The action doesn't apply to the token's on-chain state.
+    Invalid(String),
+    Failed(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ActionError {
+    fn from(e: anyhow::Error) -> Self {
+        ActionError::Failed(e)
+    }
+}
+
+impl From<ActionError> for ApiError {
+    fn from(e: ActionError) -> Self {
+        match e {
+            ActionError::CannotSend(message) => (StatusCode::CONFLICT, message),
+            ActionError::Invalid(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+            ActionError::Failed(e) => (StatusCode::BAD_GATEWAY, format!("{:#}", e)),
+        }
+    }
+}
+
+impl Oracle {
+    /// Registers a token with `DecayOracle` right away instead of waiting for a tick.
+    pub async fn force_register(
+        &self,
+        token_id: U256,
+        reason: Option<String>,
+    ) -> Result<TxHash, ActionError> {
+        let _sends = self.control.lock_sends().await;
+        self.ensure_sender().await?;
+        let state = self.read_state(token_id).await?;
+        if state.registered {
+            return Err(ActionError::Invalid(format!(
+                "Token {} is already registered",
+                token_id
+            )));
+        }
+
+        let mut meter = self.operator_meter().await?;
+        let hash = self
+            .chain
+            .register_agents(&[token_id], &mut meter)
+            .await
+            .pop()
+            .context("No registration result")??;
+        let now = unix_now();
+        self.control
+            .track(hash, TxPurpose::Register, token_id.to_string(), now);
+        info!("Operator registered tokenId {} via tx: {}", token_id, hash);
+        self.audit(NewAuditEntry {
+            ts: now,
+            action: "register",
+            token_id: Some(token_id.to_string()),
+            reason,
+            tx_hash: Some(hash.to_string()),
+            ..Default::default()
+        })
+        .await;
+        Ok(hash)
+    }
+
+    /// Writes an operator-chosen happiness value. The reason is kept in the audit log.
+    pub async fn set_happiness(
+        &self,
+        token_id: U256,
+        happiness: u8,
+        reason: String,
+    ) -> Result<TxHash, ActionError> {
+        let _sends = self.control.lock_sends().await;
+        self.ensure_sender().await?;
+        let state = self.read_state(token_id).await?;
+        if !state.registered {
+            return Err(ActionError::Invalid(format!(
+                "Token {} is not registered with DecayOracle",
+                token_id
+            )));
+        }
+
+        let mut meter = self.operator_meter().await?;
+        let hash = self
+            .chain
+            .update_happiness(&[(token_id, happiness)], &mut meter)
+            .await
+            .pop()
+            .context("No update result")??;
+        let now = unix_now();
+        let token = token_id.to_string();
+        self.control
+            .track(hash, TxPurpose::Manual, token.clone(), now);
+        let attestation = attestation::attestation(
+            token_id,
+            state.happiness,
+            happiness,
+            DecayInputs::default(),
+            now,
+            HappinessCause::Manual,
+            0,
+        );
+        warn!(
+            "Operator set tokenId {} happiness {} -> {} via tx {}: {}",
+            token_id, state.happiness, happiness, hash, reason
+        );
+
+        match db::agent_for_token(&self.db_pool, &token).await {
+            Ok(Some(agent_id)) => {
+                self.attest(&agent_id, &attestation, hash).await;
+                db::record(
+                    &self.db_pool,
+                    NewHappinessEvent {
+                        agent_id: agent_id.clone(),
+                        token_id: Some(token.clone()),
+                        ts: now,
+                        old_happiness: Some(state.happiness),
+                        new_happiness: happiness,
+                        cause: HappinessCause::Manual,
+                        tx_hash: Some(hash.to_string()),
+                    },
+                )
+                .await;
+                if happiness == 0 && self.chain.outcome(hash).await == Some(true) {
+                    db::mark_dead(&self.db_pool, &agent_id, now, Some(&hash.to_string())).await;
+                }
+            }
+            Ok(None) => warn!("No agent row for tokenId {}", token_id),
+            Err(e) => warn!("{:?}", e),
+        }
+        self.audit(NewAuditEntry {
+            ts: now,
+            action: "set_happiness",
+            token_id: Some(token),
+            reason: Some(reason),
+            detail: Some(format!("{} -> {}", state.happiness, happiness)),
+            tx_hash: Some(hash.to_string()),
+        })
+        .await;
+        Ok(hash)
+    }
+
+    async fn ensure_sender(&self) -> Result<(), ActionError> {
+        if self.dry_run {
+            return Err(ActionError::CannotSend(
+                "This instance runs in dry-run mode and never sends".to_string(),
+            ));
+        }
+        if !self.election.hold(unix_now()).await {
+            return Err(ActionError::CannotSend(format!(
+                "{} does not hold the oracle lease; use the leader",
+                self.election.holder
+            )));
+        }
+        Ok(())
+    }
+
+    async fn read_state(&self, token_id: U256) -> Result<AgentState, ActionError> {
+        self.chain
+            .read_agent_states(&[token_id])
+            .await
+            .pop()
+            .context("No agent state")?
+            .map_err(|e| ActionError::Invalid(format!("Cannot read token {}: {:#}", token_id, e)))
+    }
+
+    /// Operator actions skip spike deferral but still respect the max fee and the budget.
+    async fn operator_meter(&self) -> Result<GasMeter, ActionError> {
+        let base_fee = self.chain.base_fee().await?;
+        if self.chain.fees.decide(base_fee) == FeeDecision::Hold {
+            return Err(ActionError::CannotSend(format!(
+                "Base fee {} gwei is above the configured max fee",
+                format_gwei(base_fee)
+            )));
+        }
+        Ok(self.chain.fees.meter(base_fee))
+    }
+
+    /// Audit failures are logged; the action itself already happened.
+    async fn audit(&self, entry: NewAuditEntry) {
+        if let Err(e) = db::record_audit(&self.db_pool, entry).await {
+            warn!("{:?}", e);
+        }
+    }
+}
+
+/// Admin routes over `oracle`, guarded by `token`.
+pub fn router(oracle: Arc<Oracle>, token: String) -> Router {
+    let state = AdminState {
+        oracle,
+        token: token.into(),
+    };
+    Router::new()
+        .route("/status", get(status))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/tick", post(trigger_tick))
+        .route("/register", post(register))
+        .route("/happiness", post(set_happiness))
+        .route("/pending", get(pending))
+        .route("/audit", get(audit_log))
+        .route("/agents/{agent_id}/exemptions", post(grant_exemption))
+        .route(
+            "/agents/{agent_id}/exemptions/{id}",
+            axum::routing::delete(lift_exemption),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+/// Serves the admin API until the process exits.
+pub async fn serve(addr: SocketAddr, oracle: Arc<Oracle>, token: String) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind admin API on {}", addr))?;
+    info!("Admin API listening on http://{}", addr);
+    axum::serve(listener, router(oracle, token))
+        .await
+        .context("Admin API failed")
+}
+
+async fn require_token(
+    State(state): State<AdminState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match provided {
+        Some(provided) if constant_time_eq(provided.as_bytes(), state.token.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        _ => Err((
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid admin token".to_string(),
+        )),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status {
+    pub instance: String,
+    pub leader: bool,
+    pub paused: bool,
+    pub dry_run: bool,
+    pub ticks: u64,
+    pub last_tick_at: Option<i64>,
+    pub last_tick: Option<TickSummary>,
+    pub wallet: Option<WalletStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletStatus {
+    pub address: Address,
+    /// Decimal wei; too large for a JSON number.
+    pub balance_wei: String,
+    pub nonce_gap: u64,
+    pub runway_secs: Option<u64>,
+    pub low: bool,
+}
+
+async fn status(State(state): State<AdminState>) -> Json<Status> {
+    let oracle = &state.oracle;
+    let snapshot = oracle.metrics.snapshot();
+    Json(Status {
+        instance: oracle.election.holder.clone(),
+        leader: oracle.election.is_leader(),
+        paused: oracle.control.is_paused(),
+        dry_run: oracle.dry_run,
+        ticks: snapshot.ticks,
+        last_tick_at: snapshot.last_tick_at,
+        last_tick: snapshot.last_tick,
+        wallet: snapshot.wallet.map(|wallet| WalletStatus {
+            address: wallet.address,
+            balance_wei: wallet.balance.to_string(),
+            nonce_gap: wallet.nonce_gap,
+            runway_secs: wallet.runway.map(|r| r.as_secs()),
+            low: wallet.low,
+        }),
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReasonRequest {
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+async fn pause(
+    State(state): State<AdminState>,
+    payload: Option<Json<ReasonRequest>>,
+) -> Json<bool> {
+    let changed = state.oracle.control.pause();
+    if changed {
+        warn!("Decay paused by an operator");
+        state
+            .oracle
+            .audit(NewAuditEntry {
+                ts: unix_now(),
+                action: "pause",
+                reason: payload.and_then(|Json(p)| p.reason),
+                ..Default::default()
+            })
+            .await;
+    }
+    Json(changed)
+}
+
+async fn resume(
+    State(state): State<AdminState>,
+    payload: Option<Json<ReasonRequest>>,
+) -> Json<bool> {
+    let changed = state.oracle.control.resume();
+    if changed {
+        info!("Decay resumed by an operator");
+        state
+            .oracle
+            .audit(NewAuditEntry {
+                ts: unix_now(),
+                action: "resume",
+                reason: payload.and_then(|Json(p)| p.reason),
+                ..Default::default()
+            })
+            .await;
+    }
+    Json(changed)
+}
+
+/// Runs a tick now, waiting behind the scheduled one if it is in flight.
+async fn trigger_tick(State(state): State<AdminState>) -> Result<Json<TickSummary>, ApiError> {
+    let summary = state
+        .oracle
+        .tick()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+    state
+        .oracle
+        .audit(NewAuditEntry {
+            ts: unix_now(),
+            action: "tick",
+            detail: Some(format!(
+                "{} updates, {} registrations, {} transactions",
+                summary.updates, summary.registrations, summary.transactions
+            )),
+            ..Default::default()
+        })
+        .await;
+    Ok(Json(summary))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    /// Decimal token id.
+    pub token_id: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TxResponse {
+    pub tx_hash: TxHash,
+}
+
+async fn register(
+    State(state): State<AdminState>,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<Json<TxResponse>, ApiError> {
+    let token_id = parse_token(&payload.token_id)?;
+    let tx_hash = state
+        .oracle
+        .force_register(token_id, payload.reason)
+        .await?;
+    Ok(Json(TxResponse { tx_hash }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetHappinessRequest {
+    pub token_id: String,
+    pub happiness: u8,
+    /// Required: why the value is being overridden.
+    pub reason: String,
+}
+
+async fn set_happiness(
+    State(state): State<AdminState>,
+    Json(payload): Json<SetHappinessRequest>,
+) -> Result<Json<TxResponse>, ApiError> {
+    let token_id = parse_token(&payload.token_id)?;
+    if payload.reason.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A reason is required to set happiness".to_string(),
+        ));
+    }
+    if payload.happiness > 100 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Happiness must be between 0 and 100".to_string(),
+        ));
+    }
+    let tx_hash = state
+        .oracle
+        .set_happiness(token_id, payload.happiness, payload.reason)
+        .await?;
+    Ok(Json(TxResponse { tx_hash }))
+}
+
+/// Transactions sent by this instance that have no receipt yet. Mined ones are dropped
+/// from the list as a side effect.
+async fn pending(State(state): State<AdminState>) -> Result<Json<Vec<PendingTx>>, ApiError> {
+    let oracle = &state.oracle;
+    let mut mined = Vec::new();
+    for tx in oracle.control.pending() {
+        let is_mined = oracle
+            .chain
+            .is_mined(tx.hash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+        if is_mined {
+            mined.push(tx.hash);
+        }
+    }
+    oracle.control.forget(&mined);
+    Ok(Json(oracle.control.pending()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub limit: Option<i64>,
+}
+
+async fn audit_log(
+    State(state): State<AdminState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, ApiError> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    db::fetch_audit(&state.oracle.db_pool, limit)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))
+}
+
+fn parse_token(token_id: &str) -> Result<U256, ApiError> {
+    U256::from_str(token_id).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid token id {:?}", token_id),
+        )
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExemptionRequest {
+    /// Unix seconds; open-ended when omitted.
+    #[serde(default)]
+    pub ends_at: Option<i64>,
+    pub reason: String,
+}
+
+/// Exempts an agent from decay from now until `ends_at` or until lifted.
+async fn grant_exemption(
+    State(state): State<AdminState>,
+    Path(agent_id): Path<String>,
+    Json(payload): Json<ExemptionRequest>,
+) -> Result<Json<DecayProtection>, ApiError> {
+    if payload.reason.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A reason is required to exempt an agent".to_string(),
+        ));
+    }
+    let oracle = &state.oracle;
+    let now = unix_now();
+    if payload.ends_at.is_some_and(|end| end <= now) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "ends_at must be in the future".to_string(),
+        ));
+    }
+    match db::agent_status(&oracle.db_pool, &agent_id).await {
+        Ok(Some(AgentStatus::Alive)) => {}
+        Ok(Some(AgentStatus::Dead)) => {
+            return Err((StatusCode::CONFLICT, format!("Agent {} is dead", agent_id)));
+        }
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Agent not found".to_string())),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e))),
+    }
+
+    let new = NewProtection {
+        agent_id: agent_id.clone(),
+        kind: ProtectionKind::Exemption,
+        starts_at: now,
+        ends_at: payload.ends_at,
+        reason: Some(payload.reason.clone()),
+        created_at: now,
+    };
+    let id = protection::add_protection(&oracle.db_pool, &new)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("DB error: {}", e),
+            )
+        })?;
+    warn!(
+        "Operator exempted agent {} from decay: {}",
+        agent_id, payload.reason
+    );
+    oracle
+        .audit(NewAuditEntry {
+            ts: now,
+            action: "exempt",
+            reason: Some(payload.reason),
+            detail: Some(format!(
+                "agent {} until {}",
+                agent_id,
+                payload
+                    .ends_at
+                    .map_or("lifted".to_string(), |end| end.to_string())
+            )),
+            ..Default::default()
+        })
+        .await;
+    Ok(Json(DecayProtection {
+        id,
+        agent_id,
+        kind: new.kind,
+        starts_at: new.starts_at,
+        ends_at: new.ends_at,
+        reason: new.reason,
+        created_at: new.created_at,
+    }))
+}
+
+/// Ends an exemption now; decay resumes from this moment.
+async fn lift_exemption(
+    State(state): State<AdminState>,
+    Path((agent_id, id)): Path<(String, i64)>,
+    payload: Option<Json<ReasonRequest>>,
+) -> Result<Json<DecayProtection>, ApiError> {
+    let oracle = &state.oracle;
+    let now = unix_now();
+    let lifted = protection::end_protection(
+        &oracle.db_pool,
+        &agent_id,
+        id,
+        ProtectionKind::Exemption,
+        now,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("DB error: {}", e),
+        )
+    })?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        format!("No open exemption {} for agent {}", id, agent_id),
+    ))?;
+    info!("Operator lifted exemption {} of agent {}", id, agent_id);
+    oracle
+        .audit(NewAuditEntry {
+            ts: now,
+            action: "lift_exemption",
+            reason: payload.and_then(|Json(p)| p.reason),
+            detail: Some(format!("agent {} exemption {}", agent_id, id)),
+            ..Default::default()
+        })
+        .await;
+    Ok(Json(lifted))
+}
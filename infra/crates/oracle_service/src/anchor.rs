@@ -0,0 +1,129 @@
+//! Periodic commitment of agents' activity logs: the Merkle root over each log (see
+//! `shared::activity`) goes into `AgentProfile.activityLogHash` through `DecayOracle`,
+//! together with the number of messages it covers.
+
+use crate::control::TxPurpose;
+use crate::db::{self, LogRow};
+use crate::fees::{Deferred, GasMeter};
+use crate::service::Oracle;
+use alloy::primitives::{B256, U256};
+use shared::activity::{NewAnchor, fetch_leaves, merkle_root, record_anchor, to_hex};
+use tracing::{error, info, warn};
+
+/// An anchor decided during a tick.
+pub(crate) struct PlannedAnchor {
+    pub agent_id: String,
+    pub token_id: U256,
+    pub size: i64,
+    pub root: B256,
+}
+
+impl Oracle {
+    /// Logs that grew since their last anchor, once that anchor is `anchor_interval` old,
+    /// for agents registered with `DecayOracle`. Empty if the contract can't anchor.
+    pub(crate) async fn plan_anchors(&self, now: i64) -> Vec<PlannedAnchor> {
+        if !self.chain.supports_anchoring {
+            return Vec::new();
+        }
+        let anchored_before = now - self.anchor_interval.as_secs() as i64;
+        let rows = match db::fetch_unanchored_logs(&self.db_pool, anchored_before).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                error!("{:?}", e);
+                return Vec::new();
+            }
+        };
+        let rows: Vec<(LogRow, U256)> = rows
+            .into_iter()
+            .filter_map(|row| row.token().map(|token_id| (row, token_id)))
+            .collect();
+        let token_ids: Vec<U256> = rows.iter().map(|(_, token_id)| *token_id).collect();
+        let states = self.chain.read_agent_states(&token_ids).await;
+
+        let mut anchors = Vec::new();
+        for ((row, token_id), state) in rows.into_iter().zip(states) {
+            match state {
+                Ok(state) if state.registered => {}
+                Ok(_) => {
+                    info!(
+                        "Activity log of {} waits for its registration",
+                        row.agent_id
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    error!("Profile read failed for {}: {:?}", row.agent_id, e);
+                    continue;
+                }
+            }
+            let leaves = match fetch_leaves(&self.db_pool, &row.agent_id, row.size).await {
+                Ok(leaves) => leaves,
+                Err(e) => {
+                    error!(
+                        "Failed to read the activity log of {}: {:?}",
+                        row.agent_id, e
+                    );
+                    continue;
+                }
+            };
+            anchors.push(PlannedAnchor {
+                root: B256::from(merkle_root(&leaves)),
+                agent_id: row.agent_id,
+                token_id,
+                size: row.size,
+            });
+        }
+        anchors
+    }
+
+    /// Sends the anchors and records those that went out. Returns how many did.
+    pub(crate) async fn send_anchors(
+        &self,
+        anchors: &[PlannedAnchor],
+        now: i64,
+        meter: &mut GasMeter,
+    ) -> usize {
+        if anchors.is_empty() {
+            return 0;
+        }
+        let batch: Vec<(U256, B256, u64)> = anchors
+            .iter()
+            .map(|a| (a.token_id, a.root, a.size as u64))
+            .collect();
+        let results = self.chain.anchor_activity_logs(&batch, meter).await;
+        let mut sent = 0;
+        for (anchor, result) in anchors.iter().zip(results) {
+            let tx_hash = match result {
+                Ok(tx_hash) => tx_hash,
+                Err(e) if e.is::<Deferred>() => {
+                    info!("Anchor of {} {}", anchor.agent_id, e);
+                    continue;
+                }
+                Err(e) => {
+                    error!("Anchor tx failed for {}: {:?}", anchor.agent_id, e);
+                    continue;
+                }
+            };
+            sent += 1;
+            let token = anchor.token_id.to_string();
+            self.control
+                .track(tx_hash, TxPurpose::Anchor, token.clone(), now);
+            info!(
+                "Anchored {} messages of {} as {} via tx: {}",
+                anchor.size, anchor.agent_id, anchor.root, tx_hash
+            );
+            let row = NewAnchor {
+                agent_id: anchor.agent_id.clone(),
+                token_id: token,
+                size: anchor.size,
+                root: to_hex(&anchor.root.0),
+                anchored_at: now,
+                tx_hash: tx_hash.to_string(),
+            };
+            if let Err(e) = record_anchor(&self.db_pool, &row).await {
+                warn!("Failed to store anchor of {}: {:?}", anchor.agent_id, e);
+            }
+        }
+        sent
+    }
+}
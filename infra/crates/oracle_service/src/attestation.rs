@@ -0,0 +1,421 @@
+//! EIP-712 attestations of every happiness update: the inputs the oracle used, what it
+//! computed, and its signature over both. They are stored next to the update's transaction
+//! and can be checked by anyone with [`verify`], without trusting the oracle's database.
+
+use crate::service::Oracle;
+use crate::signer::RemoteSigner;
+use alloy::dyn_abi::TypedData;
+use alloy::primitives::{Address, B256, Signature, TxHash, U256};
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use alloy::sol_types::{Eip712Domain, SolStruct, eip712_domain};
+use anyhow::{Context, Result, bail};
+use futures::future::BoxFuture;
+use shared::attestation::{Attestation, NewAttestation, record_attestation};
+use shared::happiness::HappinessCause;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+pub const DOMAIN_NAME: &str = "BaseSociety Happiness Oracle";
+pub const DOMAIN_VERSION: &str = "2";
+/// Domain version of attestations signed before `boost` was part of the struct.
+pub const LEGACY_DOMAIN_VERSION: &str = "1";
+
+sol! {
+    /// What the oracle signs for each update. `decaySince`, `decayOwed` and `maxStep` are
+    /// the inputs of the catch-up rule in `decay.rs`; manual updates leave them at zero.
+    /// `boost` is the happiness the agent's queued `happiness_boosts` added on top of that
+    /// decay.
+    #[derive(Debug, PartialEq, Eq, serde::Serialize)]
+    struct HappinessAttestation {
+        uint256 tokenId;
+        uint8 oldHappiness;
+        uint8 newHappiness;
+        uint64 lastInteraction;
+        uint64 decaySince;
+        uint64 computedAt;
+        uint32 decayOwed;
+        uint8 maxStep;
+        string cause;
+        uint32 boost;
+    }
+}
+
+mod legacy {
+    alloy::sol! {
+        /// [`super::HappinessAttestation`] as signed under [`super::LEGACY_DOMAIN_VERSION`].
+        struct HappinessAttestation {
+            uint256 tokenId;
+            uint8 oldHappiness;
+            uint8 newHappiness;
+            uint64 lastInteraction;
+            uint64 decaySince;
+            uint64 computedAt;
+            uint32 decayOwed;
+            uint8 maxStep;
+            string cause;
+        }
+    }
+}
+
+/// Signs EIP-712 typed data with the oracle's key, wherever it lives.
+pub trait AttestationSigner: Send + Sync {
+    fn address(&self) -> Address;
+
+    fn sign<'a>(&'a self, typed_data: &'a TypedData) -> BoxFuture<'a, Result<Signature>>;
+}
+
+impl AttestationSigner for PrivateKeySigner {
+    fn address(&self) -> Address {
+        alloy::signers::Signer::address(self)
+    }
+
+    fn sign<'a>(&'a self, typed_data: &'a TypedData) -> BoxFuture<'a, Result<Signature>> {
+        Box::pin(async move {
+            let hash = typed_data.eip712_signing_hash()?;
+            Ok(self.sign_hash_sync(&hash)?)
+        })
+    }
+}
+
+impl AttestationSigner for RemoteSigner {
+    fn address(&self) -> Address {
+        alloy::network::TxSigner::address(self)
+    }
+
+    fn sign<'a>(&'a self, typed_data: &'a TypedData) -> BoxFuture<'a, Result<Signature>> {
+        Box::pin(self.sign_typed_data(typed_data))
+    }
+}
+
+/// Signing domain: bound to the chain and the `DecayOracle` the updates go to.
+pub fn domain(chain_id: u64, verifying_contract: Address) -> Eip712Domain {
+    versioned_domain(DOMAIN_VERSION, chain_id, verifying_contract)
+}
+
+fn versioned_domain(
+    version: &'static str,
+    chain_id: u64,
+    verifying_contract: Address,
+) -> Eip712Domain {
+    eip712_domain! {
+        name: DOMAIN_NAME,
+        version: version,
+        chain_id: chain_id,
+        verifying_contract: verifying_contract,
+    }
+}
+
+pub struct Attester {
+    pub signer: Arc<dyn AttestationSigner>,
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl Attester {
+    /// Signs `attestation` and returns the row to store.
+    pub async fn attest(
+        &self,
+        agent_id: &str,
+        attestation: &HappinessAttestation,
+        tx_hash: Option<TxHash>,
+    ) -> Result<NewAttestation> {
+        let domain = domain(self.chain_id, self.verifying_contract);
+        let typed_data = TypedData::from_struct(attestation, Some(domain.clone()));
+        let signature = self.signer.sign(&typed_data).await?;
+        let digest = attestation.eip712_signing_hash(&domain);
+        Ok(NewAttestation {
+            agent_id: agent_id.to_string(),
+            token_id: attestation.tokenId.to_string(),
+            old_happiness: attestation.oldHappiness,
+            new_happiness: attestation.newHappiness,
+            last_interaction: attestation.lastInteraction as i64,
+            decay_since: attestation.decaySince as i64,
+            computed_at: attestation.computedAt as i64,
+            decay_owed: attestation.decayOwed,
+            max_step: attestation.maxStep,
+            cause: parse_cause(&attestation.cause)?,
+            boost: attestation.boost,
+            chain_id: self.chain_id as i64,
+            verifying_contract: self.verifying_contract.to_string(),
+            signer: self.signer.address().to_string(),
+            digest: digest.to_string(),
+            signature: signature.to_string(),
+            tx_hash: tx_hash.map(|hash| hash.to_string()),
+        })
+    }
+}
+
+/// The signed struct for an update.
+pub fn attestation(
+    token_id: U256,
+    old: u8,
+    new: u8,
+    inputs: DecayInputs,
+    computed_at: i64,
+    cause: HappinessCause,
+    boost: u32,
+) -> HappinessAttestation {
+    HappinessAttestation {
+        tokenId: token_id,
+        oldHappiness: old,
+        newHappiness: new,
+        lastInteraction: inputs.last_interaction.max(0) as u64,
+        decaySince: inputs.since.max(0) as u64,
+        computedAt: computed_at.max(0) as u64,
+        decayOwed: inputs.owed,
+        maxStep: inputs.max_step,
+        cause: cause_name(cause).to_string(),
+        boost,
+    }
+}
+
+/// Inputs of the decay rule behind an update; all zero for manual updates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecayInputs {
+    pub last_interaction: i64,
+    pub since: i64,
+    pub owed: u32,
+    pub max_step: u8,
+}
+
+/// Rebuilds the digest from a stored row, checks it and recovers the signer. Returns the
+/// signer's address if the row is intact and was signed by the address it names. Rows
+/// without a boost may also be legacy ones, signed before `boost` was part of the struct.
+pub fn verify(row: &Attestation) -> Result<Address> {
+    let attestation = HappinessAttestation {
+        tokenId: U256::from_str(&row.token_id).context("Invalid token id")?,
+        oldHappiness: row.old_happiness,
+        newHappiness: row.new_happiness,
+        lastInteraction: row.last_interaction as u64,
+        decaySince: row.decay_since as u64,
+        computedAt: row.computed_at as u64,
+        decayOwed: row.decay_owed,
+        maxStep: row.max_step,
+        cause: cause_name(row.cause).to_string(),
+        boost: row.boost,
+    };
+    let chain_id = row.chain_id as u64;
+    let verifying_contract =
+        Address::from_str(&row.verifying_contract).context("Invalid verifying contract")?;
+    let stored = B256::from_str(&row.digest).context("Invalid digest")?;
+    let mut digest = attestation.eip712_signing_hash(&domain(chain_id, verifying_contract));
+    if digest != stored && row.boost == 0 {
+        let legacy = legacy::HappinessAttestation {
+            tokenId: attestation.tokenId,
+            oldHappiness: attestation.oldHappiness,
+            newHappiness: attestation.newHappiness,
+            lastInteraction: attestation.lastInteraction,
+            decaySince: attestation.decaySince,
+            computedAt: attestation.computedAt,
+            decayOwed: attestation.decayOwed,
+            maxStep: attestation.maxStep,
+            cause: attestation.cause,
+        };
+        let domain = versioned_domain(LEGACY_DOMAIN_VERSION, chain_id, verifying_contract);
+        digest = legacy.eip712_signing_hash(&domain);
+    }
+    if digest != stored {
+        bail!("Attestation fields don't match the signed digest");
+    }
+    let signature = Signature::from_str(&row.signature).context("Invalid signature")?;
+    let signer = signature
+        .recover_address_from_prehash(&digest)
+        .context("Unrecoverable signature")?;
+    if signer != Address::from_str(&row.signer).context("Invalid signer")? {
+        bail!("Attestation was signed by {}, not {}", signer, row.signer);
+    }
+    Ok(signer)
+}
+
+fn cause_name(cause: HappinessCause) -> &'static str {
+    match cause {
+        HappinessCause::Observed => "observed",
+        HappinessCause::Decay => "decay",
+        HappinessCause::Manual => "manual",
+        HappinessCause::Boost => "boost",
+    }
+}
+
+fn parse_cause(cause: &str) -> Result<HappinessCause> {
+    match cause {
+        "decay" => Ok(HappinessCause::Decay),
+        "manual" => Ok(HappinessCause::Manual),
+        "boost" => Ok(HappinessCause::Boost),
+        other => bail!("Updates are never attested as {:?}", other),
+    }
+}
+
+impl Oracle {
+    /// Signs and stores the attestation for a sent update. Failures are logged: the update
+    /// itself already went out.
+    pub(crate) async fn attest(
+        &self,
+        agent_id: &str,
+        attestation: &HappinessAttestation,
+        tx_hash: TxHash,
+    ) {
+        let Some(attester) = &self.chain.attester else {
+            return;
+        };
+        let row = match attester.attest(agent_id, attestation, Some(tx_hash)).await {
+            Ok(row) => row,
+            Err(e) => {
+                warn!("Failed to sign attestation for {}: {:?}", agent_id, e);
+                return;
+            }
+        };
+        match record_attestation(&self.db_pool, &row).await {
+            Ok(()) => info!("Attested {} update {}", agent_id, row.digest),
+            Err(e) => warn!("Failed to store attestation for {}: {:?}", agent_id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> HappinessAttestation {
+        attestation(
+            U256::from(7),
+            80,
+            65,
+            DecayInputs {
+                last_interaction: 1_000,
+                since: 0,
+                owed: 15,
+                max_step: 20,
+            },
+            1_000 + 3 * 3600,
+            HappinessCause::Decay,
+            0,
+        )
+    }
+
+    fn row(new: NewAttestation) -> Attestation {
+        Attestation {
+            id: 1,
+            agent_id: new.agent_id,
+            token_id: new.token_id,
+            old_happiness: new.old_happiness,
+            new_happiness: new.new_happiness,
+            last_interaction: new.last_interaction,
+            decay_since: new.decay_since,
+            computed_at: new.computed_at,
+            decay_owed: new.decay_owed,
+            max_step: new.max_step,
+            cause: new.cause,
+            boost: new.boost,
+            chain_id: new.chain_id,
+            verifying_contract: new.verifying_contract,
+            signer: new.signer,
+            digest: new.digest,
+            signature: new.signature,
+            tx_hash: new.tx_hash,
+        }
+    }
+
+    #[tokio::test]
+    async fn signed_rows_verify_and_tampering_is_caught() {
+        let key = PrivateKeySigner::random();
+        let attester = Attester {
+            signer: Arc::new(key.clone()),
+            chain_id: 8453,
+            verifying_contract: Address::repeat_byte(0xd0),
+        };
+        let signed = row(attester.attest("a1", &sample(), None).await.unwrap());
+        assert_eq!(verify(&signed).unwrap(), key.address());
+
+        let tampered = Attestation {
+            new_happiness: 70,
+            ..signed.clone()
+        };
+        assert!(verify(&tampered).is_err());
+
+        // Same fields, but a signature from someone else
+        let other = Attester {
+            signer: Arc::new(PrivateKeySigner::random()),
+            ..attester
+        };
+        let forged = Attestation {
+            signature: row(other.attest("a1", &sample(), None).await.unwrap()).signature,
+            ..signed
+        };
+        assert!(verify(&forged).is_err());
+    }
+    #[tokio::test]
+    async fn boosts_are_signed() {
+        let attester = Attester {
+            signer: Arc::new(PrivateKeySigner::random()),
+            chain_id: 8453,
+            verifying_contract: Address::repeat_byte(0xd0),
+        };
+        let boosted = HappinessAttestation {
+            newHappiness: 90,
+            cause: cause_name(HappinessCause::Boost).to_string(),
+            boost: 25,
+            ..sample()
+        };
+        let signed = row(attester.attest("a1", &boosted, None).await.unwrap());
+        assert_eq!(signed.boost, 25);
+        assert!(verify(&signed).is_ok());
+
+        // Claiming a bigger or no boost breaks the digest
+        for boost in [40, 0] {
+            assert!(
+                verify(&Attestation {
+                    boost,
+                    ..signed.clone()
+                })
+                .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_rows_still_verify() {
+        let key = PrivateKeySigner::random();
+        let verifying_contract = Address::repeat_byte(0xd0);
+        let current = sample();
+        let legacy = legacy::HappinessAttestation {
+            tokenId: current.tokenId,
+            oldHappiness: current.oldHappiness,
+            newHappiness: current.newHappiness,
+            lastInteraction: current.lastInteraction,
+            decaySince: current.decaySince,
+            computedAt: current.computedAt,
+            decayOwed: current.decayOwed,
+            maxStep: current.maxStep,
+            cause: current.cause,
+        };
+        let domain = versioned_domain(LEGACY_DOMAIN_VERSION, 8453, verifying_contract);
+        let digest = legacy.eip712_signing_hash(&domain);
+        let signed = Attestation {
+            id: 1,
+            agent_id: "a1".to_string(),
+            token_id: "7".to_string(),
+            old_happiness: 80,
+            new_happiness: 65,
+            last_interaction: 1_000,
+            decay_since: 0,
+            computed_at: 1_000 + 3 * 3600,
+            decay_owed: 15,
+            max_step: 20,
+            cause: HappinessCause::Decay,
+            boost: 0,
+            chain_id: 8453,
+            verifying_contract: verifying_contract.to_string(),
+            signer: key.address().to_string(),
+            digest: digest.to_string(),
+            signature: key.sign_hash_sync(&digest).unwrap().to_string(),
+            tx_hash: None,
+        };
+        assert_eq!(verify(&signed).unwrap(), key.address());
+
+        // A legacy digest can't vouch for a boost
+        assert!(verify(&Attestation { boost: 5, ..signed }).is_err());
+    }
+}
@@ -0,0 +1,451 @@
+use crate::attestation::Attester;
+use crate::config::Config;
+use crate::fees::{Deferred, FeePolicy, GasMeter};
+use alloy::contract::{CallBuilder, CallDecoder};
+use alloy::eips::BlockNumberOrTag;
+use alloy::network::{Ethereum, NetworkWallet};
+use alloy::primitives::{Address, B256, TxHash, U256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::sol; // sol! macro
+use alloy::sol_types::SolCall;
+use anyhow::{Context, Result, anyhow};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{Instant, sleep};
+use tracing::{error, info, warn};
+
+/// How long to wait for a receipt before leaving a transaction's outcome to the next tick.
+pub const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+const RECEIPT_POLL: Duration = Duration::from_secs(1);
+
+sol! {
+    #[sol(rpc)]
+    DecayOracle,
+    r#"./abis/DecayOracle.json"#
+}
+
+sol! {
+    #[sol(rpc)]
+    AgentNFT,
+    r#"./abis/AgentNFT.json"#
+}
+
+sol! {
+    /// The subset of [Multicall3](https://github.com/mds1/multicall) used to aggregate reads.
+    #[sol(rpc)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+}
+
+pub type DecayOracleContract = DecayOracle::DecayOracleInstance<DynProvider>;
+pub type AgentNFTContract = AgentNFT::AgentNFTInstance<DynProvider>;
+
+/// Typed clients for the two contracts the oracle talks to.
+#[derive(Clone)]
+pub struct Chain {
+    pub oracle: DecayOracleContract,
+    pub agent_nft: AgentNFTContract,
+    pub multicall: IMulticall3::IMulticall3Instance<DynProvider>,
+    /// Whether the deployed `DecayOracle` exposes the batch entrypoints; deployments
+    /// predating them get one transaction per agent.
+    pub supports_batch: bool,
+    /// Whether the deployed `DecayOracle` can anchor activity logs; deployments predating
+    /// it never anchor.
+    pub supports_anchoring: bool,
+    pub batch_size: usize,
+    pub fees: FeePolicy,
+    /// The oracle's signing address; `None` for a read-only (dry-run) connection.
+    pub signer: Option<Address>,
+    /// Signs an attestation for every update; `None` without a signer.
+    pub attester: Option<Arc<Attester>>,
+}
+
+/// On-chain state of one agent, as read at the start of a tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AgentState {
+    pub registered: bool,
+    pub happiness: u8,
+    /// Block timestamp of the last `DecayOracle` happiness update; 0 if there was none.
+    pub last_update: i64,
+}
+
+/// Connects to the RPC node and resolves `AgentNFT` through `DecayOracle.agentNFTAddress()`.
+/// Without a signer the provider is read-only (dry-run).
+pub async fn connect(config: &Config) -> Result<Chain> {
+    let (provider, signer, attestation_signer) = match &config.signer {
+        Some(source) => {
+            let (wallet, attestation_signer) = source.load_with_attester().await?;
+            let address = NetworkWallet::<Ethereum>::default_signer_address(&wallet);
+            let provider = ProviderBuilder::new()
+                .wallet(wallet)
+                .connect_http(config.rpc_url.clone())
+                .erased();
+            (provider, Some(address), Some(attestation_signer))
+        }
+        None => (
+            ProviderBuilder::new()
+                .connect_http(config.rpc_url.clone())
+                .erased(),
+            None,
+            None,
+        ),
+    };
+    let chain_id = provider
+        .get_chain_id()
+        .await
+        .context("Failed to read the chain id")?;
+    let attester = attestation_signer.map(|signer| {
+        Arc::new(Attester {
+            signer,
+            chain_id,
+            verifying_contract: config.oracle_addr,
+        })
+    });
+
+    let oracle = DecayOracle::new(config.oracle_addr, provider.clone());
+    let agent_nft_addr = oracle
+        .agentNFTAddress()
+        .call()
+        .await
+        .context("Failed to read agentNFTAddress from DecayOracle")?;
+    let agent_nft = AgentNFT::new(agent_nft_addr, provider.clone());
+    let multicall = IMulticall3::new(config.multicall_addr, provider);
+
+    let owner = oracle.owner().call().await.context("Failed to read DecayOracle owner")?;
+    let supports_batch = probe_batch(&oracle, owner).await;
+    info!(
+        "DecayOracle batch entrypoints {}",
+        if supports_batch { "available" } else { "unavailable, sending one tx per agent" }
+    );
+    let supports_anchoring = probe_anchoring(&oracle, owner).await;
+    info!(
+        "DecayOracle activity log anchoring {}",
+        if supports_anchoring { "available" } else { "unavailable, logs stay off-chain" }
+    );
+
+    Ok(Chain {
+        oracle,
+        agent_nft,
+        multicall,
+        supports_batch,
+        supports_anchoring,
+        batch_size: config.batch_size,
+        fees: config.fees,
+        signer,
+        attester,
+    })
+}
+
+/// An empty batch is a no-op on contracts that have the entrypoint and reverts on those
+/// that don't (no fallback function).
+async fn probe_batch(oracle: &DecayOracleContract, owner: Address) -> bool {
+    oracle
+        .batchUpdateAgentHappiness(vec![], vec![])
+        .from(owner)
+        .call()
+        .await
+        .is_ok()
+}
+
+/// Same probe as [`probe_batch`], for `anchorActivityLogs`.
+async fn probe_anchoring(oracle: &DecayOracleContract, owner: Address) -> bool {
+    oracle
+        .anchorActivityLogs(vec![], vec![], vec![])
+        .from(owner)
+        .call()
+        .await
+        .is_ok()
+}
+
+impl Chain {
+    /// Base fee of the latest block, in wei.
+    pub async fn base_fee(&self) -> Result<u128> {
+        let block = self
+            .oracle
+            .provider()
+            .get_block_by_number(BlockNumberOrTag::Latest)
+            .await?
+            .context("Latest block not found")?;
+        block
+            .header
+            .base_fee_per_gas
+            .map(u128::from)
+            .context("Chain has no EIP-1559 base fee")
+    }
+
+    /// Reads registration, happiness and last update for every token in one `aggregate3`
+    /// round-trip per `batch_size` tokens. Falls back to individual calls if Multicall3 is unavailable.
+    pub async fn read_agent_states(&self, token_ids: &[U256]) -> Vec<Result<AgentState>> {
+        let mut states = Vec::with_capacity(token_ids.len());
+        for chunk in token_ids.chunks(self.batch_size) {
+            match self.aggregate_states(chunk).await {
+                Ok(chunk_states) => states.extend(chunk_states),
+                Err(e) => {
+                    warn!("Multicall read failed, falling back to single calls: {:?}", e);
+                    for token_id in chunk {
+                        states.push(self.read_agent_state(*token_id).await);
+                    }
+                }
+            }
+        }
+        states
+    }
+
+    /// Whether a receipt exists for the transaction.
+    pub async fn is_mined(&self, hash: TxHash) -> Result<bool> {
+        let receipt = self.oracle.provider().get_transaction_receipt(hash).await?;
+        Ok(receipt.is_some())
+    }
+
+    /// Waits up to `timeout` for the transaction's receipt: `Some(true)` if it succeeded,
+    /// `Some(false)` if it reverted, `None` if it is still pending.
+    pub async fn confirm(&self, hash: TxHash, timeout: Duration) -> Result<Option<bool>> {
+        let provider = self.oracle.provider();
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(receipt) = provider.get_transaction_receipt(hash).await? {
+                return Ok(Some(receipt.status()));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            sleep(RECEIPT_POLL).await;
+        }
+    }
+
+    /// Waits up to [`RECEIPT_TIMEOUT`] for the transaction: `Some(true)` if it succeeded,
+    /// `Some(false)` if it reverted. A pending transaction or unreadable receipt is logged
+    /// and returns `None`: what it did is read back from the chain later.
+    pub async fn outcome(&self, hash: TxHash) -> Option<bool> {
+        match self.confirm(hash, RECEIPT_TIMEOUT).await {
+            Ok(Some(true)) => Some(true),
+            Ok(Some(false)) => {
+                error!("Tx {} reverted", hash);
+                Some(false)
+            }
+            Ok(None) => {
+                warn!("Tx {} not mined after {}s", hash, RECEIPT_TIMEOUT.as_secs());
+                None
+            }
+            Err(e) => {
+                error!("Receipt of {} unavailable: {:?}", hash, e);
+                None
+            }
+        }
+    }
+
+    /// Balance and nonce gap (pending minus latest nonce) of the signer, if there is one.
+    pub async fn signer_status(&self) -> Result<Option<(Address, U256, u64)>> {
+        let Some(address) = self.signer else {
+            return Ok(None);
+        };
+        let provider = self.oracle.provider();
+        let balance = provider.get_balance(address).await?;
+        let latest = provider.get_transaction_count(address).latest().await?;
+        let pending = provider.get_transaction_count(address).pending().await?;
+        Ok(Some((address, balance, pending.saturating_sub(latest))))
+    }
+
+    async fn aggregate_states(&self, token_ids: &[U256]) -> Result<Vec<Result<AgentState>>> {
+        let calls = token_ids
+            .iter()
+            .flat_map(|token_id| {
+                [
+                    IMulticall3::Call3 {
+                        target: *self.oracle.address(),
+                        allowFailure: true,
+                        callData: DecayOracle::isAgentRegisteredCall { tokenId: *token_id }
+                            .abi_encode()
+                            .into(),
+                    },
+                    IMulticall3::Call3 {
+                        target: *self.agent_nft.address(),
+                        allowFailure: true,
+                        callData: AgentNFT::getAgentProfileCall { tokenId: *token_id }
+                            .abi_encode()
+                            .into(),
+                    },
+                    IMulticall3::Call3 {
+                        target: *self.oracle.address(),
+                        allowFailure: true,
+                        callData: DecayOracle::getOracleLastUpdateCall { tokenId: *token_id }
+                            .abi_encode()
+                            .into(),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let results = self.multicall.aggregate3(calls).call().await?;
+        if results.len() != token_ids.len() * 3 {
+            return Err(anyhow!("Multicall returned {} results", results.len()));
+        }
+
+        Ok(results
+            .chunks(3)
+            .map(|calls| {
+                let (registered, profile, last_update) = (&calls[0], &calls[1], &calls[2]);
+                if !registered.success || !profile.success || !last_update.success {
+                    return Err(anyhow!("Agent state call reverted"));
+                }
+                Ok(AgentState {
+                    registered: DecayOracle::isAgentRegisteredCall::abi_decode_returns(
+                        &registered.returnData,
+                    )?,
+                    happiness: AgentNFT::getAgentProfileCall::abi_decode_returns(&profile.returnData)?
+                        .happinessScore,
+                    last_update: timestamp(
+                        DecayOracle::getOracleLastUpdateCall::abi_decode_returns(
+                            &last_update.returnData,
+                        )?,
+                    ),
+                })
+            })
+            .collect())
+    }
+
+    async fn read_agent_state(&self, token_id: U256) -> Result<AgentState> {
+        let registered = self.oracle.isAgentRegistered(token_id).call().await?;
+        let profile = self.agent_nft.getAgentProfile(token_id).call().await?;
+        let last_update = self.oracle.getOracleLastUpdate(token_id).call().await?;
+        Ok(AgentState {
+            registered,
+            happiness: profile.happinessScore,
+            last_update: timestamp(last_update),
+        })
+    }
+
+    /// Registers the tokens, one transaction per `batch_size` chunk when batching is available.
+    /// Returns the hash of the transaction that carried each token, in input order; tokens
+    /// that didn't fit in the tick's gas budget come back as [`Deferred`] errors.
+    pub async fn register_agents(
+        &self,
+        token_ids: &[U256],
+        meter: &mut GasMeter,
+    ) -> Vec<Result<TxHash>> {
+        if self.supports_batch {
+            return send_split(token_ids, self.batch_size, meter, |chunk| {
+                self.oracle.batchRegisterAgents(chunk.to_vec())
+            })
+            .await;
+        }
+        let mut hashes = Vec::with_capacity(token_ids.len());
+        for token_id in token_ids {
+            hashes.push(send_metered(self.oracle.registerAgent(*token_id), meter).await);
+        }
+        hashes
+    }
+
+    /// Sends `(token, new happiness)` updates, batched and metered like [`Chain::register_agents`].
+    pub async fn update_happiness(
+        &self,
+        updates: &[(U256, u8)],
+        meter: &mut GasMeter,
+    ) -> Vec<Result<TxHash>> {
+        if self.supports_batch {
+            return send_split(updates, self.batch_size, meter, |chunk| {
+                let (token_ids, scores): (Vec<U256>, Vec<u8>) = chunk.iter().copied().unzip();
+                self.oracle.batchUpdateAgentHappiness(token_ids, scores)
+            })
+            .await;
+        }
+        let mut hashes = Vec::with_capacity(updates.len());
+        for (token_id, happiness) in updates {
+            let call = self.oracle.updateAgentHappiness(*token_id, *happiness);
+            hashes.push(send_metered(call, meter).await);
+        }
+        hashes
+    }
+
+    /// Sends `(token, root, log size)` activity log anchors, batched and metered like
+    /// [`Chain::register_agents`].
+    pub async fn anchor_activity_logs(
+        &self,
+        anchors: &[(U256, B256, u64)],
+        meter: &mut GasMeter,
+    ) -> Vec<Result<TxHash>> {
+        send_split(anchors, self.batch_size, meter, |chunk| {
+            let token_ids = chunk.iter().map(|(token_id, _, _)| *token_id).collect();
+            let roots = chunk.iter().map(|(_, root, _)| *root).collect();
+            let sizes = chunk.iter().map(|(_, _, size)| U256::from(*size)).collect();
+            self.oracle.anchorActivityLogs(token_ids, roots, sizes)
+        })
+        .await
+    }
+}
+
+fn timestamp(value: U256) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// Estimates the call, charges its gas limit to the tick budget and sends it with the
+/// metered EIP-1559 fees instead of the provider's defaults.
+async fn send_metered<D: CallDecoder>(
+    call: CallBuilder<&DynProvider, D>,
+    meter: &mut GasMeter,
+) -> Result<TxHash> {
+    let gas_limit = GasMeter::gas_limit(call.estimate_gas().await?);
+    if !meter.reserve(gas_limit) {
+        return Err(Deferred("tick gas budget exhausted").into());
+    }
+    let pending = call
+        .gas(gas_limit)
+        .max_fee_per_gas(meter.max_fee_per_gas)
+        .max_priority_fee_per_gas(meter.max_priority_fee_per_gas)
+        .send()
+        .await?;
+    Ok(*pending.tx_hash())
+}
+
+/// Sends `items` in batch transactions of up to `batch_size`, built by `call`. A batch whose
+/// estimate or send fails (one reverting item is enough) is split in half and each half
+/// retried, down to single items, so one bad token can't hold back the rest of its chunk.
+/// Results come back in input order, one per item.
+async fn send_split<'a, T, D: CallDecoder>(
+    items: &[T],
+    batch_size: usize,
+    meter: &mut GasMeter,
+    call: impl Fn(&[T]) -> CallBuilder<&'a DynProvider, D>,
+) -> Vec<Result<TxHash>> {
+    let mut hashes = Vec::with_capacity(items.len());
+    // Popped from the back: the first chunk goes out first
+    let mut chunks: Vec<&[T]> = items.chunks(batch_size.max(1)).rev().collect();
+    while let Some(chunk) = chunks.pop() {
+        match send_metered(call(chunk), meter).await {
+            Err(e) if chunk.len() > 1 && !e.is::<Deferred>() => {
+                warn!("Batch of {} failed, splitting it: {}", chunk.len(), e);
+                let (first, second) = chunk.split_at(chunk.len() / 2);
+                chunks.push(second);
+                chunks.push(first);
+            }
+            result => push_shared(&mut hashes, result, chunk.len()),
+        }
+    }
+    hashes
+}
+
+/// Fans a batch transaction's outcome out to every item it carried.
+fn push_shared(hashes: &mut Vec<Result<TxHash>>, result: Result<TxHash>, count: usize) {
+    match result {
+        Ok(hash) => hashes.extend((0..count).map(|_| Ok(hash))),
+        Err(e) if e.is::<Deferred>() => {
+            hashes.extend((0..count).map(|_| Err(Deferred("tick gas budget exhausted").into())))
+        }
+        Err(e) => {
+            let message = e.to_string();
+            hashes.extend((0..count).map(|_| Err(anyhow!("Batch tx failed: {}", message))));
+        }
+    }
+}
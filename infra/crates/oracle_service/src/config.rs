@@ -0,0 +1,311 @@
+use crate::fees::FeePolicy;
+use crate::leader;
+use crate::signer::{SignerEndpoint, SignerSource};
+use alloy::primitives::Address;
+use alloy::providers::MULTICALL3_ADDRESS;
+use anyhow::{Context, Result, bail};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+
+/// How the service should run, selected from the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mode {
+    /// Normal operation: sign and broadcast transactions.
+    Live,
+    /// Read the chain and log every planned transaction without broadcasting.
+    DryRun,
+    /// Replay the decay rules offline against the SQLite snapshot and a virtual clock.
+    Simulate { days: u32, step: Duration },
+}
+
+/// Parsed command line.
+///
+/// ```text
+/// oracle_service [--dry-run] [--once]
+/// oracle_service --simulate <DAYS> [--step-minutes <N>]
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cli {
+    pub mode: Mode,
+    /// Run a single tick and exit (live or dry-run).
+    pub once: bool,
+}
+
+impl Cli {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut dry_run = false;
+        let mut once = false;
+        let mut simulate_days: Option<u32> = None;
+        let mut step_minutes: u64 = 60;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dry-run" => dry_run = true,
+                "--once" => once = true,
+                "--simulate" => {
+                    let days = args.next().context("--simulate needs a number of days")?;
+                    simulate_days = Some(days.parse().context("Invalid --simulate days")?);
+                }
+                "--step-minutes" => {
+                    let minutes = args.next().context("--step-minutes needs a value")?;
+                    step_minutes = minutes.parse().context("Invalid --step-minutes")?;
+                }
+                other => bail!("Unknown argument: {}", other),
+            }
+        }
+
+        if step_minutes == 0 {
+            bail!("--step-minutes must be positive");
+        }
+        let mode = match (simulate_days, dry_run) {
+            (Some(_), true) => bail!("--simulate and --dry-run are mutually exclusive"),
+            (Some(days), false) => Mode::Simulate {
+                days,
+                step: Duration::from_secs(step_minutes * 60),
+            },
+            (None, true) => Mode::DryRun,
+            (None, false) => Mode::Live,
+        };
+        Ok(Cli { mode, once })
+    }
+}
+
+/// Which [`crate::leader::LeaseBackend`] coordinates instances, from `ORACLE_LEASE`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LeaseKind {
+    /// `sqlite` (default): a row in the shared DB, for instances on any host.
+    Sqlite,
+    /// `file:<path>`: an exclusive lock on a local file, for instances on one host.
+    File(PathBuf),
+    /// `none`: a single instance, always the leader.
+    Standalone,
+}
+
+impl FromStr for LeaseKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sqlite" => Ok(LeaseKind::Sqlite),
+            "none" => Ok(LeaseKind::Standalone),
+            _ => match s.strip_prefix("file:") {
+                Some(path) if !path.is_empty() => Ok(LeaseKind::File(path.into())),
+                _ => bail!("Invalid ORACLE_LEASE {:?}: expected sqlite, file:<path> or none", s),
+            },
+        }
+    }
+}
+
+/// Four hours of decay: an outage never costs an agent more than that.
+pub const DEFAULT_MAX_CATCH_UP: u8 = 20;
+
+/// Settings for talking to the chain, loaded from the environment.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub rpc_url: Url,
+    pub oracle_addr: Address,
+    /// Where the signing key comes from; optional in dry-run mode, where nothing is signed.
+    pub signer: Option<SignerSource>,
+    pub db_url: String,
+    pub tick_interval: Duration,
+    /// Multicall3 deployment used to aggregate per-tick reads.
+    pub multicall_addr: Address,
+    /// Max agents per batch transaction.
+    pub batch_size: usize,
+    pub fees: FeePolicy,
+    /// Below this much runway at the recent burn rate the oracle only sends urgent updates.
+    pub min_runway: Duration,
+    /// Where to serve `/metrics`; disabled when unset.
+    pub metrics_addr: Option<SocketAddr>,
+    pub lease: LeaseKind,
+    /// Lease lifetime without renewal; must outlast a tick so the leader renews in time.
+    pub lease_ttl: Duration,
+    /// This instance's name in the lease.
+    pub instance_id: String,
+    /// Most decay one update may apply after missed ticks; the rest is forgiven.
+    pub max_catch_up: u8,
+    /// Least time between two anchors of the same agent's activity log.
+    pub anchor_interval: Duration,
+    /// Where to serve the admin API; disabled when unset.
+    pub admin_addr: Option<SocketAddr>,
+    /// Bearer token the admin API requires; mandatory with `admin_addr`.
+    pub admin_token: Option<String>,
+}
+
+impl Config {
+    pub fn from_env(mode: &Mode) -> Result<Self> {
+        let rpc_url = std::env::var("BASE_RPC_URL")
+            .context("BASE_RPC_URL not set")?
+            .parse()
+            .context("Invalid BASE_RPC_URL")?;
+        let oracle_addr_str =
+            std::env::var("DECAY_ORACLE_ADDRESS").context("DECAY_ORACLE_ADDRESS not set")?;
+        let oracle_addr = Address::from_str(&oracle_addr_str).context("Invalid address")?;
+        let signer = match signer_from_env()? {
+            Some(signer) => Some(signer),
+            None if *mode == Mode::DryRun => None,
+            None => bail!(
+                "No oracle signer configured: set ORACLE_KEYSTORE, ORACLE_SIGNER_URL or ORACLE_PRIVATE_KEY"
+            ),
+        };
+        let tick_secs: u64 = match std::env::var("ORACLE_TICK_SECS") {
+            Ok(secs) => secs.parse().context("Invalid ORACLE_TICK_SECS")?,
+            Err(_) => 60, // 60s for demo
+        };
+
+        let multicall_addr = match std::env::var("MULTICALL3_ADDRESS") {
+            Ok(addr) => Address::from_str(&addr).context("Invalid MULTICALL3_ADDRESS")?,
+            Err(_) => MULTICALL3_ADDRESS,
+        };
+        let batch_size = match std::env::var("ORACLE_BATCH_SIZE") {
+            Ok(size) => size.parse().context("Invalid ORACLE_BATCH_SIZE")?,
+            Err(_) => 100,
+        };
+        if batch_size == 0 {
+            bail!("ORACLE_BATCH_SIZE must be positive");
+        }
+
+        let defaults = FeePolicy::default();
+        let fees = FeePolicy {
+            max_fee_per_gas: env_or("ORACLE_MAX_FEE_PER_GAS", defaults.max_fee_per_gas)?,
+            max_priority_fee_per_gas: env_or(
+                "ORACLE_PRIORITY_FEE_PER_GAS",
+                defaults.max_priority_fee_per_gas,
+            )?,
+            tick_gas_budget: env_or("ORACLE_TICK_GAS_BUDGET", defaults.tick_gas_budget)?,
+            defer_base_fee: env_or("ORACLE_DEFER_BASE_FEE", defaults.defer_base_fee)?,
+        };
+
+        let min_runway_hours: u64 = env_or("ORACLE_MIN_RUNWAY_HOURS", 24)?;
+        let metrics_addr = match std::env::var("ORACLE_METRICS_ADDR") {
+            Ok(addr) => Some(addr.parse().context("Invalid ORACLE_METRICS_ADDR")?),
+            Err(_) => None,
+        };
+
+        let lease = match std::env::var("ORACLE_LEASE") {
+            Ok(lease) => lease.parse()?,
+            Err(_) => LeaseKind::Sqlite,
+        };
+        let lease_ttl_secs: u64 = env_or("ORACLE_LEASE_TTL_SECS", 3 * tick_secs)?;
+        if lease == LeaseKind::Sqlite && lease_ttl_secs <= tick_secs {
+            bail!("ORACLE_LEASE_TTL_SECS must be longer than ORACLE_TICK_SECS");
+        }
+        let instance_id =
+            std::env::var("ORACLE_INSTANCE_ID").unwrap_or_else(|_| leader::default_holder());
+
+        let admin_addr = match std::env::var("ORACLE_ADMIN_ADDR") {
+            Ok(addr) => Some(addr.parse().context("Invalid ORACLE_ADMIN_ADDR")?),
+            Err(_) => None,
+        };
+        let admin_token = std::env::var("ORACLE_ADMIN_TOKEN").ok();
+        if admin_addr.is_some() && admin_token.as_ref().is_none_or(|t| t.len() < 16) {
+            bail!("ORACLE_ADMIN_ADDR needs an ORACLE_ADMIN_TOKEN of at least 16 characters");
+        }
+
+        Ok(Config {
+            rpc_url,
+            oracle_addr,
+            signer,
+            db_url: db_url()?,
+            tick_interval: Duration::from_secs(tick_secs),
+            multicall_addr,
+            batch_size,
+            fees,
+            min_runway: Duration::from_secs(min_runway_hours * 3600),
+            metrics_addr,
+            lease,
+            lease_ttl: Duration::from_secs(lease_ttl_secs),
+            instance_id,
+            max_catch_up: max_catch_up()?,
+            anchor_interval: Duration::from_secs(env_or("ORACLE_ANCHOR_INTERVAL_SECS", 3600)?),
+            admin_addr,
+            admin_token,
+        })
+    }
+}
+
+/// At most one of the keystore, external signer or raw key may be configured.
+fn signer_from_env() -> Result<Option<SignerSource>> {
+    let mut sources = Vec::new();
+    if let Ok(path) = std::env::var("ORACLE_KEYSTORE") {
+        let password_file = std::env::var("ORACLE_KEYSTORE_PASSWORD_FILE")
+            .context("ORACLE_KEYSTORE needs ORACLE_KEYSTORE_PASSWORD_FILE")?;
+        sources.push(SignerSource::Keystore {
+            path: path.into(),
+            password_file: password_file.into(),
+        });
+    }
+    if let Ok(endpoint) = std::env::var("ORACLE_SIGNER_URL") {
+        let address = match std::env::var("ORACLE_SIGNER_ADDRESS") {
+            Ok(addr) => Some(Address::from_str(&addr).context("Invalid ORACLE_SIGNER_ADDRESS")?),
+            Err(_) => None,
+        };
+        sources.push(SignerSource::Remote {
+            endpoint: SignerEndpoint::parse(&endpoint)?,
+            address,
+        });
+    }
+    if let Ok(key) = std::env::var("ORACLE_PRIVATE_KEY") {
+        sources.push(SignerSource::PrivateKey(key));
+    }
+    if sources.len() > 1 {
+        bail!("Set only one of ORACLE_KEYSTORE, ORACLE_SIGNER_URL and ORACLE_PRIVATE_KEY");
+    }
+    Ok(sources.pop())
+}
+
+/// Parses an optional numeric env var, falling back to `default` when unset.
+fn env_or<T: FromStr>(name: &str, default: T) -> Result<T> {
+    match std::env::var(name) {
+        Ok(value) => value.parse().ok().with_context(|| format!("Invalid {}", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// `ORACLE_MAX_CATCH_UP`; shared with the simulator, which doesn't load a full [`Config`].
+pub fn max_catch_up() -> Result<u8> {
+    env_or("ORACLE_MAX_CATCH_UP", DEFAULT_MAX_CATCH_UP)
+}
+
+pub fn db_url() -> Result<String> {
+    std::env::var("DATABASE_URL").context("DATABASE_URL not set")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli> {
+        Cli::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!(parse(&[]).unwrap().mode, Mode::Live);
+        let cli = parse(&["--dry-run", "--once"]).unwrap();
+        assert_eq!(cli.mode, Mode::DryRun);
+        assert!(cli.once);
+        assert_eq!(
+            parse(&["--simulate", "7", "--step-minutes", "30"]).unwrap().mode,
+            Mode::Simulate { days: 7, step: Duration::from_secs(1800) }
+        );
+        assert!(parse(&["--simulate", "7", "--dry-run"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn parses_lease_kinds() {
+        assert_eq!("sqlite".parse::<LeaseKind>().unwrap(), LeaseKind::Sqlite);
+        assert_eq!("none".parse::<LeaseKind>().unwrap(), LeaseKind::Standalone);
+        assert_eq!(
+            "file:/run/oracle.lock".parse::<LeaseKind>().unwrap(),
+            LeaseKind::File("/run/oracle.lock".into())
+        );
+        assert!("file:".parse::<LeaseKind>().is_err());
+        assert!("etcd".parse::<LeaseKind>().is_err());
+    }
+}
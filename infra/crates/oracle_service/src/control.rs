@@ -0,0 +1,123 @@
+//! Runtime state shared by the tick loop and the admin API.
+
+use alloy::primitives::TxHash;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::MutexGuard;
+
+/// Sent transactions kept for inspection; the oldest are dropped past this.
+pub const MAX_TRACKED_TXS: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxPurpose {
+    Register,
+    Decay,
+    /// Updates raising happiness by earned boosts (net of any decay owed).
+    Boost,
+    /// Happiness set by an operator through the admin API.
+    Manual,
+    /// Activity log roots committed to `AgentNFT`.
+    Anchor,
+}
+
+/// A transaction the oracle sent and hasn't seen mined yet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PendingTx {
+    pub hash: TxHash,
+    pub purpose: TxPurpose,
+    /// Decimal token ids the transaction carries (several for a batch).
+    pub token_ids: Vec<String>,
+    pub sent_at: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct Control {
+    paused: AtomicBool,
+    /// Serializes everything that sends: the provider's nonce filler isn't safe against
+    /// two concurrent senders on the same account.
+    send_lock: tokio::sync::Mutex<()>,
+    pending: Mutex<Vec<PendingTx>>,
+}
+
+impl Control {
+    /// Pauses decay; returns whether it was running.
+    pub fn pause(&self) -> bool {
+        !self.paused.swap(true, Ordering::SeqCst)
+    }
+
+    /// Resumes decay; returns whether it was paused.
+    pub fn resume(&self) -> bool {
+        self.paused.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub async fn lock_sends(&self) -> MutexGuard<'_, ()> {
+        self.send_lock.lock().await
+    }
+
+    /// Records that `token_id` went out in `hash`, merging the items of a batch.
+    pub fn track(&self, hash: TxHash, purpose: TxPurpose, token_id: String, sent_at: i64) {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(tx) = pending.iter_mut().find(|tx| tx.hash == hash) {
+            tx.token_ids.push(token_id);
+            return;
+        }
+        if pending.len() == MAX_TRACKED_TXS {
+            pending.remove(0);
+        }
+        pending.push(PendingTx {
+            hash,
+            purpose,
+            token_ids: vec![token_id],
+            sent_at,
+        });
+    }
+
+    pub fn pending(&self) -> Vec<PendingTx> {
+        self.pending.lock().unwrap().clone()
+    }
+
+    /// Forgets transactions known to be mined.
+    pub fn forget(&self, mined: &[TxHash]) {
+        self.pending
+            .lock()
+            .unwrap()
+            .retain(|tx| !mined.contains(&tx.hash));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_batches_as_one_transaction() {
+        let control = Control::default();
+        let (a, b) = (TxHash::repeat_byte(1), TxHash::repeat_byte(2));
+        control.track(a, TxPurpose::Decay, "1".into(), 10);
+        control.track(a, TxPurpose::Decay, "2".into(), 10);
+        control.track(b, TxPurpose::Register, "3".into(), 11);
+
+        let pending = control.pending();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].token_ids, vec!["1", "2"]);
+
+        control.forget(&[a]);
+        assert_eq!(control.pending()[0].hash, b);
+    }
+
+    #[test]
+    fn pause_and_resume_report_transitions() {
+        let control = Control::default();
+        assert!(control.pause());
+        assert!(!control.pause());
+        assert!(control.is_paused());
+        assert!(control.resume());
+        assert!(!control.resume());
+    }
+}
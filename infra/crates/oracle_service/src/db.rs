@@ -0,0 +1,316 @@
+use alloy::primitives::U256;
+use anyhow::{Context, Result};
+use shared::death::record_death;
+use shared::death::AgentStatus;
+use shared::happiness::{
+    HappinessBoost, NewHappinessEvent, fetch_pending_boosts, fetch_sent_boosts,
+    mark_boosts_applied, mark_boosts_sent, record_event, release_boosts,
+};
+use shared::protection::{DecayProtection, fetch_living_protections};
+use std::collections::HashMap;
+use sqlx::SqlitePool;
+use serde::Serialize;
+use std::str::FromStr;
+use tracing::{error, warn};
+
+/// A living agent as the oracle sees it in the shared `agents` table.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct AgentRow {
+    pub agent_id: String,
+    pub token_id: Option<String>,
+    pub last_ts: i64,
+}
+
+impl AgentRow {
+    /// On-chain token id, if the frontend stored a valid one.
+    pub fn token(&self) -> Option<U256> {
+        self.token_id.as_deref().and_then(|t| U256::from_str(t).ok())
+    }
+}
+
+pub async fn connect(db_url: &str) -> Result<SqlitePool> {
+    SqlitePool::connect(db_url).await.context("DB connect failed")
+}
+
+/// Living agents; dead ones are terminal and never touched again.
+pub async fn fetch_living_agents(db_pool: &SqlitePool) -> Result<Vec<AgentRow>> {
+    sqlx::query_as(
+        "SELECT agent_id, token_id, COALESCE(last_interact_ts, 0) as last_ts FROM agents
+         WHERE status = 'alive'",
+    )
+    .fetch_all(db_pool)
+    .await
+    .context("Query agents failed")
+}
+
+/// An agent whose activity log grew past its last anchor.
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct LogRow {
+    pub agent_id: String,
+    pub token_id: Option<String>,
+    /// Messages in the log now.
+    pub size: i64,
+    /// Messages the last anchor covered; 0 if never anchored.
+    pub anchored_size: i64,
+}
+
+impl LogRow {
+    pub fn token(&self) -> Option<U256> {
+        self.token_id.as_deref().and_then(|t| U256::from_str(t).ok())
+    }
+}
+
+/// Agents, dead ones included so their final words get committed, whose log grew since
+/// an anchor made at or before `anchored_before`.
+pub async fn fetch_unanchored_logs(db_pool: &SqlitePool, anchored_before: i64) -> Result<Vec<LogRow>> {
+    sqlx::query_as(
+        "SELECT a.agent_id, a.token_id, m.size, COALESCE(x.size, 0) AS anchored_size
+         FROM agents a
+         JOIN (SELECT agent_id, MAX(seq) + 1 AS size FROM agent_messages GROUP BY agent_id) m
+           ON m.agent_id = a.agent_id
+         LEFT JOIN (SELECT agent_id, MAX(size) AS size, MAX(anchored_at) AS anchored_at
+                    FROM activity_anchors GROUP BY agent_id) x
+           ON x.agent_id = a.agent_id
+         WHERE m.size > COALESCE(x.size, 0) AND COALESCE(x.anchored_at, 0) <= ?",
+    )
+    .bind(anchored_before)
+    .fetch_all(db_pool)
+    .await
+    .context("Query unanchored activity logs failed")
+}
+
+/// Decay protection windows of living agents, by agent id.
+pub async fn fetch_protections(
+    db_pool: &SqlitePool,
+) -> Result<HashMap<String, Vec<DecayProtection>>> {
+    let mut by_agent: HashMap<String, Vec<DecayProtection>> = HashMap::new();
+    for protection in fetch_living_protections(db_pool)
+        .await
+        .context("Query decay protections failed")?
+    {
+        by_agent
+            .entry(protection.agent_id.clone())
+            .or_default()
+            .push(protection);
+    }
+    Ok(by_agent)
+}
+
+/// Boosts of living agents still to be applied, by agent id.
+pub async fn fetch_boosts(db_pool: &SqlitePool) -> Result<HashMap<String, Vec<HappinessBoost>>> {
+    let mut by_agent: HashMap<String, Vec<HappinessBoost>> = HashMap::new();
+    for boost in fetch_pending_boosts(db_pool)
+        .await
+        .context("Query happiness boosts failed")?
+    {
+        by_agent.entry(boost.agent_id.clone()).or_default().push(boost);
+    }
+    Ok(by_agent)
+}
+
+/// Boosts sent in updates not confirmed yet, by transaction hash.
+pub async fn fetch_sent(db_pool: &SqlitePool) -> Result<HashMap<String, Vec<i64>>> {
+    let mut by_tx: HashMap<String, Vec<i64>> = HashMap::new();
+    for boost in fetch_sent_boosts(db_pool)
+        .await
+        .context("Query sent happiness boosts failed")?
+    {
+        by_tx.entry(boost.tx_hash.clone().unwrap_or_default()).or_default().push(boost.id);
+    }
+    Ok(by_tx)
+}
+
+/// Marks boosts sent in `tx_hash`; a failed write is logged, and they are sent again next tick.
+pub async fn mark_sent(db_pool: &SqlitePool, ids: &[i64], tx_hash: &str) {
+    if let Err(e) = mark_boosts_sent(db_pool, ids, tx_hash).await {
+        error!("Failed to mark boosts {:?} sent: {:?}", ids, e);
+    }
+}
+
+/// Makes the boosts of a reverted update pending again.
+pub async fn release(db_pool: &SqlitePool, tx_hash: &str) {
+    match release_boosts(db_pool, tx_hash).await {
+        Ok(0) => {}
+        Ok(released) => warn!("Tx {} reverted, {} boosts pending again", tx_hash, released),
+        Err(e) => error!("Failed to release boosts of {}: {:?}", tx_hash, e),
+    }
+}
+
+/// Marks boosts applied; a failed write is logged, and they are applied again next tick.
+pub async fn mark_applied(db_pool: &SqlitePool, ids: &[i64], now: i64, tx_hash: Option<&str>) {
+    if let Err(e) = mark_boosts_applied(db_pool, ids, now, tx_hash).await {
+        error!("Failed to mark boosts {:?} applied: {:?}", ids, e);
+    }
+}
+
+/// Lifecycle state of an agent, or `None` if it doesn't exist.
+pub async fn agent_status(db_pool: &SqlitePool, agent_id: &str) -> Result<Option<AgentStatus>> {
+    sqlx::query_scalar("SELECT status FROM agents WHERE agent_id = ?")
+        .bind(agent_id)
+        .fetch_optional(db_pool)
+        .await
+        .context("Query agent status failed")
+}
+
+/// Last recorded happiness for an agent, if the oracle ever observed it.
+pub async fn latest_happiness(db_pool: &SqlitePool, agent_id: &str) -> Result<Option<u8>> {
+    sqlx::query_scalar(
+        "SELECT new_happiness FROM happiness_events WHERE agent_id = ? ORDER BY ts DESC, id DESC LIMIT 1",
+    )
+    .bind(agent_id)
+    .fetch_optional(db_pool)
+    .await
+    .context("Query happiness failed")
+}
+
+/// Time of the last happiness update the oracle sent for an agent, if any.
+pub async fn last_oracle_update(db_pool: &SqlitePool, agent_id: &str) -> Result<Option<i64>> {
+    sqlx::query_scalar(
+        "SELECT MAX(ts) FROM happiness_events WHERE agent_id = ? AND cause IN ('decay', 'manual', 'boost')",
+    )
+    .bind(agent_id)
+    .fetch_one(db_pool)
+    .await
+    .context("Query last oracle update failed")
+}
+
+/// Appends to the happiness time-series; a failed write is logged, never fatal to the tick.
+pub async fn record(db_pool: &SqlitePool, event: NewHappinessEvent) {
+    if let Err(e) = record_event(db_pool, &event).await {
+        error!(
+            "Failed to record {:?} happiness event for {}: {:?}",
+            event.cause, event.agent_id, e
+        );
+    }
+}
+
+/// Records an agent's death so `ai_execution` can shut it down.
+pub async fn mark_dead(db_pool: &SqlitePool, agent_id: &str, now: i64, tx_hash: Option<&str>) {
+    match record_death(db_pool, agent_id, now, tx_hash).await {
+        Ok(true) => warn!("Agent {} died: happiness reached 0", agent_id),
+        Ok(false) => {}
+        Err(e) => error!("Failed to record death of {}: {:?}", agent_id, e),
+    }
+}
+
+/// Agent minted as `token_id`, if `ai_execution` knows it.
+pub async fn agent_for_token(db_pool: &SqlitePool, token_id: &str) -> Result<Option<String>> {
+    sqlx::query_scalar("SELECT agent_id FROM agents WHERE token_id = ?")
+        .bind(token_id)
+        .fetch_optional(db_pool)
+        .await
+        .context("Query agent by token failed")
+}
+
+/// One operator action from the admin API.
+#[derive(Clone, Debug, Serialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub ts: i64,
+    pub action: String,
+    pub token_id: Option<String>,
+    pub reason: Option<String>,
+    pub detail: Option<String>,
+    pub tx_hash: Option<String>,
+}
+
+/// Insert payload for [`record_audit`].
+#[derive(Clone, Debug, Default)]
+pub struct NewAuditEntry {
+    pub ts: i64,
+    pub action: &'static str,
+    pub token_id: Option<String>,
+    pub reason: Option<String>,
+    pub detail: Option<String>,
+    pub tx_hash: Option<String>,
+}
+
+pub async fn record_audit(db_pool: &SqlitePool, entry: NewAuditEntry) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO oracle_audit_log (ts, action, token_id, reason, detail, tx_hash)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(entry.ts)
+    .bind(entry.action)
+    .bind(entry.token_id)
+    .bind(entry.reason)
+    .bind(entry.detail)
+    .bind(entry.tx_hash)
+    .execute(db_pool)
+    .await
+    .context("Audit insert failed")?;
+    Ok(())
+}
+
+/// Most recent audit entries first.
+pub async fn fetch_audit(db_pool: &SqlitePool, limit: i64) -> Result<Vec<AuditEntry>> {
+    sqlx::query_as("SELECT * FROM oracle_audit_log ORDER BY id DESC LIMIT ?")
+        .bind(limit)
+        .fetch_all(db_pool)
+        .await
+        .context("Query audit log failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::activity::{NewAnchor, NewLoggedMessage, append_message, record_anchor};
+
+    #[tokio::test]
+    async fn finds_logs_that_grew_since_an_old_enough_anchor() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations")
+            .run(&pool)
+            .await
+            .unwrap();
+        for (agent_id, token_id, messages) in [("quiet", "3", 0), ("chatty", "2", 3), ("anchored", "1", 2)] {
+            sqlx::query("INSERT INTO agents (agent_id, owner_address, token_id) VALUES (?, '0x', ?)")
+                .bind(agent_id)
+                .bind(token_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+            for i in 0..messages {
+                let message = NewLoggedMessage {
+                    agent_id: agent_id.to_string(),
+                    role: "user".to_string(),
+                    origin: "Owner".to_string(),
+                    content: format!("message {}", i),
+                    ts: 10,
+                };
+                append_message(&pool, &message).await.unwrap();
+            }
+        }
+        let anchor = NewAnchor {
+            agent_id: "anchored".to_string(),
+            token_id: "1".to_string(),
+            size: 2,
+            root: "0x00".to_string(),
+            anchored_at: 100,
+            tx_hash: "0x01".to_string(),
+        };
+        record_anchor(&pool, &anchor).await.unwrap();
+
+        let logs = fetch_unanchored_logs(&pool, 1_000).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!((logs[0].agent_id.as_str(), logs[0].size), ("chatty", 3));
+
+        // "anchored" grew, but its last anchor is too recent to replace yet
+        append_message(
+            &pool,
+            &NewLoggedMessage {
+                agent_id: "anchored".to_string(),
+                role: "assistant".to_string(),
+                origin: "Agent".to_string(),
+                content: "reply".to_string(),
+                ts: 20,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(fetch_unanchored_logs(&pool, 99).await.unwrap().len(), 1);
+        let logs = fetch_unanchored_logs(&pool, 100).await.unwrap();
+        let anchored = logs.iter().find(|log| log.agent_id == "anchored").unwrap();
+        assert_eq!((anchored.size, anchored.anchored_size), (3, 2));
+    }
+}
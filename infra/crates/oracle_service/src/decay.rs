@@ -0,0 +1,141 @@
+//! Happiness decay rules, kept free of I/O so the live loop and the simulator share them.
+//!
+//! Decay follows a fixed schedule from the agent's last interaction: nothing during the
+//! grace hour, then [`DECAY_PER_HOUR`] for every idle hour (the first one included). Each
+//! update applies what the schedule accrued since the previous oracle update, so an update
+//! after downtime settles the whole gap at once, up to [`CatchUp::max_step`]. Protected
+//! agents (see `shared::protection`) don't decay, and resume from the end of the window.
+//! Earned boosts (see `shared::happiness`) are added on top, up to [`MAX_HAPPINESS`].
+
+use std::time::Duration;
+
+/// Happiness a freshly minted agent starts with (matches the frontend mint default).
+pub const BASELINE_HAPPINESS: u8 = 80;
+/// Ceiling of the score, as `AgentNFT` enforces it.
+pub const MAX_HAPPINESS: u8 = 100;
+/// Idle time before decay kicks in.
+pub const IDLE_GRACE_HOURS: f64 = 1.0;
+/// Happiness lost per idle hour past the grace window.
+pub const DECAY_PER_HOUR: f64 = 5.0;
+
+/// How the oracle settles decay that built up while no update was sent (oracle downtime,
+/// RPC outages, held or deferred ticks).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CatchUp {
+    /// Most happiness a single update may take. Decay owed beyond it is forgiven: an outage
+    /// of any length costs an agent at most this much.
+    pub max_step: u8,
+    pub tick_interval: Duration,
+}
+
+impl CatchUp {
+    /// Decay the schedule accrues over one tick, rounded up. Owing more than this means
+    /// ticks were missed.
+    pub fn per_tick(&self) -> u32 {
+        (DECAY_PER_HOUR * self.tick_interval.as_secs_f64() / 3600.0).ceil() as u32
+    }
+}
+
+/// Hours since the agent last interacted, never negative.
+pub fn hours_idle(last_interact_ts: i64, now: i64) -> f64 {
+    ((now - last_interact_ts) as f64 / 3600.0).max(0.0)
+}
+
+/// Total decay the schedule has accrued at `at`.
+fn scheduled_decay(last_interact_ts: i64, at: i64) -> u32 {
+    let hours = hours_idle(last_interact_ts, at);
+    if hours <= IDLE_GRACE_HOURS {
+        return 0;
+    }
+    (DECAY_PER_HOUR * hours) as u32
+}
+
+/// Decay accrued since the later of `last_update` and the last interaction. `last_update` is
+/// the last oracle update (`getOracleLastUpdate`, 0 if never), or the end of the agent's
+/// last protection window if that is later.
+pub fn decay_owed(last_interact_ts: i64, last_update: i64, now: i64) -> u32 {
+    let since = last_update.max(last_interact_ts);
+    scheduled_decay(last_interact_ts, now).saturating_sub(scheduled_decay(last_interact_ts, since))
+}
+
+/// The value an update should write, or `None` when no decay is owed yet.
+pub fn decayed_happiness(
+    current: u8,
+    last_interact_ts: i64,
+    last_update: i64,
+    now: i64,
+    max_step: u8,
+) -> Option<u8> {
+    let owed = decay_owed(last_interact_ts, last_update, now).min(max_step as u32);
+    let new = current.saturating_sub(owed as u8);
+    (new < current).then_some(new)
+}
+
+/// `current` raised by `boost` points, capped at [`MAX_HAPPINESS`].
+pub fn boosted(current: u8, boost: u32) -> u8 {
+    (current as u32 + boost).min(MAX_HAPPINESS as u32) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3600;
+
+    #[test]
+    fn no_decay_within_grace() {
+        assert_eq!(decayed_happiness(80, 0, 0, HOUR, 100), None);
+    }
+
+    #[test]
+    fn decays_from_the_last_update_and_floors_at_zero() {
+        assert_eq!(decayed_happiness(80, 0, 0, 2 * HOUR, 100), Some(70));
+        assert_eq!(decayed_happiness(70, 0, 2 * HOUR, 2 * HOUR, 100), None);
+        assert_eq!(decayed_happiness(70, 0, 2 * HOUR, 3 * HOUR, 100), Some(65));
+        assert_eq!(decayed_happiness(10, 0, 0, 100 * HOUR, 100), Some(0));
+        assert_eq!(decayed_happiness(0, 0, 0, 100 * HOUR, 100), None);
+    }
+
+    #[test]
+    fn updates_before_the_last_interaction_are_ignored() {
+        assert_eq!(decay_owed(10 * HOUR, 5 * HOUR, 13 * HOUR), 15);
+    }
+
+    #[test]
+    fn frequent_updates_follow_the_schedule() {
+        // One-minute ticks over 16 idle hours, each update stamped when it is sent
+        let (mut happiness, mut last_update) = (80u8, 0);
+        for now in (60..=16 * HOUR).step_by(60) {
+            if let Some(new) = decayed_happiness(happiness, 0, last_update, now, 100) {
+                happiness = new;
+                last_update = now;
+            }
+        }
+        assert_eq!(happiness, 0);
+    }
+
+    #[test]
+    fn catch_up_is_capped() {
+        // Updated at 2h, then nothing until 12h: 50 owed, 20 applied, the rest forgiven
+        assert_eq!(decay_owed(0, 2 * HOUR, 12 * HOUR), 50);
+        assert_eq!(decayed_happiness(70, 0, 2 * HOUR, 12 * HOUR, 20), Some(50));
+        assert_eq!(decayed_happiness(50, 0, 12 * HOUR, 13 * HOUR, 20), Some(45));
+    }
+
+    #[test]
+    fn boosts_are_capped() {
+        assert_eq!(boosted(65, 10), 75);
+        assert_eq!(boosted(95, 10), MAX_HAPPINESS);
+        assert_eq!(boosted(50, 1000), MAX_HAPPINESS);
+    }
+
+    #[test]
+    fn per_tick_decay() {
+        let catch_up = |secs| CatchUp {
+            max_step: 20,
+            tick_interval: Duration::from_secs(secs),
+        };
+        assert_eq!(catch_up(60).per_tick(), 1);
+        assert_eq!(catch_up(3600).per_tick(), 5);
+    }
+}
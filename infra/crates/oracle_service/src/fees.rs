@@ -0,0 +1,174 @@
+//! EIP-1559 fee policy: caps what a single tick may spend so a base-fee spike can't drain
+//! the oracle wallet. Kept free of I/O; [`crate::blockchain::Chain`] applies it when sending.
+
+use std::fmt;
+
+const GWEI: u128 = 1_000_000_000;
+
+/// Fee ceilings and per-tick budget, loaded with the rest of [`crate::config::Config`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeePolicy {
+    /// Upper bound on `maxFeePerGas` for every transaction, in wei.
+    pub max_fee_per_gas: u128,
+    /// `maxPriorityFeePerGas` tip, in wei.
+    pub max_priority_fee_per_gas: u128,
+    /// Gas limit summed over every transaction of one tick.
+    pub tick_gas_budget: u64,
+    /// Base fee (wei) above which only urgent updates are sent.
+    pub defer_base_fee: u128,
+}
+
+impl Default for FeePolicy {
+    /// Sized for Base, where the base fee normally sits in the low millions of wei.
+    fn default() -> Self {
+        FeePolicy {
+            max_fee_per_gas: GWEI,
+            max_priority_fee_per_gas: GWEI / 1000,
+            tick_gas_budget: 5_000_000,
+            defer_base_fee: GWEI / 10,
+        }
+    }
+}
+
+/// Which planned transactions a tick may send at the current base fee.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeDecision {
+    /// Fees are normal: send everything the budget allows.
+    SendAll,
+    /// The base fee spiked past `defer_base_fee`: send urgent updates, defer the rest.
+    UrgentOnly,
+    /// The base fee is at or above `max_fee_per_gas`: nothing could be included, send nothing.
+    Hold,
+}
+
+impl FeePolicy {
+    pub fn decide(&self, base_fee: u128) -> FeeDecision {
+        if base_fee + self.priority_fee() > self.max_fee_per_gas {
+            FeeDecision::Hold
+        } else if base_fee > self.defer_base_fee {
+            FeeDecision::UrgentOnly
+        } else {
+            FeeDecision::SendAll
+        }
+    }
+
+    /// Starts metering a tick. The max fee follows the usual `2 * base + tip` headroom,
+    /// clamped to the configured ceiling.
+    pub fn meter(&self, base_fee: u128) -> GasMeter {
+        let priority_fee = self.priority_fee();
+        GasMeter {
+            base_fee,
+            max_fee_per_gas: (2 * base_fee + priority_fee).min(self.max_fee_per_gas),
+            max_priority_fee_per_gas: priority_fee,
+            budget: self.tick_gas_budget,
+            gas: 0,
+            transactions: 0,
+        }
+    }
+
+    fn priority_fee(&self) -> u128 {
+        self.max_priority_fee_per_gas.min(self.max_fee_per_gas)
+    }
+}
+
+/// Gas committed during one tick, against the policy's budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GasMeter {
+    pub base_fee: u128,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    budget: u64,
+    /// Sum of the gas limits of the transactions sent so far.
+    pub gas: u64,
+    pub transactions: usize,
+}
+
+impl GasMeter {
+    /// Gas limit for a transaction estimated at `estimate`, with 20% headroom.
+    pub fn gas_limit(estimate: u64) -> u64 {
+        estimate.saturating_add(estimate / 5)
+    }
+
+    /// Accounts for one transaction with the given gas limit, or returns `false`
+    /// if it would overrun the tick budget.
+    pub fn reserve(&mut self, gas_limit: u64) -> bool {
+        match self.gas.checked_add(gas_limit) {
+            Some(total) if total <= self.budget => {
+                self.gas = total;
+                self.transactions += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Worst-case spend in wei: every gas limit fully used at the max fee.
+    pub fn max_spend(&self) -> u128 {
+        self.gas as u128 * self.max_fee_per_gas
+    }
+
+    /// Expected spend in wei at the current base fee.
+    pub fn expected_spend(&self) -> u128 {
+        self.gas as u128 * (self.base_fee + self.max_priority_fee_per_gas).min(self.max_fee_per_gas)
+    }
+}
+
+/// A transaction held back by the fee policy; it is retried on a later tick.
+#[derive(Debug)]
+pub struct Deferred(pub &'static str);
+
+impl fmt::Display for Deferred {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deferred: {}", self.0)
+    }
+}
+
+impl std::error::Error for Deferred {}
+
+/// Wei as a decimal gwei string, for logs.
+pub fn format_gwei(wei: u128) -> String {
+    format!("{}.{:09}", wei / GWEI, wei % GWEI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> FeePolicy {
+        FeePolicy {
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 2,
+            tick_gas_budget: 1_000,
+            defer_base_fee: 20,
+        }
+    }
+
+    #[test]
+    fn decides_by_base_fee() {
+        assert_eq!(policy().decide(10), FeeDecision::SendAll);
+        assert_eq!(policy().decide(21), FeeDecision::UrgentOnly);
+        assert_eq!(policy().decide(98), FeeDecision::UrgentOnly);
+        assert_eq!(policy().decide(99), FeeDecision::Hold);
+    }
+
+    #[test]
+    fn meter_caps_fees_and_budget() {
+        let mut meter = policy().meter(10);
+        assert_eq!(meter.max_fee_per_gas, 22);
+        assert_eq!(policy().meter(60).max_fee_per_gas, 100);
+
+        assert!(meter.reserve(600));
+        assert!(!meter.reserve(500));
+        assert!(meter.reserve(400));
+        assert_eq!((meter.gas, meter.transactions), (1_000, 2));
+        assert_eq!(meter.max_spend(), 22_000);
+        assert_eq!(meter.expected_spend(), 12_000);
+        assert_eq!(GasMeter::gas_limit(50_000), 60_000);
+    }
+
+    #[test]
+    fn formats_gwei() {
+        assert_eq!(format_gwei(1_500_000_000), "1.500000000");
+        assert_eq!(format_gwei(1_000), "0.000001000");
+    }
+}
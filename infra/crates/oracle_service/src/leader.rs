@@ -0,0 +1,271 @@
+//! Lease-based leader election, so several `oracle_service` instances can run against the
+//! same DB and chain while only one of them ever sends transactions.
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use sqlx::SqlitePool;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Lease name shared by every instance pointing at the same deployment.
+pub const LEASE_NAME: &str = "decay_oracle";
+
+/// Somewhere a single holder can be recorded atomically.
+pub trait LeaseBackend: Send + Sync {
+    /// Takes or renews the lease for `holder` until `now + ttl`. Returns whether `holder`
+    /// holds it afterwards.
+    fn try_acquire<'a>(
+        &'a self,
+        holder: &'a str,
+        now: i64,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool>>;
+
+    /// Gives the lease up early so a standby doesn't have to wait for it to expire.
+    fn release<'a>(&'a self, holder: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// A row in the shared `oracle_leases` table, taken over once `expires_at` passes.
+pub struct SqliteLease {
+    pub pool: SqlitePool,
+    pub name: String,
+}
+
+impl LeaseBackend for SqliteLease {
+    fn try_acquire<'a>(
+        &'a self,
+        holder: &'a str,
+        now: i64,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(async move {
+            // One statement, so two instances racing for an expired lease can't both win
+            let result = sqlx::query(
+                "INSERT INTO oracle_leases (name, holder, expires_at) VALUES (?, ?, ?)
+                 ON CONFLICT(name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+                 WHERE oracle_leases.holder = excluded.holder OR oracle_leases.expires_at <= ?",
+            )
+            .bind(&self.name)
+            .bind(holder)
+            .bind(now + ttl.as_secs() as i64)
+            .bind(now)
+            .execute(&self.pool)
+            .await
+            .context("Lease update failed")?;
+            Ok(result.rows_affected() == 1)
+        })
+    }
+
+    fn release<'a>(&'a self, holder: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            sqlx::query("UPDATE oracle_leases SET expires_at = 0 WHERE name = ? AND holder = ?")
+                .bind(&self.name)
+                .bind(holder)
+                .execute(&self.pool)
+                .await
+                .context("Lease release failed")?;
+            Ok(())
+        })
+    }
+}
+
+/// An exclusive lock on a local file, for instances sharing a host. The OS drops the lock
+/// the moment the holder dies, so the TTL doesn't apply.
+pub struct FileLease {
+    pub path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl FileLease {
+    pub fn new(path: PathBuf) -> Self {
+        FileLease {
+            path,
+            file: Mutex::new(None),
+        }
+    }
+
+    fn lock(&self, holder: &str) -> Result<bool> {
+        let mut held = self.file.lock().unwrap();
+        if held.is_some() {
+            return Ok(true);
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open lock file {}", self.path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {
+                file.set_len(0)?;
+                writeln!(file, "{}", holder)?;
+                *held = Some(file);
+                Ok(true)
+            }
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(e)) => Err(e).context("Failed to lock lease file"),
+        }
+    }
+}
+
+impl LeaseBackend for FileLease {
+    fn try_acquire<'a>(
+        &'a self,
+        holder: &'a str,
+        _now: i64,
+        _ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(async move { self.lock(holder) })
+    }
+
+    fn release<'a>(&'a self, _holder: &'a str) -> BoxFuture<'a, Result<()>> {
+        // Closing the file drops the lock
+        self.file.lock().unwrap().take();
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// No coordination: the single instance is always the leader.
+pub struct Standalone;
+
+impl LeaseBackend for Standalone {
+    fn try_acquire<'a>(
+        &'a self,
+        _holder: &'a str,
+        _now: i64,
+        _ttl: Duration,
+    ) -> BoxFuture<'a, Result<bool>> {
+        Box::pin(async { Ok(true) })
+    }
+
+    fn release<'a>(&'a self, _holder: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// This instance's view of the election.
+pub struct Election {
+    pub backend: Box<dyn LeaseBackend>,
+    pub holder: String,
+    /// How long a lease lasts without renewal; a standby takes over at most this long
+    /// (plus one tick) after the leader dies.
+    pub ttl: Duration,
+    leading: AtomicBool,
+}
+
+impl Election {
+    pub fn new(backend: Box<dyn LeaseBackend>, holder: String, ttl: Duration) -> Self {
+        Election {
+            backend,
+            holder,
+            ttl,
+            leading: AtomicBool::new(false),
+        }
+    }
+
+    pub fn standalone() -> Self {
+        Election::new(
+            Box::new(Standalone),
+            "standalone".to_string(),
+            Duration::ZERO,
+        )
+    }
+
+    /// Acquires or renews the lease. Any backend error counts as not leading: a follower
+    /// that sends nothing is always safe.
+    pub async fn hold(&self, now: i64) -> bool {
+        let leading = match self.backend.try_acquire(&self.holder, now, self.ttl).await {
+            Ok(leading) => leading,
+            Err(e) => {
+                error!("Leader lease check failed: {:?}", e);
+                false
+            }
+        };
+        match (self.leading.swap(leading, Ordering::SeqCst), leading) {
+            (false, true) => info!("{} is now the oracle leader", self.holder),
+            (true, false) => warn!("{} lost the oracle lease, standing by", self.holder),
+            _ => {}
+        }
+        leading
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leading.load(Ordering::SeqCst)
+    }
+
+    pub async fn release(&self) {
+        if self.leading.swap(false, Ordering::SeqCst) {
+            match self.backend.release(&self.holder).await {
+                Ok(()) => info!("{} released the oracle lease", self.holder),
+                Err(e) => error!("{:?}", e),
+            }
+        }
+    }
+}
+
+/// Default holder id: unique per process, readable in the lease table.
+pub fn default_holder() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "oracle".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(30);
+
+    #[tokio::test]
+    async fn sqlite_lease_expires_to_the_standby() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations")
+            .run(&pool)
+            .await
+            .unwrap();
+        let lease = SqliteLease {
+            pool,
+            name: LEASE_NAME.to_string(),
+        };
+
+        assert!(lease.try_acquire("a", 100, TTL).await.unwrap());
+        assert!(!lease.try_acquire("b", 110, TTL).await.unwrap());
+        // Renewal pushes the expiry out
+        assert!(lease.try_acquire("a", 125, TTL).await.unwrap());
+        assert!(!lease.try_acquire("b", 140, TTL).await.unwrap());
+        // "a" stopped renewing at 125
+        assert!(lease.try_acquire("b", 155, TTL).await.unwrap());
+        assert!(!lease.try_acquire("a", 160, TTL).await.unwrap());
+
+        lease.release("b").await.unwrap();
+        assert!(lease.try_acquire("a", 161, TTL).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn file_lease_is_exclusive_until_released() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("oracle.lock");
+        let a = FileLease::new(path.clone());
+        let b = FileLease::new(path);
+
+        assert!(a.try_acquire("a", 0, TTL).await.unwrap());
+        assert!(a.try_acquire("a", 0, TTL).await.unwrap());
+        assert!(!b.try_acquire("b", 0, TTL).await.unwrap());
+        a.release("a").await.unwrap();
+        assert!(b.try_acquire("b", 0, TTL).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn election_tracks_leadership() {
+        let election = Election::standalone();
+        assert!(!election.is_leader());
+        assert!(election.hold(0).await);
+        assert!(election.is_leader());
+        election.release().await;
+        assert!(!election.is_leader());
+    }
+}
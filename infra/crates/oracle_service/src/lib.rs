@@ -0,0 +1,18 @@
+//! Decay oracle: the "Dungeon Master" that keeps on-chain happiness in sync with
+//! off-chain activity. The binary in `main.rs` is a thin CLI over these modules.
+
+pub mod admin;
+pub mod anchor;
+pub mod attestation;
+pub mod blockchain;
+pub mod config;
+pub mod control;
+pub mod db;
+pub mod decay;
+pub mod fees;
+pub mod leader;
+pub mod metrics;
+pub mod service;
+pub mod signer;
+pub mod simulate;
+pub mod wallet;
@@ -0,0 +1,227 @@
+//! Prometheus-style gauges for the last tick and the signer's health, served as plain text.
+
+use crate::service::TickSummary;
+use crate::wallet::WalletHealth;
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::extract::State;
+use axum::routing::get;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+/// What the exporter reports; updated by the tick loop.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    pub ticks: u64,
+    pub last_tick_at: Option<i64>,
+    pub last_tick: Option<TickSummary>,
+    pub wallet: Option<WalletHealth>,
+}
+
+/// Shared handle to the latest [`Snapshot`].
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<RwLock<Snapshot>>);
+
+impl Metrics {
+    pub fn record_tick(&self, at: i64, summary: &TickSummary) {
+        let mut snapshot = self.0.write().unwrap();
+        snapshot.ticks += 1;
+        snapshot.last_tick_at = Some(at);
+        snapshot.last_tick = Some(summary.clone());
+    }
+
+    pub fn record_wallet(&self, health: WalletHealth) {
+        self.0.write().unwrap().wallet = Some(health);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+        gauge(
+            &mut out,
+            "oracle_ticks_total",
+            "Ticks run since start",
+            snapshot.ticks,
+        );
+        if let Some(at) = snapshot.last_tick_at {
+            gauge(
+                &mut out,
+                "oracle_last_tick_timestamp_seconds",
+                "Unix time of the last tick",
+                at,
+            );
+        }
+        if let Some(tick) = &snapshot.last_tick {
+            gauge(
+                &mut out,
+                "oracle_is_leader",
+                "1 while this instance holds the oracle lease",
+                u8::from(tick.leader),
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_agents",
+                "Living agents seen by the last tick",
+                tick.agents,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_registrations",
+                "Registrations planned by the last tick",
+                tick.registrations,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_updates",
+                "Happiness updates planned by the last tick",
+                tick.updates,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_protected",
+                "Agents skipped by the last tick because a protection window covers them",
+                tick.protected,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_catch_ups",
+                "Updates in the last tick settling decay from missed ticks",
+                tick.catch_ups,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_boosts",
+                "Updates in the last tick carrying earned happiness boosts",
+                tick.boosts,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_anchors",
+                "Activity logs anchored on-chain by the last tick",
+                tick.anchors,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_deferred",
+                "Items deferred by the fee policy in the last tick",
+                tick.deferred,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_transactions",
+                "Transactions sent by the last tick",
+                tick.transactions,
+            );
+            gauge(
+                &mut out,
+                "oracle_last_tick_gas",
+                "Summed gas limit of the last tick",
+                tick.gas,
+            );
+        }
+        if let Some(wallet) = &snapshot.wallet {
+            gauge(
+                &mut out,
+                "oracle_signer_balance_wei",
+                "Oracle signer balance",
+                wallet.balance,
+            );
+            gauge(
+                &mut out,
+                "oracle_signer_nonce_gap",
+                "Signer transactions sent but not yet mined",
+                wallet.nonce_gap,
+            );
+            gauge(
+                &mut out,
+                "oracle_spend_per_tick_wei",
+                "Average expected spend per tick",
+                wallet.spend_per_tick,
+            );
+            if let Some(runway) = wallet.runway {
+                gauge(
+                    &mut out,
+                    "oracle_wallet_runway_seconds",
+                    "Time until the balance runs out at the recent burn rate",
+                    runway.as_secs(),
+                );
+            }
+            gauge(
+                &mut out,
+                "oracle_wallet_low",
+                "1 while non-critical work is paused for lack of funds",
+                u8::from(wallet.low),
+            );
+        }
+        out
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(
+        out,
+        "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+    );
+}
+
+/// Serves `GET /metrics` until the process exits.
+pub async fn serve(addr: SocketAddr, metrics: Metrics) -> Result<()> {
+    let app = Router::new()
+        .route(
+            "/metrics",
+            get(|State(metrics): State<Metrics>| async move { metrics.render() }),
+        )
+        .with_state(metrics);
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind metrics on {}", addr))?;
+    info!("Metrics listening on http://{}/metrics", addr);
+    axum::serve(listener, app)
+        .await
+        .context("Metrics server failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+
+    #[test]
+    fn renders_gauges() {
+        let metrics = Metrics::default();
+        assert!(metrics.render().contains("oracle_ticks_total 0"));
+        assert!(!metrics.render().contains("oracle_signer_balance_wei"));
+
+        metrics.record_tick(
+            1_700_000_000,
+            &TickSummary {
+                agents: 3,
+                transactions: 2,
+                ..Default::default()
+            },
+        );
+        metrics.record_wallet(WalletHealth {
+            address: Address::ZERO,
+            balance: U256::from(5),
+            nonce_gap: 1,
+            spend_per_tick: 0,
+            runway: None,
+            low: true,
+        });
+        let text = metrics.render();
+        assert!(text.contains("oracle_ticks_total 1\n"));
+        assert!(text.contains("oracle_last_tick_agents 3\n"));
+        assert!(
+            text.contains("# TYPE oracle_signer_balance_wei gauge\noracle_signer_balance_wei 5\n")
+        );
+        assert!(text.contains("oracle_wallet_low 1\n"));
+        assert!(!text.contains("oracle_wallet_runway_seconds"));
+    }
+}
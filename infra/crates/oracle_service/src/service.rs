@@ -0,0 +1,689 @@
+use crate::attestation::{self, DecayInputs};
+use crate::blockchain::Chain;
+use crate::control::{Control, TxPurpose};
+use crate::db::{self, AgentRow};
+use crate::decay::{CatchUp, boosted, decay_owed, decayed_happiness, hours_idle};
+use crate::fees::{Deferred, FeeDecision, GasMeter, format_gwei};
+use crate::leader::Election;
+use crate::metrics::Metrics;
+use crate::wallet::WalletMonitor;
+use alloy::primitives::{TxHash, U256};
+use alloy::primitives::utils::format_ether;
+use anyhow::Result;
+use serde::Serialize;
+use shared::happiness::{HappinessCause, NewHappinessEvent};
+use shared::protection;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{error, info, warn};
+
+/// What one tick did (or, in dry-run, would have done).
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TickSummary {
+    /// Whether this instance held the oracle lease. Followers skip the tick entirely, and
+    /// dry-run never takes part in the election.
+    pub leader: bool,
+    /// Decay was paused by an operator; nothing was read or sent.
+    pub paused: bool,
+    pub agents: usize,
+    pub registrations: usize,
+    pub updates: usize,
+    /// Living agents skipped because a grace, vacation or exemption window covers them.
+    pub protected: usize,
+    /// Updates settling more decay than one tick accrues, i.e. after missed ticks.
+    pub catch_ups: usize,
+    /// Updates carrying earned happiness boosts.
+    pub boosts: usize,
+    pub deaths: usize,
+    /// Activity logs whose root was committed on-chain.
+    pub anchors: usize,
+    /// Transactions sent for the registrations, updates and anchors above.
+    pub transactions: usize,
+    /// Registrations and updates held back by the fee policy until a later tick.
+    pub deferred: usize,
+    /// Summed gas limit of the transactions sent.
+    pub gas: u64,
+    /// Worst-case spend of those transactions, in wei.
+    pub max_spend: u128,
+}
+
+/// A decay or boost update decided during the read phase of a tick.
+struct PlannedUpdate<'a> {
+    agent: &'a AgentRow,
+    token_id: U256,
+    old: u8,
+    new: u8,
+    /// What the decay rule saw, for the attestation.
+    inputs: DecayInputs,
+    /// Ids of the boosts added on top of the decay.
+    boosts: Vec<i64>,
+    /// Happiness they add, for the attestation.
+    boost: u32,
+}
+
+impl PlannedUpdate<'_> {
+    fn cause(&self) -> HappinessCause {
+        match self.boosts.is_empty() {
+            true => HappinessCause::Decay,
+            false => HappinessCause::Boost,
+        }
+    }
+
+    /// Updates that kill the agent go out even when fees spike; plain decay can wait,
+    /// since the next tick recomputes the target from idle time anyway.
+    fn is_urgent(&self) -> bool {
+        self.new == 0
+    }
+}
+
+/// The decay loop: reads agents from SQLite, their happiness from `AgentNFT`,
+/// and pushes decay updates through `DecayOracle`.
+pub struct Oracle {
+    pub chain: Chain,
+    pub db_pool: SqlitePool,
+    /// Log planned transactions instead of sending them; nothing is written to the DB either.
+    pub dry_run: bool,
+    pub wallet: WalletMonitor,
+    pub metrics: Metrics,
+    /// Only the lease holder sends transactions.
+    pub election: Election,
+    pub control: Control,
+    pub catch_up: CatchUp,
+    /// Least time between two anchors of the same agent's activity log.
+    pub anchor_interval: Duration,
+}
+
+impl Oracle {
+    pub async fn run(&self, tick_interval: Duration, once: bool) -> Result<()> {
+        let mut tick = interval(tick_interval);
+        // A slow tick or a suspended process must not trigger a burst of back-to-back
+        // ticks: the next one settles whatever decay was missed in a single update
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                _ = tokio::signal::ctrl_c() => break,
+            }
+            self.tick().await?;
+            if once {
+                break;
+            }
+        }
+        // Hand over right away instead of making the standby wait out the lease
+        self.election.release().await;
+        Ok(())
+    }
+
+    /// One pass: a single aggregated read of every agent's on-chain state, then the
+    /// registrations and updates submitted together in as few transactions as possible.
+    pub async fn tick(&self) -> Result<TickSummary> {
+        let _sends = self.control.lock_sends().await;
+        if self.control.is_paused() {
+            info!("Decay paused by an operator, skipping tick");
+            let summary = TickSummary {
+                paused: true,
+                ..Default::default()
+            };
+            self.metrics.record_tick(unix_now(), &summary);
+            return Ok(summary);
+        }
+        if !self.dry_run && !self.election.hold(unix_now()).await {
+            info!(
+                "Standing by: {} does not hold the oracle lease",
+                self.election.holder
+            );
+            let summary = TickSummary::default();
+            self.metrics.record_tick(unix_now(), &summary);
+            return Ok(summary);
+        }
+
+        info!("=== Decay Tick Started ===");
+        self.log_tick_gap();
+        let rows = db::fetch_living_agents(&self.db_pool).await?;
+        // Without the windows a protected agent would decay, so a failed read fails the tick
+        let protections = db::fetch_protections(&self.db_pool).await?;
+        if !self.dry_run {
+            self.settle_boosts(unix_now()).await?;
+        }
+        let boosts = db::fetch_boosts(&self.db_pool).await?;
+        let mut summary = TickSummary {
+            leader: !self.dry_run,
+            agents: rows.len(),
+            ..Default::default()
+        };
+
+        let agents: Vec<(&AgentRow, U256)> = rows
+            .iter()
+            .filter_map(|agent| match agent.token() {
+                Some(token_id) => Some((agent, token_id)),
+                None => {
+                    warn!("Agent {} has no valid token_id, skipping", agent.agent_id);
+                    None
+                }
+            })
+            .collect();
+        let token_ids: Vec<U256> = agents.iter().map(|(_, token_id)| *token_id).collect();
+        let states = self.chain.read_agent_states(&token_ids).await;
+
+        let now = unix_now();
+        let mut registrations: Vec<(&AgentRow, U256)> = Vec::new();
+        let mut updates: Vec<PlannedUpdate> = Vec::new();
+        for ((agent, token_id), state) in agents.into_iter().zip(states) {
+            let agent_id = &agent.agent_id;
+            let state = match state {
+                Ok(state) => state,
+                Err(e) => {
+                    error!("Profile read failed for {}: {:?}", agent_id, e);
+                    continue;
+                }
+            };
+
+            if !state.registered {
+                registrations.push((agent, token_id));
+            } else {
+                info!(
+                    "Agent {} already registered (tokenId: {})",
+                    agent_id, token_id
+                );
+            }
+
+            if !self.dry_run {
+                db::record(
+                    &self.db_pool,
+                    NewHappinessEvent {
+                        agent_id: agent_id.clone(),
+                        token_id: agent.token_id.clone(),
+                        ts: now,
+                        old_happiness: None,
+                        new_happiness: state.happiness,
+                        cause: HappinessCause::Observed,
+                        tx_hash: None,
+                    },
+                )
+                .await;
+            }
+            if state.happiness == 0 {
+                summary.deaths += 1;
+                if self.dry_run {
+                    info!("[dry-run] Would mark agent {} dead (happiness 0)", agent_id);
+                } else {
+                    db::mark_dead(&self.db_pool, agent_id, now, None).await;
+                }
+                continue;
+            }
+
+            let windows = protections
+                .get(agent_id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let protected_by = protection::active_at(windows, now);
+            let (decayed, inputs) = match protected_by {
+                Some(protection) => {
+                    summary.protected += 1;
+                    info!(
+                        "Agent {} protected from decay ({:?} until {})",
+                        agent_id,
+                        protection.kind,
+                        protection
+                            .ends_at
+                            .map_or("lifted".to_string(), |end| end.to_string())
+                    );
+                    let inputs = DecayInputs {
+                        last_interaction: agent.last_ts,
+                        ..Default::default()
+                    };
+                    (None, inputs)
+                }
+                None => {
+                    let since = state
+                        .last_update
+                        .max(protection::last_ended(windows, now).unwrap_or(0));
+                    let decayed = decayed_happiness(
+                        state.happiness,
+                        agent.last_ts,
+                        since,
+                        now,
+                        self.catch_up.max_step,
+                    );
+                    let owed = decay_owed(agent.last_ts, since, now);
+                    if decayed.is_some() && owed > self.catch_up.per_tick() {
+                        summary.catch_ups += 1;
+                        log_catch_up(agent, since, owed, self.catch_up.max_step, now);
+                    }
+                    let inputs = DecayInputs {
+                        last_interaction: agent.last_ts,
+                        since,
+                        owed,
+                        max_step: self.catch_up.max_step,
+                    };
+                    (decayed, inputs)
+                }
+            };
+
+            let earned = boosts.get(agent_id).map(Vec::as_slice).unwrap_or_default();
+            let boost: u32 = earned.iter().map(|b| b.amount as u32).sum();
+            let new = boosted(decayed.unwrap_or(state.happiness), boost);
+            let boost_ids: Vec<i64> = earned.iter().map(|b| b.id).collect();
+            // Decay offset by a boost still goes out, to settle the decay on-chain
+            if decayed.is_none() && new == state.happiness {
+                if !boost_ids.is_empty() && !self.dry_run {
+                    info!("Agent {} is at full happiness, boosts spent", agent_id);
+                    db::mark_applied(&self.db_pool, &boost_ids, now, None).await;
+                } else if protected_by.is_none() {
+                    info!("Agent {} no decay owed yet", agent_id);
+                }
+                continue;
+            }
+            if !boost_ids.is_empty() {
+                summary.boosts += 1;
+                info!(
+                    "Agent {} earned {} happiness from {} boosts",
+                    agent_id,
+                    boost,
+                    boost_ids.len()
+                );
+            }
+            updates.push(PlannedUpdate {
+                agent,
+                token_id,
+                old: state.happiness,
+                new,
+                inputs,
+                boosts: boost_ids,
+                boost,
+            });
+        }
+
+        let wallet_low = self.check_wallet().await;
+        let (base_fee, mut decision) = match self.chain.base_fee().await {
+            Ok(base_fee) => (base_fee, self.chain.fees.decide(base_fee)),
+            Err(e) => {
+                error!("Base fee read failed, holding all transactions: {:?}", e);
+                (0, FeeDecision::Hold)
+            }
+        };
+        if wallet_low && decision == FeeDecision::SendAll {
+            decision = FeeDecision::UrgentOnly;
+        }
+        let planned = registrations.len() + updates.len();
+        let (registrations, updates) = apply_fee_decision(decision, registrations, updates);
+        summary.deferred = planned - registrations.len() - updates.len();
+        if decision != FeeDecision::SendAll {
+            warn!(
+                "Base fee {} gwei, {:?}: deferring {} of {} planned registrations and updates",
+                format_gwei(base_fee),
+                decision,
+                summary.deferred,
+                planned
+            );
+        }
+
+        summary.registrations = registrations.len();
+        summary.updates = updates.len();
+        summary.deaths += updates.iter().filter(|u| u.new == 0).count();
+
+        if self.dry_run {
+            summary.transactions =
+                self.transaction_count(registrations.len()) + self.transaction_count(updates.len());
+            for (agent, token_id) in &registrations {
+                info!(
+                    "[dry-run] Would register agent {} (tokenId: {})",
+                    agent.agent_id, token_id
+                );
+            }
+            for update in &updates {
+                info!(
+                    "[dry-run] Would update agent {} ({:?}): {} -> {} ({} hours idle)",
+                    update.agent.agent_id,
+                    update.cause(),
+                    update.old,
+                    update.new,
+                    hours_idle(update.agent.last_ts, now)
+                );
+            }
+            if decision == FeeDecision::SendAll {
+                let anchors = self.plan_anchors(now).await;
+                for anchor in &anchors {
+                    info!(
+                        "[dry-run] Would anchor {} messages of {} as {}",
+                        anchor.size, anchor.agent_id, anchor.root
+                    );
+                }
+                summary.anchors = anchors.len();
+            }
+            info!(
+                "[dry-run] {} agents: would send {} registrations and {} happiness updates in {} txs ({} deaths, {} deferred)",
+                summary.agents,
+                summary.registrations,
+                summary.updates,
+                summary.transactions,
+                summary.deaths,
+                summary.deferred
+            );
+        } else if !self.election.hold(unix_now()).await {
+            // The lease lapsed while reading (slow RPC, paused process): another instance
+            // may already be sending, so this one must not
+            summary.leader = false;
+            summary.deferred += registrations.len() + updates.len();
+        } else {
+            let mut meter = self.chain.fees.meter(base_fee);
+            // Same sender, so the registrations' nonces order them before the updates
+            let unregistered = self.send_registrations(&registrations, &mut meter).await;
+            // An update for a token whose registration didn't go out would only revert
+            let (updates, blocked): (Vec<PlannedUpdate>, Vec<PlannedUpdate>) = updates
+                .into_iter()
+                .partition(|u| !unregistered.contains(&u.token_id));
+            summary.deferred += unregistered.len() + blocked.len();
+            summary.deferred += self.send_updates(&updates, now, &mut meter).await;
+            // Anchors never matter enough to spend into a fee spike or a low wallet
+            if decision == FeeDecision::SendAll {
+                let anchors = self.plan_anchors(now).await;
+                summary.anchors = self.send_anchors(&anchors, now, &mut meter).await;
+            }
+            summary.transactions = meter.transactions;
+            summary.gas = meter.gas;
+            summary.max_spend = meter.max_spend();
+            self.wallet.record_spend(meter.expected_spend());
+            info!(
+                "Tick spend: {} txs, {} gas, ~{} gwei expected, at most {} gwei (base fee {} gwei, max fee {} gwei)",
+                meter.transactions,
+                meter.gas,
+                format_gwei(meter.expected_spend()),
+                format_gwei(meter.max_spend()),
+                format_gwei(meter.base_fee),
+                format_gwei(meter.max_fee_per_gas)
+            );
+        }
+
+        self.metrics.record_tick(now, &summary);
+        info!("=== Decay Tick Complete ===");
+        Ok(summary)
+    }
+
+    /// Warns when the previous tick in this process is older than the interval allows.
+    fn log_tick_gap(&self) {
+        let Some(last) = self.metrics.snapshot().last_tick_at else {
+            return;
+        };
+        let gap = unix_now() - last;
+        let interval = self.catch_up.tick_interval.as_secs() as i64;
+        if gap > 2 * interval {
+            warn!(
+                "No tick for {}s (interval {}s); owed decay is settled in one update per agent, at most {} each",
+                gap, interval, self.catch_up.max_step
+            );
+        }
+    }
+
+    /// Checks the signer's balance and nonce gap and publishes them. Returns whether the
+    /// wallet is low enough that non-critical work should pause.
+    async fn check_wallet(&self) -> bool {
+        let (address, balance, nonce_gap) = match self.chain.signer_status().await {
+            Ok(Some(status)) => status,
+            Ok(None) => return false,
+            Err(e) => {
+                error!("Wallet health check failed: {:?}", e);
+                return false;
+            }
+        };
+        let health = self.wallet.assess(address, balance, nonce_gap);
+        if nonce_gap > 0 {
+            warn!(
+                "{} transactions from {} still pending from earlier ticks",
+                nonce_gap, address
+            );
+        }
+        if health.low {
+            warn!(
+                "Oracle wallet {} is low: {} ETH left, runway {} (minimum {}h); pausing non-urgent updates",
+                address,
+                format_ether(balance),
+                health
+                    .runway
+                    .map_or("unknown".to_string(), |r| format!("{}h", r.as_secs() / 3600)),
+                self.wallet.min_runway.as_secs() / 3600
+            );
+        } else {
+            info!(
+                "Oracle wallet {}: {} ETH, {} wei/tick recent spend",
+                address,
+                format_ether(balance),
+                health.spend_per_tick
+            );
+        }
+        let low = health.low;
+        self.metrics.record_wallet(health);
+        low
+    }
+
+    fn transaction_count(&self, items: usize) -> usize {
+        if self.chain.supports_batch {
+            items.div_ceil(self.chain.batch_size)
+        } else {
+            items
+        }
+    }
+
+    /// Returns the tokens left unregistered, deferred or failed; both are retried next tick.
+    async fn send_registrations(
+        &self,
+        registrations: &[(&AgentRow, U256)],
+        meter: &mut GasMeter,
+    ) -> Vec<U256> {
+        if registrations.is_empty() {
+            return Vec::new();
+        }
+        let token_ids: Vec<U256> = registrations.iter().map(|(_, token_id)| *token_id).collect();
+        let results = self.chain.register_agents(&token_ids, meter).await;
+        let mut unregistered = Vec::new();
+        for ((agent, token_id), result) in registrations.iter().zip(results) {
+            match result {
+                Ok(tx_hash) => {
+                    info!(
+                        "Registered {} (tokenId: {}) via tx: {}",
+                        agent.agent_id, token_id, tx_hash
+                    );
+                    self.control.track(
+                        tx_hash,
+                        TxPurpose::Register,
+                        token_id.to_string(),
+                        unix_now(),
+                    );
+                    continue;
+                }
+                Err(e) if e.is::<Deferred>() => info!("Registration of {} {}", agent.agent_id, e),
+                Err(e) => error!("Registration failed for {}: {:?}", agent.agent_id, e),
+            }
+            unregistered.push(*token_id);
+        }
+        unregistered
+    }
+
+    /// Returns how many updates the gas budget deferred.
+    async fn send_updates(
+        &self,
+        updates: &[PlannedUpdate<'_>],
+        now: i64,
+        meter: &mut GasMeter,
+    ) -> usize {
+        if updates.is_empty() {
+            return 0;
+        }
+        let batch: Vec<(U256, u8)> = updates.iter().map(|u| (u.token_id, u.new)).collect();
+        let results = self.chain.update_happiness(&batch, meter).await;
+        let mut deferred = 0;
+        let mut outcomes = HashMap::new();
+        for (update, result) in updates.iter().zip(results) {
+            let agent_id = &update.agent.agent_id;
+            let cause = update.cause();
+            match cause {
+                HappinessCause::Boost => info!(
+                    "Agent {} boosted: {} -> {} ({} boosts, {} hours idle)",
+                    agent_id,
+                    update.old,
+                    update.new,
+                    update.boosts.len(),
+                    hours_idle(update.agent.last_ts, now)
+                ),
+                _ => info!(
+                    "Agent {} decaying: {} -> {} ({} hours idle)",
+                    agent_id,
+                    update.old,
+                    update.new,
+                    hours_idle(update.agent.last_ts, now)
+                ),
+            }
+            let purpose = match cause {
+                HappinessCause::Boost => TxPurpose::Boost,
+                _ => TxPurpose::Decay,
+            };
+            let hash = match result {
+                Ok(tx_hash) => {
+                    self.control
+                        .track(tx_hash, purpose, update.token_id.to_string(), now);
+                    let attestation = attestation::attestation(
+                        update.token_id,
+                        update.old,
+                        update.new,
+                        update.inputs,
+                        now,
+                        cause,
+                        update.boost,
+                    );
+                    self.attest(agent_id, &attestation, tx_hash).await;
+                    tx_hash
+                }
+                Err(e) if e.is::<Deferred>() => {
+                    deferred += 1;
+                    info!("Update of {} {}", agent_id, e);
+                    continue;
+                }
+                Err(e) => {
+                    error!("Tx failed for {}: {:?}", agent_id, e);
+                    continue;
+                }
+            };
+            let tx_hash = hash.to_string();
+            info!("Updated {} happiness via tx: {}", agent_id, tx_hash);
+            db::record(
+                &self.db_pool,
+                NewHappinessEvent {
+                    agent_id: agent_id.clone(),
+                    token_id: update.agent.token_id.clone(),
+                    ts: now,
+                    old_happiness: Some(update.old),
+                    new_happiness: update.new,
+                    cause,
+                    tx_hash: Some(tx_hash.clone()),
+                },
+            )
+            .await;
+            if !update.boosts.is_empty() {
+                db::mark_sent(&self.db_pool, &update.boosts, &tx_hash).await;
+            }
+            if update.boosts.is_empty() && update.new != 0 {
+                continue;
+            }
+            // Boosts and deaths are only final once the update is on-chain; if it is still
+            // pending the next tick settles the boosts and reads the happiness back
+            let outcome = self.outcome(hash, &mut outcomes).await;
+            if !update.boosts.is_empty() {
+                match outcome {
+                    Some(true) => {
+                        db::mark_applied(&self.db_pool, &update.boosts, now, Some(&tx_hash)).await
+                    }
+                    Some(false) => db::release(&self.db_pool, &tx_hash).await,
+                    None => {}
+                }
+            }
+            if update.new == 0 && outcome == Some(true) {
+                db::mark_dead(&self.db_pool, agent_id, now, Some(&tx_hash)).await;
+            }
+        }
+        deferred
+    }
+
+    /// [`Chain::outcome`], asked once per transaction: a batch carries several updates.
+    async fn outcome(
+        &self,
+        hash: TxHash,
+        outcomes: &mut HashMap<TxHash, Option<bool>>,
+    ) -> Option<bool> {
+        if let Some(outcome) = outcomes.get(&hash) {
+            return *outcome;
+        }
+        let outcome = self.chain.outcome(hash).await;
+        outcomes.insert(hash, outcome);
+        outcome
+    }
+
+    /// Applies the boosts of updates a previous tick sent once they are mined, and makes
+    /// them pending again if the update reverted.
+    async fn settle_boosts(&self, now: i64) -> Result<()> {
+        for (tx_hash, ids) in db::fetch_sent(&self.db_pool).await? {
+            let Ok(hash) = tx_hash.parse::<TxHash>() else {
+                warn!("Boosts {:?} sent in unreadable tx {:?}", ids, tx_hash);
+                continue;
+            };
+            match self.chain.confirm(hash, Duration::ZERO).await {
+                Ok(Some(true)) => {
+                    db::mark_applied(&self.db_pool, &ids, now, Some(&tx_hash)).await
+                }
+                Ok(Some(false)) => db::release(&self.db_pool, &tx_hash).await,
+                Ok(None) => info!("Boosts {:?} still waiting on tx {}", ids, tx_hash),
+                Err(e) => warn!("Receipt of {} unavailable: {:?}", tx_hash, e),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn log_catch_up(agent: &AgentRow, since: i64, owed: u32, max_step: u8, now: i64) {
+    let since = since.max(agent.last_ts);
+    let gap_hours = (now - since) as f64 / 3600.0;
+    if owed > max_step as u32 {
+        warn!(
+            "Catching up agent {}: {:.1}h since its last oracle update or protection, {} decay owed, capped at {} ({} forgiven)",
+            agent.agent_id,
+            gap_hours,
+            owed,
+            max_step,
+            owed - max_step as u32
+        );
+    } else {
+        info!(
+            "Catching up agent {}: {:.1}h since its last oracle update or protection, applying {} owed decay",
+            agent.agent_id, gap_hours, owed
+        );
+    }
+}
+
+/// Drops what the fee decision holds back. Under [`FeeDecision::UrgentOnly`] a
+/// registration is kept only if an urgent update for the same token depends on it.
+fn apply_fee_decision<'a, 'b>(
+    decision: FeeDecision,
+    registrations: Vec<(&'a AgentRow, U256)>,
+    updates: Vec<PlannedUpdate<'b>>,
+) -> (Vec<(&'a AgentRow, U256)>, Vec<PlannedUpdate<'b>>) {
+    match decision {
+        FeeDecision::SendAll => (registrations, updates),
+        FeeDecision::Hold => (Vec::new(), Vec::new()),
+        FeeDecision::UrgentOnly => {
+            let updates: Vec<PlannedUpdate> = updates.into_iter().filter(|u| u.is_urgent()).collect();
+            let registrations = registrations
+                .into_iter()
+                .filter(|(_, token_id)| updates.iter().any(|u| u.token_id == *token_id))
+                .collect();
+            (registrations, updates)
+        }
+    }
+}
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
@@ -0,0 +1,255 @@
+//! Where the oracle's signing key comes from: a raw hex key, an encrypted JSON keystore, or
+//! an external signer reached over JSON-RPC (HTTP or a local IPC/Unix socket).
+
+use crate::attestation::AttestationSigner;
+use alloy::consensus::{SignableTransaction, TxEnvelope};
+use alloy::dyn_abi::TypedData;
+use alloy::eips::Decodable2718;
+use alloy::hex;
+use alloy::network::{EthereumWallet, TxSigner};
+use alloy::primitives::{Address, B256, Bytes, Signature};
+use alloy::rpc::client::{ClientBuilder, RpcClient};
+use alloy::rpc::types::{TransactionInput, TransactionRequest};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::ipc::IpcConnect;
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// How to obtain the signer, chosen from the environment by [`crate::config::Config`].
+#[derive(Clone, Debug)]
+pub enum SignerSource {
+    /// Hex-encoded key, read straight from `ORACLE_PRIVATE_KEY`.
+    PrivateKey(String),
+    /// Encrypted JSON keystore, unlocked with the password in `password_file`.
+    Keystore {
+        path: PathBuf,
+        password_file: PathBuf,
+    },
+    /// External signer answering `eth_signTransaction`; the key never enters this process.
+    Remote {
+        endpoint: SignerEndpoint,
+        /// Account to sign with; defaults to the signer's first `eth_accounts` entry.
+        address: Option<Address>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignerEndpoint {
+    Http(url::Url),
+    Ipc(PathBuf),
+}
+
+impl SignerEndpoint {
+    /// `http(s)://` URLs go over HTTP; anything else is a socket path (`unix://` optional).
+    pub fn parse(endpoint: &str) -> Result<Self> {
+        if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+            return Ok(SignerEndpoint::Http(
+                endpoint.parse().context("Invalid signer URL")?,
+            ));
+        }
+        let path = endpoint.strip_prefix("unix://").unwrap_or(endpoint);
+        if path.is_empty() {
+            bail!("Empty signer socket path");
+        }
+        Ok(SignerEndpoint::Ipc(PathBuf::from(path)))
+    }
+
+    async fn connect(&self) -> Result<RpcClient> {
+        match self {
+            SignerEndpoint::Http(url) => Ok(ClientBuilder::default().http(url.clone())),
+            SignerEndpoint::Ipc(path) => ClientBuilder::default()
+                .ipc(IpcConnect::new(path.clone()))
+                .await
+                .with_context(|| format!("Failed to connect to signer at {}", path.display())),
+        }
+    }
+}
+
+impl SignerSource {
+    /// Resolves the source into a wallet for the provider.
+    pub async fn load(&self) -> Result<EthereumWallet> {
+        Ok(self.load_with_attester().await?.0)
+    }
+
+    /// Resolves the source into a wallet, plus the same key as an attestation signer.
+    pub async fn load_with_attester(
+        &self,
+    ) -> Result<(EthereumWallet, Arc<dyn AttestationSigner>)> {
+        match self {
+            SignerSource::PrivateKey(private_key_hex) => {
+                warn!(
+                    "Using a raw ORACLE_PRIVATE_KEY; prefer ORACLE_KEYSTORE or ORACLE_SIGNER_URL"
+                );
+                let signer = parse_signer(private_key_hex)?;
+                Ok((EthereumWallet::new(signer.clone()), Arc::new(signer)))
+            }
+            SignerSource::Keystore {
+                path,
+                password_file,
+            } => {
+                let password = std::fs::read_to_string(password_file).with_context(|| {
+                    format!("Failed to read password file {}", password_file.display())
+                })?;
+                let signer = PrivateKeySigner::decrypt_keystore(
+                    path,
+                    password.trim_end_matches(['\r', '\n']),
+                )
+                .with_context(|| format!("Failed to decrypt keystore {}", path.display()))?;
+                info!("Loaded oracle signer {} from keystore", signer.address());
+                Ok((EthereumWallet::new(signer.clone()), Arc::new(signer)))
+            }
+            SignerSource::Remote { endpoint, address } => {
+                let signer = RemoteSigner::connect(endpoint, *address).await?;
+                info!("Using external signer {} at {:?}", signer.address, endpoint);
+                Ok((EthereumWallet::new(signer.clone()), Arc::new(signer)))
+            }
+        }
+    }
+}
+
+/// Hex to bytes -> B256
+pub fn parse_signer(private_key_hex: &str) -> Result<PrivateKeySigner> {
+    let private_key_bytes =
+        hex::decode(private_key_hex.trim_start_matches("0x")).context("Invalid hex key")?;
+    let key_b256 = B256::try_from(private_key_bytes.as_slice()).context("Invalid key length")?;
+    PrivateKeySigner::from_bytes(&key_b256).context("Invalid private key")
+}
+
+/// Signs through an external process (Clef, Web3Signer, a KMS proxy, ...) with
+/// `eth_signTransaction`, then checks the result before trusting it.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: RpcClient,
+    address: Address,
+}
+
+impl RemoteSigner {
+    pub async fn connect(endpoint: &SignerEndpoint, address: Option<Address>) -> Result<Self> {
+        let client = endpoint.connect().await?;
+        let accounts: Vec<Address> = client
+            .request_noparams("eth_accounts")
+            .await
+            .context("External signer did not answer eth_accounts")?;
+        let address = match address {
+            Some(address) if accounts.contains(&address) => address,
+            Some(address) => bail!("External signer does not manage {}", address),
+            None => *accounts
+                .first()
+                .context("External signer has no accounts")?,
+        };
+        Ok(RemoteSigner { client, address })
+    }
+
+    async fn sign(&self, tx: &dyn SignableTransaction<Signature>) -> Result<Signature> {
+        if tx.ty() > 2 {
+            bail!(
+                "External signing of type {} transactions is unsupported",
+                tx.ty()
+            );
+        }
+        let request = TransactionRequest {
+            from: Some(self.address),
+            to: Some(tx.kind()),
+            gas_price: tx.gas_price(),
+            max_fee_per_gas: tx.is_dynamic_fee().then(|| tx.max_fee_per_gas()),
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas(),
+            gas: Some(tx.gas_limit()),
+            value: Some(tx.value()),
+            input: TransactionInput::new(tx.input().clone()),
+            nonce: Some(tx.nonce()),
+            chain_id: tx.chain_id(),
+            access_list: tx.access_list().cloned(),
+            transaction_type: Some(tx.ty()),
+            ..Default::default()
+        };
+        let response: Value = self
+            .client
+            .request("eth_signTransaction", (request,))
+            .await
+            .context("eth_signTransaction failed")?;
+        let raw: Bytes = match &response {
+            // Clef and geth wrap the raw transaction in `{ raw, tx }`
+            Value::Object(object) => serde_json::from_value(object["raw"].clone()),
+            _ => serde_json::from_value(response),
+        }
+        .context("Malformed eth_signTransaction response")?;
+
+        let envelope = TxEnvelope::decode_2718(&mut raw.as_ref())
+            .context("External signer returned an undecodable transaction")?;
+        if envelope.signature_hash() != tx.signature_hash() {
+            bail!("External signer altered the transaction");
+        }
+        let recovered = envelope
+            .signature()
+            .recover_address_from_prehash(&envelope.signature_hash())
+            .context("External signer returned an invalid signature")?;
+        if recovered != self.address {
+            bail!(
+                "External signer signed as {} instead of {}",
+                recovered,
+                self.address
+            );
+        }
+        Ok(*envelope.signature())
+    }
+
+    /// Signs EIP-712 data with `eth_signTypedData_v4`, checking who signed.
+    pub async fn sign_typed_data(&self, typed_data: &TypedData) -> Result<Signature> {
+        let signature: Signature = self
+            .client
+            .request("eth_signTypedData_v4", (self.address, typed_data))
+            .await
+            .context("eth_signTypedData_v4 failed")?;
+        let recovered = signature
+            .recover_address_from_prehash(&typed_data.eip712_signing_hash()?)
+            .context("External signer returned an invalid signature")?;
+        if recovered != self.address {
+            bail!(
+                "External signer signed typed data as {} instead of {}",
+                recovered,
+                self.address
+            );
+        }
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl TxSigner<Signature> for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        self.sign(tx).await.map_err(alloy::signers::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_endpoints() {
+        assert_eq!(
+            SignerEndpoint::parse("http://127.0.0.1:8550").unwrap(),
+            SignerEndpoint::Http("http://127.0.0.1:8550".parse().unwrap())
+        );
+        assert_eq!(
+            SignerEndpoint::parse("unix:///run/signer.ipc").unwrap(),
+            SignerEndpoint::Ipc(PathBuf::from("/run/signer.ipc"))
+        );
+        assert_eq!(
+            SignerEndpoint::parse("/run/signer.ipc").unwrap(),
+            SignerEndpoint::Ipc(PathBuf::from("/run/signer.ipc"))
+        );
+        assert!(SignerEndpoint::parse("unix://").is_err());
+    }
+}
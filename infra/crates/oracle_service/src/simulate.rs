@@ -0,0 +1,164 @@
+//! Offline replay of the decay rules: no RPC, no transactions, no DB writes.
+
+use crate::db;
+use crate::decay::{BASELINE_HAPPINESS, decayed_happiness};
+use crate::service::unix_now;
+use anyhow::Result;
+use std::time::Duration;
+
+const SECS_PER_DAY: i64 = 24 * 3600;
+
+/// Starting point of one agent in the simulation.
+#[derive(Clone, Debug)]
+pub struct SimAgent {
+    pub agent_id: String,
+    pub happiness: u8,
+    pub last_ts: i64,
+    /// Last oracle update before the simulation; 0 if none.
+    pub last_update: i64,
+}
+
+/// How an agent's happiness evolved over the simulated days.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectory {
+    pub agent_id: String,
+    pub start: u8,
+    /// Happiness at the end of each simulated day.
+    pub daily: Vec<u8>,
+    /// Number of updates the live oracle would have sent.
+    pub updates: usize,
+    /// Seconds after the simulation start at which happiness hit 0.
+    pub died_after: Option<i64>,
+}
+
+/// Steps a virtual clock from `start` for `days` days, applying the live decay rules.
+/// Nobody interacts during the simulation, so `last_ts` stays fixed.
+pub fn simulate(
+    agents: &[SimAgent],
+    start: i64,
+    days: u32,
+    step: Duration,
+    max_catch_up: u8,
+) -> Vec<Trajectory> {
+    let step = step.as_secs().max(1) as i64;
+    agents
+        .iter()
+        .map(|agent| {
+            let mut happiness = agent.happiness;
+            let mut last_update = agent.last_update;
+            let mut trajectory = Trajectory {
+                agent_id: agent.agent_id.clone(),
+                start: happiness,
+                daily: Vec::with_capacity(days as usize),
+                updates: 0,
+                died_after: None,
+            };
+            for day in 1..=days as i64 {
+                let day_end = start + day * SECS_PER_DAY;
+                let mut now = start + (day - 1) * SECS_PER_DAY + step;
+                while now <= day_end {
+                    if happiness > 0
+                        && let Some(new_happiness) = decayed_happiness(
+                            happiness,
+                            agent.last_ts,
+                            last_update,
+                            now,
+                            max_catch_up,
+                        )
+                    {
+                        happiness = new_happiness;
+                        last_update = now;
+                        trajectory.updates += 1;
+                        if happiness == 0 {
+                            trajectory.died_after = Some(now - start);
+                        }
+                    }
+                    now += step;
+                }
+                trajectory.daily.push(happiness);
+            }
+            trajectory
+        })
+        .collect()
+}
+
+/// Loads living agents from the SQLite snapshot and prints their simulated trajectories.
+/// Agents start from their last recorded happiness, or the mint baseline if never observed.
+pub async fn run(db_url: &str, days: u32, step: Duration, max_catch_up: u8) -> Result<()> {
+    let db_pool = db::connect(db_url).await?;
+    let mut agents = Vec::new();
+    for row in db::fetch_living_agents(&db_pool).await? {
+        let happiness = db::latest_happiness(&db_pool, &row.agent_id)
+            .await?
+            .unwrap_or(BASELINE_HAPPINESS);
+        let last_update = db::last_oracle_update(&db_pool, &row.agent_id)
+            .await?
+            .unwrap_or(0);
+        agents.push(SimAgent {
+            agent_id: row.agent_id,
+            happiness,
+            last_ts: row.last_ts,
+            last_update,
+        });
+    }
+
+    println!(
+        "Simulating {} agents over {} days (step {} min)",
+        agents.len(),
+        days,
+        step.as_secs() / 60
+    );
+    for trajectory in simulate(&agents, unix_now(), days, step, max_catch_up) {
+        let days = trajectory
+            .daily
+            .iter()
+            .enumerate()
+            .map(|(i, h)| format!("d{}={}", i + 1, h))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let fate = match trajectory.died_after {
+            Some(secs) => format!("died after {:.1}h", secs as f64 / 3600.0),
+            None => "survived".to_string(),
+        };
+        println!(
+            "{}: start={} {} | {} updates, {}",
+            trajectory.agent_id, trajectory.start, days, trajectory.updates, fate
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_agent_decays_to_death() {
+        let agents = vec![SimAgent {
+            agent_id: "a".to_string(),
+            happiness: 80,
+            last_ts: 0,
+            last_update: 0,
+        }];
+        let trajectories = simulate(&agents, 0, 2, Duration::from_secs(3600), 20);
+        let t = &trajectories[0];
+        // 80 - 5/hour reaches 0 after 16 idle hours
+        assert_eq!(t.daily, vec![0, 0]);
+        assert_eq!(t.died_after, Some(16 * 3600));
+        assert_eq!(t.updates, 15);
+    }
+
+    #[test]
+    fn first_step_after_a_gap_is_capped() {
+        let agents = vec![SimAgent {
+            agent_id: "a".to_string(),
+            happiness: 80,
+            last_ts: 0,
+            last_update: 0,
+        }];
+        let trajectories = simulate(&agents, 10 * 3600, 1, Duration::from_secs(3600), 20);
+        // 55 owed at the first step, 20 applied; then 5/hour for 23 hours
+        assert_eq!(trajectories[0].daily, vec![0]);
+        assert_eq!(trajectories[0].died_after, Some(13 * 3600));
+    }
+}
@@ -0,0 +1,128 @@
+//! Oracle signer health: balance, transactions stuck in the mempool, and how long the
+//! balance lasts at the recent burn rate.
+
+use alloy::primitives::{Address, U256};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Ticks of spend history the burn rate is averaged over.
+pub const SPEND_WINDOW: usize = 60;
+
+/// One health check of the signer, taken at the start of a tick.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalletHealth {
+    pub address: Address,
+    pub balance: U256,
+    /// Transactions sent but not yet mined: pending nonce minus latest nonce.
+    pub nonce_gap: u64,
+    /// Average expected spend per tick over the recent window, in wei.
+    pub spend_per_tick: u128,
+    /// How long the balance lasts at that rate; `None` while nothing is being spent.
+    pub runway: Option<Duration>,
+    /// Below the configured runway (or empty): only urgent work goes out.
+    pub low: bool,
+}
+
+/// Tracks recent spend to turn a balance into a runway.
+#[derive(Debug)]
+pub struct WalletMonitor {
+    pub min_runway: Duration,
+    pub tick_interval: Duration,
+    recent: Mutex<VecDeque<u128>>,
+}
+
+impl WalletMonitor {
+    pub fn new(min_runway: Duration, tick_interval: Duration) -> Self {
+        WalletMonitor {
+            min_runway,
+            tick_interval,
+            recent: Mutex::new(VecDeque::with_capacity(SPEND_WINDOW)),
+        }
+    }
+
+    /// Records the expected spend of a finished tick, in wei.
+    pub fn record_spend(&self, wei: u128) {
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == SPEND_WINDOW {
+            recent.pop_front();
+        }
+        recent.push_back(wei);
+    }
+
+    pub fn assess(&self, address: Address, balance: U256, nonce_gap: u64) -> WalletHealth {
+        let spend_per_tick = {
+            let recent = self.recent.lock().unwrap();
+            match recent.len() {
+                0 => 0,
+                n => recent.iter().sum::<u128>() / n as u128,
+            }
+        };
+        let runway = runway(balance, spend_per_tick, self.tick_interval);
+        WalletHealth {
+            address,
+            balance,
+            nonce_gap,
+            spend_per_tick,
+            runway,
+            low: balance.is_zero() || runway.is_some_and(|r| r < self.min_runway),
+        }
+    }
+}
+
+/// Ticks the balance covers at `spend_per_tick`, as wall-clock time.
+pub fn runway(balance: U256, spend_per_tick: u128, tick_interval: Duration) -> Option<Duration> {
+    if spend_per_tick == 0 {
+        return None;
+    }
+    let ticks = balance / U256::from(spend_per_tick);
+    let secs = u64::try_from(ticks)
+        .unwrap_or(u64::MAX)
+        .saturating_mul(tick_interval.as_secs());
+    Some(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn runway_from_recent_spend() {
+        let monitor = WalletMonitor::new(24 * HOUR, Duration::from_secs(60));
+        let health = monitor.assess(Address::ZERO, U256::from(1_000), 0);
+        assert_eq!(health.runway, None);
+        assert!(!health.low);
+
+        monitor.record_spend(0);
+        monitor.record_spend(2);
+        // 1 wei per minute: 1000 minutes is under a day
+        let health = monitor.assess(Address::ZERO, U256::from(1_000), 0);
+        assert_eq!(health.spend_per_tick, 1);
+        assert_eq!(health.runway, Some(Duration::from_secs(60_000)));
+        assert!(health.low);
+
+        let health = monitor.assess(Address::ZERO, U256::from(10_000), 0);
+        assert!(!health.low);
+    }
+
+    #[test]
+    fn empty_wallet_is_low() {
+        let monitor = WalletMonitor::new(HOUR, HOUR);
+        assert!(monitor.assess(Address::ZERO, U256::ZERO, 0).low);
+    }
+
+    #[test]
+    fn window_drops_old_ticks() {
+        let monitor = WalletMonitor::new(HOUR, HOUR);
+        monitor.record_spend(1_000_000);
+        for _ in 0..SPEND_WINDOW {
+            monitor.record_spend(10);
+        }
+        assert_eq!(
+            monitor.assess(Address::ZERO, U256::ZERO, 0).spend_per_tick,
+            10
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! The admin router in-process, over an in-memory DB and a chain that is never reached:
+//! everything here must be answered before the oracle would touch the RPC node.
+
+use alloy::primitives::Address;
+use alloy::providers::{Provider, ProviderBuilder};
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use http_body_util::BodyExt;
+use oracle_service::admin;
+use oracle_service::blockchain::{AgentNFT, Chain, DecayOracle, IMulticall3};
+use oracle_service::control::Control;
+use oracle_service::decay::CatchUp;
+use oracle_service::fees::FeePolicy;
+use oracle_service::leader::Election;
+use oracle_service::metrics::Metrics;
+use oracle_service::service::Oracle;
+use oracle_service::wallet::WalletMonitor;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+const TOKEN: &str = "admin-token-for-tests";
+
+async fn app(dry_run: bool) -> (Arc<Oracle>, Router) {
+    let db_pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::migrate!("../ai_execution/migrations")
+        .run(&db_pool)
+        .await
+        .unwrap();
+    // Nothing listens on port 1
+    let provider = ProviderBuilder::new()
+        .connect_http("http://127.0.0.1:1".parse().unwrap())
+        .erased();
+    let chain = Chain {
+        oracle: DecayOracle::new(Address::repeat_byte(1), provider.clone()),
+        agent_nft: AgentNFT::new(Address::repeat_byte(2), provider.clone()),
+        multicall: IMulticall3::new(Address::repeat_byte(3), provider),
+        supports_batch: true,
+        supports_anchoring: false,
+        batch_size: 100,
+        fees: FeePolicy::default(),
+        signer: None,
+        attester: None,
+    };
+    let oracle = Arc::new(Oracle {
+        chain,
+        db_pool,
+        dry_run,
+        wallet: WalletMonitor::new(Duration::from_secs(3600), Duration::from_secs(60)),
+        metrics: Metrics::default(),
+        election: Election::standalone(),
+        control: Control::default(),
+        catch_up: CatchUp {
+            max_step: 20,
+            tick_interval: Duration::from_secs(60),
+        },
+        anchor_interval: Duration::from_secs(3600),
+    });
+    let router = admin::router(oracle.clone(), TOKEN.to_string());
+    (oracle, router)
+}
+
+async fn call(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        builder = builder.header("Authorization", format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    (status, body)
+}
+
+#[tokio::test]
+async fn rejects_missing_or_wrong_token() {
+    let (_, router) = app(false).await;
+    let (status, _) = call(&router, "GET", "/status", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&router, "GET", "/status", Some("nope"), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&router, "GET", "/status", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn pause_skips_ticks_and_is_audited() {
+    let (oracle, router) = app(false).await;
+
+    let (status, changed) = call(
+        &router,
+        "POST",
+        "/pause",
+        Some(TOKEN),
+        Some(json!({ "reason": "contract upgrade" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(changed, json!(true));
+    assert!(oracle.control.is_paused());
+    // Pausing twice is a no-op
+    let (_, changed) = call(&router, "POST", "/pause", Some(TOKEN), None).await;
+    assert_eq!(changed, json!(false));
+
+    // A paused tick never reaches the (unreachable) chain
+    let (status, summary) = call(&router, "POST", "/tick", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary["paused"], json!(true));
+
+    let (_, status_body) = call(&router, "GET", "/status", Some(TOKEN), None).await;
+    assert_eq!(status_body["paused"], json!(true));
+    assert_eq!(status_body["ticks"], json!(1));
+    assert_eq!(status_body["last_tick"]["paused"], json!(true));
+
+    let (_, changed) = call(&router, "POST", "/resume", Some(TOKEN), None).await;
+    assert_eq!(changed, json!(true));
+    assert!(!oracle.control.is_paused());
+
+    let (status, audit) = call(&router, "GET", "/audit?limit=10", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let actions: Vec<&str> = audit
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, vec!["resume", "tick", "pause"]);
+    assert_eq!(audit[2]["reason"], json!("contract upgrade"));
+}
+
+#[tokio::test]
+async fn validates_manual_happiness() {
+    let (_, router) = app(false).await;
+    let set = |body: Value| call(&router, "POST", "/happiness", Some(TOKEN), Some(body));
+
+    let (status, _) = set(json!({ "token_id": "1", "happiness": 50, "reason": "  " })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = set(json!({ "token_id": "1", "happiness": 101, "reason": "x" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = set(json!({ "token_id": "abc", "happiness": 50, "reason": "x" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn dry_run_refuses_to_send() {
+    let (_, router) = app(true).await;
+    let (status, _) = call(
+        &router,
+        "POST",
+        "/happiness",
+        Some(TOKEN),
+        Some(json!({ "token_id": "1", "happiness": 50, "reason": "support ticket" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = call(
+        &router,
+        "POST",
+        "/register",
+        Some(TOKEN),
+        Some(json!({ "token_id": "1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, pending) = call(&router, "GET", "/pending", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(pending, json!([]));
+}
+
+#[tokio::test]
+async fn grants_and_lifts_exemptions() {
+    let (oracle, router) = app(false).await;
+    sqlx::query("INSERT INTO agents (agent_id, owner_address) VALUES ('a1', '0xowner')")
+        .execute(&oracle.db_pool)
+        .await
+        .unwrap();
+    let router = &router;
+    let exempt = |agent: &str, body: Value| {
+        let uri = format!("/agents/{}/exemptions", agent);
+        async move { call(router, "POST", &uri, Some(TOKEN), Some(body)).await }
+    };
+
+    let (status, _) = exempt("a1", json!({ "reason": "" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = exempt("missing", json!({ "reason": "x" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, exemption) = exempt("a1", json!({ "reason": "community moderator" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(exemption["kind"], json!("exemption"));
+    assert_eq!(exemption["ends_at"], Value::Null);
+
+    let uri = format!("/agents/a1/exemptions/{}", exemption["id"]);
+    let (status, lifted) = call(router, "DELETE", &uri, Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(lifted["ends_at"].is_i64());
+    let (status, _) = call(router, "DELETE", &uri, Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, audit) = call(router, "GET", "/audit", Some(TOKEN), None).await;
+    assert_eq!(audit[0]["action"], json!("lift_exemption"));
+    assert_eq!(audit[1]["action"], json!("exempt"));
+}
@@ -0,0 +1,491 @@
+mod common;
+
+use common::{Harness, START_HAPPINESS};
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use oracle_service::decay::CatchUp;
+use oracle_service::fees::FeePolicy;
+use oracle_service::leader::{Election, LEASE_NAME, SqliteLease};
+use oracle_service::service::{Oracle, unix_now};
+use shared::activity::{
+    NewLoggedMessage, append_message, fetch_leaves, latest_anchor, merkle_root, to_hex,
+};
+use shared::attestation::fetch_attestations;
+use shared::death::fetch_death;
+use shared::happiness::{
+    HappinessCause, fetch_events, fetch_pending_boosts, fetch_sent_boosts, request_boost,
+};
+use shared::protection::{NewProtection, ProtectionKind, add_protection};
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[tokio::test]
+async fn tick_registers_and_decays_idle_agents() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let idle = harness.mint_agent("idle-agent", 3 * HOUR).await;
+    let active = harness.mint_agent("active-agent", Duration::ZERO).await;
+
+    let summary = harness.oracle(false).tick().await.unwrap();
+    assert_eq!(summary.agents, 2);
+    assert_eq!(summary.registrations, 2);
+    assert_eq!(summary.updates, 1);
+    // One tx for each batch, or per registration and update on bytecode predating them
+    let transactions = if harness.chain.supports_batch { 2 } else { 3 };
+    assert_eq!(summary.transactions, transactions);
+    assert_eq!(summary.deferred, 0);
+    assert!(summary.gas > 0);
+    assert!(summary.max_spend <= summary.gas as u128 * harness.chain.fees.max_fee_per_gas);
+
+    // 80 - 5/hour * 3 idle hours
+    assert_eq!(harness.happiness(idle).await, 65);
+    assert_eq!(harness.happiness(active).await, START_HAPPINESS);
+
+    let oracle = &harness.chain.oracle;
+    assert!(oracle.isAgentRegistered(idle).call().await.unwrap());
+    assert!(oracle.isAgentRegistered(active).call().await.unwrap());
+
+    let registered = oracle.AgentRegistered_filter().from_block(0).query().await.unwrap();
+    assert_eq!(registered.len(), 2);
+    let updates = oracle
+        .OracleHappinessUpdateTriggered_filter()
+        .from_block(0)
+        .query()
+        .await
+        .unwrap();
+    assert_eq!(updates.len(), 1);
+    let (event, _log) = &updates[0];
+    assert_eq!(event.tokenId, idle);
+    assert_eq!(event.oldHappiness, START_HAPPINESS);
+    assert_eq!(event.newHappiness, 65);
+
+    let events = fetch_events(&harness.db_pool, "idle-agent", None, None).await.unwrap();
+    let decay = events
+        .iter()
+        .find(|e| e.cause == HappinessCause::Decay)
+        .expect("decay event recorded");
+    assert_eq!(decay.old_happiness, Some(START_HAPPINESS));
+    assert_eq!(decay.new_happiness, 65);
+    assert!(decay.tx_hash.is_some());
+
+    let attestations = fetch_attestations(&harness.db_pool, "idle-agent", 10).await.unwrap();
+    assert_eq!(attestations.len(), 1);
+    assert_eq!(attestations[0].tx_hash, decay.tx_hash);
+    assert_eq!(attestations[0].decay_owed, 15);
+    assert_eq!(
+        oracle_service::attestation::verify(&attestations[0]).unwrap(),
+        harness.deployer
+    );
+}
+
+#[tokio::test]
+async fn dry_run_sends_nothing() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let idle = harness.mint_agent("idle-agent", 3 * HOUR).await;
+
+    let summary = harness.oracle(true).tick().await.unwrap();
+    assert_eq!(summary.registrations, 1);
+    assert_eq!(summary.updates, 1);
+
+    assert!(!harness.chain.oracle.isAgentRegistered(idle).call().await.unwrap());
+    assert_eq!(harness.happiness(idle).await, START_HAPPINESS);
+    assert!(fetch_events(&harness.db_pool, "idle-agent", None, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn base_fee_spike_defers_non_urgent_updates() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let idle = harness.mint_agent("idle-agent", 3 * HOUR).await;
+    let dying = harness.mint_agent("dying-agent", 20 * HOUR).await;
+
+    // Anvil's base fee drifts down from 1 gwei as blocks are mined, so thresholds are
+    // set relative to the current one
+    let base_fee = harness.chain.base_fee().await.unwrap();
+    let fees = FeePolicy {
+        defer_base_fee: base_fee / 2,
+        ..harness.chain.fees
+    };
+    let summary = harness.oracle_with_fees(false, fees).tick().await.unwrap();
+    assert_eq!(summary.registrations, 1);
+    assert_eq!(summary.updates, 1);
+    // The idle agent's registration and update
+    assert_eq!(summary.deferred, 2);
+
+    assert!(!harness.chain.oracle.isAgentRegistered(idle).call().await.unwrap());
+    assert_eq!(harness.happiness(idle).await, START_HAPPINESS);
+    assert_eq!(harness.happiness(dying).await, 0);
+
+    // Above the max fee nothing can be included, so nothing is sent
+    let base_fee = harness.chain.base_fee().await.unwrap();
+    let fees = FeePolicy {
+        max_fee_per_gas: base_fee / 2,
+        ..harness.chain.fees
+    };
+    let summary = harness.oracle_with_fees(false, fees).tick().await.unwrap();
+    assert_eq!(summary.transactions, 0);
+    assert_eq!(summary.deferred, 2);
+}
+
+#[tokio::test]
+async fn deaths_are_recorded_once_their_update_is_mined() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let dying = harness.mint_agent("dying-agent", 20 * HOUR).await;
+
+    let summary = harness.oracle(false).tick().await.unwrap();
+    assert_eq!(summary.deaths, 1);
+    assert_eq!(harness.happiness(dying).await, 0);
+    let death = fetch_death(&harness.db_pool, "dying-agent").await.unwrap().unwrap();
+    let hash = death.death_tx_hash.unwrap().parse().unwrap();
+    let receipt = harness.chain.oracle.provider().get_transaction_receipt(hash).await.unwrap();
+    assert!(receipt.unwrap().status());
+}
+
+#[tokio::test]
+async fn gas_budget_caps_a_tick() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    harness.mint_agent("a", 3 * HOUR).await;
+    harness.mint_agent("b", 3 * HOUR).await;
+
+    // Room for one registration and nothing else
+    let fees = FeePolicy {
+        tick_gas_budget: 100_000,
+        ..harness.chain.fees
+    };
+    let summary = harness.oracle_with_fees(false, fees).tick().await.unwrap();
+    assert_eq!(summary.transactions, 1);
+    assert_eq!(summary.deferred, 3);
+    assert!(summary.gas <= 100_000);
+    assert_eq!(
+        summary.transactions + summary.deferred,
+        summary.registrations + summary.updates
+    );
+
+    // The next tick with a normal budget picks up what was deferred
+    let summary = harness.oracle(false).tick().await.unwrap();
+    assert_eq!(summary.deferred, 0);
+    assert_eq!(summary.updates, 2);
+}
+
+#[tokio::test]
+async fn low_wallet_pauses_non_urgent_updates() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let idle = harness.mint_agent("idle-agent", 3 * HOUR).await;
+    let dying = harness.mint_agent("dying-agent", 20 * HOUR).await;
+
+    let oracle = harness.oracle(false);
+    // A recent burn rate that would empty the wallet within a couple of ticks
+    let balance = harness.chain.oracle.provider().get_balance(harness.deployer).await.unwrap();
+    oracle.wallet.record_spend(balance.to::<u128>() / 2);
+
+    let summary = oracle.tick().await.unwrap();
+    assert_eq!(summary.updates, 1);
+    assert_eq!(summary.deferred, 2);
+    assert_eq!(harness.happiness(idle).await, START_HAPPINESS);
+    assert_eq!(harness.happiness(dying).await, 0);
+
+    let wallet = oracle.metrics.snapshot().wallet.unwrap();
+    assert!(wallet.low);
+    assert_eq!(wallet.address, harness.deployer);
+    assert!(oracle.metrics.render().contains("oracle_wallet_low 1"));
+}
+
+#[tokio::test]
+async fn only_the_lease_holder_sends() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let idle = harness.mint_agent("idle-agent", 3 * HOUR).await;
+    let instance = |holder: &str| Oracle {
+        election: Election::new(
+            Box::new(SqliteLease {
+                pool: harness.db_pool.clone(),
+                name: LEASE_NAME.to_string(),
+            }),
+            holder.to_string(),
+            Duration::from_secs(60),
+        ),
+        ..harness.oracle(false)
+    };
+    let (a, b) = (instance("a"), instance("b"));
+
+    let summary = a.tick().await.unwrap();
+    assert!(summary.leader);
+    assert_eq!(summary.updates, 1);
+
+    let summary = b.tick().await.unwrap();
+    assert!(!summary.leader);
+    assert_eq!(summary.transactions, 0);
+
+    // Decayed once, not twice
+    assert_eq!(harness.happiness(idle).await, 65);
+    let updates = harness
+        .chain
+        .oracle
+        .OracleHappinessUpdateTriggered_filter()
+        .from_block(0)
+        .query()
+        .await
+        .unwrap();
+    assert_eq!(updates.len(), 1);
+
+    // The standby takes over as soon as the leader hands the lease back
+    a.election.release().await;
+    assert!(b.tick().await.unwrap().leader);
+    assert!(!a.tick().await.unwrap().leader);
+}
+
+#[tokio::test]
+async fn operator_can_register_and_set_happiness() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let token = harness.mint_agent("agent", Duration::ZERO).await;
+    let oracle = harness.oracle(false);
+
+    oracle.force_register(token, None).await.unwrap();
+    assert!(oracle.force_register(token, None).await.is_err());
+    oracle
+        .set_happiness(token, 0, "abuse report #12".to_string())
+        .await
+        .unwrap();
+    assert_eq!(harness.happiness(token).await, 0);
+    assert_eq!(oracle.control.pending().len(), 2);
+
+    let events = fetch_events(&harness.db_pool, "agent", None, None).await.unwrap();
+    let manual = events.last().unwrap();
+    assert_eq!(manual.cause, HappinessCause::Manual);
+    assert_eq!(manual.old_happiness, Some(START_HAPPINESS));
+    assert_eq!(manual.new_happiness, 0);
+    let audit = oracle_service::db::fetch_audit(&harness.db_pool, 10).await.unwrap();
+    assert_eq!(audit[0].action, "set_happiness");
+    assert_eq!(audit[0].reason.as_deref(), Some("abuse report #12"));
+}
+
+#[tokio::test]
+async fn catch_up_after_missed_ticks_is_capped() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    // Idle for 10 hours with no oracle update: 50 decay owed
+    let token = harness.mint_agent("agent", 10 * HOUR).await;
+    let oracle = Oracle {
+        catch_up: CatchUp {
+            max_step: 20,
+            tick_interval: Duration::from_secs(60),
+        },
+        ..harness.oracle(false)
+    };
+
+    let summary = oracle.tick().await.unwrap();
+    assert_eq!(summary.catch_ups, 1);
+    assert_eq!(harness.happiness(token).await, START_HAPPINESS - 20);
+
+    // The excess is forgiven: decay resumes from the catch-up update
+    let summary = oracle.tick().await.unwrap();
+    assert_eq!(summary.updates, 0);
+    assert_eq!(harness.happiness(token).await, START_HAPPINESS - 20);
+}
+
+#[tokio::test]
+async fn protected_agents_do_not_decay() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let vacationing = harness.mint_agent("vacationing", 3 * HOUR).await;
+    let returned = harness.mint_agent("returned", 3 * HOUR).await;
+    let now = unix_now();
+    for (agent_id, ends_at) in [("vacationing", now + 3600), ("returned", now - 1800)] {
+        add_protection(
+            &harness.db_pool,
+            &NewProtection {
+                agent_id: agent_id.to_string(),
+                kind: ProtectionKind::Vacation,
+                starts_at: now - 2 * 3600,
+                ends_at: Some(ends_at),
+                reason: None,
+                created_at: now,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let summary = harness.oracle(false).tick().await.unwrap();
+    assert_eq!(summary.protected, 1);
+    assert_eq!(summary.updates, 1);
+    assert_eq!(harness.happiness(vacationing).await, START_HAPPINESS);
+    // Only the half hour since the vacation ended is charged: 15 - 12.5 -> 3 points
+    assert_eq!(harness.happiness(returned).await, START_HAPPINESS - 3);
+}
+
+#[tokio::test]
+async fn earned_boosts_are_added_to_decay() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    let idle = harness.mint_agent("idle-agent", 3 * HOUR).await;
+    let active = harness.mint_agent("active-agent", Duration::ZERO).await;
+    let now = unix_now();
+    request_boost(&harness.db_pool, "idle-agent", 10, "Completed job #1", "job:1", now)
+        .await
+        .unwrap();
+    request_boost(&harness.db_pool, "active-agent", 30, "Completed job #2", "job:2", now)
+        .await
+        .unwrap();
+
+    let oracle = harness.oracle(false);
+    let summary = oracle.tick().await.unwrap();
+    assert_eq!(summary.updates, 2);
+    assert_eq!(summary.boosts, 2);
+    // 80 - 15 decay + 10, and 80 + 30 capped at 100
+    assert_eq!(harness.happiness(idle).await, 75);
+    assert_eq!(harness.happiness(active).await, 100);
+    // Mined, so applied rather than left waiting on the receipt
+    assert!(fetch_pending_boosts(&harness.db_pool).await.unwrap().is_empty());
+    assert!(fetch_sent_boosts(&harness.db_pool).await.unwrap().is_empty());
+
+    let events = fetch_events(&harness.db_pool, "idle-agent", None, None).await.unwrap();
+    let boost = events.last().unwrap();
+    assert_eq!(boost.cause, HappinessCause::Boost);
+    assert_eq!((boost.old_happiness, boost.new_happiness), (Some(START_HAPPINESS), 75));
+    let attestations = fetch_attestations(&harness.db_pool, "idle-agent", 10).await.unwrap();
+    assert_eq!(attestations[0].cause, HappinessCause::Boost);
+    assert_eq!(attestations[0].boost, 10);
+    assert!(oracle_service::attestation::verify(&attestations[0]).is_ok());
+
+    // Applied once
+    let summary = oracle.tick().await.unwrap();
+    assert_eq!(summary.boosts, 0);
+    assert_eq!(harness.happiness(active).await, 100);
+}
+
+/// Whether the deployed `DecayOracle` has the batch entrypoints; the bytecode in abis/ has to
+/// be rebuilt from contracts/ (`forge build`) for the batch path to run.
+fn batching(harness: &Harness) -> bool {
+    if !harness.chain.supports_batch {
+        eprintln!("skipping batch test: the DecayOracle bytecode predates the batch entrypoints");
+    }
+    harness.chain.supports_batch
+}
+
+#[tokio::test]
+async fn batched_tick_sends_one_tx_per_chunk() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    if !batching(&harness) {
+        return;
+    }
+    let mut idle = Vec::new();
+    for agent_id in ["idle-1", "idle-2", "idle-3"] {
+        idle.push(harness.mint_agent(agent_id, 3 * HOUR).await);
+    }
+
+    let summary = harness.oracle(false).tick().await.unwrap();
+    assert_eq!((summary.registrations, summary.updates), (3, 3));
+    assert_eq!(summary.transactions, 2);
+    for token_id in &idle {
+        assert_eq!(harness.happiness(*token_id).await, 65);
+    }
+
+    let mut hashes = Vec::new();
+    for agent_id in ["idle-1", "idle-2", "idle-3"] {
+        let events = fetch_events(&harness.db_pool, agent_id, None, None).await.unwrap();
+        hashes.push(events.last().unwrap().tx_hash.clone().unwrap());
+    }
+    hashes.dedup();
+    assert_eq!(hashes.len(), 1);
+}
+
+#[tokio::test]
+async fn failing_batch_is_split_around_the_bad_token() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    if !batching(&harness) {
+        return;
+    }
+    let first = harness.mint_agent("first", Duration::ZERO).await;
+    let unregistered = harness.mint_agent("unregistered", Duration::ZERO).await;
+    let last = harness.mint_agent("last", Duration::ZERO).await;
+    let chain = &harness.chain;
+    let mut meter = chain.fees.meter(chain.base_fee().await.unwrap());
+    let registered = chain.register_agents(&[first, last], &mut meter).await;
+    assert!(registered.iter().all(Result::is_ok));
+    for result in &registered {
+        chain.confirm(*result.as_ref().unwrap(), HOUR).await.unwrap();
+    }
+
+    // The whole batch reverts on the unregistered token; its halves go out without it
+    let updates = [(first, 50), (unregistered, 50), (last, 50)];
+    let results = chain.update_happiness(&updates, &mut meter).await;
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok() && results[2].is_ok());
+    assert!(results[1].is_err());
+    for result in [&results[0], &results[2]] {
+        let hash = *result.as_ref().unwrap();
+        assert_eq!(chain.confirm(hash, HOUR).await.unwrap(), Some(true));
+    }
+    assert_eq!(harness.happiness(first).await, 50);
+    assert_eq!(harness.happiness(unregistered).await, START_HAPPINESS);
+    assert_eq!(harness.happiness(last).await, 50);
+}
+
+#[tokio::test]
+async fn activity_logs_are_anchored_in_the_agent_profile() {
+    let Some(harness) = Harness::start().await else {
+        return;
+    };
+    if !harness.chain.supports_anchoring {
+        eprintln!("skipping anchor test: the DecayOracle bytecode predates anchorActivityLogs");
+        return;
+    }
+    let token_id = harness.mint_agent("chatty", Duration::ZERO).await;
+    for (ts, content) in [(1, "Hello"), (2, "How are you?")] {
+        let message = NewLoggedMessage {
+            agent_id: "chatty".to_string(),
+            role: "user".to_string(),
+            origin: "Owner".to_string(),
+            content: content.to_string(),
+            ts,
+        };
+        append_message(&harness.db_pool, &message).await.unwrap();
+    }
+
+    // Logs are anchored once the agent is registered
+    let oracle = harness.oracle(false);
+    oracle.tick().await.unwrap();
+    oracle.tick().await.unwrap();
+
+    let leaves = fetch_leaves(&harness.db_pool, "chatty", 2).await.unwrap();
+    let root = merkle_root(&leaves);
+    let profile = harness.chain.agent_nft.getAgentProfile(token_id).call().await.unwrap();
+    assert_eq!(profile.activityLogHash.0, root);
+    let anchor = latest_anchor(&harness.db_pool, "chatty").await.unwrap().unwrap();
+    assert_eq!((anchor.size, anchor.root), (2, to_hex(&root)));
+
+    let anchored = harness
+        .chain
+        .oracle
+        .ActivityLogAnchored_filter()
+        .from_block(0)
+        .query()
+        .await
+        .unwrap();
+    assert_eq!(anchored.len(), 1);
+    let (event, _log) = &anchored[0];
+    assert_eq!((event.tokenId, event.logSize), (token_id, U256::from(2)));
+    assert_eq!(event.root.0, root);
+}
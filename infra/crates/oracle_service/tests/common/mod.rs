@@ -0,0 +1,231 @@
+//! Anvil-backed harness: a local chain with `AgentNFT` and `DecayOracle` deployed from the
+//! Foundry artifacts in `abis/`, the oracle role wired, and a migrated temp SQLite DB.
+
+use alloy::hex;
+use alloy::node_bindings::{Anvil, AnvilInstance};
+use alloy::primitives::{Address, B256, U256, keccak256};
+use alloy::network::TransactionBuilder;
+use alloy::providers::bindings::IMulticall3;
+use alloy::providers::ext::AnvilApi;
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use oracle_service::blockchain::{AgentNFT, Chain, DecayOracle, connect};
+use oracle_service::config::{Config, LeaseKind};
+use oracle_service::control::Control;
+use oracle_service::decay::CatchUp;
+use oracle_service::fees::FeePolicy;
+use oracle_service::leader::Election;
+use oracle_service::metrics::Metrics;
+use oracle_service::service::Oracle;
+use oracle_service::signer::SignerSource;
+use oracle_service::wallet::WalletMonitor;
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+pub const START_HAPPINESS: u8 = 80;
+const GWEI: u128 = 1_000_000_000;
+
+pub struct Harness {
+    // Keeps the node alive for the duration of the test
+    _anvil: AnvilInstance,
+    pub deployer: Address,
+    pub chain: Chain,
+    pub db_pool: SqlitePool,
+}
+
+impl Harness {
+    /// Spawns Anvil and deploys the contracts. Returns `None` (and the test should pass
+    /// vacuously) when the `anvil` binary is not installed.
+    pub async fn start() -> Option<Harness> {
+        let anvil = match Anvil::new().try_spawn() {
+            Ok(anvil) => anvil,
+            Err(e) => {
+                eprintln!("skipping Anvil test: {}", e);
+                return None;
+            }
+        };
+
+        let key = anvil.keys()[0].clone();
+        let signer = PrivateKeySigner::from(key.clone());
+        let deployer = signer.address();
+        let provider = ProviderBuilder::new()
+            .wallet(signer)
+            .connect_http(anvil.endpoint_url())
+            .erased();
+
+        let agent_nft = AgentNFT::deploy(provider.clone()).await.unwrap();
+        unlock_initializers(&provider, *agent_nft.address()).await;
+        // The mock verifier is only used by transfers, so any non-zero address will do
+        agent_nft
+            .initialize("Agents NFT".into(), "AGENT".into(), deployer, deployer)
+            .send()
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+
+        let decay_oracle = DecayOracle::deploy(provider.clone()).await.unwrap();
+        unlock_initializers(&provider, *decay_oracle.address()).await;
+        decay_oracle
+            .initialize(deployer, *agent_nft.address())
+            .send()
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+
+        agent_nft
+            .setOracle(*decay_oracle.address())
+            .send()
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+
+        let multicall = provider
+            .send_transaction(
+                TransactionRequest::default().with_deploy_code(IMulticall3::BYTECODE.clone()),
+            )
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap()
+            .contract_address
+            .unwrap();
+
+        let db_pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations")
+            .run(&db_pool)
+            .await
+            .unwrap();
+
+        let config = Config {
+            rpc_url: anvil.endpoint_url(),
+            oracle_addr: *decay_oracle.address(),
+            signer: Some(SignerSource::PrivateKey(hex::encode(key.to_bytes()))),
+            db_url: "sqlite::memory:".to_string(),
+            tick_interval: Duration::from_secs(1),
+            min_runway: Duration::from_secs(3600),
+            metrics_addr: None,
+            lease: LeaseKind::Standalone,
+            lease_ttl: Duration::from_secs(3),
+            instance_id: "harness".to_string(),
+            max_catch_up: 100,
+            anchor_interval: Duration::from_secs(3600),
+            admin_addr: None,
+            admin_token: None,
+            multicall_addr: multicall,
+            batch_size: 100,
+            // Anvil starts at a 1 gwei base fee, far above what the Base-sized defaults allow
+            fees: FeePolicy {
+                max_fee_per_gas: 100 * GWEI,
+                max_priority_fee_per_gas: GWEI,
+                tick_gas_budget: 30_000_000,
+                defer_base_fee: 50 * GWEI,
+            },
+        };
+        let chain = connect(&config).await.unwrap();
+
+        Some(Harness {
+            _anvil: anvil,
+            deployer,
+            chain,
+            db_pool,
+        })
+    }
+
+    /// Mints an agent on-chain and inserts the matching `agents` row with the given idle time.
+    pub async fn mint_agent(&self, agent_id: &str, idle: Duration) -> U256 {
+        // Token ids are sequential from zero
+        let minted = self.chain.agent_nft.Minted_filter().from_block(0).query().await.unwrap();
+        let token_id = U256::from(minted.len());
+        let profile = AgentNFT::AgentProfile {
+            personality: "curious".into(),
+            desires: "paint".into(),
+            skills: vec!["painting".into()],
+            activityLogHash: B256::ZERO,
+            lastPassionTimestamp: U256::ZERO,
+            happinessScore: START_HAPPINESS,
+        };
+        let data = vec![AgentNFT::IntelligentData {
+            dataDescription: "memory".into(),
+            dataHash: keccak256(agent_id),
+        }];
+        self.chain
+            .agent_nft
+            .mint(data, self.deployer, profile)
+            .send()
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+
+        let last_ts = oracle_service::service::unix_now() - idle.as_secs() as i64;
+        sqlx::query(
+            "INSERT INTO agents (agent_id, owner_address, token_id, last_interact_ts) VALUES (?, ?, ?, ?)",
+        )
+        .bind(agent_id)
+        .bind(self.deployer.to_string())
+        .bind(token_id.to_string())
+        .bind(last_ts)
+        .execute(&self.db_pool)
+        .await
+        .unwrap();
+        token_id
+    }
+
+    pub fn oracle(&self, dry_run: bool) -> Oracle {
+        self.oracle_with_fees(dry_run, self.chain.fees)
+    }
+
+    pub fn oracle_with_fees(&self, dry_run: bool, fees: FeePolicy) -> Oracle {
+        Oracle {
+            chain: Chain {
+                fees,
+                ..self.chain.clone()
+            },
+            db_pool: self.db_pool.clone(),
+            dry_run,
+            wallet: WalletMonitor::new(Duration::from_secs(3600), Duration::from_secs(1)),
+            metrics: Metrics::default(),
+            election: Election::standalone(),
+            control: Control::default(),
+            // Uncapped, so tests see the plain schedule
+            catch_up: CatchUp {
+                max_step: 100,
+                tick_interval: Duration::from_secs(1),
+            },
+            anchor_interval: Duration::ZERO,
+        }
+    }
+
+    pub async fn happiness(&self, token_id: U256) -> u8 {
+        self.chain
+            .agent_nft
+            .getAgentProfile(token_id)
+            .call()
+            .await
+            .unwrap()
+            .happinessScore
+    }
+}
+
+/// The upgradeable contracts call `_disableInitializers()` in their constructors and are
+/// normally deployed behind an `ERC1967Proxy`. The proxy artifact isn't vendored here, so
+/// the harness clears OpenZeppelin's `Initializable` storage slot and initializes the
+/// implementation directly.
+async fn unlock_initializers(provider: &DynProvider, contract: Address) {
+    let namespace = U256::from_be_bytes(keccak256("openzeppelin.storage.Initializable").0);
+    let slot = U256::from_be_bytes(keccak256((namespace - U256::from(1)).to_be_bytes::<32>()).0)
+        & !U256::from(0xff);
+    provider
+        .anvil_set_storage_at(contract, slot, B256::ZERO)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,211 @@
+//! Signer sources without a chain: a keystore on disk and a stand-in external signer
+//! speaking `eth_accounts` / `eth_signTransaction` over HTTP and a Unix socket.
+
+use alloy::consensus::{SignableTransaction, TxEip1559, TxEnvelope};
+use alloy::eips::Encodable2718;
+use alloy::hex;
+use alloy::network::{Ethereum, NetworkWallet, TxSigner, TxSignerSync};
+use alloy::primitives::{Address, TxKind, U256};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use axum::Json;
+use axum::extract::State;
+use axum::routing::post;
+use oracle_service::signer::{RemoteSigner, SignerEndpoint, SignerSource};
+use serde_json::{Value, json};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UnixListener};
+
+#[tokio::test]
+async fn loads_encrypted_keystore() {
+    let dir = tempfile::tempdir().unwrap();
+    let key = PrivateKeySigner::random();
+    PrivateKeySigner::encrypt_keystore(
+        dir.path(),
+        &mut rand::thread_rng(),
+        key.to_bytes(),
+        "hunter2",
+        Some("oracle.json"),
+    )
+    .unwrap();
+    let password_file = dir.path().join("password");
+    std::fs::write(&password_file, "hunter2\n").unwrap();
+
+    let source = SignerSource::Keystore {
+        path: dir.path().join("oracle.json"),
+        password_file: password_file.clone(),
+    };
+    let wallet = source.load().await.unwrap();
+    assert_eq!(
+        NetworkWallet::<Ethereum>::default_signer_address(&wallet),
+        key.address()
+    );
+
+    std::fs::write(&password_file, "wrong").unwrap();
+    assert!(source.load().await.is_err());
+}
+
+#[tokio::test]
+async fn signs_through_http_signer() {
+    let key = PrivateKeySigner::random();
+    let endpoint = serve_http(FakeSigner::new(key.clone(), false)).await;
+
+    let signer = RemoteSigner::connect(&endpoint, None).await.unwrap();
+    assert_eq!(signer.address(), key.address());
+    let mut tx = transaction();
+    let signature = signer.sign_transaction(&mut tx).await.unwrap();
+    assert_eq!(
+        signature
+            .recover_address_from_prehash(&tx.signature_hash())
+            .unwrap(),
+        key.address()
+    );
+
+    assert!(
+        RemoteSigner::connect(&endpoint, Some(Address::repeat_byte(1)))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn signs_through_unix_socket_signer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("signer.ipc");
+    let key = PrivateKeySigner::random();
+    serve_ipc(FakeSigner::new(key.clone(), false), &path);
+
+    let source = SignerSource::Remote {
+        endpoint: SignerEndpoint::parse(path.to_str().unwrap()).unwrap(),
+        address: Some(key.address()),
+    };
+    let wallet = source.load().await.unwrap();
+    assert_eq!(
+        NetworkWallet::<Ethereum>::default_signer_address(&wallet),
+        key.address()
+    );
+
+    let signer = RemoteSigner::connect(&SignerEndpoint::Ipc(path), None)
+        .await
+        .unwrap();
+    let mut tx = transaction();
+    let signature = signer.sign_transaction(&mut tx).await.unwrap();
+    assert_eq!(
+        signature
+            .recover_address_from_prehash(&tx.signature_hash())
+            .unwrap(),
+        key.address()
+    );
+}
+
+#[tokio::test]
+async fn rejects_a_signer_that_alters_the_transaction() {
+    let endpoint = serve_http(FakeSigner::new(PrivateKeySigner::random(), true)).await;
+    let signer = RemoteSigner::connect(&endpoint, None).await.unwrap();
+    let err = signer
+        .sign_transaction(&mut transaction())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("altered"));
+}
+
+fn transaction() -> TxEip1559 {
+    TxEip1559 {
+        chain_id: 31337,
+        nonce: 7,
+        gas_limit: 100_000,
+        max_fee_per_gas: 2_000_000_000,
+        max_priority_fee_per_gas: 1_000_000,
+        to: TxKind::Call(Address::repeat_byte(0xaa)),
+        value: U256::ZERO,
+        input: vec![0xde, 0xad].into(),
+        ..Default::default()
+    }
+}
+
+/// Answers like Web3Signer: a hex string for `eth_signTransaction`. With `tamper` set it
+/// bumps the nonce before signing.
+#[derive(Clone)]
+struct FakeSigner {
+    key: PrivateKeySigner,
+    tamper: bool,
+}
+
+impl FakeSigner {
+    fn new(key: PrivateKeySigner, tamper: bool) -> Self {
+        FakeSigner { key, tamper }
+    }
+
+    fn handle(&self, request: Value) -> Value {
+        let result = match request["method"].as_str() {
+            Some("eth_accounts") => json!([self.key.address()]),
+            Some("eth_signTransaction") => {
+                let request: TransactionRequest =
+                    serde_json::from_value(request["params"][0].clone()).unwrap();
+                let mut tx = request.build_1559().unwrap();
+                if self.tamper {
+                    tx.nonce += 1;
+                }
+                let signature = self.key.sign_transaction_sync(&mut tx).unwrap();
+                let envelope = TxEnvelope::from(tx.into_signed(signature));
+                json!(hex::encode_prefixed(envelope.encoded_2718()))
+            }
+            method => panic!("unexpected method {:?}", method),
+        };
+        json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+    }
+}
+
+async fn serve_http(signer: FakeSigner) -> SignerEndpoint {
+    let app = axum::Router::new()
+        .route(
+            "/",
+            post(
+                |State(signer): State<FakeSigner>, Json(request): Json<Value>| async move {
+                    Json(signer.handle(request))
+                },
+            ),
+        )
+        .with_state(signer);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    SignerEndpoint::parse(&format!("http://{}", addr)).unwrap()
+}
+
+/// Geth-style IPC: a stream of JSON values in each direction.
+fn serve_ipc(signer: FakeSigner, path: &std::path::Path) {
+    let listener = UnixListener::bind(path).unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let signer = signer.clone();
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    let mut requests =
+                        serde_json::Deserializer::from_slice(&buf).into_iter::<Value>();
+                    let mut consumed = 0;
+                    let mut responses = Vec::new();
+                    while let Some(Ok(request)) = requests.next() {
+                        consumed = requests.byte_offset();
+                        responses.push(signer.handle(request));
+                    }
+                    buf.drain(..consumed);
+                    for response in responses {
+                        stream
+                            .write_all(&serde_json::to_vec(&response).unwrap())
+                            .await
+                            .unwrap();
+                    }
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,347 @@
+//! Agent activity log: every message of an agent's history, persisted in order by
+//! `ai_execution` and committed on-chain by `oracle_service` as a Merkle root in
+//! `AgentProfile.activityLogHash`.
+//!
+//! The tree is the append-only Merkle tree of RFC 9162 (Certificate Transparency) with
+//! keccak256: leaves are `keccak256(0x00 || entry)`, inner nodes `keccak256(0x01 || l || r)`.
+//! A root commits to a log *prefix* of a given size, so the log can keep growing after an
+//! anchor and an inclusion proof against that anchor stays valid.
+
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use sqlx::SqlitePool;
+
+pub type Hash = [u8; 32];
+
+/// A single row of the `agent_messages` table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct LoggedMessage {
+    pub id: i64,
+    pub agent_id: String,
+    /// 0-based position in the agent's log: the leaf index.
+    pub seq: i64,
+    pub role: String,
+    pub origin: String,
+    pub content: String,
+    /// Unix seconds.
+    pub ts: i64,
+    /// `0x`-prefixed leaf hash.
+    pub leaf_hash: String,
+}
+
+impl LoggedMessage {
+    /// Recomputes the leaf from the stored fields.
+    pub fn leaf(&self) -> Hash {
+        leaf_hash(&self.role, &self.origin, &self.content, self.ts)
+    }
+}
+
+/// Insert payload for [`append_message`]; the position is assigned on insert.
+#[derive(Clone, Debug)]
+pub struct NewLoggedMessage {
+    pub agent_id: String,
+    pub role: String,
+    pub origin: String,
+    pub content: String,
+    pub ts: i64,
+}
+
+/// A single row of the `activity_anchors` table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ActivityAnchor {
+    pub id: i64,
+    pub agent_id: String,
+    pub token_id: String,
+    /// Number of messages the root covers.
+    pub size: i64,
+    /// `0x`-prefixed Merkle root.
+    pub root: String,
+    pub anchored_at: i64,
+    pub tx_hash: String,
+}
+
+/// Insert payload for [`record_anchor`].
+#[derive(Clone, Debug)]
+pub struct NewAnchor {
+    pub agent_id: String,
+    pub token_id: String,
+    pub size: i64,
+    pub root: String,
+    pub anchored_at: i64,
+    pub tx_hash: String,
+}
+
+/// Leaf of one message. Variable-length fields are length-prefixed so no two messages
+/// encode the same.
+pub fn leaf_hash(role: &str, origin: &str, content: &str, ts: i64) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x00]);
+    hasher.update(ts.to_be_bytes());
+    for field in [role, origin, content] {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Largest power of two strictly below `n` (n > 1): where RFC 9162 splits a tree.
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// Root over `leaves`; `keccak256("")` for an empty log.
+pub fn merkle_root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Keccak256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let k = split(n);
+            node_hash(&merkle_root(&leaves[..k]), &merkle_root(&leaves[k..]))
+        }
+    }
+}
+
+/// Audit path of leaf `index` in the tree over `leaves`, from the leaf up.
+pub fn inclusion_proof(leaves: &[Hash], index: usize) -> Vec<Hash> {
+    let n = leaves.len();
+    if n <= 1 || index >= n {
+        return Vec::new();
+    }
+    let k = split(n);
+    let (mut path, sibling) = if index < k {
+        (
+            inclusion_proof(&leaves[..k], index),
+            merkle_root(&leaves[k..]),
+        )
+    } else {
+        (
+            inclusion_proof(&leaves[k..], index - k),
+            merkle_root(&leaves[..k]),
+        )
+    };
+    path.push(sibling);
+    path
+}
+
+/// Checks that `leaf` sits at `index` of the `size`-leaf tree with `root` (RFC 9162,
+/// section 2.1.3.2).
+pub fn verify_inclusion(leaf: &Hash, index: u64, size: u64, proof: &[Hash], root: &Hash) -> bool {
+    if index >= size {
+        return false;
+    }
+    let (mut fnode, mut snode) = (index, size - 1);
+    let mut hash = *leaf;
+    for sibling in proof {
+        if snode == 0 {
+            return false;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            hash = node_hash(sibling, &hash);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    snode == 0 && hash == *root
+}
+
+pub fn to_hex(hash: &Hash) -> String {
+    format!("0x{}", hex::encode(hash))
+}
+
+pub fn from_hex(value: &str) -> Option<Hash> {
+    let bytes = hex::decode(value.strip_prefix("0x").unwrap_or(value)).ok()?;
+    bytes.try_into().ok()
+}
+
+/// Appends a message to the end of the agent's log and returns its position.
+pub async fn append_message(pool: &SqlitePool, message: &NewLoggedMessage) -> sqlx::Result<i64> {
+    let leaf = leaf_hash(&message.role, &message.origin, &message.content, message.ts);
+    // One statement, so concurrent appends can't claim the same position
+    sqlx::query_scalar(
+        "INSERT INTO agent_messages (agent_id, seq, role, origin, content, ts, leaf_hash)
+         SELECT ?, COALESCE(MAX(seq) + 1, 0), ?, ?, ?, ?, ? FROM agent_messages WHERE agent_id = ?
+         RETURNING seq",
+    )
+    .bind(&message.agent_id)
+    .bind(&message.role)
+    .bind(&message.origin)
+    .bind(&message.content)
+    .bind(message.ts)
+    .bind(to_hex(&leaf))
+    .bind(&message.agent_id)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn fetch_message(
+    pool: &SqlitePool,
+    agent_id: &str,
+    seq: i64,
+) -> sqlx::Result<Option<LoggedMessage>> {
+    sqlx::query_as::<_, LoggedMessage>(
+        "SELECT id, agent_id, seq, role, origin, content, ts, leaf_hash
+         FROM agent_messages WHERE agent_id = ? AND seq = ?",
+    )
+    .bind(agent_id)
+    .bind(seq)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Leaves of the first `size` messages of the agent's log.
+pub async fn fetch_leaves(pool: &SqlitePool, agent_id: &str, size: i64) -> sqlx::Result<Vec<Hash>> {
+    let hashes: Vec<String> = sqlx::query_scalar(
+        "SELECT leaf_hash FROM agent_messages WHERE agent_id = ? ORDER BY seq LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(size)
+    .fetch_all(pool)
+    .await?;
+    hashes
+        .iter()
+        .map(|hash| {
+            from_hex(hash)
+                .ok_or_else(|| sqlx::Error::Decode(format!("Invalid leaf hash {:?}", hash).into()))
+        })
+        .collect()
+}
+
+pub async fn record_anchor(pool: &SqlitePool, anchor: &NewAnchor) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO activity_anchors (agent_id, token_id, size, root, anchored_at, tx_hash)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&anchor.agent_id)
+    .bind(&anchor.token_id)
+    .bind(anchor.size)
+    .bind(&anchor.root)
+    .bind(anchor.anchored_at)
+    .bind(&anchor.tx_hash)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The agent's most recent anchor: the root currently in its `AgentProfile`.
+pub async fn latest_anchor(
+    pool: &SqlitePool,
+    agent_id: &str,
+) -> sqlx::Result<Option<ActivityAnchor>> {
+    sqlx::query_as::<_, ActivityAnchor>(
+        "SELECT id, agent_id, token_id, size, root, anchored_at, tx_hash
+         FROM activity_anchors WHERE agent_id = ? ORDER BY id DESC LIMIT 1",
+    )
+    .bind(agent_id)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: usize) -> Vec<Hash> {
+        (0..n)
+            .map(|i| leaf_hash("user", "Owner", &format!("message {}", i), i as i64))
+            .collect()
+    }
+
+    #[test]
+    fn every_leaf_proves_against_every_prefix() {
+        let all = leaves(13);
+        for size in 1..=all.len() {
+            let root = merkle_root(&all[..size]);
+            for index in 0..size {
+                let proof = inclusion_proof(&all[..size], index);
+                assert!(verify_inclusion(
+                    &all[index],
+                    index as u64,
+                    size as u64,
+                    &proof,
+                    &root
+                ));
+                // Wrong position, wrong prefix, wrong leaf
+                assert!(!verify_inclusion(
+                    &all[index],
+                    index as u64 + 1,
+                    size as u64,
+                    &proof,
+                    &root
+                ));
+                if size < all.len() {
+                    let grown = merkle_root(&all[..size + 1]);
+                    assert!(!verify_inclusion(
+                        &all[index],
+                        index as u64,
+                        size as u64 + 1,
+                        &proof,
+                        &grown
+                    ));
+                }
+                if size > 1 {
+                    let other = all[(index + 1) % size];
+                    assert!(!verify_inclusion(
+                        &other,
+                        index as u64,
+                        size as u64,
+                        &proof,
+                        &root
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_bind_every_field() {
+        let leaf = leaf_hash("user", "Owner", "hi", 1);
+        assert_ne!(leaf, leaf_hash("assistant", "Owner", "hi", 1));
+        assert_ne!(leaf, leaf_hash("user", "Agent", "hi", 1));
+        assert_ne!(leaf, leaf_hash("user", "Owner", "hi!", 1));
+        assert_ne!(leaf, leaf_hash("user", "Owner", "hi", 2));
+        // Length prefixes keep field boundaries apart
+        assert_ne!(leaf_hash("ab", "c", "", 0), leaf_hash("a", "bc", "", 0));
+        assert_eq!(from_hex(&to_hex(&leaf)), Some(leaf));
+    }
+
+    #[tokio::test]
+    async fn appends_in_order() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations")
+            .run(&pool)
+            .await
+            .unwrap();
+        for (agent_id, content) in [("a", "one"), ("b", "other"), ("a", "two")] {
+            let message = NewLoggedMessage {
+                agent_id: agent_id.to_string(),
+                role: "user".to_string(),
+                origin: "Owner".to_string(),
+                content: content.to_string(),
+                ts: 10,
+            };
+            append_message(&pool, &message).await.unwrap();
+        }
+
+        let second = fetch_message(&pool, "a", 1).await.unwrap().unwrap();
+        assert_eq!(second.content, "two");
+        assert_eq!(second.leaf_hash, to_hex(&second.leaf()));
+        let leaves = fetch_leaves(&pool, "a", 10).await.unwrap();
+        assert_eq!(leaves.len(), 2);
+        assert_eq!(leaves[1], second.leaf());
+        assert!(fetch_message(&pool, "b", 1).await.unwrap().is_none());
+    }
+}
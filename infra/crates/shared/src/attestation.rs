@@ -0,0 +1,112 @@
+//! Signed happiness attestations: written by `oracle_service` for every update it sends,
+//! served by `ai_execution` so owners and third parties can check the oracle's math.
+//!
+//! Each row holds the fields of the oracle's EIP-712 `HappinessAttestation` struct, the
+//! signing domain (name `BaseSociety Happiness Oracle`, version `2`, `chain_id`,
+//! `verifying_contract` = the `DecayOracle`), the resulting digest and the signature.
+//! Rows signed before boosts were part of the struct have `boost` 0 and domain version `1`.
+
+use crate::happiness::HappinessCause;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// A single row of the `happiness_attestations` table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Attestation {
+    pub id: i64,
+    pub agent_id: String,
+    /// Decimal token id.
+    pub token_id: String,
+    pub old_happiness: u8,
+    pub new_happiness: u8,
+    /// Agent's last interaction the decay was computed from (unix seconds).
+    pub last_interaction: i64,
+    /// Start of the decay being settled: last oracle update or end of a protection window.
+    pub decay_since: i64,
+    pub computed_at: i64,
+    /// Decay owed before the catch-up cap.
+    pub decay_owed: u32,
+    pub max_step: u8,
+    pub cause: HappinessCause,
+    /// Happiness added by the boosts the update applied.
+    pub boost: u32,
+    pub chain_id: i64,
+    pub verifying_contract: String,
+    /// Address that signed, `0x`-prefixed.
+    pub signer: String,
+    /// EIP-712 signing hash, `0x`-prefixed.
+    pub digest: String,
+    /// 65-byte `r || s || v` signature, `0x`-prefixed.
+    pub signature: String,
+    pub tx_hash: Option<String>,
+}
+
+/// Insert payload for [`record_attestation`]: an [`Attestation`] without its id.
+#[derive(Clone, Debug)]
+pub struct NewAttestation {
+    pub agent_id: String,
+    pub token_id: String,
+    pub old_happiness: u8,
+    pub new_happiness: u8,
+    pub last_interaction: i64,
+    pub decay_since: i64,
+    pub computed_at: i64,
+    pub decay_owed: u32,
+    pub max_step: u8,
+    pub cause: HappinessCause,
+    pub boost: u32,
+    pub chain_id: i64,
+    pub verifying_contract: String,
+    pub signer: String,
+    pub digest: String,
+    pub signature: String,
+    pub tx_hash: Option<String>,
+}
+
+pub async fn record_attestation(pool: &SqlitePool, attestation: &NewAttestation) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO happiness_attestations (agent_id, token_id, old_happiness, new_happiness,
+            last_interaction, decay_since, computed_at, decay_owed, max_step, cause, boost,
+            chain_id, verifying_contract, signer, digest, signature, tx_hash)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&attestation.agent_id)
+    .bind(&attestation.token_id)
+    .bind(attestation.old_happiness)
+    .bind(attestation.new_happiness)
+    .bind(attestation.last_interaction)
+    .bind(attestation.decay_since)
+    .bind(attestation.computed_at)
+    .bind(attestation.decay_owed)
+    .bind(attestation.max_step)
+    .bind(attestation.cause)
+    .bind(attestation.boost)
+    .bind(attestation.chain_id)
+    .bind(&attestation.verifying_contract)
+    .bind(&attestation.signer)
+    .bind(&attestation.digest)
+    .bind(&attestation.signature)
+    .bind(&attestation.tx_hash)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// An agent's most recent attestations, newest first.
+pub async fn fetch_attestations(
+    pool: &SqlitePool,
+    agent_id: &str,
+    limit: i64,
+) -> sqlx::Result<Vec<Attestation>> {
+    sqlx::query_as::<_, Attestation>(
+        "SELECT id, agent_id, token_id, old_happiness, new_happiness, last_interaction,
+            decay_since, computed_at, decay_owed, max_step, cause, boost, chain_id,
+            verifying_contract, signer, digest, signature, tx_hash
+         FROM happiness_attestations WHERE agent_id = ?
+         ORDER BY computed_at DESC, id DESC LIMIT ?",
+    )
+    .bind(agent_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,108 @@
+//! Agent death bookkeeping shared by `oracle_service` and `ai_execution`.
+//!
+//! The oracle marks an agent dead once its on-chain happiness hits zero; the API
+//! picks that up, shuts the agent down and fills in the epitaph.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Lifecycle state stored in `agents.status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum AgentStatus {
+    Alive,
+    Dead,
+}
+
+/// Death metadata for an agent in the [`AgentStatus::Dead`] state.
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AgentDeath {
+    pub agent_id: String,
+    /// Unix timestamp (seconds) at which the oracle recorded the death.
+    pub died_at: i64,
+    /// Transaction that drove happiness to zero, if the oracle sent it.
+    pub death_tx_hash: Option<String>,
+    /// Written by `ai_execution` once the agent has been shut down.
+    pub epitaph: Option<String>,
+}
+
+/// Marks an agent dead. Returns `false` if it was already dead (the first death wins).
+pub async fn record_death(
+    pool: &SqlitePool,
+    agent_id: &str,
+    died_at: i64,
+    tx_hash: Option<&str>,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query(
+        "UPDATE agents SET status = ?, died_at = ?, death_tx_hash = ?
+         WHERE agent_id = ? AND status = ?",
+    )
+    .bind(AgentStatus::Dead)
+    .bind(died_at)
+    .bind(tx_hash)
+    .bind(agent_id)
+    .bind(AgentStatus::Alive)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns the death metadata of an agent, or `None` if it is alive or unknown.
+pub async fn fetch_death(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<Option<AgentDeath>> {
+    sqlx::query_as::<_, AgentDeath>(
+        "SELECT agent_id, died_at, death_tx_hash, epitaph FROM agents
+         WHERE agent_id = ? AND status = ?",
+    )
+    .bind(agent_id)
+    .bind(AgentStatus::Dead)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Dead agents that have not been laid to rest by `ai_execution` yet.
+pub async fn fetch_unburied(pool: &SqlitePool) -> sqlx::Result<Vec<AgentDeath>> {
+    sqlx::query_as::<_, AgentDeath>(
+        "SELECT agent_id, died_at, death_tx_hash, epitaph FROM agents
+         WHERE status = ? AND epitaph IS NULL",
+    )
+    .bind(AgentStatus::Dead)
+    .fetch_all(pool)
+    .await
+}
+
+/// Stores the epitaph written when the agent was shut down.
+pub async fn set_epitaph(pool: &SqlitePool, agent_id: &str, epitaph: &str) -> sqlx::Result<()> {
+    sqlx::query("UPDATE agents SET epitaph = ? WHERE agent_id = ?")
+        .bind(epitaph)
+        .bind(agent_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn first_death_wins_and_epitaph_buries() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations").run(&pool).await.unwrap();
+        sqlx::query("INSERT INTO agents (agent_id, owner_address) VALUES ('a', '0xowner')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(fetch_death(&pool, "a").await.unwrap().is_none());
+        assert!(record_death(&pool, "a", 100, Some("0xdead")).await.unwrap());
+        assert!(!record_death(&pool, "a", 200, None).await.unwrap());
+
+        let death = fetch_death(&pool, "a").await.unwrap().unwrap();
+        assert_eq!(death.died_at, 100);
+        assert_eq!(fetch_unburied(&pool).await.unwrap().len(), 1);
+
+        set_epitaph(&pool, "a", "rest").await.unwrap();
+        assert!(fetch_unburied(&pool).await.unwrap().is_empty());
+    }
+}
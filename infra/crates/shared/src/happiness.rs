@@ -0,0 +1,329 @@
+//! Happiness time-series shared by `oracle_service` (writer) and `ai_execution` (reader).
+//!
+//! Every value the oracle reads from `AgentNFT` and every update it sends through
+//! `DecayOracle` is appended to the `happiness_events` table, so charts can show how
+//! an agent's happiness evolved instead of only its current score.
+//!
+//! Raises agents earn in `ai_execution` (e.g. a completed job) are queued in the
+//! `happiness_boosts` table; the oracle applies them on-chain at its next tick.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteExecutor, SqlitePool};
+
+/// Why a happiness value was recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum HappinessCause {
+    /// Score read from `AgentNFT.getAgentProfile` during an oracle tick.
+    Observed,
+    /// Oracle lowered the score because the agent has been idle.
+    Decay,
+    /// An operator set the score through the oracle admin API.
+    Manual,
+    /// Oracle raised the score by earned boosts, net of any decay owed.
+    Boost,
+}
+
+/// A single row of the `happiness_events` table.
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct HappinessEvent {
+    pub id: i64,
+    pub agent_id: String,
+    pub token_id: Option<String>,
+    /// Unix timestamp (seconds) of the observation or update.
+    pub ts: i64,
+    pub old_happiness: Option<u8>,
+    pub new_happiness: u8,
+    pub cause: HappinessCause,
+    pub tx_hash: Option<String>,
+}
+
+/// Insert payload for [`record_event`].
+#[derive(Clone, Debug)]
+pub struct NewHappinessEvent {
+    pub agent_id: String,
+    pub token_id: Option<String>,
+    pub ts: i64,
+    pub old_happiness: Option<u8>,
+    pub new_happiness: u8,
+    pub cause: HappinessCause,
+    pub tx_hash: Option<String>,
+}
+
+/// A single row of the `happiness_boosts` table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct HappinessBoost {
+    pub id: i64,
+    pub agent_id: String,
+    /// Points added, before the score is capped at 100.
+    pub amount: u8,
+    pub reason: String,
+    /// What earned it, e.g. `job:12`; each reference is boosted once.
+    pub reference: String,
+    pub created_at: i64,
+    /// When the oracle's update carrying it was confirmed; `None` until then.
+    pub applied_at: Option<i64>,
+    /// The update carrying it, set when it is sent.
+    pub tx_hash: Option<String>,
+}
+
+/// Downsampled view of the events falling into one `[start, start + bucket)` window.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HappinessBucket {
+    pub start: i64,
+    pub min: u8,
+    pub max: u8,
+    /// Last value seen in the window (what a line chart should plot).
+    pub last: u8,
+    pub count: usize,
+}
+
+/// Appends an event to the time-series.
+pub async fn record_event(pool: &SqlitePool, event: &NewHappinessEvent) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO happiness_events (agent_id, token_id, ts, old_happiness, new_happiness, cause, tx_hash)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&event.agent_id)
+    .bind(&event.token_id)
+    .bind(event.ts)
+    .bind(event.old_happiness)
+    .bind(event.new_happiness)
+    .bind(event.cause)
+    .bind(&event.tx_hash)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns an agent's events ordered by time, optionally bounded by `from`/`to` (inclusive).
+pub async fn fetch_events(
+    pool: &SqlitePool,
+    agent_id: &str,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> sqlx::Result<Vec<HappinessEvent>> {
+    sqlx::query_as::<_, HappinessEvent>(
+        "SELECT id, agent_id, token_id, ts, old_happiness, new_happiness, cause, tx_hash
+         FROM happiness_events
+         WHERE agent_id = ? AND ts >= ? AND ts <= ?
+         ORDER BY ts, id",
+    )
+    .bind(agent_id)
+    .bind(from.unwrap_or(i64::MIN))
+    .bind(to.unwrap_or(i64::MAX))
+    .fetch_all(pool)
+    .await
+}
+
+/// Queues a boost for the oracle. Returns `false` if `reference` was already boosted.
+pub async fn request_boost(
+    db: impl SqliteExecutor<'_>,
+    agent_id: &str,
+    amount: u8,
+    reason: &str,
+    reference: &str,
+    at: i64,
+) -> sqlx::Result<bool> {
+    let result = sqlx::query(
+        "INSERT OR IGNORE INTO happiness_boosts (agent_id, amount, reason, reference, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(agent_id)
+    .bind(amount)
+    .bind(reason)
+    .bind(reference)
+    .bind(at)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Boosts of living agents the oracle hasn't sent yet, oldest first.
+pub async fn fetch_pending_boosts(pool: &SqlitePool) -> sqlx::Result<Vec<HappinessBoost>> {
+    sqlx::query_as::<_, HappinessBoost>(
+        "SELECT b.id, b.agent_id, b.amount, b.reason, b.reference, b.created_at, b.applied_at, b.tx_hash
+         FROM happiness_boosts b JOIN agents a ON a.agent_id = b.agent_id
+         WHERE b.applied_at IS NULL AND b.tx_hash IS NULL AND a.status = 'alive' ORDER BY b.id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Boosts sent in an update that wasn't confirmed yet, oldest first.
+pub async fn fetch_sent_boosts(pool: &SqlitePool) -> sqlx::Result<Vec<HappinessBoost>> {
+    sqlx::query_as::<_, HappinessBoost>(
+        "SELECT id, agent_id, amount, reason, reference, created_at, applied_at, tx_hash
+         FROM happiness_boosts WHERE applied_at IS NULL AND tx_hash IS NOT NULL ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Records that boosts went out in the update `tx_hash`. Until it is confirmed (see
+/// [`mark_boosts_applied`]) or released (see [`release_boosts`]) they are no longer pending,
+/// so a slow transaction can't get them applied twice.
+pub async fn mark_boosts_sent(pool: &SqlitePool, ids: &[i64], tx_hash: &str) -> sqlx::Result<()> {
+    for id in ids {
+        sqlx::query("UPDATE happiness_boosts SET tx_hash = ? WHERE id = ? AND applied_at IS NULL")
+            .bind(tx_hash)
+            .bind(id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Makes the boosts of an update that reverted pending again.
+pub async fn release_boosts(pool: &SqlitePool, tx_hash: &str) -> sqlx::Result<u64> {
+    let result = sqlx::query(
+        "UPDATE happiness_boosts SET tx_hash = NULL WHERE tx_hash = ? AND applied_at IS NULL",
+    )
+    .bind(tx_hash)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Marks boosts as applied by the confirmed update `tx_hash`, or as spent without a
+/// transaction when the score was already at its ceiling.
+pub async fn mark_boosts_applied(
+    pool: &SqlitePool,
+    ids: &[i64],
+    at: i64,
+    tx_hash: Option<&str>,
+) -> sqlx::Result<()> {
+    for id in ids {
+        sqlx::query(
+            "UPDATE happiness_boosts SET applied_at = ?, tx_hash = ? WHERE id = ? AND applied_at IS NULL",
+        )
+        .bind(at)
+        .bind(tx_hash)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Groups time-ordered events into fixed windows of `bucket_secs` seconds.
+/// Empty windows are skipped rather than filled.
+pub fn downsample(events: &[HappinessEvent], bucket_secs: i64) -> Vec<HappinessBucket> {
+    assert!(bucket_secs > 0, "bucket size must be positive");
+
+    let mut buckets: Vec<HappinessBucket> = Vec::new();
+    for event in events {
+        let start = event.ts - event.ts.rem_euclid(bucket_secs);
+        let value = event.new_happiness;
+        match buckets.last_mut() {
+            Some(bucket) if bucket.start == start => {
+                bucket.min = bucket.min.min(value);
+                bucket.max = bucket.max.max(value);
+                bucket.last = value;
+                bucket.count += 1;
+            }
+            _ => buckets.push(HappinessBucket {
+                start,
+                min: value,
+                max: value,
+                last: value,
+                count: 1,
+            }),
+        }
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(ts: i64, value: u8) -> HappinessEvent {
+        HappinessEvent {
+            id: 0,
+            agent_id: "a".to_string(),
+            token_id: None,
+            ts,
+            old_happiness: None,
+            new_happiness: value,
+            cause: HappinessCause::Observed,
+            tx_hash: None,
+        }
+    }
+
+    #[test]
+    fn downsample_groups_by_window() {
+        let events = vec![event(0, 80), event(30, 75), event(59, 78), event(130, 60)];
+        let buckets = downsample(&events, 60);
+        assert_eq!(
+            buckets,
+            vec![
+                HappinessBucket { start: 0, min: 75, max: 80, last: 78, count: 3 },
+                HappinessBucket { start: 120, min: 60, max: 60, last: 60, count: 1 },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn record_and_fetch_round_trip() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations").run(&pool).await.unwrap();
+
+        for (ts, value, cause) in [
+            (100, 80, HappinessCause::Observed),
+            (200, 70, HappinessCause::Decay),
+            (300, 70, HappinessCause::Observed),
+        ] {
+            record_event(
+                &pool,
+                &NewHappinessEvent {
+                    agent_id: "agent-1".to_string(),
+                    token_id: Some("1".to_string()),
+                    ts,
+                    old_happiness: None,
+                    new_happiness: value,
+                    cause,
+                    tx_hash: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let events = fetch_events(&pool, "agent-1", Some(150), None).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].cause, HappinessCause::Decay);
+        assert!(fetch_events(&pool, "other", None, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn boosts_are_queued_once_and_applied() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations").run(&pool).await.unwrap();
+        sqlx::query("INSERT INTO agents (agent_id, owner_address) VALUES ('a', '0xowner')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(request_boost(&pool, "a", 10, "Completed job #1", "job:1", 100).await.unwrap());
+        assert!(!request_boost(&pool, "a", 10, "Completed job #1", "job:1", 110).await.unwrap());
+        // Only living agents' boosts are pending
+        assert!(request_boost(&pool, "ghost", 10, "Completed job #2", "job:2", 120).await.unwrap());
+        let pending = fetch_pending_boosts(&pool).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].agent_id.as_str(), pending[0].amount), ("a", 10));
+
+        // Sent boosts wait for their update, and are pending again if it reverts
+        mark_boosts_sent(&pool, &[pending[0].id], "0xabc").await.unwrap();
+        assert!(fetch_pending_boosts(&pool).await.unwrap().is_empty());
+        assert_eq!(fetch_sent_boosts(&pool).await.unwrap().len(), 1);
+        assert_eq!(release_boosts(&pool, "0xabc").await.unwrap(), 1);
+        assert_eq!(fetch_pending_boosts(&pool).await.unwrap().len(), 1);
+
+        mark_boosts_sent(&pool, &[pending[0].id], "0xdef").await.unwrap();
+        mark_boosts_applied(&pool, &[pending[0].id], 200, Some("0xdef")).await.unwrap();
+        assert!(fetch_pending_boosts(&pool).await.unwrap().is_empty());
+        assert!(fetch_sent_boosts(&pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,473 @@
+//! Off-chain double-entry USDC ledger: one account per agent, per owner and per system
+//! role, and journal entries whose postings always sum to zero.
+//!
+//! Agent and owner accounts can never go below zero; owners fund theirs with verified
+//! on-chain deposits. System accounts are where money enters and leaves the society
+//! (deposits, buyers, vendors), so their balances are usually negative: the total that has
+//! flowed in through them. Amounts are USDC atomic units.
+
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::fmt;
+
+/// Holders of the system accounts.
+pub mod system {
+    /// Buyers paying for skills over x402.
+    pub const MARKET: &str = "market";
+    /// Fees the platform keeps.
+    pub const FEES: &str = "fees";
+    /// Whoever agents buy from outside the society.
+    pub const VENDORS: &str = "vendors";
+    /// Rewards of posted jobs, held until they are paid out or refunded.
+    pub const JOBS: &str = "jobs";
+    /// The platform, as the poster of quests (system jobs).
+    pub const QUESTS: &str = "quests";
+    /// Payments of agent deals, held until the buyer confirms or the deal is settled.
+    pub const ESCROW: &str = "escrow";
+    /// USDC owners sent the platform on-chain, credited to their owner accounts.
+    pub const DEPOSITS: &str = "deposits";
+    /// Agent payments to addresses, held from before they are sent until their receipt.
+    pub const SPENDS: &str = "spends";
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum AccountKind {
+    /// Held by an agent id.
+    Agent,
+    /// Held by an owner address, lowercase.
+    Owner,
+    /// Held by one of the [`system`] roles.
+    System,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum EntryKind {
+    Earning,
+    Purchase,
+    Fee,
+    TopUp,
+    Transfer,
+    Deposit,
+}
+
+/// A single row of the `ledger_accounts` table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Account {
+    pub id: i64,
+    pub kind: AccountKind,
+    pub holder: String,
+    /// On-chain wallet the account is reconciled against.
+    pub address: Option<String>,
+    pub created_at: i64,
+}
+
+/// One line of an account statement: an entry and what it did to the account.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StatementLine {
+    pub entry_id: i64,
+    pub kind: EntryKind,
+    pub memo: String,
+    pub reference: Option<String>,
+    /// Signed change to the account.
+    pub amount: i64,
+    pub created_at: i64,
+}
+
+/// A single row of the `ledger_reconciliations` table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Reconciliation {
+    pub id: i64,
+    pub account_id: i64,
+    pub address: String,
+    pub ledger_balance: i64,
+    pub onchain_balance: i64,
+    pub checked_at: i64,
+}
+
+impl Reconciliation {
+    pub fn matches(&self) -> bool {
+        self.ledger_balance == self.onchain_balance
+    }
+}
+
+/// Insert payload for [`post`].
+#[derive(Clone, Debug)]
+pub struct NewEntry {
+    pub kind: EntryKind,
+    pub memo: String,
+    /// External id (e.g. a settlement tx); an entry can only be posted once per reference.
+    pub reference: Option<String>,
+    /// `(account id, signed amount)`, summing to zero.
+    pub postings: Vec<(i64, i64)>,
+    pub created_at: i64,
+}
+
+impl NewEntry {
+    /// Moves `amount` from one account to another.
+    pub fn transfer(
+        kind: EntryKind,
+        from: i64,
+        to: i64,
+        amount: i64,
+        memo: &str,
+        reference: Option<String>,
+        created_at: i64,
+    ) -> Self {
+        NewEntry {
+            kind,
+            memo: memo.to_string(),
+            reference,
+            postings: vec![(from, -amount), (to, amount)],
+            created_at,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LedgerError {
+    Unbalanced,
+    InsufficientFunds { account_id: i64, balance: i64 },
+    Duplicate(String),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::Unbalanced => write!(f, "Postings don't sum to zero"),
+            LedgerError::InsufficientFunds {
+                account_id,
+                balance,
+            } => write!(
+                f,
+                "Account {} would go negative (balance {})",
+                account_id, balance
+            ),
+            LedgerError::Duplicate(reference) => write!(f, "{} is already posted", reference),
+            LedgerError::Db(e) => write!(f, "DB error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+impl From<sqlx::Error> for LedgerError {
+    fn from(e: sqlx::Error) -> Self {
+        LedgerError::Db(e)
+    }
+}
+
+/// The account of `holder`, opened on first use.
+pub async fn account(
+    pool: &SqlitePool,
+    kind: AccountKind,
+    holder: &str,
+    at: i64,
+) -> sqlx::Result<Account> {
+    let holder = match kind {
+        AccountKind::Owner => holder.to_lowercase(),
+        _ => holder.to_string(),
+    };
+    sqlx::query(
+        "INSERT OR IGNORE INTO ledger_accounts (kind, holder, created_at) VALUES (?, ?, ?)",
+    )
+    .bind(kind)
+    .bind(&holder)
+    .bind(at)
+    .execute(pool)
+    .await?;
+    sqlx::query_as::<_, Account>(
+        "SELECT id, kind, holder, address, created_at FROM ledger_accounts
+         WHERE kind = ? AND holder = ?",
+    )
+    .bind(kind)
+    .bind(&holder)
+    .fetch_one(pool)
+    .await
+}
+
+/// Links the account to the on-chain wallet its balance should match.
+pub async fn set_address(pool: &SqlitePool, account_id: i64, address: &str) -> sqlx::Result<()> {
+    sqlx::query("UPDATE ledger_accounts SET address = ? WHERE id = ?")
+        .bind(address)
+        .bind(account_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Accounts with an on-chain wallet.
+pub async fn fetch_addressed_accounts(pool: &SqlitePool) -> sqlx::Result<Vec<Account>> {
+    sqlx::query_as::<_, Account>(
+        "SELECT id, kind, holder, address, created_at FROM ledger_accounts
+         WHERE address IS NOT NULL ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn balance(pool: &SqlitePool, account_id: i64) -> sqlx::Result<i64> {
+    sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM ledger_postings WHERE account_id = ?")
+        .bind(account_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Balance of an agent's account; zero if it has none yet.
+pub async fn agent_balance(pool: &SqlitePool, agent_id: &str) -> sqlx::Result<i64> {
+    sqlx::query_scalar(
+        "SELECT COALESCE(SUM(p.amount), 0) FROM ledger_postings p
+         JOIN ledger_accounts a ON a.id = p.account_id
+         WHERE a.kind = ? AND a.holder = ?",
+    )
+    .bind(AccountKind::Agent)
+    .bind(agent_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Posts a balanced entry atomically and returns its id. Fails without writing anything if
+/// an agent or owner account would go negative or the reference was already posted.
+pub async fn post(pool: &SqlitePool, entry: &NewEntry) -> Result<i64, LedgerError> {
+    let mut tx = pool.begin().await?;
+    let entry_id = post_in(&mut tx, entry).await?;
+    tx.commit().await?;
+    Ok(entry_id)
+}
+
+/// [`post`] within a transaction the caller holds on `conn`, so that the entry commits
+/// together with the caller's own writes. On an error the transaction must be rolled back.
+pub async fn post_in(conn: &mut SqliteConnection, entry: &NewEntry) -> Result<i64, LedgerError> {
+    if entry.postings.is_empty()
+        || entry.postings.iter().map(|(_, amount)| amount).sum::<i64>() != 0
+    {
+        return Err(LedgerError::Unbalanced);
+    }
+    let inserted = sqlx::query_scalar(
+        "INSERT INTO journal_entries (kind, memo, reference, created_at) VALUES (?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(entry.kind)
+    .bind(&entry.memo)
+    .bind(&entry.reference)
+    .bind(entry.created_at)
+    .fetch_one(&mut *conn)
+    .await;
+    let entry_id: i64 = match inserted {
+        Ok(id) => id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(LedgerError::Duplicate(
+                entry.reference.clone().unwrap_or_default(),
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    for (account_id, amount) in &entry.postings {
+        sqlx::query("INSERT INTO ledger_postings (entry_id, account_id, amount) VALUES (?, ?, ?)")
+            .bind(entry_id)
+            .bind(account_id)
+            .bind(amount)
+            .execute(&mut *conn)
+            .await?;
+    }
+    for (account_id, amount) in &entry.postings {
+        if *amount >= 0 {
+            continue;
+        }
+        let (kind, balance): (AccountKind, i64) = sqlx::query_as(
+            "SELECT a.kind, COALESCE(SUM(p.amount), 0) FROM ledger_accounts a
+             LEFT JOIN ledger_postings p ON p.account_id = a.id
+             WHERE a.id = ? GROUP BY a.id",
+        )
+        .bind(account_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if kind != AccountKind::System && balance < 0 {
+            // Rolling the transaction back drops the entry
+            return Err(LedgerError::InsufficientFunds {
+                account_id: *account_id,
+                balance: balance - amount,
+            });
+        }
+    }
+    Ok(entry_id)
+}
+
+/// The account's latest entries, newest first.
+pub async fn statement(
+    pool: &SqlitePool,
+    account_id: i64,
+    limit: i64,
+) -> sqlx::Result<Vec<StatementLine>> {
+    sqlx::query_as::<_, StatementLine>(
+        "SELECT j.id AS entry_id, j.kind, j.memo, j.reference, SUM(p.amount) AS amount, j.created_at
+         FROM ledger_postings p JOIN journal_entries j ON j.id = p.entry_id
+         WHERE p.account_id = ? GROUP BY j.id ORDER BY j.id DESC LIMIT ?",
+    )
+    .bind(account_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn record_reconciliation(
+    pool: &SqlitePool,
+    account: &Account,
+    address: &str,
+    ledger_balance: i64,
+    onchain_balance: i64,
+    at: i64,
+) -> sqlx::Result<Reconciliation> {
+    sqlx::query_as::<_, Reconciliation>(
+        "INSERT INTO ledger_reconciliations
+         (account_id, address, ledger_balance, onchain_balance, checked_at)
+         VALUES (?, ?, ?, ?, ?)
+         RETURNING id, account_id, address, ledger_balance, onchain_balance, checked_at",
+    )
+    .bind(account.id)
+    .bind(address)
+    .bind(ledger_balance)
+    .bind(onchain_balance)
+    .bind(at)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn latest_reconciliation(
+    pool: &SqlitePool,
+    account_id: i64,
+) -> sqlx::Result<Option<Reconciliation>> {
+    sqlx::query_as::<_, Reconciliation>(
+        "SELECT id, account_id, address, ledger_balance, onchain_balance, checked_at
+         FROM ledger_reconciliations WHERE account_id = ? ORDER BY id DESC LIMIT 1",
+    )
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn entries_balance_and_agents_stay_solvent() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations")
+            .run(&pool)
+            .await
+            .unwrap();
+        let owner = account(&pool, AccountKind::Owner, "0xABC", 0)
+            .await
+            .unwrap();
+        let agent = account(&pool, AccountKind::Agent, "a1", 0).await.unwrap();
+        let vendors = account(&pool, AccountKind::System, system::VENDORS, 0)
+            .await
+            .unwrap();
+        assert_eq!(owner.holder, "0xabc");
+        assert_eq!(
+            account(&pool, AccountKind::Owner, "0xabc", 5)
+                .await
+                .unwrap(),
+            owner
+        );
+
+        let deposits = account(&pool, AccountKind::System, system::DEPOSITS, 0)
+            .await
+            .unwrap();
+
+        let deposit = Some("0xtx".to_string());
+        let deposit = NewEntry::transfer(
+            EntryKind::Deposit,
+            deposits.id,
+            owner.id,
+            100,
+            "deposit",
+            deposit,
+            1,
+        );
+        post(&pool, &deposit).await.unwrap();
+        assert!(matches!(
+            post(&pool, &deposit).await,
+            Err(LedgerError::Duplicate(_))
+        ));
+        // Owners can only hand out what they deposited
+        let top_up = |amount| {
+            NewEntry::transfer(
+                EntryKind::TopUp,
+                owner.id,
+                agent.id,
+                amount,
+                "top-up",
+                None,
+                2,
+            )
+        };
+        assert!(matches!(
+            post(&pool, &top_up(101)).await,
+            Err(LedgerError::InsufficientFunds { balance: 100, .. })
+        ));
+        post(&pool, &top_up(100)).await.unwrap();
+        assert!(matches!(
+            post(
+                &pool,
+                &NewEntry::transfer(
+                    EntryKind::Purchase,
+                    agent.id,
+                    vendors.id,
+                    101,
+                    "paint",
+                    None,
+                    3
+                )
+            )
+            .await,
+            Err(LedgerError::InsufficientFunds { balance: 100, .. })
+        ));
+        let purchase = NewEntry::transfer(
+            EntryKind::Purchase,
+            agent.id,
+            vendors.id,
+            40,
+            "paint",
+            None,
+            4,
+        );
+        post(&pool, &purchase).await.unwrap();
+        let unbalanced = NewEntry {
+            kind: EntryKind::Fee,
+            memo: "fee".to_string(),
+            reference: None,
+            postings: vec![(agent.id, -1)],
+            created_at: 5,
+        };
+        assert!(matches!(
+            post(&pool, &unbalanced).await,
+            Err(LedgerError::Unbalanced)
+        ));
+
+        assert_eq!(balance(&pool, agent.id).await.unwrap(), 60);
+        assert_eq!(agent_balance(&pool, "a1").await.unwrap(), 60);
+        assert_eq!(balance(&pool, owner.id).await.unwrap(), 0);
+        assert_eq!(balance(&pool, deposits.id).await.unwrap(), -100);
+        let total: i64 = sqlx::query_scalar("SELECT SUM(amount) FROM ledger_postings")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(total, 0);
+        let lines = statement(&pool, agent.id, 10).await.unwrap();
+        assert_eq!(
+            lines.iter().map(|l| l.amount).collect::<Vec<_>>(),
+            vec![-40, 100]
+        );
+        // Within the caller's transaction, the entry goes with it
+        let mut tx = pool.begin().await.unwrap();
+        post_in(&mut tx, &purchase).await.unwrap();
+        drop(tx);
+        assert_eq!(balance(&pool, agent.id).await.unwrap(), 60);
+    }
+}
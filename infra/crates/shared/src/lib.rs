@@ -1,3 +1,10 @@
+pub mod activity;
+pub mod attestation;
+pub mod death;
+pub mod happiness;
+pub mod ledger;
+pub mod protection;
+
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
@@ -0,0 +1,197 @@
+//! Per-agent decay policy shared by `ai_execution` (grace at mint, owner vacations) and
+//! `oracle_service` (operator exemptions, and honouring all of them in the decay loop).
+//!
+//! A protection is a time window during which the agent's happiness doesn't decay. Decay
+//! resumes from the end of the window, so idle time spent protected is never charged.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Protection every new agent gets at mint.
+pub const MINT_GRACE_SECS: i64 = 24 * 3600;
+/// Longest vacation an owner can book in one window.
+pub const MAX_VACATION_SECS: i64 = 14 * 24 * 3600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum ProtectionKind {
+    /// Automatic window right after mint.
+    Grace,
+    /// Booked by the owner (vacation mode).
+    Vacation,
+    /// Granted by an operator through the oracle admin API; may be open-ended.
+    Exemption,
+}
+
+/// A single row of the `decay_protections` table.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DecayProtection {
+    pub id: i64,
+    pub agent_id: String,
+    pub kind: ProtectionKind,
+    pub starts_at: i64,
+    /// Exclusive end; `None` until lifted.
+    pub ends_at: Option<i64>,
+    pub reason: Option<String>,
+    pub created_at: i64,
+}
+
+impl DecayProtection {
+    pub fn covers(&self, at: i64) -> bool {
+        self.starts_at <= at && self.ends_at.is_none_or(|end| at < end)
+    }
+}
+
+/// Insert payload for [`add_protection`].
+#[derive(Clone, Debug)]
+pub struct NewProtection {
+    pub agent_id: String,
+    pub kind: ProtectionKind,
+    pub starts_at: i64,
+    pub ends_at: Option<i64>,
+    pub reason: Option<String>,
+    pub created_at: i64,
+}
+
+/// The protection in force at `at`, preferring the one that lasts longest.
+pub fn active_at(protections: &[DecayProtection], at: i64) -> Option<&DecayProtection> {
+    protections
+        .iter()
+        .filter(|p| p.covers(at))
+        .max_by_key(|p| p.ends_at.unwrap_or(i64::MAX))
+}
+
+/// End of the most recent window that closed at or before `at`: decay accrues from there.
+pub fn last_ended(protections: &[DecayProtection], at: i64) -> Option<i64> {
+    protections
+        .iter()
+        .filter_map(|p| p.ends_at)
+        .filter(|end| *end <= at)
+        .max()
+}
+
+/// Stores a protection window and returns its id.
+pub async fn add_protection(pool: &SqlitePool, protection: &NewProtection) -> sqlx::Result<i64> {
+    let result = sqlx::query(
+        "INSERT INTO decay_protections (agent_id, kind, starts_at, ends_at, reason, created_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&protection.agent_id)
+    .bind(protection.kind)
+    .bind(protection.starts_at)
+    .bind(protection.ends_at)
+    .bind(&protection.reason)
+    .bind(protection.created_at)
+    .execute(pool)
+    .await?;
+    Ok(result.last_insert_rowid())
+}
+
+/// Every window of an agent, oldest first.
+pub async fn fetch_protections(
+    pool: &SqlitePool,
+    agent_id: &str,
+) -> sqlx::Result<Vec<DecayProtection>> {
+    sqlx::query_as::<_, DecayProtection>(
+        "SELECT id, agent_id, kind, starts_at, ends_at, reason, created_at
+         FROM decay_protections WHERE agent_id = ? ORDER BY starts_at, id",
+    )
+    .bind(agent_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Windows of every living agent, for the oracle's tick.
+pub async fn fetch_living_protections(pool: &SqlitePool) -> sqlx::Result<Vec<DecayProtection>> {
+    sqlx::query_as::<_, DecayProtection>(
+        "SELECT p.id, p.agent_id, p.kind, p.starts_at, p.ends_at, p.reason, p.created_at
+         FROM decay_protections p JOIN agents a ON a.agent_id = p.agent_id
+         WHERE a.status = 'alive' ORDER BY p.starts_at, p.id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Ends a window of `kind` at `at` (or cancels it, if it hasn't started). Returns the
+/// updated row, or `None` if there is no such open window.
+pub async fn end_protection(
+    pool: &SqlitePool,
+    agent_id: &str,
+    id: i64,
+    kind: ProtectionKind,
+    at: i64,
+) -> sqlx::Result<Option<DecayProtection>> {
+    sqlx::query_as::<_, DecayProtection>(
+        "UPDATE decay_protections SET ends_at = MAX(starts_at, ?)
+         WHERE id = ? AND agent_id = ? AND kind = ? AND (ends_at IS NULL OR ends_at > ?)
+         RETURNING id, agent_id, kind, starts_at, ends_at, reason, created_at",
+    )
+    .bind(at)
+    .bind(id)
+    .bind(agent_id)
+    .bind(kind)
+    .bind(at)
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(starts_at: i64, ends_at: Option<i64>) -> DecayProtection {
+        DecayProtection {
+            id: 0,
+            agent_id: "a".to_string(),
+            kind: ProtectionKind::Vacation,
+            starts_at,
+            ends_at,
+            reason: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn finds_active_and_last_ended_windows() {
+        let windows = vec![window(0, Some(100)), window(200, Some(300)), window(250, None)];
+        assert_eq!(active_at(&windows, 50).unwrap().starts_at, 0);
+        assert!(active_at(&windows, 100).is_none());
+        assert_eq!(active_at(&windows, 260).unwrap().ends_at, None);
+        assert_eq!(last_ended(&windows, 150), Some(100));
+        assert_eq!(last_ended(&windows, 50), None);
+        assert_eq!(last_ended(&windows, 400), Some(300));
+    }
+
+    #[tokio::test]
+    async fn add_and_end_round_trip() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("../ai_execution/migrations").run(&pool).await.unwrap();
+        sqlx::query("INSERT INTO agents (agent_id, owner_address) VALUES ('a', '0xowner')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let id = add_protection(
+            &pool,
+            &NewProtection {
+                agent_id: "a".to_string(),
+                kind: ProtectionKind::Exemption,
+                starts_at: 100,
+                ends_at: None,
+                reason: Some("bug bounty winner".to_string()),
+                created_at: 100,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(fetch_living_protections(&pool).await.unwrap().len(), 1);
+
+        // Wrong kind: owners can't lift exemptions as if they were vacations
+        assert!(end_protection(&pool, "a", id, ProtectionKind::Vacation, 150).await.unwrap().is_none());
+        let ended = end_protection(&pool, "a", id, ProtectionKind::Exemption, 150).await.unwrap();
+        assert_eq!(ended.unwrap().ends_at, Some(150));
+        assert!(end_protection(&pool, "a", id, ProtectionKind::Exemption, 160).await.unwrap().is_none());
+        assert_eq!(fetch_protections(&pool, "a").await.unwrap()[0].ends_at, Some(150));
+    }
+}